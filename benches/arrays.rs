@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Arrays {
+    heights: Vec<u64>,
+    ports: Vec<u16>,
+    difficulties: Vec<f64>,
+    names: Vec<String>
+}
+
+fn arrays_bytes(len: usize) -> Vec<u8> {
+    let arrays = Arrays {
+        heights: (0..len as u64).collect(),
+        ports: (0..len).map(|i| i as u16).collect(),
+        difficulties: (0..len).map(|i| i as f64 * 1.5).collect(),
+        names: (0..len).map(|i| format!("node-{}", i)).collect()
+    };
+    serde_epee::to_bytes(&arrays).unwrap()
+}
+
+fn decode_arrays(c: &mut Criterion) {
+    // Below monerod's default string limit
+    let bytes = arrays_bytes(50_000);
+
+    c.bench_function("large arrays from_slice", |b| b.iter(|| {
+        serde_epee::from_slice::<Arrays>(black_box(&bytes)).unwrap()
+    }));
+    c.bench_function("large arrays from_reader", |b| b.iter(|| {
+        serde_epee::from_reader::<Arrays, _>(black_box(bytes.as_slice())).unwrap()
+    }));
+}
+
+criterion_group!(benches, decode_arrays);
+criterion_main!(benches);
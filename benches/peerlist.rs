@@ -0,0 +1,36 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::{Deserialize, Serialize};
+use serde_epee::peerlist::{read_peerlist, AddressFields, NetworkAddress, PeerlistEntry};
+
+#[derive(Serialize, Deserialize)]
+struct HandshakeResponse {
+    local_peerlist_new: Vec<PeerlistEntry>
+}
+
+fn handshake_bytes(num_peers: u32) -> Vec<u8> {
+    let peers = (0..num_peers).map(|i| PeerlistEntry {
+        adr: NetworkAddress { addr_type: 1, addr: AddressFields { m_ip: Some(i), m_port: Some(18080), ..Default::default() } },
+        id: i as u64,
+        last_seen: 1700000000,
+        pruning_seed: 384,
+        rpc_port: 18089,
+        rpc_credits_per_hash: 0
+    }).collect();
+    serde_epee::to_bytes(&HandshakeResponse { local_peerlist_new: peers }).unwrap()
+}
+
+fn decode_peerlist(c: &mut Criterion) {
+    // monerod sends at most 250 peers per handshake
+    let bytes = handshake_bytes(250);
+
+    c.bench_function("peerlist derive", |b| b.iter(|| {
+        let response: HandshakeResponse = serde_epee::from_bytes(&mut black_box(bytes.as_slice())).unwrap();
+        response.local_peerlist_new
+    }));
+    c.bench_function("peerlist native", |b| b.iter(|| {
+        read_peerlist(black_box(&bytes), "local_peerlist_new").unwrap()
+    }));
+}
+
+criterion_group!(benches, decode_peerlist);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::Serialize;
+use serde_epee::Section;
+
+#[derive(Serialize)]
+struct Peer {
+    host: String,
+    ip: u32,
+    port: u16,
+    last_seen: i64,
+    pruning_seed: u32
+}
+
+#[derive(Serialize)]
+struct Handshake {
+    node_id: u64,
+    heights: Vec<u64>,
+    local_peerlist_new: Vec<Peer>
+}
+
+fn handshake_bytes(len: usize) -> Vec<u8> {
+    let handshake = Handshake {
+        node_id: 0x0123456789abcdef,
+        heights: (0..len as u64).collect(),
+        local_peerlist_new: (0..len).map(|i| Peer {
+            host: format!("node-{}.example.com", i),
+            ip: i as u32,
+            port: 18080,
+            last_seen: 1700000000 + i as i64,
+            pruning_seed: 0
+        }).collect()
+    };
+    serde_epee::to_bytes(&handshake).unwrap()
+}
+
+fn decode_section(c: &mut Criterion) {
+    let bytes = handshake_bytes(5_000);
+
+    c.bench_function("section from_bytes", |b| b.iter(|| {
+        serde_epee::section::from_bytes(black_box(&bytes)).unwrap()
+    }));
+    c.bench_function("section through serde", |b| b.iter(|| {
+        serde_epee::from_slice::<Section>(black_box(&bytes)).unwrap()
+    }));
+}
+
+criterion_group!(benches, decode_section);
+criterion_main!(benches);
@@ -0,0 +1,250 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenTree};
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::parse::ParseStream;
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Expr, Fields, Ident, LitStr, Token};
+
+// Adapters which #[epee(...)] field attributes stand for
+const ADAPTERS: &[(&str, &str)] = &[
+	("blob", "::serde_epee::interop::blob"),
+	("pod", "::serde_epee::interop::pod"),
+	("pod_list", "::serde_epee::interop::pod_list")
+];
+
+// Goes above #[derive(Serialize, Deserialize)] on structs with named fields:
+//
+//     #[epee_object]
+//     #[derive(Serialize, Deserialize)]
+//     struct ChainResponse {
+//         #[epee(pod_list)]
+//         m_block_ids: Vec<Hash>,
+//         #[epee(blob)]
+//         first_block: Vec<u8>,
+//         #[serde(rename = "current_height")]
+//         height: u64
+//     }
+//
+// Section keys (field names, or their serde renames, rename_all included) are
+// checked at compile time for both serializing and deserializing,
+// #[epee(...)] adapters become the matching #[serde(with = ...)], and the
+// struct gets a serde_epee::EpeeObject impl listing its keys as serialized.
+#[proc_macro_attribute]
+pub fn epee_object(args: TokenStream, input: TokenStream) -> TokenStream {
+	let mut input = parse_macro_input!(input as DeriveInput);
+	if !args.is_empty() {
+		return Error::new(Span::call_site(), "#[epee_object] takes no arguments").to_compile_error().into();
+	}
+
+	let keys = match expand_fields(&mut input) {
+		Ok(keys) => keys,
+		Err(err) => return err.to_compile_error().into()
+	};
+
+	let name = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	quote! {
+		#input
+
+		impl #impl_generics ::serde_epee::EpeeObject for #name #ty_generics #where_clause {
+			const KEYS: &'static [&'static str] = &[#(#keys),*];
+		}
+	}.into()
+}
+
+// Replaces #[epee(...)] attributes and returns the section keys of the fields
+fn expand_fields(input: &mut DeriveInput) -> syn::Result<Vec<String>> {
+	let rename_all = rename_all(&input.attrs)?;
+	let fields = match &mut input.data {
+		Data::Struct(data) => match &mut data.fields {
+			Fields::Named(fields) => fields,
+			_ => return Err(Error::new_spanned(&input.ident, "#[epee_object] structs need named fields"))
+		},
+		_ => return Err(Error::new_spanned(&input.ident, "#[epee_object] only works on structs"))
+	};
+
+	let mut keys = Vec::new();
+	for field in fields.named.iter_mut() {
+		let mut attrs = Vec::with_capacity(field.attrs.len());
+		for attr in field.attrs.drain(..) {
+			if attr.path().is_ident("epee") {
+				attrs.push(adapter_attr(&attr)?);
+			} else {
+				attrs.push(attr);
+			}
+		}
+		field.attrs = attrs;
+
+		let renames = match serde_key(&field.attrs)? {
+			SerdeKey::Skipped => continue,
+			SerdeKey::Renamed(renames) => renames
+		};
+		let ident = field.ident.as_ref().expect("named fields have idents");
+		let name = ident.to_string().trim_start_matches("r#").to_string();
+
+		// Both keys are checked, the one serialized is listed
+		let mut serialized_key = None;
+		for (rename, rule) in renames.iter().zip(&rename_all) {
+			let (key, span) = match (rename, rule) {
+				(Some(lit), _) => (lit.value(), lit.span()),
+				(None, Some(rule)) => (rule.apply(&name), ident.span()),
+				(None, None) => (name.clone(), ident.span())
+			};
+			if key.is_empty() || key.len() > 255 {
+				return Err(Error::new(span, format!("EPEE section keys must be 1 to 255 bytes long, \"{}\" is {} bytes", key, key.len())));
+			}
+			serialized_key.get_or_insert(key);
+		}
+		keys.extend(serialized_key);
+	}
+	Ok(keys)
+}
+
+fn adapter_attr(attr: &Attribute) -> syn::Result<Attribute> {
+	let adapter: Ident = attr.parse_args()?;
+	match ADAPTERS.iter().find(|(name, _)| adapter == name) {
+		Some((_, module)) => Ok(parse_quote!(#[serde(with = #module)])),
+		None => {
+			let names: Vec<&str> = ADAPTERS.iter().map(|(name, _)| *name).collect();
+			Err(Error::new_spanned(adapter, format!("unknown EPEE adapter, expected one of: {}", names.join(", "))))
+		}
+	}
+}
+
+// Renames for serializing and deserializing, in that order
+type Renames<T> = [Option<T>; 2];
+
+enum SerdeKey {
+	Renamed(Renames<LitStr>), // None where the field name is used
+	Skipped // skipped or flattened fields have no key of their own
+}
+
+fn serde_key(attrs: &[Attribute]) -> syn::Result<SerdeKey> {
+	let mut renames = [None, None];
+	let mut skipped = false;
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("skip") || meta.path.is_ident("flatten") {
+				skipped = true;
+			} else if meta.path.is_ident("rename") {
+				return parse_renames(&meta, &mut renames, |value| value.parse());
+			}
+			skip_meta(&meta)
+		})?;
+	}
+	Ok(if skipped { SerdeKey::Skipped } else { SerdeKey::Renamed(renames) })
+}
+
+// Case conventions of the struct's rename_all, if any
+fn rename_all(attrs: &[Attribute]) -> syn::Result<Renames<RenameRule>> {
+	let mut rules = [None, None];
+	for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("rename_all") {
+				return parse_renames(&meta, &mut rules, |value| {
+					let lit: LitStr = value.parse()?;
+					RenameRule::from_str(&lit.value()).ok_or_else(|| {
+						let names: Vec<&str> = RENAME_RULES.iter().map(|(name, _)| *name).collect();
+						Error::new(lit.span(), format!("unknown rename_all rule, expected one of: {}", names.join(", ")))
+					})
+				});
+			}
+			skip_meta(&meta)
+		})?;
+	}
+	Ok(rules)
+}
+
+// Parses `name = value` into both renames, or `name(serialize = value,
+// deserialize = value)` into the ones given
+fn parse_renames<T, F>(meta: &ParseNestedMeta, renames: &mut Renames<T>, parse: F) -> syn::Result<()>
+where
+	T: Clone,
+	F: Fn(ParseStream) -> syn::Result<T>
+{
+	if meta.input.peek(Token![=]) {
+		let value = parse(meta.value()?)?;
+		*renames = [Some(value.clone()), Some(value)];
+		return Ok(());
+	}
+	meta.parse_nested_meta(|direction| {
+		let index = if direction.path.is_ident("serialize") {
+			0
+		} else if direction.path.is_ident("deserialize") {
+			1
+		} else {
+			return Err(direction.error("expected serialize or deserialize"));
+		};
+		renames[index] = Some(parse(direction.value()?)?);
+		Ok(())
+	})
+}
+
+// Consumes the rest of an attribute we don't look at, e.g. default = "..."
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+	if meta.input.peek(Token![=]) {
+		meta.value()?.parse::<Expr>()?;
+	} else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+		meta.input.parse::<TokenTree>()?;
+	}
+	Ok(())
+}
+
+// serde's rename_all conventions, applied to snake_case field names
+#[derive(Clone, Copy)]
+enum RenameRule {
+	Lower,
+	Upper,
+	Pascal,
+	Camel,
+	Snake,
+	ScreamingSnake,
+	Kebab,
+	ScreamingKebab
+}
+
+const RENAME_RULES: &[(&str, RenameRule)] = &[
+	("lowercase", RenameRule::Lower),
+	("UPPERCASE", RenameRule::Upper),
+	("PascalCase", RenameRule::Pascal),
+	("camelCase", RenameRule::Camel),
+	("snake_case", RenameRule::Snake),
+	("SCREAMING_SNAKE_CASE", RenameRule::ScreamingSnake),
+	("kebab-case", RenameRule::Kebab),
+	("SCREAMING-KEBAB-CASE", RenameRule::ScreamingKebab)
+];
+
+impl RenameRule {
+	fn from_str(name: &str) -> Option<Self> {
+		RENAME_RULES.iter().find(|(rule_name, _)| *rule_name == name).map(|(_, rule)| *rule)
+	}
+
+	fn apply(self, field: &str) -> String {
+		match self {
+			RenameRule::Lower | RenameRule::Snake => field.to_string(),
+			RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+			RenameRule::Pascal => {
+				let mut pascal = String::with_capacity(field.len());
+				let mut capitalize = true;
+				for c in field.chars() {
+					if c == '_' {
+						capitalize = true;
+					} else if capitalize {
+						pascal.push(c.to_ascii_uppercase());
+						capitalize = false;
+					} else {
+						pascal.push(c);
+					}
+				}
+				pascal
+			},
+			RenameRule::Camel => {
+				let pascal = RenameRule::Pascal.apply(field);
+				let mut chars = pascal.chars();
+				chars.next().map(|first| first.to_ascii_lowercase().to_string() + chars.as_str()).unwrap_or_default()
+			},
+			RenameRule::Kebab => field.replace('_', "-"),
+			RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-")
+		}
+	}
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::types::{EpeeEntryType, EpeeScalarType};
+use crate::VarInt;
+
+///////////////////////////////////////////////////////////////////////////////
+// Reports                                                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NondeterminismSource {
+	// Same fields, different order, e.g. from iterating a HashMap
+	FieldOrder { path: String },
+	// Floating point value with a different bit pattern (NaN payloads, -0.0)
+	FloatValue { path: String, first: f64, second: f64 },
+	// Any other differing value, or fields present in only one encoding
+	ValueChanged { path: String },
+	// Encodings are structurally unreadable, only the bytes could be compared
+	ByteMismatch { offset: usize }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeterminismReport {
+	// xxhash64 of each encoding, stable across builds, see envelope::xxhash64
+	pub first_digest: u64,
+	pub second_digest: u64,
+	pub first_len: usize,
+	pub second_len: usize,
+	pub sources: Vec<NondeterminismSource>
+}
+
+impl DeterminismReport {
+	pub fn is_deterministic(&self) -> bool {
+		self.sources.is_empty()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Serializes value twice and compares the encodings
+pub fn audit_determinism<T: Serialize>(value: &T) -> Result<DeterminismReport> {
+	let first = crate::to_bytes(value)?;
+	let second = crate::to_bytes(value)?;
+	Ok(compare_encodings(&first, &second))
+}
+
+// Serializes value, decodes it into a fresh T and serializes that again. This
+// catches state which doesn't survive decoding, like HashMap iteration order.
+pub fn audit_round_trip<T: Serialize + DeserializeOwned>(value: &T) -> Result<DeterminismReport> {
+	let first = crate::to_bytes(value)?;
+	let decoded: T = crate::from_bytes(&mut first.as_slice())?;
+	let second = crate::to_bytes(&decoded)?;
+	Ok(compare_encodings(&first, &second))
+}
+
+pub fn compare_encodings(first: &[u8], second: &[u8]) -> DeterminismReport {
+	let mut report = DeterminismReport {
+		first_digest: crate::envelope::xxhash64(first),
+		second_digest: crate::envelope::xxhash64(second),
+		first_len: first.len(),
+		second_len: second.len(),
+		sources: Vec::new()
+	};
+
+	if first == second {
+		return report;
+	}
+
+	match (flatten(first), flatten(second)) {
+		(Ok(first_flat), Ok(second_flat)) => diff_flattened(&first_flat, &second_flat, &mut report.sources),
+		_ => {
+			let offset = first.iter().zip(second.iter()).position(|(a, b)| a != b)
+				.unwrap_or(std::cmp::min(first.len(), second.len()));
+			report.sources.push(NondeterminismSource::ByteMismatch { offset });
+		}
+	}
+
+	report
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Structural comparison                                                     //
+///////////////////////////////////////////////////////////////////////////////
+
+// Wire-order view of a document: every leaf value and every section's key order
+#[derive(Default)]
+struct Flattened {
+	leaves: Vec<(String, u8, Vec<u8>)>, // path, type code, raw value bytes
+	key_orders: Vec<(String, Vec<String>)>
+}
+
+fn diff_flattened(first: &Flattened, second: &Flattened, sources: &mut Vec<NondeterminismSource>) {
+	// Paths are looked up in maps so that big documents aren't quadratic. The
+	// first of any repeated path wins, like a front to back search would.
+	let mut second_orders = HashMap::new();
+	for (path, keys) in &second.key_orders {
+		second_orders.entry(path.as_str()).or_insert(keys);
+	}
+	let mut second_leaves = HashMap::new();
+	for (path, type_code, raw) in &second.leaves {
+		second_leaves.entry(path.as_str()).or_insert((*type_code, raw));
+	}
+	let first_paths: HashSet<&str> = first.leaves.iter().map(|(path, _, _)| path.as_str()).collect();
+
+	for (path, first_keys) in &first.key_orders {
+		if let Some(second_keys) = second_orders.get(path.as_str()) {
+			let mut first_sorted = first_keys.clone();
+			let mut second_sorted = (*second_keys).clone();
+			first_sorted.sort();
+			second_sorted.sort();
+			if first_keys != *second_keys && first_sorted == second_sorted {
+				sources.push(NondeterminismSource::FieldOrder { path: path.clone() });
+			}
+		}
+	}
+
+	for (path, type_code, raw) in &first.leaves {
+		match second_leaves.get(path.as_str()) {
+			Some((other_code, other_raw)) if other_code == type_code && *other_raw == raw => (),
+			Some((other_code, other_raw)) if *type_code == constants::SERIALIZE_TYPE_DOUBLE && other_code == type_code => {
+				sources.push(NondeterminismSource::FloatValue {
+					path: path.clone(),
+					first: f64::from_le_bytes(raw.as_slice().try_into().unwrap_or([0; 8])),
+					second: f64::from_le_bytes(other_raw.as_slice().try_into().unwrap_or([0; 8]))
+				});
+			},
+			_ => sources.push(NondeterminismSource::ValueChanged { path: path.clone() })
+		}
+	}
+
+	for (path, _, _) in &second.leaves {
+		if !first_paths.contains(path.as_str()) {
+			sources.push(NondeterminismSource::ValueChanged { path: path.clone() });
+		}
+	}
+}
+
+fn flatten(bytes: &[u8]) -> Result<Flattened> {
+	let mut reader = bytes;
+	let mut flat = Flattened::default();
+
+	let mut sigbuf = [0u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE];
+	std::io::Read::read_exact(&mut reader, &mut sigbuf)?;
+	if sigbuf != constants::PORTABLE_STORAGE_SIGNATURE {
+		return epee_err!(ExpectedFormatSignature);
+	}
+
+	flatten_section(&mut reader, "", &mut flat, 0)?;
+	Ok(flat)
+}
+
+fn take<'a>(reader: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+	if reader.len() < len {
+		return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+	}
+	let (taken, rest) = reader.split_at(len);
+	*reader = rest;
+	Ok(taken)
+}
+
+fn flatten_section(reader: &mut &[u8], path: &str, flat: &mut Flattened, depth: usize) -> Result<()> {
+	if depth >= constants::MONEROD_RECURSION_LIMIT {
+		return epee_err!(SectionTooDeep { max: constants::MONEROD_RECURSION_LIMIT as u64 }, "max depth is {}", constants::MONEROD_RECURSION_LIMIT);
+	}
+
+	let num_fields: usize = VarInt::from_reader(reader)?.try_into()?;
+	let mut keys = Vec::new();
+
+	for _ in 0..num_fields {
+		let key_len = take(reader, 1)?[0] as usize;
+		let key = String::from_utf8_lossy(take(reader, key_len)?).into_owned();
+		let type_code = take(reader, 1)?[0];
+		let entry_type = EpeeEntryType::from_type_code(type_code)?;
+		let field_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+
+		if entry_type.is_array {
+			let count: usize = VarInt::from_reader(reader)?.try_into()?;
+			for i in 0..count {
+				let elem_path = format!("{}[{}]", field_path, i);
+				flatten_scalar(reader, entry_type.scalar_type, elem_path, flat, depth)?;
+			}
+		} else {
+			flatten_scalar(reader, entry_type.scalar_type, field_path, flat, depth)?;
+		}
+
+		keys.push(key);
+	}
+
+	flat.key_orders.push((path.to_string(), keys));
+	Ok(())
+}
+
+fn flatten_scalar(reader: &mut &[u8], scalar_type: EpeeScalarType, path: String, flat: &mut Flattened, depth: usize) -> Result<()> {
+	let raw = match scalar_type {
+		EpeeScalarType::Object => return flatten_section(reader, &path, flat, depth + 1),
+		EpeeScalarType::Str => {
+			let len: usize = VarInt::from_reader(reader)?.try_into()?;
+			take(reader, len)?
+		},
+		_ => take(reader, scalar_type.fixed_size().unwrap_or(0))?
+	};
+
+	flat.leaves.push((path, scalar_type.to_type_code(), raw.to_vec()));
+	Ok(())
+}
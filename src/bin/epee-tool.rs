@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+use serde_epee::scan::{PathSize, ScanEntry};
+use serde_epee::Section;
+
+const USAGE: &str = "\
+usage: epee-tool <command> [args]
+
+commands:
+  diff <first> <second>               show how two portable storage files differ
+  grep [--values] <pattern> <file>... list entries whose key path (or value,
+                                      with --values) matches a glob pattern
+  stat [--depth <n>] <file>...        bytes taken up by each key path, down to
+                                      n keys deep (1 by default), over all files";
+
+fn main() -> ExitCode {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	let res = match args.first().map(String::as_str) {
+		Some("diff") if args.len() == 3 => diff(&args[1], &args[2]),
+		Some("grep") if args.get(1).is_some_and(|arg| arg == "--values") && args.len() >= 4 => grep(&args[2], &args[3..], true),
+		Some("grep") if args.len() >= 3 => grep(&args[1], &args[2..], false),
+		Some("stat") if args.get(1).is_some_and(|arg| arg == "--depth") && args.len() >= 4 => match args[2].parse() {
+			Ok(depth) => stat(depth, &args[3..]),
+			Err(_) => Err(format!("bad depth {:?}", args[2]))
+		},
+		Some("stat") if args.len() >= 2 => stat(1, &args[1..]),
+		_ => {
+			eprintln!("{}", USAGE);
+			return ExitCode::from(2);
+		}
+	};
+
+	match res {
+		Ok(code) => code,
+		Err(err) => {
+			eprintln!("epee-tool: {}", err);
+			ExitCode::from(2)
+		}
+	}
+}
+
+// Exits with 0 if the files are the same, 1 if they differ, like diff(1)
+fn diff(first_path: &str, second_path: &str) -> Result<ExitCode, String> {
+	let first = load(first_path)?;
+	let second = load(second_path)?;
+
+	let changes = serde_epee::diff::diff(&first, &second);
+	for change in &changes {
+		println!("{}", change);
+	}
+	Ok(if changes.is_empty() { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+// Prints "offset: path = TYPE value" for every match, prefixed with the file
+// name if there are several files. Exits with 0 if anything matched, 1 if not,
+// like grep(1).
+fn grep(pattern: &str, paths: &[String], values: bool) -> Result<ExitCode, String> {
+	let mut matched = false;
+	for path in paths {
+		let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+		let prefix = if paths.len() > 1 { format!("{}:", path) } else { String::new() };
+		serde_epee::scan::scan(BufReader::new(file), |entry| {
+			let subject = if values { entry_value(entry) } else { Some(entry.path.to_string()) };
+			if subject.is_some_and(|subject| glob_match(pattern.as_bytes(), subject.as_bytes())) {
+				matched = true;
+				match &entry.value {
+					Some(value) => println!("{}{:08x}: {} = {} {}", prefix, entry.offset, entry.path, entry.entry_type, value),
+					None => println!("{}{:08x}: {} = {}", prefix, entry.offset, entry.path, entry.entry_type)
+				}
+			}
+			Ok(())
+		}).map_err(|err| format!("{}: {}", path, err))?;
+	}
+	Ok(if matched { ExitCode::SUCCESS } else { ExitCode::from(1) })
+}
+
+// Prints a table of key paths, biggest first, with their share of all bytes
+fn stat(depth: usize, paths: &[String]) -> Result<ExitCode, String> {
+	let mut total = 0;
+	let mut sizes: BTreeMap<String, PathSize> = BTreeMap::new();
+	for path in paths {
+		let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+		let (len, file_sizes) = serde_epee::scan::path_sizes(BufReader::new(file), depth).map_err(|err| format!("{}: {}", path, err))?;
+		total += len;
+		for (key_path, size) in file_sizes {
+			let sum = sizes.entry(key_path).or_default();
+			sum.bytes += size.bytes;
+			sum.count += size.count;
+		}
+	}
+
+	let mut rows: Vec<(String, PathSize)> = sizes.into_iter().collect();
+	rows.sort_by(|(a_path, a), (b_path, b)| b.bytes.cmp(&a.bytes).then_with(|| a_path.cmp(b_path)));
+	println!("{:>12} {:>7} {:>8}  path", "bytes", "share", "count");
+	for (key_path, size) in rows {
+		let share = if total == 0 { 0.0 } else { 100.0 * size.bytes as f64 / total as f64 };
+		println!("{:>12} {:>6.1}% {:>8}  {}", size.bytes, share, size.count, key_path);
+	}
+	println!("{:>12} {:>7} {:>8}  total of {} files", total, "", "", paths.len());
+	Ok(ExitCode::SUCCESS)
+}
+
+// Strings are matched as text, other scalars as they are printed
+fn entry_value(entry: &ScanEntry) -> Option<String> {
+	let value = entry.value.as_ref()?;
+	Some(value.as_str().map(String::from).unwrap_or_else(|| value.to_string()))
+}
+
+// Whole string match, where * is any run of bytes and ? any one byte. On a
+// mismatch only the last * is retried one byte further, so this takes at most
+// pattern times subject length steps instead of backtracking into every *.
+fn glob_match(pattern: &[u8], subject: &[u8]) -> bool {
+	let (mut p, mut s) = (0, 0);
+	let mut last_star: Option<(usize, usize)> = None; // pattern index after it, subject index it resumes at
+	while s < subject.len() {
+		match pattern.get(p) {
+			Some(b'*') => {
+				p += 1;
+				last_star = Some((p, s));
+			},
+			Some(&c) if c == b'?' || c == subject[s] => {
+				p += 1;
+				s += 1;
+			},
+			_ => match last_star {
+				Some((star_p, star_s)) => {
+					p = star_p;
+					s = star_s + 1;
+					last_star = Some((star_p, s));
+				},
+				None => return false
+			}
+		}
+	}
+	pattern[p..].iter().all(|&c| c == b'*')
+}
+
+fn load(path: &str) -> Result<Section, String> {
+	Section::load(path).map_err(|err| format!("{}: {}", path, err))
+}
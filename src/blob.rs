@@ -0,0 +1,137 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::Result;
+
+///////////////////////////////////////////////////////////////////////////////
+// Encrypted blob fields                                                     //
+///////////////////////////////////////////////////////////////////////////////
+
+// User supplied transformation applied to a blob on its way to and from the
+// wire, e.g. the chacha encryption of Monero wallet cache fields. Keys have to
+// come from the implementing type itself (constant, thread local, etc.) since
+// serde doesn't pass any context into ser/de.
+pub trait BlobCipher {
+	fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>>;
+	fn decrypt(ciphertext: &[u8]) -> Result<Vec<u8>>;
+}
+
+// Identity cipher, for fields which are only sometimes encrypted
+#[derive(Debug, Copy, Clone)]
+pub struct NoCipher;
+
+impl BlobCipher for NoCipher {
+	fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>> {
+		Ok(plaintext.to_vec())
+	}
+
+	fn decrypt(ciphertext: &[u8]) -> Result<Vec<u8>> {
+		Ok(ciphertext.to_vec())
+	}
+}
+
+// Blob field which holds the plaintext in memory and the ciphertext on the wire
+pub struct EncryptedBlob<C: BlobCipher> {
+	plaintext: Vec<u8>,
+	cipher: PhantomData<C>
+}
+
+impl<C: BlobCipher> EncryptedBlob<C> {
+	pub fn new(plaintext: Vec<u8>) -> Self {
+		Self {
+			plaintext,
+			cipher: PhantomData
+		}
+	}
+
+	pub fn into_inner(self) -> Vec<u8> {
+		self.plaintext
+	}
+}
+
+impl<C: BlobCipher> Deref for EncryptedBlob<C> {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.plaintext
+	}
+}
+
+impl<C: BlobCipher> Clone for EncryptedBlob<C> {
+	fn clone(&self) -> Self {
+		Self::new(self.plaintext.clone())
+	}
+}
+
+impl<C: BlobCipher> fmt::Debug for EncryptedBlob<C> {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_fmt(format_args!("EncryptedBlob({} bytes)", self.plaintext.len()))
+	}
+}
+
+impl<C: BlobCipher> PartialEq for EncryptedBlob<C> {
+	fn eq(&self, other: &Self) -> bool {
+		self.plaintext == other.plaintext
+	}
+}
+
+impl<C: BlobCipher> Serialize for EncryptedBlob<C> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serialize_encrypted::<C, S>(&self.plaintext, serializer)
+	}
+}
+
+impl<'de, C: BlobCipher> Deserialize<'de> for EncryptedBlob<C> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+		deserialize_encrypted::<C, D>(deserializer).map(Self::new)
+	}
+}
+
+// For plain byte vector fields:
+// #[serde(serialize_with = "serialize_encrypted::<MyCipher, _>", deserialize_with = "deserialize_encrypted::<MyCipher, _>")]
+pub fn serialize_encrypted<C, S>(plaintext: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+	C: BlobCipher,
+	S: Serializer
+{
+	let ciphertext = C::encrypt(plaintext).map_err(serde::ser::Error::custom)?;
+	serializer.serialize_bytes(&ciphertext)
+}
+
+pub fn deserialize_encrypted<'de, C, D>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error>
+where
+	C: BlobCipher,
+	D: Deserializer<'de>
+{
+	let ciphertext = deserializer.deserialize_byte_buf(BlobVisitor)?;
+	C::decrypt(&ciphertext).map_err(de::Error::custom)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Visitors                                                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+struct BlobVisitor;
+
+impl<'de> de::Visitor<'de> for BlobVisitor {
+	type Value = Vec<u8>;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a blob")
+	}
+
+	fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<Vec<u8>, E> {
+		Ok(v.to_vec())
+	}
+
+	fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<Vec<u8>, E> {
+		Ok(v)
+	}
+
+	fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Vec<u8>, E> {
+		Ok(v.as_bytes().to_vec())
+	}
+}
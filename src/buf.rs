@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use bytes::{Buf, BufMut, Bytes};
+use serde::de::{self, DeserializeOwned, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::Result;
+
+pub(crate) const SHARED_BLOB_TOKEN: &str = "$serde_epee::private::SharedBlob";
+
+thread_local! {
+	// Slice of the shared input on its way from the deserializer to
+	// SharedBlobVisitor. The input itself stays with the Deserializer, this
+	// only holds a value for the duration of one visit_seq call, so nested
+	// deserializers never see each other's input.
+	static HANDED_OVER: RefCell<Option<Bytes>> = const { RefCell::new(None) };
+}
+
+// Calls visit with blob available to SharedBlobVisitor::visit_seq
+pub(crate) fn hand_over<T>(blob: Bytes, visit: impl FnOnce() -> T) -> T {
+	let previous = HANDED_OVER.with(|slot| slot.replace(Some(blob)));
+	let _guard = HandOverGuard { previous };
+	visit()
+}
+
+// Puts back what an outer hand_over left, even if visiting panics
+struct HandOverGuard {
+	previous: Option<Bytes>
+}
+
+impl Drop for HandOverGuard {
+	fn drop(&mut self) {
+		let previous = self.previous.take();
+		HANDED_OVER.with(|slot| *slot.borrow_mut() = previous);
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Encodes straight into buf, e.g. a pooled BytesMut, which grows as needed.
+// Fixed size buffers fail with an IOError once they are full.
+pub fn to_buf_mut<T: Serialize, B: BufMut>(value: &T, buf: &mut B) -> Result<()> {
+	crate::to_writer(buf.writer(), value)
+}
+
+// Decodes from buf without copying it into one slice first, so chained or
+// partially consumed receive buffers work as they are
+pub fn from_buf<T: DeserializeOwned, B: Buf>(buf: B) -> Result<T> {
+	crate::from_reader(buf.reader())
+}
+
+// Like from_buf, but SharedBlob values are slices of bytes sharing its memory
+// instead of copies. Other blobs and strings are still copied.
+pub fn from_shared_bytes<T: DeserializeOwned>(bytes: &Bytes) -> Result<T> {
+	let mut deserializer = crate::Deserializer::from_reader(bytes.as_ref()).with_shared_input(bytes.clone());
+	T::deserialize(&mut deserializer)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Shared blobs                                                              //
+///////////////////////////////////////////////////////////////////////////////
+
+// A STRING entry kept as Bytes. Decoded by from_shared_bytes it points into
+// the input buffer, everywhere else (including other serde formats) it is a
+// copy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SharedBlob(pub Bytes);
+
+impl Serialize for SharedBlob {
+	fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serializer.serialize_bytes(&self.0)
+	}
+}
+
+impl<'de> Deserialize<'de> for SharedBlob {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+		deserializer.deserialize_newtype_struct(SHARED_BLOB_TOKEN, SharedBlobVisitor)
+	}
+}
+
+struct SharedBlobVisitor;
+
+impl<'de> Visitor<'de> for SharedBlobVisitor {
+	type Value = SharedBlob;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a blob")
+	}
+
+	fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<SharedBlob, E> {
+		Ok(SharedBlob(Bytes::copy_from_slice(v)))
+	}
+
+	fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<SharedBlob, E> {
+		Ok(SharedBlob(Bytes::from(v)))
+	}
+
+	fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<SharedBlob, D::Error> {
+		let blob = serde_bytes::ByteBuf::deserialize(deserializer)?;
+		Ok(SharedBlob(Bytes::from(blob.into_vec())))
+	}
+
+	// The deserializer hands over the blob's slice of the shared input, see
+	// hand_over. The sequence itself is empty.
+	fn visit_seq<A: SeqAccess<'de>>(self, _seq: A) -> std::result::Result<SharedBlob, A::Error> {
+		match HANDED_OVER.with(|slot| slot.borrow_mut().take()) {
+			Some(blob) => Ok(SharedBlob(blob)),
+			None => Err(de::Error::invalid_type(de::Unexpected::Seq, &self))
+		}
+	}
+}
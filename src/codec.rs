@@ -0,0 +1,155 @@
+use std::marker::PhantomData;
+
+use bytes::{Buf, BufMut, BytesMut};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::de::Deserializer;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::limits::Limits;
+
+///////////////////////////////////////////////////////////////////////////////
+// Levin framing                                                             //
+///////////////////////////////////////////////////////////////////////////////
+
+// Bucket header which monerod puts in front of every P2P payload
+pub const LEVIN_SIGNATURE: u64 = 0x0101010101012101;
+pub const LEVIN_PROTOCOL_VER_1: u32 = 1;
+pub const LEVIN_HEADER_SIZE: usize = 33;
+pub const LEVIN_DEFAULT_MAX_PACKET_SIZE: u64 = 100000000; // LEVIN_DEFAULT_MAX_PACKET_SIZE in levin_base.h
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LevinHeader {
+	pub expect_response: bool,
+	pub command: u32,
+	pub return_code: i32,
+	pub flags: u32,
+	pub protocol_version: u32
+}
+
+impl LevinHeader {
+	pub fn new(command: u32) -> Self {
+		Self {
+			expect_response: false,
+			command,
+			return_code: 0,
+			flags: 0,
+			protocol_version: LEVIN_PROTOCOL_VER_1
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevinMessage<T> {
+	pub header: LevinHeader,
+	pub body: T
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Codec                                                                     //
+///////////////////////////////////////////////////////////////////////////////
+
+// Decoder/Encoder of levin framed EPEE documents for tokio_util's Framed. The
+// decoder waits until a whole frame is buffered, and rejects frames larger
+// than max_payload (BudgetExceeded) as soon as their header arrives.
+#[derive(Debug)]
+pub struct EpeeCodec<T> {
+	max_payload: u64,
+	limits: Limits,
+	body: PhantomData<fn() -> T>
+}
+
+impl<T> EpeeCodec<T> {
+	pub fn new() -> Self {
+		Self {
+			max_payload: LEVIN_DEFAULT_MAX_PACKET_SIZE,
+			limits: Limits::monerod_levin(),
+			body: PhantomData
+		}
+	}
+
+	pub fn with_max_payload(mut self, max_payload: u64) -> Self {
+		self.max_payload = max_payload;
+		self
+	}
+
+	pub fn with_limits(mut self, limits: Limits) -> Self {
+		self.limits = limits;
+		self
+	}
+}
+
+impl<T> Default for EpeeCodec<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: DeserializeOwned> Decoder for EpeeCodec<T> {
+	type Item = LevinMessage<T>;
+	type Error = Error;
+
+	fn decode(&mut self, src: &mut BytesMut) -> Result<Option<LevinMessage<T>>> {
+		if src.len() < LEVIN_HEADER_SIZE {
+			src.reserve(LEVIN_HEADER_SIZE - src.len());
+			return Ok(None);
+		}
+
+		let mut header_bytes = &src[..LEVIN_HEADER_SIZE];
+		if header_bytes.get_u64_le() != LEVIN_SIGNATURE {
+			return epee_err!(ExpectedFormatSignature, "bad levin signature");
+		}
+		let payload_len = header_bytes.get_u64_le();
+		if payload_len > self.max_payload {
+			return epee_err!(BudgetExceeded { max_bytes: self.max_payload }, "levin payload of {} bytes is too big", payload_len);
+		}
+
+		let frame_len = match usize::try_from(payload_len).ok().and_then(|len| len.checked_add(LEVIN_HEADER_SIZE)) {
+			Some(frame_len) => frame_len,
+			None => return epee_err!(LengthOverflow { len: payload_len }, "levin payload of {} bytes does not fit in memory", payload_len)
+		};
+		if src.len() < frame_len {
+			src.reserve(frame_len - src.len());
+			return Ok(None);
+		}
+
+		let header = LevinHeader {
+			expect_response: header_bytes.get_u8() != 0,
+			command: header_bytes.get_u32_le(),
+			return_code: header_bytes.get_i32_le(),
+			flags: header_bytes.get_u32_le(),
+			protocol_version: header_bytes.get_u32_le()
+		};
+
+		let frame = src.split_to(frame_len);
+		let mut deserializer = Deserializer::from_slice(&frame[LEVIN_HEADER_SIZE..]).with_limits(self.limits).with_frame_len(payload_len);
+		let body = T::deserialize(&mut deserializer)?;
+		let rest = deserializer.into_inner();
+		if !rest.is_empty() {
+			return epee_err!(ExpectedEnd, "{} bytes left over after levin payload", rest.len());
+		}
+		Ok(Some(LevinMessage { header, body }))
+	}
+}
+
+impl<T: Serialize> Encoder<LevinMessage<T>> for EpeeCodec<T> {
+	type Error = Error;
+
+	fn encode(&mut self, message: LevinMessage<T>, dst: &mut BytesMut) -> Result<()> {
+		let payload = crate::to_bytes(&message.body)?;
+		if payload.len() as u64 > self.max_payload {
+			return epee_err!(BudgetExceeded { max_bytes: self.max_payload }, "levin payload of {} bytes is too big", payload.len());
+		}
+
+		dst.reserve(LEVIN_HEADER_SIZE + payload.len());
+		dst.put_u64_le(LEVIN_SIGNATURE);
+		dst.put_u64_le(payload.len() as u64);
+		dst.put_u8(message.header.expect_response as u8);
+		dst.put_u32_le(message.header.command);
+		dst.put_i32_le(message.header.return_code);
+		dst.put_u32_le(message.header.flags);
+		dst.put_u32_le(message.header.protocol_version);
+		dst.put_slice(&payload);
+		Ok(())
+	}
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::section::{Section, SectionArray, SectionEntry};
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Best guess Rust definitions for a sample document, as a starting point for
+// typed models of undocumented endpoints. The root section becomes `Root` and
+// every nested section gets its own struct named after its key. Fields are
+// sorted by key since Section doesn't keep the wire order. Blobs which are
+// valid UTF-8 are guessed to be strings, everything else gets serde_bytes.
+pub fn infer_types(section: &Section) -> String {
+	let mut generator = Generator::default();
+	generator.add_struct("Root", &[section]);
+
+	let mut out = String::new();
+	for (i, definition) in generator.definitions.iter().enumerate() {
+		if i > 0 {
+			out.push('\n');
+		}
+		out.push_str(definition);
+	}
+	out
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Generator                                                                 //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+struct Generator {
+	definitions: Vec<String>,
+	names: Vec<String>
+}
+
+struct Field {
+	rust_type: String,
+	serde_with: Option<&'static str>
+}
+
+impl Generator {
+	// Emits a struct covering the keys of all samples. Keys missing from some
+	// of the samples (elements of a section array) become Options.
+	fn add_struct(&mut self, name: &str, samples: &[&Section]) -> String {
+		let name = self.unique_name(name);
+		let index = self.definitions.len();
+		self.definitions.push(String::new());
+
+		let mut keys: BTreeMap<&str, Vec<&SectionEntry>> = BTreeMap::new();
+		for sample in samples {
+			for (key, entry) in sample.iter() {
+				keys.entry(key.as_str()).or_default().push(entry);
+			}
+		}
+
+		let mut definition = String::new();
+		let _ = writeln!(definition, "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]");
+		let _ = writeln!(definition, "pub struct {} {{", name);
+		for (key, entries) in &keys {
+			let field = self.infer_field(key, entries);
+			let optional = entries.len() < samples.len();
+			let ident = field_ident(key);
+
+			if ident != *key {
+				let _ = writeln!(definition, "\t#[serde(rename = \"{}\")]", key.escape_default());
+			}
+			match (field.serde_with, optional) {
+				(Some(with), false) => { let _ = writeln!(definition, "\t#[serde(with = \"{}\")]", with); },
+				(Some(with), true) => { let _ = writeln!(definition, "\t#[serde(default, with = \"{}\")]", with); },
+				(None, true) => { let _ = writeln!(definition, "\t#[serde(default)]"); },
+				(None, false) => {}
+			}
+			let rust_type = if optional { format!("Option<{}>", field.rust_type) } else { field.rust_type };
+			let _ = writeln!(definition, "\tpub {}: {},", ident, rust_type);
+		}
+		definition.push_str("}\n");
+
+		self.definitions[index] = definition;
+		name
+	}
+
+	fn infer_field(&mut self, key: &str, entries: &[&SectionEntry]) -> Field {
+		let plain = |rust_type: &str| Field { rust_type: rust_type.to_string(), serde_with: None };
+
+		match entries[0] {
+			SectionEntry::Int64(_) => plain("i64"),
+			SectionEntry::Int32(_) => plain("i32"),
+			SectionEntry::Int16(_) => plain("i16"),
+			SectionEntry::Int8(_) => plain("i8"),
+			SectionEntry::UInt64(_) => plain("u64"),
+			SectionEntry::UInt32(_) => plain("u32"),
+			SectionEntry::UInt16(_) => plain("u16"),
+			SectionEntry::UInt8(_) => plain("u8"),
+			SectionEntry::Double(_) => plain("f64"),
+			SectionEntry::Bool(_) => plain("bool"),
+			SectionEntry::Blob(_) => {
+				let blobs = entries.iter().filter_map(|e| e.as_bytes());
+				if all_utf8(blobs) {
+					plain("String")
+				} else {
+					Field { rust_type: "Vec<u8>".to_string(), serde_with: Some("serde_bytes") }
+				}
+			},
+			SectionEntry::Object(_) => {
+				let sections: Vec<&Section> = entries.iter().filter_map(|e| e.as_section()).collect();
+				Field { rust_type: self.add_struct(&struct_name(key), &sections), serde_with: None }
+			},
+			SectionEntry::Array(array) => self.infer_array_field(key, array, entries),
+			SectionEntry::Nil => plain("()")
+		}
+	}
+
+	fn infer_array_field(&mut self, key: &str, array: &SectionArray, entries: &[&SectionEntry]) -> Field {
+		let vec_of = |rust_type: &str| Field { rust_type: format!("Vec<{}>", rust_type), serde_with: None };
+
+		match array {
+			SectionArray::Int64(_) => vec_of("i64"),
+			SectionArray::Int32(_) => vec_of("i32"),
+			SectionArray::Int16(_) => vec_of("i16"),
+			SectionArray::Int8(_) => vec_of("i8"),
+			SectionArray::UInt64(_) => vec_of("u64"),
+			SectionArray::UInt32(_) => vec_of("u32"),
+			SectionArray::UInt16(_) => vec_of("u16"),
+			SectionArray::UInt8(_) => vec_of("u8"),
+			SectionArray::Double(_) => vec_of("f64"),
+			SectionArray::Bool(_) => vec_of("bool"),
+			SectionArray::Blob(_) => {
+				let blobs = entries.iter()
+					.filter_map(|e| e.as_array().and_then(|a| a.iter_blobs()))
+					.flatten();
+				if all_utf8(blobs) { vec_of("String") } else { vec_of("serde_bytes::ByteBuf") }
+			},
+			SectionArray::Object(_) => {
+				let sections: Vec<&Section> = entries.iter()
+					.filter_map(|e| e.as_array().and_then(|a| a.iter_sections()))
+					.flatten()
+					.collect();
+				let name = self.add_struct(&struct_name(&singular(key)), &sections);
+				vec_of(&name)
+			}
+		}
+	}
+
+	fn unique_name(&mut self, name: &str) -> String {
+		let mut candidate = name.to_string();
+		let mut suffix = 2;
+		while self.names.contains(&candidate) {
+			candidate = format!("{}{}", name, suffix);
+			suffix += 1;
+		}
+		self.names.push(candidate.clone());
+		candidate
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Naming helpers                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+const RUST_KEYWORDS: &[&str] = &[
+	"as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+	"extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+	"mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+	"type", "unsafe", "use", "where", "while", "yield"
+];
+
+fn all_utf8<'a>(mut blobs: impl Iterator<Item = &'a [u8]>) -> bool {
+	blobs.all(|blob| std::str::from_utf8(blob).is_ok())
+}
+
+// Snake case identifier for a key, e.g. "blockHash" => "block_hash"
+fn field_ident(key: &str) -> String {
+	let mut ident = String::new();
+	for c in key.chars() {
+		if c.is_ascii_uppercase() {
+			if !ident.is_empty() && !ident.ends_with('_') {
+				ident.push('_');
+			}
+			ident.push(c.to_ascii_lowercase());
+		} else if c.is_ascii_alphanumeric() || c == '_' {
+			ident.push(c);
+		} else if !ident.ends_with('_') {
+			ident.push('_');
+		}
+	}
+
+	if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+		ident.insert(0, '_');
+	}
+	if RUST_KEYWORDS.contains(&ident.as_str()) {
+		ident.push('_');
+	}
+	ident
+}
+
+// Camel case type name for a key, e.g. "node_data" => "NodeData"
+fn struct_name(key: &str) -> String {
+	let mut name = String::new();
+	let mut upper_next = true;
+	for c in key.chars() {
+		if c.is_ascii_alphanumeric() {
+			name.push(if upper_next { c.to_ascii_uppercase() } else { c });
+			upper_next = false;
+		} else {
+			upper_next = true;
+		}
+	}
+
+	if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+		name.insert(0, 'T');
+	}
+	name
+}
+
+// Name for the elements of an array, e.g. "peers" => "peer"
+fn singular(key: &str) -> String {
+	match key.strip_suffix('s') {
+		Some(stem) if !stem.is_empty() && !stem.ends_with('s') => stem.to_string(),
+		_ => format!("{}_item", key)
+	}
+}
@@ -0,0 +1,57 @@
+use std::io::{Read, Write};
+
+use serde::{de, Serialize};
+
+use crate::de::from_reader_limited;
+use crate::error::Result;
+use crate::ser::to_writer;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+	#[cfg(feature = "gzip")]
+	Gzip,
+	#[cfg(feature = "zstd")]
+	Zstd
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// The read budget applies to the decompressed stream, so a small compressed
+// payload can't expand into an unbounded amount of work (BudgetExceeded)
+pub fn from_reader_compressed<T, R>(reader: R, compression: Compression, max_decompressed: u64) -> Result<T>
+where
+	T: de::DeserializeOwned,
+	R: Read
+{
+	match compression {
+		#[cfg(feature = "gzip")]
+		Compression::Gzip => from_reader_limited(flate2::read::GzDecoder::new(reader), max_decompressed),
+		#[cfg(feature = "zstd")]
+		Compression::Zstd => from_reader_limited(zstd::stream::read::Decoder::new(reader)?, max_decompressed)
+	}
+}
+
+pub fn to_writer_compressed<T, W>(writer: W, value: &T, compression: Compression) -> Result<()>
+where
+	T: Serialize,
+	W: Write
+{
+	match compression {
+		#[cfg(feature = "gzip")]
+		Compression::Gzip => {
+			let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+			to_writer(&mut encoder, value)?;
+			encoder.finish()?;
+		},
+		#[cfg(feature = "zstd")]
+		Compression::Zstd => {
+			let mut encoder = zstd::stream::write::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+			to_writer(&mut encoder, value)?;
+			encoder.finish()?;
+		}
+	}
+
+	Ok(())
+}
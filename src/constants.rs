@@ -1,8 +1,37 @@
+use std::fmt;
+
+use crate::error::{Error, ErrorKind, Result, epee_err};
+
 pub const PORTABLE_STORAGE_SIGNATUREA: u32 = 0x01011101;
 pub const PORTABLE_STORAGE_SIGNATUREB: u32 = 0x01020101;
 pub const PORTABLE_STORAGE_FORMAT_VER: u8 = 0x01;
 pub const PORTABLE_STORAGE_SIGNATURE_SIZE: usize = 9;
-pub const PORTABLE_STORAGE_SIGNATURE: [u8; PORTABLE_STORAGE_SIGNATURE_SIZE] = [0x01, 0x11, 0x01, 0x01, 0x01, 0x01, 0x02, 0x01, 0x01];
+pub const PORTABLE_STORAGE_SIGNATURE: [u8; PORTABLE_STORAGE_SIGNATURE_SIZE] = make_signature(
+	PORTABLE_STORAGE_SIGNATUREA,
+	PORTABLE_STORAGE_SIGNATUREB,
+	PORTABLE_STORAGE_FORMAT_VER
+);
+
+// Wire form of a signature: both u32s in little endian, then the version byte
+pub const fn make_signature(signature_a: u32, signature_b: u32, version: u8) -> [u8; PORTABLE_STORAGE_SIGNATURE_SIZE] {
+	let a = signature_a.to_le_bytes();
+	let b = signature_b.to_le_bytes();
+	[a[0], a[1], a[2], a[3], b[0], b[1], b[2], b[3], version]
+}
+
+// Inverse of make_signature
+pub const fn split_signature(signature: &[u8; PORTABLE_STORAGE_SIGNATURE_SIZE]) -> (u32, u32, u8) {
+	let a = u32::from_le_bytes([signature[0], signature[1], signature[2], signature[3]]);
+	let b = u32::from_le_bytes([signature[4], signature[5], signature[6], signature[7]]);
+	(a, b, signature[8])
+}
+
+// Both forms have to agree, whatever the constants above are set to
+const _: () = {
+	let (a, b, version) = split_signature(&PORTABLE_STORAGE_SIGNATURE);
+	assert!(a == PORTABLE_STORAGE_SIGNATUREA && b == PORTABLE_STORAGE_SIGNATUREB && version == PORTABLE_STORAGE_FORMAT_VER);
+	assert!(PORTABLE_STORAGE_SIGNATURE_SIZE == 2 * std::mem::size_of::<u32>() + 1);
+};
 
 pub const SERIALIZE_TYPE_UNKNOWN:u8 =       0;
 pub const SERIALIZE_TYPE_INT64  :u8 =       1;
@@ -21,7 +50,176 @@ pub const SERIALIZE_TYPE_OBJECT :u8 =      12;
 
 pub const SERIALIZE_FLAG_ARRAY  :u8 =    0x80;
 
-pub const MAX_NUM_SECTION_FIELDS:usize = 10000; // I made this limit up, not related to Monero/EPEE
+pub const EMPTY_ARRAY_TYPE_CODE :u8 = SERIALIZE_TYPE_UINT8; // Element type written for empty sequences
+
+// epee never writes SERIALIZE_TYPE_UNKNOWN, so a zero where a type code belongs
+// usually means the document is zeroed or corrupt from there on. Entries of
+// that type fail with ErrorKind::UnknownTypeCode, since there is no telling how
+// long they are. The one exception is an empty array of unknown type, which
+// has no payload: it is skipped wherever unknown entries are skipped (lenient
+// decoding, skip_entry) and left out by the native Section decoders.
+pub const EMPTY_UNKNOWN_ARRAY_TYPE_CODE:u8 = SERIALIZE_TYPE_UNKNOWN | SERIALIZE_FLAG_ARRAY;
+
+pub const MAX_NUM_SECTION_FIELDS:usize = MONEROD_OBJECT_FIELD_LIMIT; // Kept for compatibility, see limits::Limits
 pub const MAX_SECTION_KEY_SIZE:  usize =  255;
 pub const MAX_STRING_LEN_POSSIBLE:usize = 2000000000; // "do not let string be so big"
-pub const MAX_STRING_BUFFER_SIZE:usize = 4096; // In order to prevent memory allocation spam
\ No newline at end of file
+pub const MAX_STRING_BUFFER_SIZE:usize = 4096; // In order to prevent memory allocation spam
+
+// Limits used by monerod's portable storage reader (portable_storage_from_bin.h)
+pub const MONEROD_RECURSION_LIMIT:           usize =   100; // EPEE_PORTABLE_STORAGE_RECURSION_LIMIT_INTERNAL
+pub const MONEROD_OBJECT_LIMIT:              usize = 65536; // EPEE_PORTABLE_STORAGE_OBJECT_LIMIT_DEFAULT
+pub const MONEROD_OBJECT_FIELD_LIMIT:        usize = 65536; // EPEE_PORTABLE_STORAGE_OBJECT_FIELD_LIMIT_DEFAULT
+pub const MONEROD_STRING_LIMIT:              usize = 65536; // EPEE_PORTABLE_STORAGE_STRING_LIMIT_DEFAULT
+pub const MONEROD_LEVIN_OBJECT_LIMIT:        usize =  8192; // levin_protocol_handler default_levin_limits
+pub const MONEROD_LEVIN_OBJECT_FIELD_LIMIT:  usize = 16384;
+pub const MONEROD_LEVIN_STRING_LIMIT:        usize = 16384;
+
+///////////////////////////////////////////////////////////////////////////////
+// Section keys                                                              //
+///////////////////////////////////////////////////////////////////////////////
+
+// Section keys must be non-empty and short enough for their one byte length prefix
+pub const fn is_valid_section_key(key: &str) -> bool {
+	!key.is_empty() && key.len() <= MAX_SECTION_KEY_SIZE
+}
+
+// Stricter check for keys which conservative parsers must accept: C style
+// identifiers like the ones the reference implementation's KV_SERIALIZE macros
+// produce, i.e. ASCII letters, digits and underscores, not starting with a digit
+pub const fn is_strict_section_key(key: &str) -> bool {
+	let bytes = key.as_bytes();
+	if !is_valid_section_key(key) || bytes[0].is_ascii_digit() {
+		return false;
+	}
+
+	let mut i = 0;
+	while i < bytes.len() {
+		if !(bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+			return false;
+		}
+		i += 1;
+	}
+	true
+}
+
+// Checks field names (e.g. the results of serde renames) at compile time:
+//
+//     serde_epee::assert_section_keys!("blocks", "missed_tx", "current_height");
+#[macro_export]
+macro_rules! assert_section_keys {
+	($($key:expr),* $(,)?) => {
+		const _: () = {
+			$(
+				assert!($crate::constants::is_valid_section_key($key), "EPEE section keys must be 1 to 255 bytes long");
+			)*
+		};
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Typed type codes                                                          //
+///////////////////////////////////////////////////////////////////////////////
+
+// Typed counterpart to the SERIALIZE_TYPE_* constants above. The array flag is
+// not part of the enum: check it with is_array_flagged() on the raw byte.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum TypeCode {
+	Unknown = SERIALIZE_TYPE_UNKNOWN,
+	Int64 = SERIALIZE_TYPE_INT64,
+	Int32 = SERIALIZE_TYPE_INT32,
+	Int16 = SERIALIZE_TYPE_INT16,
+	Int8 = SERIALIZE_TYPE_INT8,
+	UInt64 = SERIALIZE_TYPE_UINT64,
+	UInt32 = SERIALIZE_TYPE_UINT32,
+	UInt16 = SERIALIZE_TYPE_UINT16,
+	UInt8 = SERIALIZE_TYPE_UINT8,
+	Double = SERIALIZE_TYPE_DOUBLE,
+	String = SERIALIZE_TYPE_STRING,
+	Bool = SERIALIZE_TYPE_BOOL,
+	Object = SERIALIZE_TYPE_OBJECT
+}
+
+impl TypeCode {
+	// Parses a raw type code byte, ignoring the array flag
+	pub fn from_u8(code: u8) -> Result<Self> {
+		match code & !SERIALIZE_FLAG_ARRAY {
+			SERIALIZE_TYPE_UNKNOWN => Ok(Self::Unknown),
+			SERIALIZE_TYPE_INT64 => Ok(Self::Int64),
+			SERIALIZE_TYPE_INT32 => Ok(Self::Int32),
+			SERIALIZE_TYPE_INT16 => Ok(Self::Int16),
+			SERIALIZE_TYPE_INT8 => Ok(Self::Int8),
+			SERIALIZE_TYPE_UINT64 => Ok(Self::UInt64),
+			SERIALIZE_TYPE_UINT32 => Ok(Self::UInt32),
+			SERIALIZE_TYPE_UINT16 => Ok(Self::UInt16),
+			SERIALIZE_TYPE_UINT8 => Ok(Self::UInt8),
+			SERIALIZE_TYPE_DOUBLE => Ok(Self::Double),
+			SERIALIZE_TYPE_STRING => Ok(Self::String),
+			SERIALIZE_TYPE_BOOL => Ok(Self::Bool),
+			SERIALIZE_TYPE_OBJECT => Ok(Self::Object),
+			_ => epee_err!(BadTypeCode { code: code }, "Invalid value: {}", code)
+		}
+	}
+
+	pub fn is_array_flagged(code: u8) -> bool {
+		0 != (code & SERIALIZE_FLAG_ARRAY)
+	}
+
+	pub fn as_u8(self) -> u8 {
+		self as u8
+	}
+
+	// Raw byte for this type code, with the array flag set if requested
+	pub fn to_wire(self, is_array: bool) -> u8 {
+		if is_array { self as u8 | SERIALIZE_FLAG_ARRAY } else { self as u8 }
+	}
+
+	// Number of payload bytes for fixed-width types, None for variable-width ones
+	pub fn wire_size_hint(self) -> Option<usize> {
+		match self {
+			Self::Int64 | Self::UInt64 | Self::Double => Some(8),
+			Self::Int32 | Self::UInt32 => Some(4),
+			Self::Int16 | Self::UInt16 => Some(2),
+			Self::Int8 | Self::UInt8 | Self::Bool => Some(1),
+			Self::Unknown | Self::String | Self::Object => None
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Unknown => "UNKNOWN",
+			Self::Int64 => "INT64",
+			Self::Int32 => "INT32",
+			Self::Int16 => "INT16",
+			Self::Int8 => "INT8",
+			Self::UInt64 => "UINT64",
+			Self::UInt32 => "UINT32",
+			Self::UInt16 => "UINT16",
+			Self::UInt8 => "UINT8",
+			Self::Double => "DOUBLE",
+			Self::String => "STRING",
+			Self::Bool => "BOOL",
+			Self::Object => "OBJECT"
+		}
+	}
+}
+
+impl fmt::Display for TypeCode {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str(self.name())
+	}
+}
+
+impl From<TypeCode> for u8 {
+	fn from(type_code: TypeCode) -> u8 {
+		type_code as u8
+	}
+}
+
+impl TryFrom<u8> for TypeCode {
+	type Error = Error;
+
+	fn try_from(code: u8) -> Result<Self> {
+		Self::from_u8(code)
+	}
+}
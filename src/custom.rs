@@ -0,0 +1,123 @@
+use std::io::{Read, Write};
+
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+
+// Type codes which private protocols may claim for their own scalar types.
+// The reference implementation stops at SERIALIZE_TYPE_ARRAY (13), and the top
+// bit is the array flag, so these can't collide with standard entries.
+pub const CUSTOM_TYPE_CODE_MIN: u8 = 0x40;
+pub const CUSTOM_TYPE_CODE_MAX: u8 = 0x7f;
+
+///////////////////////////////////////////////////////////////////////////////
+// Custom types                                                              //
+///////////////////////////////////////////////////////////////////////////////
+
+// Values which custom types are decoded into and encoded from. Decoded values
+// are handed to serde like the matching primitive (visit_i128, visit_u128 and
+// visit_byte_buf respectively).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomValue {
+	I128(i128),
+	U128(u128),
+	Bytes(Vec<u8>)
+}
+
+// Which serde values the serializer writes as a custom type: i128 and u128 values
+// go to the first type registered for them. Bytes types are only ever decoded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CustomKind {
+	I128,
+	U128,
+	Bytes
+}
+
+impl CustomValue {
+	pub fn kind(&self) -> CustomKind {
+		match self {
+			Self::I128(_) => CustomKind::I128,
+			Self::U128(_) => CustomKind::U128,
+			Self::Bytes(_) => CustomKind::Bytes
+		}
+	}
+}
+
+// Writes a value's payload, i.e. everything after the type code
+pub type EncodeFn = fn(&CustomValue, &mut dyn Write) -> Result<()>;
+
+// Reads exactly one value's payload, which is also how values are skipped
+pub type DecodeFn = fn(&mut dyn Read) -> Result<CustomValue>;
+
+#[derive(Debug, Copy, Clone)]
+pub struct CustomType {
+	pub name: &'static str,
+	pub kind: CustomKind,
+	pub encode: EncodeFn,
+	pub decode: DecodeFn
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Registry                                                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+// Custom types by type code, given to Serializer::with_type_registry and
+// Deserializer::with_type_registry. Only single entries are supported, arrays
+// of custom types fail with BadTypeCode. Skipping a custom value decodes it, so
+// custom values nested inside sections which are skipped as a whole (e.g. by
+// skip_entry or lenient decoding) fail with BadTypeCode too.
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+	types: Vec<(u8, CustomType)>
+}
+
+impl TypeRegistry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Fails with BadTypeCode if code is outside of the custom range or taken
+	pub fn register(mut self, code: u8, custom_type: CustomType) -> Result<Self> {
+		if !(CUSTOM_TYPE_CODE_MIN..=CUSTOM_TYPE_CODE_MAX).contains(&code) {
+			return epee_err!(BadTypeCode { code: code }, "custom type codes must be within {:#x}-{:#x}", CUSTOM_TYPE_CODE_MIN, CUSTOM_TYPE_CODE_MAX);
+		} else if self.get(code).is_some() {
+			return epee_err!(BadTypeCode { code: code }, "type code {:#x} is already registered", code);
+		}
+
+		self.types.push((code, custom_type));
+		Ok(self)
+	}
+
+	pub fn get(&self, code: u8) -> Option<&CustomType> {
+		self.types.iter().find(|(c, _)| *c == code).map(|(_, custom_type)| custom_type)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.types.is_empty()
+	}
+
+	// Type code and type which values of the given kind are written as
+	pub fn for_kind(&self, kind: CustomKind) -> Option<(u8, &CustomType)> {
+		self.types.iter().find(|(_, custom_type)| custom_type.kind == kind).map(|(code, custom_type)| (*code, custom_type))
+	}
+
+	// Payload of value, checking that the encoder wrote something
+	pub(crate) fn encode(&self, value: &CustomValue) -> Result<(u8, Vec<u8>)> {
+		let (code, custom_type) = match self.for_kind(value.kind()) {
+			Some(found) => found,
+			None => return epee_err!(SerdeModelUnsupported, "no custom type is registered for {:?} values", value.kind())
+		};
+
+		let mut payload = Vec::new();
+		(custom_type.encode)(value, &mut payload)?;
+		Ok((code, payload))
+	}
+}
+
+// Whether a raw type code byte is a custom one, array flag included
+pub fn is_custom_type_code(code: u8) -> bool {
+	(CUSTOM_TYPE_CODE_MIN..=CUSTOM_TYPE_CODE_MAX).contains(&(code & !constants::SERIALIZE_FLAG_ARRAY))
+}
+
+pub(crate) fn unsupported_custom_array(code: u8) -> Error {
+	Error::new(ErrorKind::BadTypeCode { code }, format!("arrays of custom type {:#x} are not supported", code & !constants::SERIALIZE_FLAG_ARRAY))
+}
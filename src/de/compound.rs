@@ -0,0 +1,297 @@
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess};
+
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::trace::{self, TraceLabel};
+use crate::types::{EpeeEntryType, EpeeScalarType};
+
+use super::read::EpeeRead;
+use super::skip::skip_entry_tracked;
+use super::{DeserState, Deserializer, PathSegment, format_path, record};
+
+pub(super) struct EpeeCompound<'a, R, S: AsMut<[u8]>> {
+	deserializer: &'a mut Deserializer<R, S>,
+	declared: u64, // entry count from the stream
+	remaining: u64,
+	index: u64,
+	started: bool,
+	size_hint: Option<usize>, // size hint provided at compile-time (used by structs & tuples)
+	array_type: Option<EpeeScalarType>, // if == None, then this compound is a section,
+	is_root: bool,
+	known_fields: Option<&'static [&'static str]> // other keys are skipped, see with_lenient
+}
+
+impl<'a, 'de, R: EpeeRead<'de>, S: AsMut<[u8]>> EpeeCompound<'a, R, S> {
+	pub(super) fn new_section(deserializer: &'a mut Deserializer<R, S>, size_hint: Option<usize>) -> Self {
+		let known_fields = deserializer.struct_fields.take();
+		Self {
+			deserializer,
+			declared: 0,
+			remaining: 0,
+			index: 0,
+			started: false,
+			size_hint,
+			array_type: None,
+			is_root: false,
+			known_fields
+		}
+	}
+
+	pub(super) fn new_root_section(deserializer: &'a mut Deserializer<R, S>, size_hint: Option<usize>) -> Self {
+		let known_fields = deserializer.struct_fields.take();
+		Self {
+			deserializer,
+			declared: 0,
+			remaining: 0,
+			index: 0,
+			started: false,
+			size_hint,
+			array_type: None,
+			is_root: true,
+			known_fields
+		}
+	}
+
+	pub(super) fn new_array(deserializer: &'a mut Deserializer<R, S>, size_hint: Option<usize>, array_type: EpeeScalarType) -> Self {
+		let known_fields = deserializer.struct_fields.take();
+		Self {
+			deserializer,
+			declared: 0,
+			remaining: 0,
+			index: 0,
+			started: false,
+			size_hint,
+			array_type: Some(array_type),
+			is_root: false,
+			known_fields
+		}
+	}
+
+	fn validate_signature(&mut self) -> Result<bool> {
+		let mut sigbuf = [0u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE];
+		let offset = self.deserializer.reader.offset();
+		self.deserializer.read_raw(&mut sigbuf)?;
+		let good_signature = sigbuf == self.deserializer.profile.signature();
+		let meaning = || if good_signature { trace::describe(TraceLabel::Signature, &sigbuf) } else { String::from("unexpected signature") };
+		record(&mut self.deserializer.trace, &self.deserializer.path, offset, TraceLabel::Signature, &sigbuf, meaning);
+		Ok(good_signature)
+	}
+
+	fn start_if_necessary(&mut self) -> Result<()> {
+		if self.started {
+			return Ok(());
+		}
+
+		if self.is_root && self.deserializer.optional_signature {
+			let signature = self.deserializer.profile.signature();
+			if self.deserializer.reader.peek_up_to(signature.len())? == signature {
+				self.validate_signature()?;
+			}
+		} else if self.is_root {
+			let good_signature = self.validate_signature()?;
+			if !good_signature {
+				return epee_err!(ExpectedFormatSignature);
+			}
+		}
+
+		// Get length from stream
+		self.remaining = self.deserializer.parse_varint()?.into();
+		self.declared = self.remaining;
+
+		match self.array_type {
+			Some(scalar_type) => {
+				self.deserializer.tracker.add_values(scalar_type, self.remaining)?;
+				// Every element takes at least a byte, strings and sections are
+				// charged again for what is inside of them
+				let element_size = scalar_type.fixed_size().unwrap_or(1) as u64;
+				self.deserializer.tracker.add_alloc_bytes(self.remaining.saturating_mul(element_size))?;
+			},
+			None => self.deserializer.tracker.enter_section(self.remaining)?
+		}
+
+		if let Some(size_hint) = self.size_hint {
+			if size_hint as u64 != self.remaining {
+				return epee_err!(SizeHintMismatch { expected: size_hint as u64, found: self.remaining }, "Deserialized length {} does not match size hint {}", self.remaining, size_hint);
+			}
+		}
+
+		self.started = true;
+
+		Ok(())
+	}
+
+	fn done(&self) -> bool {
+		self.remaining == 0
+	}
+
+	// Counts off the next entry. Callers check done first, so this only fails
+	// if they don't.
+	fn take_entry(&mut self) -> Result<()> {
+		self.remaining = match self.remaining.checked_sub(1) {
+			Some(remaining) => remaining,
+			None => return epee_err!(LengthOverflow { len: self.declared }, "read past the {} declared entries", self.declared)
+		};
+		Ok(())
+	}
+
+	// Entries taken so far
+	fn taken(&self) -> u64 {
+		self.declared.saturating_sub(self.remaining)
+	}
+
+	// Running out of input in the middle of an entry means the declared count
+	// was wrong, so say so at the compound instead of deep inside a key or value.
+	// Only the innermost compound reclassifies, the ones around it see the result.
+	fn check_truncated(&self, err: Error) -> Error {
+		self.check_truncated_after(err, self.taken().saturating_sub(1))
+	}
+
+	fn check_truncated_after(&self, err: Error, got: u64) -> Error {
+		if !err.is_eof() {
+			return err;
+		}
+
+		let msg = format!("input ended after {} of {} entries", got, self.declared);
+		err.reclassify(ErrorKind::TruncatedCompound { expected: self.declared, got }, msg)
+			.with_path(&format_path(&self.deserializer.path))
+	}
+
+	fn skip_field(&mut self) -> Result<()> {
+		self.deserializer.read_key()?;
+		let type_code = self.deserializer.parse_raw_type_code()?;
+		self.deserializer.skip_traced(|reader, tracker| skip_entry_tracked(reader, type_code, tracker))
+	}
+
+	// Called once the visitor is done with the compound. Entries it didn't read
+	// would be parsed as whatever comes after the compound, so they are an error.
+	fn end(mut self) -> Result<()> {
+		self.start_if_necessary()?;
+		if !self.done() {
+			let got = self.taken();
+			return epee_err!(SurplusEntries { expected: self.declared, got: got }, "only {} of {} entries were read", got, self.declared);
+		}
+		if self.is_root {
+			self.deserializer.state = DeserState::Done;
+		}
+		Ok(())
+	}
+
+	// Consumes fields whose keys aren't in known_fields, up to the next known one
+	fn skip_unknown_fields(&mut self) -> Result<()> {
+		let known_fields = match self.known_fields {
+			Some(known_fields) => known_fields,
+			None => return Ok(())
+		};
+
+		while !self.done() {
+			self.deserializer.state = DeserState::ExpectingKey;
+			let peeked = self.deserializer.peek_key().map_err(|err| self.check_truncated_after(err, self.taken()))?;
+			match peeked {
+				Some(key) if !known_fields.contains(&key.as_str()) => {},
+				_ => break
+			}
+
+			self.take_entry()?;
+			self.skip_field().map_err(|err| self.check_truncated(err))?;
+			self.deserializer.note_entries(1);
+		}
+		Ok(())
+	}
+}
+
+// Hands compound to a visitor, then checks that the visitor read all of it
+pub(super) fn visit_compound<'a, 'de, R, S, T, F>(mut compound: EpeeCompound<'a, R, S>, visit: F) -> Result<T>
+where
+	R: EpeeRead<'de>,
+	S: AsMut<[u8]>,
+	F: FnOnce(&mut EpeeCompound<'a, R, S>) -> Result<T>
+{
+	let value = visit(&mut compound)?;
+	compound.end()?;
+	Ok(value)
+}
+
+impl<'de, R: EpeeRead<'de>, S: AsMut<[u8]>> SeqAccess<'de> for EpeeCompound<'_, R, S> {
+	type Error = Error;
+
+	// @TODO enforce that types are homogenous
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		self.start_if_necessary()?;
+
+		if self.done() {
+			return Ok(None);
+		}
+
+		self.take_entry()?;
+
+		if let Some(array_type) = self.array_type {
+			self.deserializer.state = DeserState::ExpectingScalar(array_type);
+			self.deserializer.last_type = Some(EpeeEntryType::new(array_type, false));
+			self.deserializer.path.push(PathSegment::Index(self.index));
+			self.index += 1;
+			let res = seed.deserialize(&mut *self.deserializer).map(Some)
+				.map_err(|err| self.deserializer.annotate_error(err));
+			self.deserializer.path.pop();
+			self.deserializer.note_entries(1);
+			let res = res.map_err(|err| self.check_truncated(err));
+
+			if self.done() {
+				self.deserializer.state = DeserState::ExpectingKey;
+			}
+
+			res
+		} else {
+			epee_err!(CompoundMissingArrayType)
+		}
+	}
+}
+
+impl<'de, R: EpeeRead<'de>, S: AsMut<[u8]>> MapAccess<'de> for EpeeCompound<'_, R, S> {
+	type Error = Error;
+
+	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+	where
+		K: DeserializeSeed<'de>,
+	{
+		self.start_if_necessary()?;
+		self.skip_unknown_fields()?;
+
+		if self.done() {
+			self.deserializer.tracker.leave_section();
+			if self.is_root {
+				self.deserializer.note_done();
+			}
+			return Ok(None)
+		}
+
+		self.take_entry()?;
+
+		self.deserializer.state = DeserState::ExpectingKey;
+		let res = seed.deserialize(&mut *self.deserializer).map(Some);
+		self.deserializer.state = DeserState::ExpectingEntry;
+
+		res.map_err(|err| self.check_truncated(err))
+	}
+
+	fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+	where
+		V: DeserializeSeed<'de>,
+	{
+		self.deserializer.state = DeserState::ExpectingEntry;
+		let spare = self.deserializer.spare_keys.pop().unwrap_or_default();
+		let key = std::mem::replace(&mut self.deserializer.last_key, spare);
+		self.deserializer.path.push(PathSegment::Key(key));
+		let res = seed.deserialize(&mut *self.deserializer)
+			.map_err(|err| self.deserializer.annotate_error(err));
+		if let Some(PathSegment::Key(key)) = self.deserializer.path.pop() {
+			// Hand the buffers back for reuse
+			let spare = std::mem::replace(&mut self.deserializer.last_key, key);
+			self.deserializer.spare_keys.push(spare);
+		}
+		self.deserializer.note_entries(1);
+		res.map_err(|err| self.check_truncated(err))
+	}
+}
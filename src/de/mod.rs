@@ -0,0 +1,1364 @@
+// @TODO Non UTF-8 string support is sketchy
+
+use std::io::{BufRead, Read};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde::de::{self, Visitor};
+
+use crate::constants;
+use crate::custom::{self, CustomType, CustomValue, TypeRegistry};
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::format::FormatProfile;
+use crate::limits::{LimitTracker, Limits};
+use crate::trace::{self, TraceLabel, TraceRecord};
+use crate::types::{EpeeEntryType, EpeeScalarType};
+use crate::VarInt;
+
+mod compound;
+pub mod read;
+mod skip;
+
+use compound::{EpeeCompound, visit_compound};
+use read::{BufIoRead, CaptureReader, EpeeRead, IoRead, LimitedReader, SliceRead};
+#[cfg(feature = "bytes")]
+use skip::skip_bytes;
+use skip::{skip_entry_tracked, skip_scalar};
+pub use skip::skip_entry;
+pub(crate) use skip::{capture_document, capture_document_into, skip_empty_unknown_array};
+
+// Largest integer JavaScript numbers hold exactly (Number.MAX_SAFE_INTEGER),
+// see Deserializer::with_js_safe_ints
+pub const JS_MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+// Longest decimal integer with_js_safe_ints reads back: 39 digits and a sign
+const MAX_INT_STRING_LEN: usize = 40;
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions  (use these if you're new here)                            //
+///////////////////////////////////////////////////////////////////////////////
+
+pub fn from_reader<T, R>(reader: R) -> Result<T>
+where
+	T: de::DeserializeOwned,
+	R: Read
+{
+	let mut deserializer = Deserializer::from_reader(reader);
+	T::deserialize(&mut deserializer)
+}
+
+// Like from_reader, but fails with BudgetExceeded instead of reading more than
+// max_bytes bytes from the reader, or allocating for strings and arrays which
+// declare more than that
+pub fn from_reader_limited<T, R>(reader: R, max_bytes: u64) -> Result<T>
+where
+	T: de::DeserializeOwned,
+	R: Read
+{
+	let mut deserializer = Deserializer::from_reader(LimitedReader::new(reader, max_bytes)).with_frame_len(max_bytes);
+	let res = T::deserialize(&mut deserializer);
+
+	match res {
+		Err(_) if deserializer.get_ref().exceeded => epee_err!(BudgetExceeded { max_bytes: max_bytes }, "tried to read more than {} bytes", max_bytes),
+		Err(e) if matches!(e.kind(), ErrorKind::AllocTooLarge { max, .. } if max == max_bytes) => {
+			epee_err!(BudgetExceeded { max_bytes: max_bytes }, "document declared more than {} bytes", max_bytes)
+		},
+		_ => res
+	}
+}
+
+// Reads a document off the front of bytes, which is left pointing at whatever
+// follows it
+pub fn from_bytes<'a, T>(bytes: &mut &'a [u8]) -> Result<T>
+where
+	T: Deserialize<'a>,
+{
+	let mut deserializer = Deserializer::from_slice(bytes);
+	let res = T::deserialize(&mut deserializer);
+	*bytes = deserializer.into_inner();
+	res
+}
+
+// Reads the single document which makes up all of bytes, failing with
+// ExpectedEnd if anything follows it. Use from_bytes to read a document off
+// the front of a slice and keep the rest.
+pub fn from_slice<'a, T>(bytes: &'a [u8]) -> Result<T>
+where
+	T: Deserialize<'a>,
+{
+	let mut deserializer = Deserializer::from_slice(bytes);
+	let value = T::deserialize(&mut deserializer)?;
+	let rest = deserializer.into_inner();
+	if !rest.is_empty() {
+		return epee_err!(ExpectedEnd, "{} bytes left over after document", rest.len());
+	}
+	Ok(value)
+}
+
+// Like from_slice, for buffers which may or may not be owned
+pub fn from_cow<T>(bytes: std::borrow::Cow<'_, [u8]>) -> Result<T>
+where
+	T: de::DeserializeOwned
+{
+	from_slice(&bytes)
+}
+
+// Like from_reader, but fields which the target structs don't declare are
+// skipped even if they deny unknown fields, see Deserializer::with_lenient
+pub fn from_reader_lenient<T, R>(reader: R) -> Result<T>
+where
+	T: de::DeserializeOwned,
+	R: Read
+{
+	let mut deserializer = Deserializer::from_reader(reader).with_lenient();
+	T::deserialize(&mut deserializer)
+}
+
+// Reads a single key/value entry as written by ser::write_entry, i.e. without
+// a signature or section header around it
+pub fn read_entry<T, R>(reader: R) -> Result<(String, T)>
+where
+	T: de::DeserializeOwned,
+	R: Read
+{
+	let mut deserializer = Deserializer::from_reader(reader);
+	let key = deserializer.parse_string_key()?;
+	deserializer.state = DeserState::ExpectingEntry;
+	let value = T::deserialize(&mut deserializer)?;
+	Ok((key, value))
+}
+
+// Reads len bytes into the scratch buffer, or returns None if there is none
+fn read_scratch<'s, R: Read, S: AsMut<[u8]>>(reader: &mut R, scratch: &'s mut Option<S>, len: usize) -> Result<Option<&'s [u8]>> {
+	match scratch {
+		Some(scratch) => {
+			let scratch = scratch.as_mut();
+			if len > scratch.len() {
+				return epee_err!(ScratchOverflow { len: len as u64, capacity: scratch.len() as u64 }, "{} byte string does not fit in {} byte scratch buffer", len, scratch.len());
+			}
+			reader.read_exact(&mut scratch[..len])?;
+			Ok(Some(&scratch[..len]))
+		},
+		None => Ok(None)
+	}
+}
+
+// Key path of a value, e.g. "blocks[2].txs"
+fn format_path(path: &[PathSegment]) -> String {
+	let mut formatted = String::new();
+	for segment in path {
+		match segment {
+			PathSegment::Key(key) if formatted.is_empty() => formatted.push_str(key),
+			PathSegment::Key(key) => { formatted.push('.'); formatted.push_str(key); },
+			PathSegment::Index(index) => formatted.push_str(&format!("[{}]", index))
+		}
+	}
+	formatted
+}
+
+// Free function so that it can be called while other fields are borrowed
+#[inline]
+fn record<F>(trace: &mut Option<Vec<TraceRecord>>, path: &[PathSegment], offset: u64, label: TraceLabel, bytes: &[u8], meaning: F)
+where
+	F: FnOnce() -> String
+{
+	if let Some(trace) = trace {
+		trace.push(TraceRecord {
+			offset: offset as usize,
+			label,
+			path: format_path(path),
+			bytes: bytes.to_vec(),
+			meaning: meaning()
+		});
+	}
+}
+
+fn key_from_utf8(raw_key: &[u8]) -> Result<&str> {
+	match std::str::from_utf8(raw_key) {
+		Ok(key) => Ok(key),
+		Err(_) => epee_err!(StringBadEncoding, "UTF-8 encoding error while parsing byte buffer for string key")
+	}
+}
+
+fn is_integer(scalar_type: EpeeScalarType) -> bool {
+	matches!(scalar_type,
+		EpeeScalarType::Int64 | EpeeScalarType::Int32 | EpeeScalarType::Int16 | EpeeScalarType::Int8 |
+		EpeeScalarType::UInt64 | EpeeScalarType::UInt32 | EpeeScalarType::UInt16 | EpeeScalarType::UInt8)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Deserializer definition                                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+enum DeserState {
+	ExpectingSection(bool), // true if expecting root section, false otherwise 
+	ExpectingKey,
+	ExpectingEntry,
+	ExpectingScalar(EpeeScalarType),
+	Done
+}
+
+// What happens to integers which don't fit the integer type they are read into,
+// e.g. a negative INT64 read into a u64
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum IntConversion {
+	#[default]
+	Checked, // fail with IntOutOfRange
+	Wrapping, // keep the low bits, like an `as` cast
+	Saturating // clamp to the target type's MIN or MAX
+}
+
+// Snapshot handed to the callback of Deserializer::with_progress
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Progress {
+	pub bytes_consumed: u64,
+	pub entries_parsed: u64, // section fields and array elements, skipped ones included
+	pub done: bool // the root section was read completely
+}
+
+struct ProgressHook {
+	callback: Box<dyn FnMut(Progress) + Send>,
+	interval: u64,
+	next_report: u64,
+	entries: u64
+}
+
+enum PathSegment {
+	Key(String),
+	Index(u64)
+}
+
+// R is the input, see read::EpeeRead. S is the type of an optional caller
+// supplied scratch buffer, see with_scratch.
+pub struct Deserializer<R, S: AsMut<[u8]> = Vec<u8>> {
+	reader: R,
+	state: DeserState,
+	profile: FormatProfile,
+	tracker: LimitTracker,
+	scratch: Option<S>,
+	path: Vec<PathSegment>, // keys and indices leading to the current value, for errors
+	last_key: String,
+	spare_keys: Vec<String>, // key buffers of sections left, reused by the next ones entered
+	last_type: Option<EpeeEntryType>,
+	trace: Option<Vec<TraceRecord>>,
+	lenient: bool,
+	optional_signature: bool,
+	blob_seqs: bool,
+	want_str: bool, // the visitor asked for a str, so hand over valid UTF-8 as one
+	want_span: bool, // the visitor wants a blob as a slice of shared_input, see buf::SharedBlob
+	#[cfg(feature = "bytes")]
+	shared_input: Option<bytes::Bytes>, // everything reader reads, see with_shared_input
+	int_conversion: IntConversion,
+	registry: Option<Arc<TypeRegistry>>,
+	progress: Option<ProgressHook>,
+	multi_doc: bool, // another root section may follow the first, see with_multi_doc
+	empty_section_defaults: bool, // see with_empty_section_defaults
+	js_safe_ints: bool, // see with_js_safe_ints
+	struct_fields: Option<&'static [&'static str]> // fields of the struct being entered, if lenient
+}
+
+// Defines a method which parses a certain primitive number type raw from stream
+// All the primitive types have a nofail from_le_bytes method but there is no trait
+macro_rules! define_parse_num {
+	( $fname:ident, $numtype:ty ) => {
+		#[inline]
+		fn $fname(&mut self) -> Result<$numtype>
+		{
+			const NBYTES: usize = std::mem::size_of::<$numtype>();
+			let mut le_bytes = [0u8; NBYTES];
+			let offset = self.reader.offset();
+			self.read_raw(&mut le_bytes)?;
+			let num = <$numtype>::from_le_bytes(le_bytes);
+			record(&mut self.trace, &self.path, offset, TraceLabel::Payload, &le_bytes, || num.to_string());
+			Ok(num)
+		}
+	}
+}
+
+// Defines a method to implement serde::Deserializer. All defs are the same since
+// we ignore type hints from deserialize instance since epee is self-describing
+macro_rules! define_simple_deser {
+	( $fname:ident ) => {
+		fn $fname<V>(self, visitor: V) -> Result<V::Value>
+		where
+			V: Visitor<'de>
+		{
+			self.deserialize_any(visitor)
+		}
+	}
+}
+
+// Like define_simple_deser, but visits default if the value is an empty section
+// and with_empty_section_defaults is on
+macro_rules! define_defaulting_deser {
+	( $fname:ident, $visit:ident, $default:expr ) => {
+		fn $fname<V>(self, visitor: V) -> Result<V::Value>
+		where
+			V: Visitor<'de>
+		{
+			if self.skip_empty_section()? {
+				return visitor.$visit($default);
+			}
+			self.deserialize_any(visitor)
+		}
+	}
+}
+
+// Defines a method which reads any integer entry into a certain integer type,
+// converting it according to int_conversion. Other entries go to deserialize_any.
+macro_rules! define_int_deser {
+	( $fname:ident, $numtype:ty, $visit:ident ) => {
+		fn $fname<V>(self, visitor: V) -> Result<V::Value>
+		where
+			V: Visitor<'de>
+		{
+			if self.skip_empty_section()? {
+				return visitor.$visit(0);
+			}
+			let value = match self.next_integer()? {
+				Some(value) => value,
+				None => return self.deserialize_any(visitor)
+			};
+
+			let converted = match self.int_conversion {
+				IntConversion::Wrapping => value as $numtype,
+				// Not clamped in i128, which u128::MAX doesn't fit in
+				IntConversion::Saturating => match <$numtype>::try_from(value) {
+					Ok(converted) => converted,
+					Err(_) if value < 0 => <$numtype>::MIN,
+					Err(_) => <$numtype>::MAX
+				},
+				IntConversion::Checked => match <$numtype>::try_from(value) {
+					Ok(converted) => converted,
+					Err(_) => return epee_err!(IntOutOfRange { value: value }, "{} does not fit in {}", value, stringify!($numtype))
+				}
+			};
+			visitor.$visit(converted)
+		}
+	}
+}
+
+impl<R: Read> Deserializer<IoRead<R>> {
+	///////////////////////////////////////////////////////////////////////////////
+	// Constructors                                                              //
+	///////////////////////////////////////////////////////////////////////////////
+	pub fn from_reader(reader: R) -> Self {
+		Self::new(IoRead::new(reader))
+	}
+}
+
+impl<R: BufRead> Deserializer<BufIoRead<R>> {
+	// Like from_reader, but peeks into the reader's own buffer
+	pub fn from_buf_read(reader: R) -> Self {
+		Self::new(BufIoRead::new(reader))
+	}
+}
+
+impl<'a> Deserializer<SliceRead<'a>> {
+	// Like from_reader, but peeks without copying, and strings and blobs can be
+	// borrowed by &str and &[u8] fields, or Cow fields with #[serde(borrow)].
+	// Readers of streams make those Cows owned instead. get_ref and into_inner
+	// give the part of bytes which wasn't read yet.
+	pub fn from_slice(bytes: &'a [u8]) -> Self {
+		Self::new(SliceRead::new(bytes))
+	}
+}
+
+impl<'de, R: EpeeRead<'de>> Deserializer<R> {
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			state: DeserState::ExpectingSection(true),
+			profile: FormatProfile::monero(),
+			tracker: LimitTracker::default(),
+			scratch: None,
+			path: Vec::new(),
+			last_key: String::new(),
+			spare_keys: Vec::new(),
+			last_type: None,
+			trace: None,
+			lenient: false,
+			optional_signature: false,
+			blob_seqs: true,
+			want_str: false,
+			want_span: false,
+			#[cfg(feature = "bytes")]
+			shared_input: None,
+			int_conversion: IntConversion::Checked,
+			registry: None,
+			progress: None,
+			multi_doc: false,
+			empty_section_defaults: false,
+			js_safe_ints: false,
+			struct_fields: None
+		}
+	}
+}
+
+impl<'de, R: EpeeRead<'de>, S: AsMut<[u8]>> Deserializer<R, S> {
+	// Reads section keys and string values into the given buffer (e.g. a
+	// &mut [u8] on the stack) instead of allocating them. Keys or strings which
+	// don't fit fail with ScratchOverflow, so the buffer size also acts as a
+	// string length limit. Only visitors which accept borrowed strs and bytes
+	// are allocation free, owned targets like String still copy out of it.
+	// Values never borrow from slice inputs when scratch is used.
+	pub fn with_scratch<T: AsMut<[u8]>>(self, scratch: T) -> Deserializer<R, T> {
+		Deserializer {
+			reader: self.reader,
+			state: self.state,
+			profile: self.profile,
+			tracker: self.tracker,
+			scratch: Some(scratch),
+			path: self.path,
+			last_key: self.last_key,
+			spare_keys: self.spare_keys,
+			last_type: self.last_type,
+			trace: self.trace,
+			lenient: self.lenient,
+			optional_signature: self.optional_signature,
+			blob_seqs: self.blob_seqs,
+			want_str: self.want_str,
+			want_span: self.want_span,
+			#[cfg(feature = "bytes")]
+			shared_input: self.shared_input,
+			int_conversion: self.int_conversion,
+			registry: self.registry,
+			progress: self.progress,
+			multi_doc: self.multi_doc,
+			empty_section_defaults: self.empty_section_defaults,
+			js_safe_ints: self.js_safe_ints,
+			struct_fields: self.struct_fields
+		}
+	}
+
+	pub fn with_limits(mut self, limits: Limits) -> Self {
+		self.tracker = LimitTracker::new(limits);
+		self
+	}
+
+	pub fn limits(&self) -> &Limits {
+		self.tracker.limits()
+	}
+
+	// Caps Limits::max_alloc_bytes at the length of the transport frame the
+	// document came in (e.g. a levin payload), since a document can't declare
+	// more strings and arrays than the bytes it takes up. Lengths which couldn't
+	// fit fail with AllocTooLarge before anything is allocated for them. Call
+	// after with_limits, which replaces all of the limits.
+	pub fn with_frame_len(self, frame_len: u64) -> Self {
+		let limits = *self.limits();
+		let max_alloc_bytes = std::cmp::min(limits.max_alloc_bytes as u64, frame_len) as usize;
+		self.with_limits(Limits { max_alloc_bytes, ..limits })
+	}
+
+	pub fn with_profile(mut self, profile: FormatProfile) -> Self {
+		self.profile = profile;
+		self
+	}
+
+	pub fn profile(&self) -> &FormatProfile {
+		&self.profile
+	}
+
+	// Records every read with its offset, role and decoded meaning, see
+	// trace::Trace. Records are kept when decoding fails, so take_trace shows
+	// how far a truncated or corrupt document could be read.
+	pub fn with_trace(mut self) -> Self {
+		self.trace = Some(Vec::new());
+		self
+	}
+
+	pub fn take_trace(&mut self) -> Option<Vec<TraceRecord>> {
+		self.trace.as_mut().map(std::mem::take)
+	}
+
+	// Skips section fields which the target struct doesn't know before serde
+	// sees them, so that documents written by a newer schema also decode into
+	// structs with #[serde(deny_unknown_fields)]
+	pub fn with_lenient(mut self) -> Self {
+		self.lenient = true;
+		self
+	}
+
+	// Reads the root section whether or not it starts with the format signature,
+	// for signature-less sections embedded in wallet caches and RPC blobs. A
+	// section body can't start with the signature in practice: its first bytes
+	// would declare 1092 fields, the first with a one byte key.
+	pub fn with_optional_signature(mut self) -> Self {
+		self.optional_signature = true;
+		self
+	}
+
+	// Whether sequences and tuples of u8 (e.g. Vec<u8> or [u8; 32]) also decode
+	// from STRING entries, one element per byte, since producers disagree on
+	// whether byte vectors are blobs or UINT8 arrays. On by default. Sequences of
+	// any other element type fail with TypeMismatch on non-empty blobs, use
+	// packed::from_packed_bytes for PODs stored as blobs.
+	pub fn with_blob_seqs(mut self, blob_seqs: bool) -> Self {
+		self.blob_seqs = blob_seqs;
+		self
+	}
+
+	// Lets buf::SharedBlob values slice input instead of copying, see
+	// buf::from_shared_bytes. input must be exactly what reader reads, from its
+	// start.
+	#[cfg(feature = "bytes")]
+	pub(crate) fn with_shared_input(mut self, input: bytes::Bytes) -> Self {
+		self.shared_input = Some(input);
+		self
+	}
+
+	// How integer entries are narrowed or sign converted into integer fields of
+	// another type. Any integer type code is accepted for any integer field.
+	pub fn with_int_conversion(mut self, int_conversion: IntConversion) -> Self {
+		self.int_conversion = int_conversion;
+		self
+	}
+
+	// Decodes entries with the custom type codes in registry, see
+	// custom::TypeRegistry
+	pub fn with_type_registry(mut self, registry: TypeRegistry) -> Self {
+		self.registry = Some(Arc::new(registry));
+		self
+	}
+
+	// Calls callback every time another interval bytes were consumed, and once
+	// more when the root section is done, so that UIs can show how far a large
+	// decode got. Bytes are counted at entry boundaries, so a single huge string
+	// is reported after it was read.
+	pub fn with_progress<F>(mut self, interval: u64, callback: F) -> Self
+	where
+		F: FnMut(Progress) + Send + 'static
+	{
+		self.progress = Some(ProgressHook {
+			callback: Box::new(callback),
+			interval: std::cmp::max(interval, 1),
+			next_report: std::cmp::max(interval, 1),
+			entries: 0
+		});
+		self
+	}
+
+	// Reads concatenated documents: once a root section is done, the next value
+	// deserialized from self is read from another root section, with fresh
+	// limit counters. Without this, deserializing anything after the root
+	// section fails with ExpectedEnd. See also documents.
+	pub fn with_multi_doc(mut self) -> Self {
+		self.multi_doc = true;
+		self
+	}
+
+	// Decodes empty sections into the default of scalar, string, sequence and
+	// option fields (0, false, "", [] and None) instead of failing with
+	// TypeMismatch, since monerod writes some default constructed values as {}.
+	// Sections with fields in them still fail like before, as do fixed size
+	// targets like [u8; 32] which can't be built from an empty sequence.
+	pub fn with_empty_section_defaults(mut self) -> Self {
+		self.empty_section_defaults = true;
+		self
+	}
+
+	// For transcoding to JSON read by JavaScript, whose numbers lose precision
+	// above JS_MAX_SAFE_INTEGER: 64 bit integers beyond it are handed to
+	// untyped visitors (e.g. serde_json::Value, or serde_transcode) as decimal
+	// strings. The other way around, integer fields also decode from STRING
+	// entries holding a decimal integer, like JSON transcoded back to EPEE has.
+	pub fn with_js_safe_ints(mut self) -> Self {
+		self.js_safe_ints = true;
+		self
+	}
+
+	// Whether the root section was read completely
+	pub fn is_done(&self) -> bool {
+		matches!(self.state, DeserState::Done)
+	}
+
+	// Iterates over concatenated documents until the input ends at a document
+	// boundary. Input ending inside of a document is an error like any other.
+	pub fn documents<T: de::DeserializeOwned>(self) -> Documents<R, S, T> {
+		Documents { deserializer: self.with_multi_doc(), failed: false, marker: std::marker::PhantomData }
+	}
+
+	// Key path of the value being decoded, e.g. "txs[2].blob", empty at the root.
+	// Lets code driving the deserializer decide how to decode a value from
+	// where it is in the document.
+	pub fn current_path(&self) -> String {
+		format_path(&self.path)
+	}
+
+	pub fn get_ref(&self) -> &R::Inner {
+		self.reader.get_ref()
+	}
+
+	pub fn get_mut(&mut self) -> &mut R::Inner {
+		self.reader.get_mut()
+	}
+
+	// Bytes which were peeked at but not consumed yet may be lost, see
+	// EpeeRead::into_inner
+	pub fn into_inner(self) -> R::Inner {
+		self.reader.into_inner()
+	}
+
+	// Bytes consumed so far
+	pub fn offset(&self) -> u64 {
+		self.reader.offset()
+	}
+
+	///////////////////////////////////////////////////////////////////////////////
+	// Lookahead                                                                 //
+	///////////////////////////////////////////////////////////////////////////////
+
+	// Type of the next value without consuming any of it, or None if the next
+	// thing on the wire isn't a value (e.g. a key, or the end of the document)
+	pub fn peek_type(&mut self) -> Result<Option<EpeeEntryType>> {
+		match self.state {
+			DeserState::ExpectingSection(_) => Ok(Some(EpeeEntryType::new(EpeeScalarType::Object, false))),
+			DeserState::ExpectingEntry => {
+				let type_code = self.reader.peek(1)?[0];
+				EpeeEntryType::from_type_code(type_code).map(Some)
+			},
+			DeserState::ExpectingScalar(scalar_type) => Ok(Some(EpeeEntryType::new(scalar_type, false))),
+			DeserState::ExpectingKey | DeserState::Done => Ok(None)
+		}
+	}
+
+	// Next section key without consuming it, or None if not positioned at a key
+	pub fn peek_key(&mut self) -> Result<Option<String>> {
+		if let DeserState::ExpectingKey = self.state {
+			let keylen = self.reader.peek(1)?[0] as usize;
+			let raw_key = &self.reader.peek(1 + keylen)?[1..];
+			key_from_utf8(raw_key).map(|key| Some(key.to_string()))
+		} else {
+			Ok(None)
+		}
+	}
+
+	///////////////////////////////////////////////////////////////////////////////
+	// Reading helpers                                                           //
+	///////////////////////////////////////////////////////////////////////////////
+
+	#[inline]
+	fn read_raw(&mut self, buf: &mut [u8]) -> Result<()> {
+		let read_res = self.reader.read_exact(buf);
+		match read_res { 
+			Ok(_) => Ok(()),
+			Err(ioe) => Err(ioe.into())
+			//Err(ioe) => panic!("Error reading {} bytes", buf.len())
+		}
+	}
+
+	// Counts one entry and reports progress if an interval was crossed
+	#[inline]
+	fn note_entries(&mut self, count: u64) {
+		if let Some(progress) = self.progress.as_mut() {
+			progress.entries += count;
+			let consumed = self.reader.offset();
+			if consumed >= progress.next_report {
+				progress.next_report = (consumed / progress.interval + 1).saturating_mul(progress.interval);
+				(progress.callback)(Progress { bytes_consumed: consumed, entries_parsed: progress.entries, done: false });
+			}
+		}
+	}
+
+	// Called before values are read from the top. Once the root section is done,
+	// there is nothing left to read unless another document may follow.
+	fn check_not_done(&mut self) -> Result<()> {
+		if !self.is_done() {
+			return Ok(());
+		} else if !self.multi_doc {
+			return epee_err!(ExpectedEnd, "the root section was already read");
+		}
+
+		self.state = DeserState::ExpectingSection(true);
+		self.tracker = LimitTracker::new(*self.tracker.limits());
+		Ok(())
+	}
+
+	fn note_done(&mut self) {
+		if let Some(progress) = self.progress.as_mut() {
+			(progress.callback)(Progress { bytes_consumed: self.reader.offset(), entries_parsed: progress.entries, done: true });
+		}
+	}
+
+	fn annotate_error(&self, err: Error) -> Error {
+		let err = match self.last_type {
+			Some(wire_type) => err.with_wire_type(wire_type),
+			None => err
+		};
+		err.with_path(&format_path(&self.path))
+	}
+
+	// Runs skip over the reader, recording the skipped bytes as one record
+	fn skip_traced<F>(&mut self, skip: F) -> Result<()>
+	where
+		F: FnOnce(&mut dyn Read, &mut LimitTracker) -> Result<()>
+	{
+		if self.trace.is_none() {
+			return skip(&mut self.reader, &mut self.tracker);
+		}
+
+		let offset = self.reader.offset();
+		let mut capture = CaptureReader { inner: &mut self.reader, captured: Vec::new() };
+		let res = skip(&mut capture, &mut self.tracker);
+		let captured = capture.captured;
+		record(&mut self.trace, &self.path, offset, TraceLabel::Skipped, &captured, || format!("{} bytes", captured.len()));
+		res
+	}
+
+	#[inline]
+	fn read_single(&mut self) -> Result<u8> {
+		let mut single_byte = [0u8];
+		match self.reader.read_exact(&mut single_byte) {
+			Ok(_) => Ok(single_byte[0]),
+			Err(ioe) => Err(ioe.into())
+		}
+	}
+
+	// Registered custom type of the next entry, if it has one
+	fn peek_custom_type(&mut self) -> Result<Option<(u8, CustomType)>> {
+		let registry = match (&self.state, &self.registry) {
+			(DeserState::ExpectingEntry, Some(registry)) => registry,
+			_ => return Ok(None)
+		};
+
+		let type_code = self.reader.peek(1)?[0];
+		if !custom::is_custom_type_code(type_code) {
+			return Ok(None);
+		} else if constants::SERIALIZE_FLAG_ARRAY & type_code != 0 {
+			return Err(custom::unsupported_custom_array(type_code));
+		}
+		Ok(registry.get(type_code).map(|custom_type| (type_code, *custom_type)))
+	}
+
+	// Consumes a custom entry, type code included
+	fn read_custom(&mut self, type_code: u8, custom_type: CustomType) -> Result<CustomValue> {
+		let offset = self.reader.offset();
+		self.read_single()?;
+		record(&mut self.trace, &self.path, offset, TraceLabel::TypeCode, &[type_code], || custom_type.name.to_string());
+
+		let offset = self.reader.offset();
+		let mut capture = CaptureReader { inner: &mut self.reader, captured: Vec::new() };
+		let value = (custom_type.decode)(&mut capture)?;
+		let captured = capture.captured;
+		record(&mut self.trace, &self.path, offset, TraceLabel::Payload, &captured, || format!("{:?}", value));
+		Ok(value)
+	}
+
+	fn deserialize_section_entry<V>(&mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if let Some((type_code, custom_type)) = self.peek_custom_type()? {
+			return match self.read_custom(type_code, custom_type)? {
+				CustomValue::I128(value) => visitor.visit_i128(value),
+				CustomValue::U128(value) => visitor.visit_u128(value),
+				CustomValue::Bytes(value) => visitor.visit_byte_buf(value)
+			};
+		}
+
+		let entry_type = self.parse_type_code()?;
+		self.last_type = Some(entry_type);
+
+		if entry_type.is_array {
+			// Array element counts are charged in EpeeCompound::start_if_necessary
+			visit_compound(EpeeCompound::new_array(self, None, entry_type.scalar_type), |compound| visitor.visit_seq(compound))
+		} else {
+			self.tracker.add_values(entry_type.scalar_type, 1)?;
+			self.state = DeserState::ExpectingScalar(entry_type.scalar_type);
+			self.deserialize_scalar(visitor)
+		}
+	}
+
+	fn deserialize_scalar<V>(&mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if let DeserState::ExpectingScalar(scalar_type) = self.state {
+			match scalar_type {
+				EpeeScalarType::Int64  => match self.parse_i64()? {
+					value if self.js_safe_ints && value.unsigned_abs() > JS_MAX_SAFE_INTEGER => visitor.visit_string(value.to_string()),
+					value => visitor.visit_i64(value)
+				},
+				EpeeScalarType::Int32  => visitor.visit_i32   (self.parse_i32()?),
+				EpeeScalarType::Int16  => visitor.visit_i16   (self.parse_i16()?),
+				EpeeScalarType::Int8   => visitor.visit_i8    (self.parse_i8()?),
+				EpeeScalarType::UInt64 => match self.parse_u64()? {
+					value if self.js_safe_ints && value > JS_MAX_SAFE_INTEGER => visitor.visit_string(value.to_string()),
+					value => visitor.visit_u64(value)
+				},
+				EpeeScalarType::UInt32 => visitor.visit_u32   (self.parse_u32()?),
+				EpeeScalarType::UInt16 => visitor.visit_u16   (self.parse_u16()?),
+				EpeeScalarType::UInt8  => visitor.visit_u8    (self.parse_u8()?),
+				EpeeScalarType::Double => visitor.visit_f64   (self.parse_f64()?),
+				EpeeScalarType::Str    => self.deserialize_string_value(visitor),
+				EpeeScalarType::Bool   => visitor.visit_bool  (self.parse_bool()?),
+				EpeeScalarType::Object => visit_compound(EpeeCompound::new_section(self, None), |compound| visitor.visit_map(compound))
+			}
+		} else {
+			epee_err!(ExpectedScalar)
+		}
+	}
+
+	// Consumes the next value if it is an empty section and those decode into
+	// defaults, see with_empty_section_defaults
+	fn skip_empty_section(&mut self) -> Result<bool> {
+		if !self.empty_section_defaults {
+			return Ok(false);
+		}
+		let is_empty_section = match self.state {
+			DeserState::ExpectingEntry => self.reader.peek_up_to(2)? == [constants::SERIALIZE_TYPE_OBJECT, 0],
+			DeserState::ExpectingScalar(EpeeScalarType::Object) => self.reader.peek_up_to(1)? == [0],
+			_ => false
+		};
+		if is_empty_section {
+			de::Deserializer::deserialize_ignored_any(&mut *self, de::IgnoredAny)?;
+		}
+		Ok(is_empty_section)
+	}
+
+	// Consumes the next value if it is a single integer, otherwise returns None
+	// and leaves it to deserialize_any
+	#[inline]
+	fn next_integer(&mut self) -> Result<Option<i128>> {
+		let scalar_type = match self.state {
+			DeserState::ExpectingEntry => {
+				if self.peek_custom_type()?.is_some() {
+					return Ok(None);
+				}
+				let entry_type = EpeeEntryType::from_type_code(self.reader.peek(1)?[0])?;
+				let is_int_string = self.js_safe_ints && entry_type.scalar_type == EpeeScalarType::Str;
+				if entry_type.is_array || !(is_integer(entry_type.scalar_type) || is_int_string) {
+					return Ok(None);
+				}
+				self.parse_type_code()?;
+				self.last_type = Some(entry_type);
+				// Only does anything for the STRING entries with_js_safe_ints lets through
+				self.tracker.add_values(entry_type.scalar_type, 1)?;
+				entry_type.scalar_type
+			},
+			DeserState::ExpectingScalar(scalar_type) if is_integer(scalar_type) => scalar_type,
+			DeserState::ExpectingScalar(EpeeScalarType::Str) if self.js_safe_ints => EpeeScalarType::Str,
+			_ => return Ok(None)
+		};
+		if scalar_type == EpeeScalarType::Str {
+			return self.parse_int_string().map(Some);
+		}
+
+		let value = match scalar_type {
+			EpeeScalarType::Int64  => self.parse_i64()? as i128,
+			EpeeScalarType::Int32  => self.parse_i32()? as i128,
+			EpeeScalarType::Int16  => self.parse_i16()? as i128,
+			EpeeScalarType::Int8   => self.parse_i8()? as i128,
+			EpeeScalarType::UInt64 => self.parse_u64()? as i128,
+			EpeeScalarType::UInt32 => self.parse_u32()? as i128,
+			EpeeScalarType::UInt16 => self.parse_u16()? as i128,
+			_                      => self.parse_u8()? as i128
+		};
+		Ok(Some(value))
+	}
+
+	// Decimal integer in a STRING, see with_js_safe_ints
+	fn parse_int_string(&mut self) -> Result<i128> {
+		let strsize = self.parse_string_len()?;
+		if strsize > MAX_INT_STRING_LEN {
+			return epee_err!(TypeMismatch, "expected an integer, found a string of {} bytes", strsize);
+		}
+
+		let mut buf = [0u8; MAX_INT_STRING_LEN];
+		let offset = self.reader.offset();
+		self.read_raw(&mut buf[..strsize])?;
+		let raw_str = &buf[..strsize];
+		record(&mut self.trace, &self.path, offset, TraceLabel::Payload, raw_str, || trace::describe_string(raw_str));
+		match std::str::from_utf8(raw_str).ok().and_then(|s| s.parse::<i128>().ok()) {
+			Some(value) => Ok(value),
+			None => epee_err!(TypeMismatch, "expected an integer, found string {:?}", String::from_utf8_lossy(raw_str))
+		}
+	}
+
+	fn at_string_value(&mut self) -> Result<bool> {
+		match self.state {
+			DeserState::ExpectingEntry => Ok(self.reader.peek(1)?[0] == constants::SERIALIZE_TYPE_STRING),
+			DeserState::ExpectingScalar(scalar_type) => Ok(scalar_type == EpeeScalarType::Str),
+			_ => Ok(false)
+		}
+	}
+
+	// Hands a STRING to a sequence visitor byte by byte, see with_blob_seqs
+	fn deserialize_blob_seq<V>(&mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if let DeserState::ExpectingEntry = self.state {
+			let entry_type = self.parse_type_code()?;
+			self.last_type = Some(entry_type);
+			self.tracker.add_values(EpeeScalarType::Str, 1)?;
+			self.state = DeserState::ExpectingScalar(EpeeScalarType::Str);
+		}
+
+		let strsize = self.parse_string_len()?;
+		let offset = self.reader.offset();
+		let mut strbuf = vec![0u8; strsize];
+		self.read_raw(strbuf.as_mut_slice())?;
+		record(&mut self.trace, &self.path, offset, TraceLabel::Payload, &strbuf, || trace::describe_string(&strbuf));
+
+		let mut seq = de::value::SeqDeserializer::<_, Error>::new(strbuf.into_iter().map(BlobByte));
+		let value = visitor.visit_seq(&mut seq)?;
+		seq.end()?;
+		Ok(value)
+	}
+
+	fn deserialize_key<V>(&mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.read_key()?;
+		visitor.visit_str(&self.last_key)
+	}
+
+	fn deserialize_string_value<V>(&mut self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let want_str = std::mem::take(&mut self.want_str);
+		let strsize = self.parse_string_len()?;
+		let offset = self.reader.offset();
+		#[cfg(feature = "bytes")]
+		if std::mem::take(&mut self.want_span) {
+			self.skip_traced(|reader, _| skip_bytes(reader, strsize as u64))?;
+			let blob = match self.shared_input.as_ref() {
+				Some(input) if offset as usize + strsize <= input.len() => input.slice(offset as usize..offset as usize + strsize),
+				_ => return epee_err!(Custom, "blob is outside of the shared input")
+			};
+			let seq = de::value::SeqDeserializer::<_, Error>::new(std::iter::empty::<u8>());
+			return crate::buf::hand_over(blob, || visitor.visit_seq(seq));
+		}
+		if self.scratch.is_none() {
+			if let Some(borrowed) = self.reader.read_borrowed(strsize)? {
+				// Lets &str, &[u8] and #[serde(borrow)] Cow fields point into the input
+				record(&mut self.trace, &self.path, offset, TraceLabel::Payload, borrowed, || trace::describe_string(borrowed));
+				return match std::str::from_utf8(borrowed) {
+					Ok(s) if want_str => visitor.visit_borrowed_str(s),
+					_ => visitor.visit_borrowed_bytes(borrowed)
+				};
+			}
+		}
+		if strsize == 0 {
+			// Nothing to read, and nothing to allocate or fit into scratch
+			record(&mut self.trace, &self.path, offset, TraceLabel::Payload, &[], || trace::describe_string(&[]));
+			return if want_str { visitor.visit_str("") } else { visitor.visit_bytes(&[]) };
+		}
+		match read_scratch(&mut self.reader, &mut self.scratch, strsize)? {
+			Some(raw_str) => {
+				record(&mut self.trace, &self.path, offset, TraceLabel::Payload, raw_str, || trace::describe_string(raw_str));
+				match std::str::from_utf8(raw_str) {
+					Ok(s) if want_str => visitor.visit_str(s),
+					_ => visitor.visit_bytes(raw_str)
+				}
+			},
+			None => {
+				// @TODO: We may not want to allocate the whole string in advance for resource security against bad connections
+				let mut strbuf = vec![0u8; strsize];
+				self.read_raw(strbuf.as_mut_slice())?;
+				record(&mut self.trace, &self.path, offset, TraceLabel::Payload, &strbuf, || trace::describe_string(&strbuf));
+				if !want_str {
+					return visitor.visit_byte_buf(strbuf);
+				}
+				match String::from_utf8(strbuf) {
+					Ok(s) => visitor.visit_string(s),
+					Err(err) => visitor.visit_byte_buf(err.into_bytes())
+				}
+			}
+		}
+	}
+
+	///////////////////////////////////////////////////////////////////////////////
+	// Parsing (note: number parsing is handled by deserialize_num macro)        //
+	///////////////////////////////////////////////////////////////////////////////
+
+	#[inline]
+	fn parse_type_code(&mut self) -> Result<EpeeEntryType> {
+		let type_code = self.parse_raw_type_code()?;
+		EpeeEntryType::from_type_code(type_code)
+	}
+
+	fn parse_raw_type_code(&mut self) -> Result<u8> {
+		let offset = self.reader.offset();
+		let type_code = self.read_single()?;
+		record(&mut self.trace, &self.path, offset, TraceLabel::TypeCode, &[type_code], || trace::describe(TraceLabel::TypeCode, &[type_code]));
+		Ok(type_code)
+	}
+
+	fn parse_bool(&mut self) -> Result<bool> {
+		let offset = self.reader.offset();
+		let bool_byte = self.read_single()?;
+		record(&mut self.trace, &self.path, offset, TraceLabel::Payload, &[bool_byte], || (bool_byte != 0).to_string());
+		Ok(bool_byte != 0)
+	}
+
+	// Reads the varint through a buffer so that traces show it as it was on the
+	// wire, even if it wasn't encoded in the smallest width
+	#[inline]
+	fn parse_varint(&mut self) -> Result<VarInt> {
+		let offset = self.reader.offset();
+		let width = 1 << (self.reader.peek(1)?[0] & 0b11);
+		let mut raw = [0u8; 8];
+		self.read_raw(&mut raw[..width])?;
+		let varint = VarInt::from_reader(&mut &raw[..width])?;
+		record(&mut self.trace, &self.path, offset, TraceLabel::VarInt, &raw[..width], || u64::from(varint).to_string());
+		Ok(varint)
+	}
+
+	fn parse_key_len(&mut self) -> Result<usize> {
+		let strlen = self.read_single()? as usize;
+		if strlen == 0 {
+			return epee_err!(EmptySectionKey, "section key length can not be zero!");
+		}
+		Ok(strlen)
+	}
+
+	// Keys are at most 255 bytes long, so they are read on the stack and copied
+	// into last_key, whose buffer is reused from key to key
+	fn parse_key_body(&mut self, strlen: usize) -> Result<()> {
+		let mut strbuf = [0u8; u8::MAX as usize];
+		self.read_raw(&mut strbuf[..strlen])?;
+		self.last_key.push_str(key_from_utf8(&strbuf[..strlen])?);
+		Ok(())
+	}
+
+	// Reads the next key into last_key
+	fn read_key(&mut self) -> Result<()> {
+		let offset = self.reader.offset();
+		let keylen = self.parse_key_len()?;
+		self.last_key.clear();
+		match read_scratch(&mut self.reader, &mut self.scratch, keylen)? {
+			Some(raw_key) => self.last_key.push_str(key_from_utf8(raw_key)?),
+			None => self.parse_key_body(keylen)?
+		}
+
+		if self.trace.is_some() {
+			let mut raw = vec![keylen as u8];
+			raw.extend_from_slice(self.last_key.as_bytes());
+			record(&mut self.trace, &self.path, offset, TraceLabel::Key, &raw, || self.last_key.clone());
+		}
+		Ok(())
+	}
+
+	fn parse_string_key(&mut self) -> Result<String> {
+		self.read_key()?;
+		Ok(self.last_key.clone())
+	}
+
+	fn parse_string_len(&mut self) -> Result<usize> {
+		let varlen = self.parse_varint()?;
+		self.tracker.check_string_len(u64::from(varlen))?;
+		self.tracker.add_alloc_bytes(u64::from(varlen))?;
+		varlen.try_into()
+	}
+
+	define_parse_num!{parse_u8, u8}
+	define_parse_num!{parse_u16, u16}
+	define_parse_num!{parse_u32, u32}
+	define_parse_num!{parse_u64, u64}
+	define_parse_num!{parse_i8, i8}
+	define_parse_num!{parse_i16, i16}
+	define_parse_num!{parse_i32, i32}
+	define_parse_num!{parse_i64, i64}
+	define_parse_num!{parse_f64, f64}
+}
+
+// Iterator returned by Deserializer::documents. Stops after the first error,
+// since the reader can't be resynchronized with the next document.
+pub struct Documents<R, S: AsMut<[u8]>, T> {
+	deserializer: Deserializer<R, S>,
+	failed: bool,
+	marker: std::marker::PhantomData<fn() -> T>
+}
+
+impl<R, S: AsMut<[u8]>, T> Documents<R, S, T> {
+	pub fn into_inner(self) -> Deserializer<R, S> {
+		self.deserializer
+	}
+}
+
+impl<'de, R: EpeeRead<'de>, S: AsMut<[u8]>, T: de::DeserializeOwned> Iterator for Documents<R, S, T> {
+	type Item = Result<T>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None;
+		}
+
+		let res = match self.deserializer.reader.peek_up_to(1) {
+			Ok([]) => return None,
+			Ok(_) => T::deserialize(&mut self.deserializer),
+			Err(err) => Err(err)
+		};
+		self.failed = res.is_err();
+		Some(res)
+	}
+}
+
+impl<'de, R: EpeeRead<'de>, S: AsMut<[u8]>> de::Deserializer<'de> for &mut Deserializer<R, S> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		self.check_not_done()?;
+		match self.state {
+			DeserState::ExpectingSection(true) => visit_compound(EpeeCompound::new_root_section(self, None), |compound| visitor.visit_map(compound)),
+			DeserState::ExpectingSection(false) => visit_compound(EpeeCompound::new_section(self, None), |compound| visitor.visit_map(compound)),
+			DeserState::ExpectingKey => self.deserialize_key(visitor),
+			DeserState::ExpectingEntry => self.deserialize_section_entry(visitor),
+			DeserState::ExpectingScalar(_) => self.deserialize_scalar(visitor),
+			DeserState::Done => unreachable!()
+		}
+	}
+
+	define_defaulting_deser!{deserialize_bool, visit_bool, false}
+	define_int_deser!{deserialize_u8, u8, visit_u8}
+	define_int_deser!{deserialize_u16, u16, visit_u16}
+	define_int_deser!{deserialize_u32, u32, visit_u32}
+	define_int_deser!{deserialize_u64, u64, visit_u64}
+	define_int_deser!{deserialize_i8, i8, visit_i8}
+	define_int_deser!{deserialize_i16, i16, visit_i16}
+	define_int_deser!{deserialize_i32, i32, visit_i32}
+	define_int_deser!{deserialize_i64, i64, visit_i64}
+	define_int_deser!{deserialize_u128, u128, visit_u128}
+	define_int_deser!{deserialize_i128, i128, visit_i128}
+	define_defaulting_deser!{deserialize_f32, visit_f32, 0.0}
+	define_defaulting_deser!{deserialize_f64, visit_f64, 0.0}
+
+	// Visitors which only take strs, like serde_with's DisplayFromStr, get one
+	// if the blob is valid UTF-8
+	fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		if self.skip_empty_section()? {
+			return visitor.visit_borrowed_str("");
+		}
+		self.want_str = true;
+		let res = self.deserialize_any(visitor);
+		self.want_str = false;
+		res
+	}
+
+	fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		self.deserialize_str(visitor)
+	}
+	define_defaulting_deser!{deserialize_bytes, visit_borrowed_bytes, &[]}
+	define_defaulting_deser!{deserialize_byte_buf, visit_borrowed_bytes, &[]}
+	define_simple_deser!{deserialize_identifier}
+
+	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		if self.skip_empty_section()? {
+			return visitor.visit_seq(de::value::SeqDeserializer::<_, Error>::new(std::iter::empty::<u8>()));
+		}
+		if self.blob_seqs && self.at_string_value()? {
+			return self.deserialize_blob_seq(visitor);
+		}
+		self.deserialize_any(visitor)
+	}
+
+	define_simple_deser!{deserialize_map}
+
+	// Skips the wire value without decoding it if we are positioned at an entry
+	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		if let Some((type_code, custom_type)) = self.peek_custom_type()? {
+			self.read_custom(type_code, custom_type)?;
+			return visitor.visit_unit();
+		}
+
+		match self.state {
+			DeserState::ExpectingEntry => {
+				let type_code = self.parse_raw_type_code()?;
+				self.skip_traced(|reader, tracker| skip_entry_tracked(reader, type_code, tracker))?;
+				visitor.visit_unit()
+			},
+			DeserState::ExpectingScalar(scalar_type) => {
+				self.skip_traced(|reader, tracker| skip_scalar(reader, scalar_type, tracker))?;
+				visitor.visit_unit()
+			},
+			_ => self.deserialize_any(visitor)
+		}
+	}
+
+	fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		unimplemented!()
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		self.check_not_done()?;
+		if self.skip_empty_section()? {
+			return visitor.visit_none();
+		}
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		self.check_not_done()?;
+		visitor.visit_unit()
+	}
+
+	fn deserialize_unit_struct<V>(
+		self,
+		_name: &'static str,
+		_visitor: V,
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("Can't deserialize unit structs")))
+	}
+
+	fn deserialize_newtype_struct<V>(
+		self,
+		name: &'static str,
+		visitor: V,
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		if name == crate::varint::VARINT_TOKEN {
+			return self.deserialize_bytes(visitor);
+		}
+
+		#[cfg(feature = "bytes")]
+		if name == crate::buf::SHARED_BLOB_TOKEN {
+			self.want_span = self.shared_input.is_some();
+			let res = self.deserialize_any(visitor);
+			self.want_span = false;
+			return res;
+		}
+
+		#[cfg(not(feature = "bytes"))]
+		let _ = visitor;
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("Can't deserialize newtype structs")))
+	}
+
+	///////////////////////////////////////////////////////////////////////////////
+	// Deserialize compound types                                                //
+	///////////////////////////////////////////////////////////////////////////////
+
+	fn deserialize_tuple<V>(
+		self,
+		_len: usize,
+		visitor: V,
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_tuple_struct<V>(
+		self,
+		_name: &'static str,
+		_len: usize,
+		_visitor: V,
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("Can't deserialize tuple structs")))
+	}
+
+	fn deserialize_struct<V>(
+		self,
+		_name: &'static str,
+		fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		if self.lenient {
+			self.struct_fields = Some(fields);
+		}
+		let res = self.deserialize_any(visitor);
+		self.struct_fields = None;
+		res
+	}
+
+	fn deserialize_enum<V>(
+		self,
+		_name: &'static str,
+		_variants: &'static [&'static str],
+		_visitor: V,
+	) -> Result<V::Value>
+	where
+		V: Visitor<'de>,
+	{
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("Can't deserialize enums")))
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Blob sequence elements                                                    //
+///////////////////////////////////////////////////////////////////////////////
+
+// Byte of a STRING handed to a sequence visitor, see with_blob_seqs. Only
+// deserializes as u8, so that e.g. a blob of 16 bytes can't silently turn into
+// a Vec<u64> of 16 elements.
+struct BlobByte(u8);
+
+impl<'de> de::IntoDeserializer<'de, Error> for BlobByte {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+impl<'de> de::Deserializer<'de> for BlobByte {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		epee_err!(TypeMismatch, "expected UINT8 array, found STRING (only sequences of u8 decode from blobs)")
+	}
+
+	fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_u8(self.0)
+	}
+
+	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_unit()
+	}
+
+	serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier
+	}
+}
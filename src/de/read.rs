@@ -0,0 +1,350 @@
+use std::io::{BufRead, Read};
+
+use crate::error::Result;
+
+// Input of a Deserializer: a Read which can also look ahead without consuming
+// anything and knows how far into the input it is. Like serde_json's Read
+// trait, this lets one parser run over slices and streams alike, each input
+// peeking and counting the cheapest way it can:
+//
+//     SliceRead  - slices, peeks without copying and lends out strings
+//     BufIoRead  - BufReads, peeks into their buffer where it can
+//     IoRead     - any other Read, peeks into a buffer of its own
+//
+// 'de is the lifetime of the input, for inputs which can lend out parts of it
+// to the values being deserialized, see read_borrowed.
+pub trait EpeeRead<'de>: Read {
+	// What the input wraps, see Deserializer::get_ref and into_inner
+	type Inner;
+
+	// Next n bytes without consuming them. Fails like read_exact if fewer are left.
+	fn peek(&mut self, n: usize) -> Result<&[u8]>;
+
+	// Like peek, but returns fewer than n bytes if the input ends first
+	fn peek_up_to(&mut self, n: usize) -> Result<&[u8]>;
+
+	// Bytes consumed so far, i.e. the offset into the document
+	fn offset(&self) -> u64;
+
+	// Consumes the next len bytes and returns them as a part of the input which
+	// lives for 'de, or consumes nothing and returns None if the input can't
+	// lend out its bytes. Strings are read with read_exact in that case.
+	#[inline]
+	fn read_borrowed(&mut self, len: usize) -> Result<Option<&'de [u8]>> {
+		let _ = len;
+		Ok(None)
+	}
+
+	fn get_ref(&self) -> &Self::Inner;
+
+	fn get_mut(&mut self) -> &mut Self::Inner;
+
+	// Bytes which were peeked at but not consumed yet may be lost
+	fn into_inner(self) -> Self::Inner;
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Slices                                                                    //
+///////////////////////////////////////////////////////////////////////////////
+
+// The inner slice is whatever wasn't consumed yet
+pub struct SliceRead<'a> {
+	slice: &'a [u8],
+	offset: u64
+}
+
+impl<'a> SliceRead<'a> {
+	pub fn new(slice: &'a [u8]) -> Self {
+		Self { slice, offset: 0 }
+	}
+}
+
+impl Read for SliceRead<'_> {
+	#[inline]
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let n = self.slice.read(buf)?;
+		self.offset += n as u64;
+		Ok(n)
+	}
+
+	#[inline]
+	fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+		let before = self.slice.len();
+		let res = self.slice.read_exact(buf);
+		self.offset += (before - self.slice.len()) as u64;
+		res
+	}
+}
+
+impl<'a> EpeeRead<'a> for SliceRead<'a> {
+	type Inner = &'a [u8];
+
+	#[inline]
+	fn peek(&mut self, n: usize) -> Result<&[u8]> {
+		match self.slice.get(..n) {
+			Some(peeked) => Ok(peeked),
+			None => Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+		}
+	}
+
+	fn peek_up_to(&mut self, n: usize) -> Result<&[u8]> {
+		Ok(&self.slice[..std::cmp::min(n, self.slice.len())])
+	}
+
+	fn offset(&self) -> u64 {
+		self.offset
+	}
+
+	#[inline]
+	fn read_borrowed(&mut self, len: usize) -> Result<Option<&'a [u8]>> {
+		if len > self.slice.len() {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+		let (borrowed, rest) = self.slice.split_at(len);
+		self.slice = rest;
+		self.offset += len as u64;
+		Ok(Some(borrowed))
+	}
+
+	fn get_ref(&self) -> &&'a [u8] {
+		&self.slice
+	}
+
+	fn get_mut(&mut self) -> &mut &'a [u8] {
+		&mut self.slice
+	}
+
+	fn into_inner(self) -> &'a [u8] {
+		self.slice
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Streams                                                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+// Reader with a small lookahead buffer, so that the deserializer can look at
+// upcoming bytes without consuming them
+pub struct IoRead<R: Read> {
+	inner: R,
+	buf: Vec<u8>,
+	pos: usize,
+	consumed: u64 // bytes handed out through read()
+}
+
+impl<R: Read> IoRead<R> {
+	pub fn new(inner: R) -> Self {
+		Self {
+			inner,
+			buf: Vec::new(),
+			pos: 0,
+			consumed: 0
+		}
+	}
+
+	fn is_buffer_empty(&self) -> bool {
+		self.pos == self.buf.len()
+	}
+}
+
+impl<R: Read> Read for IoRead<R> {
+	#[inline]
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		if self.pos < self.buf.len() {
+			let n = std::cmp::min(buf.len(), self.buf.len() - self.pos);
+			buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+			self.pos += n;
+			self.consumed += n as u64;
+			Ok(n)
+		} else {
+			let n = self.inner.read(buf)?;
+			self.consumed += n as u64;
+			Ok(n)
+		}
+	}
+
+	// Hands whatever isn't buffered to the inner reader's read_exact, which is
+	// a single copy for slices instead of the default loop over read
+	#[inline]
+	fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+		let n = std::cmp::min(buf.len(), self.buf.len() - self.pos);
+		buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+		self.pos += n;
+		self.consumed += n as u64;
+
+		self.inner.read_exact(&mut buf[n..])?;
+		self.consumed += (buf.len() - n) as u64;
+		Ok(())
+	}
+}
+
+impl<R: Read> EpeeRead<'_> for IoRead<R> {
+	type Inner = R;
+
+	#[inline]
+	fn peek(&mut self, n: usize) -> Result<&[u8]> {
+		if self.is_buffer_empty() {
+			self.buf.clear();
+			self.pos = 0;
+		}
+
+		let buffered = self.buf.len() - self.pos;
+		if buffered < n {
+			let start = self.buf.len();
+			self.buf.resize(start + n - buffered, 0);
+			if let Err(ioe) = self.inner.read_exact(&mut self.buf[start..]) {
+				self.buf.truncate(start);
+				return Err(ioe.into());
+			}
+		}
+
+		Ok(&self.buf[self.pos..self.pos + n])
+	}
+
+	fn peek_up_to(&mut self, n: usize) -> Result<&[u8]> {
+		if self.is_buffer_empty() {
+			self.buf.clear();
+			self.pos = 0;
+		}
+
+		while self.buf.len() - self.pos < n {
+			let start = self.buf.len();
+			self.buf.resize(self.pos + n, 0);
+			match self.inner.read(&mut self.buf[start..]) {
+				Ok(0) => { self.buf.truncate(start); break; },
+				Ok(nread) => self.buf.truncate(start + nread),
+				Err(ioe) if ioe.kind() == std::io::ErrorKind::Interrupted => self.buf.truncate(start),
+				Err(ioe) => { self.buf.truncate(start); return Err(ioe.into()); }
+			}
+		}
+
+		let end = std::cmp::min(self.buf.len(), self.pos + n);
+		Ok(&self.buf[self.pos..end])
+	}
+
+	fn offset(&self) -> u64 {
+		self.consumed
+	}
+
+	fn get_ref(&self) -> &R {
+		&self.inner
+	}
+
+	fn get_mut(&mut self) -> &mut R {
+		&mut self.inner
+	}
+
+	fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+// Like IoRead, but peeks straight into the BufRead's buffer unless what is
+// peeked at straddles the end of it
+pub struct BufIoRead<R: BufRead> {
+	io: IoRead<R>
+}
+
+impl<R: BufRead> BufIoRead<R> {
+	pub fn new(inner: R) -> Self {
+		Self { io: IoRead::new(inner) }
+	}
+}
+
+impl<R: BufRead> Read for BufIoRead<R> {
+	#[inline]
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		self.io.read(buf)
+	}
+
+	#[inline]
+	fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+		self.io.read_exact(buf)
+	}
+}
+
+impl<R: BufRead> EpeeRead<'_> for BufIoRead<R> {
+	type Inner = R;
+
+	#[inline]
+	fn peek(&mut self, n: usize) -> Result<&[u8]> {
+		if self.io.is_buffer_empty() && self.io.inner.fill_buf()?.len() >= n {
+			return Ok(&self.io.inner.fill_buf()?[..n]);
+		}
+		self.io.peek(n)
+	}
+
+	fn peek_up_to(&mut self, n: usize) -> Result<&[u8]> {
+		if self.io.is_buffer_empty() && self.io.inner.fill_buf()?.len() >= n {
+			return Ok(&self.io.inner.fill_buf()?[..n]);
+		}
+		self.io.peek_up_to(n)
+	}
+
+	fn offset(&self) -> u64 {
+		self.io.offset()
+	}
+
+	fn get_ref(&self) -> &R {
+		self.io.get_ref()
+	}
+
+	fn get_mut(&mut self) -> &mut R {
+		self.io.get_mut()
+	}
+
+	fn into_inner(self) -> R {
+		self.io.into_inner()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Wrappers used while decoding                                              //
+///////////////////////////////////////////////////////////////////////////////
+
+// Take-like reader which remembers whether somebody tried reading past the limit
+pub(crate) struct LimitedReader<R: Read> {
+	inner: R,
+	remaining: u64,
+	pub(crate) exceeded: bool
+}
+
+impl<R: Read> LimitedReader<R> {
+	pub(crate) fn new(inner: R, limit: u64) -> Self {
+		Self {
+			inner,
+			remaining: limit,
+			exceeded: false
+		}
+	}
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		} else if self.remaining == 0 {
+			self.exceeded = true;
+			return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "read budget exceeded"));
+		}
+
+		let max_read = std::cmp::min(buf.len() as u64, self.remaining) as usize;
+		let nread = self.inner.read(&mut buf[..max_read])?;
+		self.remaining -= nread as u64;
+		Ok(nread)
+	}
+}
+
+// Reader which keeps a copy of everything read through it
+pub(crate) struct CaptureReader<'r, R: Read + ?Sized> {
+	pub(crate) inner: &'r mut R,
+	pub(crate) captured: Vec<u8>
+}
+
+impl<R: Read + ?Sized> Read for CaptureReader<'_, R> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let n = self.inner.read(buf)?;
+		self.captured.extend_from_slice(&buf[..n]);
+		Ok(n)
+	}
+}
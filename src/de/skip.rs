@@ -0,0 +1,112 @@
+use std::io::Read;
+
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::format::FormatProfile;
+use crate::limits::LimitTracker;
+use crate::types::{EpeeEntryType, EpeeScalarType};
+use crate::VarInt;
+
+use super::read::CaptureReader;
+
+// Consumes exactly one value of the given raw type code (array flag included)
+// from the reader without decoding it
+pub fn skip_entry<R: Read>(reader: &mut R, type_code: u8) -> Result<()> {
+	skip_entry_tracked(reader, type_code, &mut LimitTracker::default())
+}
+
+pub(super) fn skip_entry_tracked<R: Read + ?Sized>(reader: &mut R, type_code: u8, tracker: &mut LimitTracker) -> Result<()> {
+	if type_code == constants::EMPTY_UNKNOWN_ARRAY_TYPE_CODE {
+		return skip_empty_unknown_array(reader);
+	}
+	let entry_type = EpeeEntryType::from_type_code(type_code)?;
+
+	if entry_type.is_array {
+		let count: u64 = VarInt::from_reader(reader)?.into();
+		tracker.add_values(entry_type.scalar_type, count)?;
+		for _ in 0..count {
+			skip_scalar(reader, entry_type.scalar_type, tracker)?;
+		}
+		Ok(())
+	} else {
+		tracker.add_values(entry_type.scalar_type, 1)?;
+		skip_scalar(reader, entry_type.scalar_type, tracker)
+	}
+}
+
+// Arrays of unknown type can only be skipped if they are empty, since there's
+// no telling how long their elements are
+pub(crate) fn skip_empty_unknown_array<R: Read + ?Sized>(reader: &mut R) -> Result<()> {
+	let count: u64 = VarInt::from_reader(reader)?.into();
+	if count != 0 {
+		return epee_err!(UnknownTypeCode, "can't skip {} elements of unknown type (SERIALIZE_TYPE_UNKNOWN)", count);
+	}
+	Ok(())
+}
+
+pub(super) fn skip_scalar<R: Read + ?Sized>(reader: &mut R, scalar_type: EpeeScalarType, tracker: &mut LimitTracker) -> Result<()> {
+	match scalar_type {
+		EpeeScalarType::Str => {
+			let strsize: u64 = VarInt::from_reader(reader)?.into();
+			tracker.check_string_len(strsize)?;
+			skip_bytes(reader, strsize)
+		},
+		EpeeScalarType::Object => {
+			let num_fields: u64 = VarInt::from_reader(reader)?.into();
+			tracker.enter_section(num_fields)?;
+			let mut byte = [0u8];
+			for _ in 0..num_fields {
+				reader.read_exact(&mut byte)?;
+				if byte[0] == 0 {
+					return epee_err!(EmptySectionKey, "section key length can not be zero!");
+				}
+				skip_bytes(reader, byte[0] as u64)?;
+				reader.read_exact(&mut byte)?;
+				skip_entry_tracked(reader, byte[0], tracker)?;
+			}
+			tracker.leave_section();
+			Ok(())
+		},
+		_ => {
+			// All other scalars are fixed width
+			let mut buf = [0u8; 8];
+			let size = scalar_type.fixed_size().unwrap_or(0);
+			reader.read_exact(&mut buf[..size])?;
+			Ok(())
+		}
+	}
+}
+
+// Reads one whole document, signature included, without decoding it. Only as
+// much as the document's own lengths say is read.
+pub(crate) fn capture_document<R: Read>(reader: &mut R, profile: &FormatProfile, tracker: &mut LimitTracker) -> Result<Vec<u8>> {
+	let mut captured = Vec::new();
+	capture_document_into(reader, profile, tracker, &mut captured)?;
+	Ok(captured)
+}
+
+// Like capture_document, but appends to captured, where whatever was read is
+// left if the document turns out to be broken or cut short
+pub(crate) fn capture_document_into<R: Read>(reader: &mut R, profile: &FormatProfile, tracker: &mut LimitTracker, captured: &mut Vec<u8>) -> Result<()> {
+	let mut capture = CaptureReader { inner: reader, captured: std::mem::take(captured) };
+	let res = skip_document(&mut capture, profile, tracker);
+	*captured = capture.captured;
+	res
+}
+
+fn skip_document<R: Read>(reader: &mut R, profile: &FormatProfile, tracker: &mut LimitTracker) -> Result<()> {
+	let mut signature = [0u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE];
+	reader.read_exact(&mut signature)?;
+	if signature != profile.signature() {
+		return epee_err!(ExpectedFormatSignature);
+	}
+	skip_scalar(reader, EpeeScalarType::Object, tracker)
+}
+
+pub(super) fn skip_bytes<R: Read + ?Sized>(reader: &mut R, len: u64) -> Result<()> {
+	let skipped = std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+	if skipped != len {
+		return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+	}
+	Ok(())
+}
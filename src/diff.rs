@@ -0,0 +1,121 @@
+use std::fmt;
+
+use crate::section::{Section, SectionArray, SectionEntry};
+
+///////////////////////////////////////////////////////////////////////////////
+// Changes                                                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+// One difference between two documents. Paths look like "blocks[2].txs".
+// Sections and arrays of sections are compared entry by entry, every other
+// entry is compared as a whole.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+	Added { path: String, entry: SectionEntry },
+	Removed { path: String, entry: SectionEntry },
+	// Same key with another wire type, e.g. UINT32 -> UINT64
+	TypeChanged { path: String, first: SectionEntry, second: SectionEntry },
+	ValueChanged { path: String, first: SectionEntry, second: SectionEntry }
+}
+
+impl Change {
+	pub fn path(&self) -> &str {
+		match self {
+			Change::Added { path, .. } | Change::Removed { path, .. } => path,
+			Change::TypeChanged { path, .. } | Change::ValueChanged { path, .. } => path
+		}
+	}
+}
+
+// One line per change, e.g.
+//
+//     + pool_info: UINT8 1
+//     - top_hash: STRING 0x0a1b...
+//     ~ height: UINT32 3000000 -> UINT64 3000000
+//     ~ status: "BUSY" -> "OK"
+impl fmt::Display for Change {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Change::Added { path, entry } => write!(formatter, "+ {}: {}", path, Typed(entry)),
+			Change::Removed { path, entry } => write!(formatter, "- {}: {}", path, Typed(entry)),
+			Change::TypeChanged { path, first, second } => write!(formatter, "~ {}: {} -> {}", path, Typed(first), Typed(second)),
+			Change::ValueChanged { path, first, second } => write!(formatter, "~ {}: {} -> {}", path, first, second)
+		}
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Changes which turn first into second, sorted by key within each section.
+// Nil entries count as absent.
+pub fn diff(first: &Section, second: &Section) -> Vec<Change> {
+	let mut changes = Vec::new();
+	diff_sections(first, second, "", &mut changes);
+	changes
+}
+
+fn join(path: &str, key: &str) -> String {
+	if path.is_empty() {
+		key.to_string()
+	} else {
+		format!("{}.{}", path, key)
+	}
+}
+
+fn diff_sections(first: &Section, second: &Section, path: &str, changes: &mut Vec<Change>) {
+	let mut keys: Vec<&String> = first.keys().chain(second.keys()).collect();
+	keys.sort();
+	keys.dedup();
+
+	for key in keys {
+		let path = join(path, key);
+		match (&first[key.as_str()], &second[key.as_str()]) {
+			(SectionEntry::Nil, SectionEntry::Nil) => (),
+			(first, SectionEntry::Nil) => changes.push(Change::Removed { path, entry: first.clone() }),
+			(SectionEntry::Nil, second) => changes.push(Change::Added { path, entry: second.clone() }),
+			(first, second) => diff_entries(first, second, path, changes)
+		}
+	}
+}
+
+fn diff_entries(first: &SectionEntry, second: &SectionEntry, path: String, changes: &mut Vec<Change>) {
+	match (first, second) {
+		_ if first.entry_type() != second.entry_type() => {
+			changes.push(Change::TypeChanged { path, first: first.clone(), second: second.clone() });
+		},
+		(SectionEntry::Object(first), SectionEntry::Object(second)) => diff_sections(first, second, &path, changes),
+		(SectionEntry::Array(SectionArray::Object(first)), SectionEntry::Array(SectionArray::Object(second))) => {
+			for i in 0..first.len().max(second.len()) {
+				let path = format!("{}[{}]", path, i);
+				match (first.get(i), second.get(i)) {
+					(Some(first), Some(second)) => diff_sections(first, second, &path, changes),
+					(Some(first), None) => changes.push(Change::Removed { path, entry: first.clone().into() }),
+					(None, Some(second)) => changes.push(Change::Added { path, entry: second.clone().into() }),
+					(None, None) => ()
+				}
+			}
+		},
+		_ if first != second => {
+			changes.push(Change::ValueChanged { path, first: first.clone(), second: second.clone() });
+		},
+		_ => ()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Formatting                                                                //
+///////////////////////////////////////////////////////////////////////////////
+
+// Entry value preceded by its wire type
+struct Typed<'a>(&'a SectionEntry);
+
+impl fmt::Display for Typed<'_> {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		match self.0.entry_type() {
+			Some(entry_type) => write!(formatter, "{} {}", entry_type, self.0),
+			None => write!(formatter, "nil")
+		}
+	}
+}
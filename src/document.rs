@@ -0,0 +1,120 @@
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+
+use serde::Deserialize;
+
+use crate::constants;
+use crate::de::{capture_document, Deserializer};
+use crate::error::{Error, ErrorKind, Result};
+use crate::format::FormatProfile;
+use crate::limits::{LimitTracker, Limits};
+use crate::schema;
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Reads one document from reader into a Document, see Document::from_reader
+pub fn from_reader_buffered<'de, T, R>(reader: R) -> Result<Document<T>>
+where
+	T: Deserialize<'de>,
+	R: Read
+{
+	Document::from_reader(reader)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Document                                                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+// A whole document held in memory, which decodes into a T that may borrow from
+// it, e.g. Document<Status<'_>> for a Status holding &str fields. The T always
+// borrows the Document, so it can't outlive the buffer, and the document can
+// be decoded any number of times without reading again.
+pub struct Document<T> {
+	bytes: Box<[u8]>,
+	profile: FormatProfile,
+	limits: Limits,
+	marker: PhantomData<fn() -> T>
+}
+
+impl<T> Document<T> {
+	// Reads exactly one document and checks its structure against the default
+	// limits, and the wire types of its fields against T, see
+	// schema::validate_as. Missing fields are left to T's Deserialize impl,
+	// since the probe can't tell which fields have defaults. Nothing past the
+	// end of the document is read, so a stream of documents can be read one at
+	// a time.
+	pub fn from_reader<'de, R: Read>(reader: R) -> Result<Self>
+	where
+		T: Deserialize<'de>
+	{
+		Self::from_reader_with(reader, &FormatProfile::monero(), Limits::default())
+	}
+
+	pub fn from_reader_with<'de, R: Read>(mut reader: R, profile: &FormatProfile, limits: Limits) -> Result<Self>
+	where
+		T: Deserialize<'de>
+	{
+		let bytes = capture_document(&mut reader, profile, &mut LimitTracker::new(limits))?;
+		let body = &bytes[constants::PORTABLE_STORAGE_SIGNATURE_SIZE..];
+		let report = schema::validate_body(body, &schema::shape_of::<T>())?;
+		if let Some(mismatch) = report.mismatched.first() {
+			let msg = format!("expected {:?}, found {:?}", mismatch.expected, mismatch.found);
+			return Err(Error::new(ErrorKind::TypeMismatch, msg).with_path(&mismatch.path));
+		}
+
+		Ok(Self { bytes: bytes.into_boxed_slice(), profile: *profile, limits, marker: PhantomData })
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.bytes.into_vec()
+	}
+
+	// Decodes the T, borrowing from the document where T does. Only values,
+	// e.g. an integer out of range for its field, and missing fields can fail
+	// to decode here.
+	pub fn value<'a>(&'a self) -> Result<T>
+	where
+		T: Deserialize<'a>
+	{
+		self.decode()
+	}
+
+	// Decodes the document as some other type than T
+	pub fn decode<'a, U: Deserialize<'a>>(&'a self) -> Result<U> {
+		let mut deserializer = Deserializer::from_slice(&self.bytes).with_profile(self.profile).with_limits(self.limits);
+		U::deserialize(&mut deserializer)
+	}
+}
+
+// Implemented by hand since deriving would require T to implement these too
+
+impl<T> Clone for Document<T> {
+	fn clone(&self) -> Self {
+		Self { bytes: self.bytes.clone(), profile: self.profile, limits: self.limits, marker: PhantomData }
+	}
+}
+
+impl<T> PartialEq for Document<T> {
+	fn eq(&self, other: &Self) -> bool {
+		self.bytes == other.bytes && self.profile == other.profile && self.limits == other.limits
+	}
+}
+
+impl<T> Eq for Document<T> {}
+
+impl<T> fmt::Debug for Document<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Document")
+			.field("bytes", &self.bytes)
+			.field("profile", &self.profile)
+			.field("limits", &self.limits)
+			.finish()
+	}
+}
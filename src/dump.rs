@@ -0,0 +1,178 @@
+use std::io::{Read, Write};
+
+use crate::constants::{self, TypeCode};
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::format::FormatProfile;
+use crate::VarInt;
+
+///////////////////////////////////////////////////////////////////////////////
+// Options                                                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DumpOptions {
+	pub max_blob_preview: usize, // bytes of each string shown, the rest is skipped
+	pub max_depth: usize, // sections nested deeper than this are skipped
+	pub profile: FormatProfile
+}
+
+impl Default for DumpOptions {
+	fn default() -> Self {
+		Self {
+			max_blob_preview: 64,
+			max_depth: constants::MONEROD_RECURSION_LIMIT,
+			profile: FormatProfile::monero()
+		}
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Writes a human readable listing of a document while reading it, one line per
+// entry or array element, without building the document in memory. Memory use
+// only depends on max_blob_preview and the nesting depth, so this works on
+// arbitrarily large files. Both ends are read and written in small pieces, so
+// pass buffered readers and writers.
+pub fn dump<R: Read, W: Write>(mut reader: R, mut writer: W, options: &DumpOptions) -> Result<()> {
+	let mut signature = [0u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE];
+	reader.read_exact(&mut signature)?;
+	if signature != options.profile.signature() {
+		return epee_err!(ExpectedFormatSignature, "unexpected signature {:02x?}", signature);
+	}
+
+	let mut dumper = Dumper { reader, writer: writer.by_ref(), options };
+	dumper.section_body(0)?;
+	writer.flush()?;
+	Ok(())
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Dumper                                                                    //
+///////////////////////////////////////////////////////////////////////////////
+
+struct Dumper<'a, R: Read, W: Write> {
+	reader: R,
+	writer: &'a mut W,
+	options: &'a DumpOptions
+}
+
+impl<R: Read, W: Write> Dumper<'_, R, W> {
+	fn section_body(&mut self, depth: usize) -> Result<()> {
+		let num_fields: u64 = VarInt::from_reader(&mut self.reader)?.into();
+		for _ in 0..num_fields {
+			let keylen = self.read_u8()? as usize;
+			if keylen == 0 {
+				return epee_err!(EmptySectionKey, "section key length can not be zero!");
+			}
+			let mut key = [0u8; constants::MAX_SECTION_KEY_SIZE];
+			self.reader.read_exact(&mut key[..keylen])?;
+
+			self.indent(depth)?;
+			write!(self.writer, "{}: ", String::from_utf8_lossy(&key[..keylen]))?;
+			let type_byte = self.read_u8()?;
+			self.entry(type_byte, depth)?;
+		}
+		Ok(())
+	}
+
+	fn entry(&mut self, type_byte: u8, depth: usize) -> Result<()> {
+		let type_code = TypeCode::from_u8(type_byte)?;
+		if !TypeCode::is_array_flagged(type_byte) {
+			return self.scalar(type_code, depth);
+		}
+
+		let count: u64 = VarInt::from_reader(&mut self.reader)?.into();
+		writeln!(self.writer, "ARRAY<{}> ({} elements)", type_code, count)?;
+		for i in 0..count {
+			self.indent(depth + 1)?;
+			write!(self.writer, "[{}] ", i)?;
+			self.scalar(type_code, depth + 1)?;
+		}
+		Ok(())
+	}
+
+	fn scalar(&mut self, type_code: TypeCode, depth: usize) -> Result<()> {
+		match type_code {
+			TypeCode::Int64 => { let v = i64::from_le_bytes(self.read_array()?); writeln!(self.writer, "INT64 {}", v)?; },
+			TypeCode::Int32 => { let v = i32::from_le_bytes(self.read_array()?); writeln!(self.writer, "INT32 {}", v)?; },
+			TypeCode::Int16 => { let v = i16::from_le_bytes(self.read_array()?); writeln!(self.writer, "INT16 {}", v)?; },
+			TypeCode::Int8 => { let v = i8::from_le_bytes(self.read_array()?); writeln!(self.writer, "INT8 {}", v)?; },
+			TypeCode::UInt64 => { let v = u64::from_le_bytes(self.read_array()?); writeln!(self.writer, "UINT64 {}", v)?; },
+			TypeCode::UInt32 => { let v = u32::from_le_bytes(self.read_array()?); writeln!(self.writer, "UINT32 {}", v)?; },
+			TypeCode::UInt16 => { let v = u16::from_le_bytes(self.read_array()?); writeln!(self.writer, "UINT16 {}", v)?; },
+			TypeCode::UInt8 => { let v = self.read_u8()?; writeln!(self.writer, "UINT8 {}", v)?; },
+			#[cfg(not(feature = "no-float"))]
+			TypeCode::Double => { let v = f64::from_le_bytes(self.read_array()?); writeln!(self.writer, "DOUBLE {}", v)?; },
+			#[cfg(feature = "no-float")]
+			TypeCode::Double => { let v: [u8; 8] = self.read_array()?; writeln!(self.writer, "DOUBLE 0x{}", hex_string(&v))?; },
+			TypeCode::Bool => { let v = self.read_u8()? != 0; writeln!(self.writer, "BOOL {}", v)?; },
+			TypeCode::String => self.string()?,
+			TypeCode::Object => {
+				if depth + 1 > self.options.max_depth {
+					self.skip_object()?;
+					writeln!(self.writer, "OBJECT {{...}}")?;
+				} else {
+					writeln!(self.writer, "OBJECT")?;
+					self.section_body(depth + 1)?;
+				}
+			},
+			TypeCode::Unknown => return crate::types::unknown_type_code()
+		}
+		Ok(())
+	}
+
+	fn string(&mut self) -> Result<()> {
+		let len: u64 = VarInt::from_reader(&mut self.reader)?.into();
+		let preview_len = std::cmp::min(len, self.options.max_blob_preview as u64) as usize;
+		let mut preview = vec![0u8; preview_len];
+		self.reader.read_exact(&mut preview)?;
+		self.skip(len - preview_len as u64)?;
+
+		write!(self.writer, "STRING({}) ", len)?;
+		match std::str::from_utf8(&preview) {
+			Ok(s) if s.chars().all(|c| !c.is_control()) => write!(self.writer, "{:?}", s)?,
+			_ => write!(self.writer, "0x{}", hex_string(&preview))?
+		}
+		if preview_len as u64 != len {
+			write!(self.writer, "...")?;
+		}
+		writeln!(self.writer)?;
+		Ok(())
+	}
+
+	fn skip_object(&mut self) -> Result<()> {
+		crate::de::skip_entry(&mut self.reader, constants::SERIALIZE_TYPE_OBJECT)
+	}
+
+	fn skip(&mut self, len: u64) -> Result<()> {
+		let skipped = std::io::copy(&mut self.reader.by_ref().take(len), &mut std::io::sink())?;
+		if skipped != len {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+		Ok(())
+	}
+
+	fn indent(&mut self, depth: usize) -> Result<()> {
+		for _ in 0..depth {
+			self.writer.write_all(b"  ")?;
+		}
+		Ok(())
+	}
+
+	fn read_u8(&mut self) -> Result<u8> {
+		let [byte] = self.read_array()?;
+		Ok(byte)
+	}
+
+	fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+		let mut buf = [0u8; N];
+		self.reader.read_exact(&mut buf)?;
+		Ok(buf)
+	}
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
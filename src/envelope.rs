@@ -0,0 +1,232 @@
+use std::io::{Read, Write};
+
+use serde::{de, Deserialize, Serialize};
+
+use crate::error::{Error, ErrorKind, Result, epee_err};
+
+// Envelope layout: magic, checksum kind, document length (u64 LE), checksum
+// (u64 LE, CRC32 zero extended), then the document itself. Everything is
+// checked before the document is parsed, so bit rot and truncated files are
+// reported as such instead of as whatever parse error they happen to cause.
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"EPEV";
+pub const ENVELOPE_HEADER_SIZE: usize = 4 + 1 + 8 + 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Checksum {
+	Crc32 = 1, // IEEE 802.3, like zlib and gzip
+	XxHash64 = 2 // seed 0
+}
+
+impl Checksum {
+	pub fn from_u8(code: u8) -> Result<Self> {
+		match code {
+			1 => Ok(Self::Crc32),
+			2 => Ok(Self::XxHash64),
+			_ => epee_err!(UnknownChecksum { code: code }, "unknown envelope checksum {}", code)
+		}
+	}
+
+	pub fn compute(self, bytes: &[u8]) -> u64 {
+		match self {
+			Self::Crc32 => crc32(bytes) as u64,
+			Self::XxHash64 => xxhash64(bytes)
+		}
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+pub fn to_bytes_enveloped<T: Serialize>(value: &T, checksum: Checksum) -> Result<Vec<u8>> {
+	let document = crate::to_bytes(value)?;
+	let mut enveloped = Vec::with_capacity(ENVELOPE_HEADER_SIZE + document.len());
+	enveloped.extend_from_slice(&header(&document, checksum));
+	enveloped.extend_from_slice(&document);
+	Ok(enveloped)
+}
+
+// The document is encoded in memory first, since the header comes before it
+pub fn to_writer_enveloped<T, W>(mut writer: W, value: &T, checksum: Checksum) -> Result<()>
+where
+	T: Serialize,
+	W: Write
+{
+	let document = crate::to_bytes(value)?;
+	writer.write_all(&header(&document, checksum))?;
+	writer.write_all(&document)?;
+	Ok(())
+}
+
+// Reads a whole envelope, failing with BudgetExceeded instead of allocating
+// room for a document longer than max_len bytes
+pub fn from_reader_enveloped<T, R>(mut reader: R, max_len: u64) -> Result<T>
+where
+	T: de::DeserializeOwned,
+	R: Read
+{
+	let mut header = [0u8; ENVELOPE_HEADER_SIZE];
+	reader.read_exact(&mut header)?;
+	let (checksum, len, expected) = parse_header(&header)?;
+	if len > max_len {
+		return epee_err!(BudgetExceeded { max_bytes: max_len }, "enveloped document is {} bytes long", len);
+	}
+
+	let mut document = Vec::new();
+	let nread = reader.take(len).read_to_end(&mut document)?;
+	check_document(&document, nread as u64, len, checksum, expected)?;
+	crate::from_slice(&document)
+}
+
+// Decodes the document of the envelope which makes up all of bytes
+pub fn from_slice_enveloped<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
+	crate::from_slice(open(bytes)?)
+}
+
+// Checks an envelope and returns the document inside of it. Bytes after the
+// document fail with ExpectedEnd.
+pub fn open(bytes: &[u8]) -> Result<&[u8]> {
+	if bytes.len() < ENVELOPE_HEADER_SIZE {
+		return epee_err!(EnvelopeTruncated { len: ENVELOPE_HEADER_SIZE as u64, found: bytes.len() as u64 }, "envelope header is cut short");
+	}
+
+	let (header, document) = bytes.split_at(ENVELOPE_HEADER_SIZE);
+	let (checksum, len, expected) = parse_header(header)?;
+	if document.len() as u64 > len {
+		return epee_err!(ExpectedEnd, "{} bytes left over after enveloped document", document.len() as u64 - len);
+	}
+	check_document(document, document.len() as u64, len, checksum, expected)?;
+	Ok(document)
+}
+
+fn header(document: &[u8], checksum: Checksum) -> [u8; ENVELOPE_HEADER_SIZE] {
+	let mut header = [0u8; ENVELOPE_HEADER_SIZE];
+	header[..4].copy_from_slice(&ENVELOPE_MAGIC);
+	header[4] = checksum as u8;
+	header[5..13].copy_from_slice(&(document.len() as u64).to_le_bytes());
+	header[13..].copy_from_slice(&checksum.compute(document).to_le_bytes());
+	header
+}
+
+fn parse_header(header: &[u8]) -> Result<(Checksum, u64, u64)> {
+	if header[..4] != ENVELOPE_MAGIC {
+		return epee_err!(ExpectedFormatSignature, "not an enveloped document");
+	}
+
+	let checksum = Checksum::from_u8(header[4])?;
+	let len = u64::from_le_bytes(header[5..13].try_into().unwrap());
+	let expected = u64::from_le_bytes(header[13..].try_into().unwrap());
+	Ok((checksum, len, expected))
+}
+
+fn check_document(document: &[u8], found: u64, len: u64, checksum: Checksum, expected: u64) -> Result<()> {
+	if found != len {
+		return epee_err!(EnvelopeTruncated { len: len, found: found }, "enveloped document should be {} bytes long, found {}", len, found);
+	}
+
+	let actual = checksum.compute(document);
+	if actual != expected {
+		return epee_err!(ChecksumMismatch { expected: expected, found: actual }, "{:?} of document is {:#x}, envelope says {:#x}", checksum, actual, expected);
+	}
+	Ok(())
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Checksums                                                                 //
+///////////////////////////////////////////////////////////////////////////////
+
+const CRC32_TABLE: [u32; 256] = {
+	let mut table = [0u32; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+			bit += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+	table
+};
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in bytes {
+		crc = CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+	}
+	!crc
+}
+
+const XXH_PRIME64_1: u64 = 0x9e3779b185ebca87;
+const XXH_PRIME64_2: u64 = 0xc2b2ae3d27d4eb4f;
+const XXH_PRIME64_3: u64 = 0x165667b19e3779f9;
+const XXH_PRIME64_4: u64 = 0x85ebca77c2b2ae63;
+const XXH_PRIME64_5: u64 = 0x27d4eb2f165667c5;
+
+fn xxh64_round(acc: u64, input: u64) -> u64 {
+	acc.wrapping_add(input.wrapping_mul(XXH_PRIME64_2)).rotate_left(31).wrapping_mul(XXH_PRIME64_1)
+}
+
+fn xxh64_merge(acc: u64, value: u64) -> u64 {
+	(acc ^ xxh64_round(0, value)).wrapping_mul(XXH_PRIME64_1).wrapping_add(XXH_PRIME64_4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+	u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+// XXH64 with seed 0
+pub fn xxhash64(bytes: &[u8]) -> u64 {
+	let mut rest = bytes;
+	let mut hash = if bytes.len() >= 32 {
+		let mut acc = [
+			XXH_PRIME64_1.wrapping_add(XXH_PRIME64_2),
+			XXH_PRIME64_2,
+			0,
+			0u64.wrapping_sub(XXH_PRIME64_1)
+		];
+		while rest.len() >= 32 {
+			for (i, lane) in acc.iter_mut().enumerate() {
+				*lane = xxh64_round(*lane, read_u64(&rest[8 * i..]));
+			}
+			rest = &rest[32..];
+		}
+
+		let mut hash = acc[0].rotate_left(1)
+			.wrapping_add(acc[1].rotate_left(7))
+			.wrapping_add(acc[2].rotate_left(12))
+			.wrapping_add(acc[3].rotate_left(18));
+		for lane in acc {
+			hash = xxh64_merge(hash, lane);
+		}
+		hash
+	} else {
+		XXH_PRIME64_5
+	};
+
+	hash = hash.wrapping_add(bytes.len() as u64);
+	while rest.len() >= 8 {
+		hash ^= xxh64_round(0, read_u64(rest));
+		hash = hash.rotate_left(27).wrapping_mul(XXH_PRIME64_1).wrapping_add(XXH_PRIME64_4);
+		rest = &rest[8..];
+	}
+	if rest.len() >= 4 {
+		let word = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+		hash ^= word.wrapping_mul(XXH_PRIME64_1);
+		hash = hash.rotate_left(23).wrapping_mul(XXH_PRIME64_2).wrapping_add(XXH_PRIME64_3);
+		rest = &rest[4..];
+	}
+	for &byte in rest {
+		hash ^= (byte as u64).wrapping_mul(XXH_PRIME64_5);
+		hash = hash.rotate_left(11).wrapping_mul(XXH_PRIME64_1);
+	}
+
+	hash ^= hash >> 33;
+	hash = hash.wrapping_mul(XXH_PRIME64_2);
+	hash ^= hash >> 29;
+	hash = hash.wrapping_mul(XXH_PRIME64_3);
+	hash ^ (hash >> 32)
+}
@@ -1,30 +1,37 @@
+use std::borrow::Cow;
 use std::fmt;
+use std::sync::Arc;
 
 use serde::{de, ser};
 
+use crate::limits::LimitBreach;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Clone, Debug, PartialEq)]
+// Variants carry the numbers behind the error where there are any, so callers
+// can match on them instead of parsing messages
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ErrorKind {
 	IOError,
 	Custom,
 	MissingFormatVersion,
 	EnumVariantIndexTooBig,
-	VarIntTooBig,
-	VarIntTooSmall,
+	VarIntTooBig, // varint is too big for the integer type it is read into
+	VarIntOverflow, // value is too big to be encoded as a varint
 	SerdeModelUnsupported,
-	TooManySectionFields,
+	TooManySectionFields { count: u64, max: u64 },
 	NoLength,
 	KeyBadType,
 	KeyBadEncoding,
-	KeyTooLong,
-	StringTooLong,
+	KeyTooLong { len: u64, max: u64 },
+	StringTooLong { len: u64, max: u64 },
 	StringBadEncoding,
 	ArrayMixedTypes,
 	NestedArrays,
-	ArrayTooLong,
+	ArrayTooLong { len: u64 },
+	SectionTooLong { len: u64 }, // more fields than a varint can count
 	TupleTooLong,
-	BadTypeCode,
+	BadTypeCode { code: u8 },
 	ExpectedArray,
 	ExpectedArrayEnd,
 	ExpectedFormatSignature,
@@ -34,32 +41,125 @@ pub enum ErrorKind {
 	NotExpectingSection,
 	NotExpectingScalar,
 	BadUnicodeScalar,
-	SizeHintMismatch,
+	SizeHintMismatch { expected: u64, found: u64 },
 	CompoundMissingArrayType,
 	EmptySectionKey,
 	TypeMismatch,
+	BudgetExceeded { max_bytes: u64 },
+	TooManyObjects { count: u64, max: u64 },
+	TooManyStrings { count: u64, max: u64 },
+	SectionTooDeep { max: u64 },
+	IOTimeout,
+	FloatDisabled,
+	ScratchOverflow { len: u64, capacity: u64 },
+	LengthOverflow { len: u64 }, // declared length or count which doesn't fit in usize or overflows size math
+	IntOutOfRange { value: i128 }, // integer entry which doesn't fit the field, see de::IntConversion
+	IncompatibleVersion { ours: u32, theirs: u32 }, // no protocol version in common, see negotiate
+	UnknownChecksum { code: u8 },
+	EnvelopeTruncated { len: u64, found: u64 }, // see envelope
+	ChecksumMismatch { expected: u64, found: u64 },
+	TruncatedCompound { expected: u64, got: u64 }, // input ended after got of expected entries of a section or array
+	SurplusEntries { expected: u64, got: u64 }, // a section or array was left with entries its visitor didn't read
+	UnknownDirection { code: u8 }, // see replay
+	UnknownTypeCode, // type code 0, see constants::EMPTY_UNKNOWN_ARRAY_TYPE_CODE
+	BadStatus, // RPC response status other than OK, see rpc::StatusError
+	AllocTooLarge { len: u64, max: u64 }, // strings and arrays declared in total, see Limits::max_alloc_bytes
 }
 
-#[derive(Debug)]
-pub struct Error {
+// Errors are boxed so that results stay small in the parsing hot paths, where
+// they are moved around after every read. Clones share the source error.
+#[derive(Clone, Debug)]
+pub struct Error(Box<ErrorImpl>);
+
+#[derive(Clone, Debug)]
+struct ErrorImpl {
 	kind: ErrorKind,
-	msg: String,
-	source: Option<Box<dyn std::error::Error>>
+	msg: Cow<'static, str>,
+	path: Option<String>, // key path of the value which failed, e.g. "blocks[2].txs"
+	source: Option<Source>
+}
+
+// What an error wraps. Only IO errors are ever wrapped, so there is no need for
+// a trait object and downcasts to get at them.
+#[derive(Clone, Debug)]
+enum Source {
+	Io(Arc<std::io::Error>)
 }
 
 ///////////////////////////////////////////////////////////////////////////////
 
 impl Error {
+	#[cold]
 	pub fn new(kind: ErrorKind, msg: String) -> Self {
-		Self { kind: kind, msg: msg, source: None }
+		Self::with_msg(kind, Cow::Owned(msg))
 	}
 
+	#[cold]
 	pub fn new_no_msg(kind: ErrorKind) -> Self {
-		Self { kind: kind, msg: String::from(""), source: None }
+		Self::with_msg(kind, Cow::Borrowed(""))
+	}
+
+	fn with_msg(kind: ErrorKind, msg: Cow<'static, str>) -> Self {
+		Self(Box::new(ErrorImpl { kind, msg, path: None, source: None }))
 	}
 
 	pub fn kind(&self) -> ErrorKind {
-		self.kind.clone()
+		self.0.kind.clone()
+	}
+
+	pub fn kind_ref(&self) -> &ErrorKind {
+		&self.0.kind
+	}
+
+	pub fn path(&self) -> Option<&str> {
+		self.0.path.as_deref()
+	}
+
+	// Which limit was exceeded, by how much, if this error is a limit breach.
+	// Together with path() this says which knob to turn for which value.
+	pub fn limit_breach(&self) -> Option<LimitBreach> {
+		LimitBreach::from_kind(&self.0.kind)
+	}
+
+	// For IO-centric middleware, see From<Error> for std::io::Error
+	pub fn into_io(self) -> std::io::Error {
+		self.into()
+	}
+
+	// Errors bubble up through every enclosing value, so only the innermost path is kept
+	pub(crate) fn with_path(mut self, path: &str) -> Self {
+		if self.0.path.is_none() && !path.is_empty() {
+			self.0.path = Some(path.to_string());
+		}
+		self
+	}
+
+	fn io_error(&self) -> Option<&std::io::Error> {
+		match &self.0.source {
+			Some(Source::Io(ioe)) => Some(ioe),
+			None => None
+		}
+	}
+
+	// Whether the input ended before a value was read completely
+	pub(crate) fn is_eof(&self) -> bool {
+		self.0.kind == ErrorKind::IOError && self.io_error().is_some_and(|ioe| ioe.kind() == std::io::ErrorKind::UnexpectedEof)
+	}
+
+	// Reclassifies an error, keeping its source so IO conversions still work
+	pub(crate) fn reclassify(mut self, kind: ErrorKind, msg: String) -> Self {
+		self.0.kind = kind;
+		self.0.msg = Cow::Owned(msg);
+		self.0.path = None;
+		self
+	}
+
+	// Names the wire type in type mismatches, which serde's visitors can't know
+	pub(crate) fn with_wire_type<T: fmt::Display>(mut self, wire_type: T) -> Self {
+		if self.0.kind == ErrorKind::TypeMismatch && self.0.path.is_none() {
+			self.0.msg = Cow::Owned(format!("{} (wire type {})", self.0.msg, wire_type));
+		}
+		self
 	}
 }
 
@@ -77,20 +177,37 @@ impl de::Error for Error {
 	fn custom<T: fmt::Display>(msg: T) -> Self {
 		Error::new(ErrorKind::Custom, msg.to_string())
 	}
+
+	fn invalid_type(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+		Error::new(ErrorKind::TypeMismatch, format!("expected {}, found {}", exp, unexp))
+	}
+
+	// Zero for serde's NonZero* impls is out of range, just like integers too
+	// wide for the field. Anything else, e.g. a bad enum variant index, stays
+	// Custom, since it isn't about the integer's range.
+	fn invalid_value(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+		let expected = exp.to_string();
+		let kind = match unexp {
+			de::Unexpected::Unsigned(value) if expected.starts_with("a nonzero ") => ErrorKind::IntOutOfRange { value: value as i128 },
+			de::Unexpected::Signed(value) if expected.starts_with("a nonzero ") => ErrorKind::IntOutOfRange { value: value as i128 },
+			_ => ErrorKind::Custom
+		};
+		Error::new(kind, format!("invalid value: {}, expected {}", unexp, expected))
+	}
 }
 
 impl fmt::Display for Error {
 	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-		formatter.write_fmt(format_args!("{:?}: {}", self.kind, self.msg))
+		match &self.0.path {
+			Some(path) => formatter.write_fmt(format_args!("{:?} at {}: {}", self.0.kind, path, self.0.msg)),
+			None => formatter.write_fmt(format_args!("{:?}: {}", self.0.kind, self.0.msg))
+		}
 	}
 }
 
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-		match &self.source {
-			Some(s) => Some(s.as_ref()),
-			None => None
-		}
+		self.io_error().map(|ioe| ioe as &(dyn std::error::Error + 'static))
 	}
 }
 
@@ -99,12 +216,37 @@ impl std::error::Error for Error {
 ///////////////////////////////////////////////////////////////////////////////
 
 impl From<std::io::Error> for Error {
+	#[cold]
 	fn from(ioe: std::io::Error) -> Self {
-		Self {
-			kind: ErrorKind::IOError,
-			msg: ioe.to_string(),
-			source: Some(Box::new(ioe))
-		}
+		// Timeouts are usually recoverable, so keep them distinguishable
+		let kind = match ioe.kind() {
+			std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => ErrorKind::IOTimeout,
+			_ => ErrorKind::IOError
+		};
+
+		Self(Box::new(ErrorImpl {
+			kind,
+			msg: Cow::Owned(ioe.to_string()),
+			path: None,
+			source: Some(Source::Io(Arc::new(ioe)))
+		}))
+	}
+}
+
+// Keeps the kind of the underlying IO error, everything else is InvalidData.
+// The whole Error stays available through io::Error::get_ref / into_inner.
+impl From<Error> for std::io::Error {
+	fn from(err: Error) -> Self {
+		let io_kind = err.io_error().map(|ioe| ioe.kind());
+
+		let kind = match (&err.0.kind, io_kind) {
+			(_, Some(io_kind)) => io_kind,
+			(ErrorKind::IOTimeout, None) => std::io::ErrorKind::TimedOut,
+			(ErrorKind::IOError, None) => std::io::ErrorKind::Other,
+			_ => std::io::ErrorKind::InvalidData
+		};
+
+		std::io::Error::new(kind, err)
 	}
 }
 
@@ -119,6 +261,12 @@ macro_rules! epee_err {
 	);
 	($kind:ident, $msg:expr) => (
 		Err(Error::new(ErrorKind::$kind, $msg.to_string()))
+	);
+	($kind:ident { $($field:ident: $value:expr),* }, $fmt:expr, $($fmt_args:expr), *) => (
+		Err(Error::new(ErrorKind::$kind { $($field: $value),* }, format!($fmt, $($fmt_args), *)))
+	);
+	($kind:ident { $($field:ident: $value:expr),* }, $msg:expr) => (
+		Err(Error::new(ErrorKind::$kind { $($field: $value),* }, $msg.to_string()))
 	)
 }
 
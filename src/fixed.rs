@@ -0,0 +1,121 @@
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+// Powers of ten up to 10^22 are exact doubles, and mantissas up to 2^53 are
+// exact too, so conversions within these bounds don't lose precision
+const MAX_SCALE: u32 = 22;
+const MAX_EXACT_MANTISSA: i64 = 1 << 53;
+
+///////////////////////////////////////////////////////////////////////////////
+// Fixed-point numbers                                                       //
+///////////////////////////////////////////////////////////////////////////////
+
+// Decimal number mantissa / 10^SCALE, for protocols which send money amounts
+// and fees as DOUBLE entries. Serializes to a DOUBLE. Decodes from a DOUBLE
+// only if it is the closest double to some mantissa / 10^SCALE, e.g. 0.0001
+// into Fixed<4> or Fixed<12>, but not into Fixed<3>, so amounts are never
+// silently rounded. Integer entries decode too. Mantissas must stay within
+// +-2^53, past which doubles can't hold every integer.
+//
+//     #[derive(Serialize, Deserialize)]
+//     struct Fee {
+//         fee: Fixed<12>, // XMR, with atomic units as the mantissa
+//     }
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed<const SCALE: u32> {
+	pub mantissa: i64
+}
+
+impl<const SCALE: u32> Fixed<SCALE> {
+	const CHECK_SCALE: () = assert!(SCALE <= MAX_SCALE, "Fixed scales past 22 can't be converted exactly");
+
+	pub fn new(mantissa: i64) -> Self {
+		Self { mantissa }
+	}
+
+	fn unit() -> f64 {
+		#[allow(clippy::let_unit_value)]
+		let _ = Self::CHECK_SCALE;
+		10f64.powi(SCALE as i32)
+	}
+
+	pub fn to_f64(self) -> f64 {
+		self.mantissa as f64 / Self::unit()
+	}
+
+	// None if value isn't the closest double to any mantissa / 10^SCALE
+	pub fn from_f64(value: f64) -> Option<Self> {
+		let scaled = (value * Self::unit()).round();
+		if !scaled.is_finite() || scaled.abs() > MAX_EXACT_MANTISSA as f64 {
+			return None;
+		}
+
+		let fixed = Self::new(scaled as i64);
+		if fixed.to_f64() == value {
+			Some(fixed)
+		} else {
+			None
+		}
+	}
+
+	pub fn from_integer(value: i64) -> Option<Self> {
+		let mantissa = value.checked_mul(10i64.checked_pow(SCALE)?)?;
+		if mantissa.unsigned_abs() > MAX_EXACT_MANTISSA as u64 {
+			return None;
+		}
+		Some(Self::new(mantissa))
+	}
+}
+
+impl<const SCALE: u32> fmt::Display for Fixed<SCALE> {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		let sign = if self.mantissa < 0 { "-" } else { "" };
+		let unit = 10u128.pow(SCALE);
+		let whole = self.mantissa.unsigned_abs() as u128 / unit;
+		let fraction = self.mantissa.unsigned_abs() as u128 % unit;
+		if SCALE == 0 {
+			write!(formatter, "{}{}", sign, whole)
+		} else {
+			write!(formatter, "{}{}.{:0width$}", sign, whole, fraction, width = SCALE as usize)
+		}
+	}
+}
+
+impl<const SCALE: u32> Serialize for Fixed<SCALE> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		if self.mantissa.unsigned_abs() > MAX_EXACT_MANTISSA as u64 {
+			return Err(serde::ser::Error::custom(format!("mantissa {} is too big to be sent as a double", self.mantissa)));
+		}
+		serializer.serialize_f64(self.to_f64())
+	}
+}
+
+impl<'de, const SCALE: u32> Deserialize<'de> for Fixed<SCALE> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_f64(FixedVisitor::<SCALE>)
+	}
+}
+
+struct FixedVisitor<const SCALE: u32>;
+
+impl<const SCALE: u32> Visitor<'_> for FixedVisitor<SCALE> {
+	type Value = Fixed<SCALE>;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		write!(formatter, "a number with at most {} decimal places", SCALE)
+	}
+
+	fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+		Fixed::from_f64(value).ok_or_else(|| E::invalid_value(Unexpected::Float(value), &self))
+	}
+
+	fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+		Fixed::from_integer(value).ok_or_else(|| E::invalid_value(Unexpected::Signed(value), &self))
+	}
+
+	fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+		i64::try_from(value).ok().and_then(Fixed::from_integer).ok_or_else(|| E::invalid_value(Unexpected::Unsigned(value), &self))
+	}
+}
@@ -0,0 +1,74 @@
+use crate::constants;
+use crate::VarInt;
+
+///////////////////////////////////////////////////////////////////////////////
+// Format profiles                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+// Signature and version written at the start of every document. Forks of
+// Monero which changed their signature can plug their own profile into the
+// Serializer and Deserializer instead of patching the constants.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatProfile {
+	pub signature_a: u32,
+	pub signature_b: u32,
+	pub version: u8
+}
+
+impl FormatProfile {
+	pub const fn monero() -> Self {
+		Self {
+			signature_a: constants::PORTABLE_STORAGE_SIGNATUREA,
+			signature_b: constants::PORTABLE_STORAGE_SIGNATUREB,
+			version: constants::PORTABLE_STORAGE_FORMAT_VER
+		}
+	}
+
+	pub const fn signature(&self) -> [u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE] {
+		constants::make_signature(self.signature_a, self.signature_b, self.version)
+	}
+}
+
+impl Default for FormatProfile {
+	fn default() -> Self {
+		Self::monero()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Format detection                                                          //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatInfo {
+	pub version: u8,
+	pub num_root_fields: Option<u64> // None if the prefix ends before the field count
+}
+
+impl FormatInfo {
+	pub fn is_supported_version(&self) -> bool {
+		self.version == constants::PORTABLE_STORAGE_FORMAT_VER
+	}
+}
+
+// Checks whether a buffer (or just its first few bytes) looks like a portable
+// storage document. Returns None if the signature doesn't match or is cut off.
+pub fn sniff(prefix: &[u8]) -> Option<FormatInfo> {
+	if prefix.len() < constants::PORTABLE_STORAGE_SIGNATURE_SIZE {
+		return None;
+	}
+
+	let signature = prefix[..constants::PORTABLE_STORAGE_SIGNATURE_SIZE].try_into().ok()?;
+	let (signature_a, signature_b, version) = constants::split_signature(signature);
+	if signature_a != constants::PORTABLE_STORAGE_SIGNATUREA || signature_b != constants::PORTABLE_STORAGE_SIGNATUREB {
+		return None;
+	}
+
+	let mut rest = &prefix[constants::PORTABLE_STORAGE_SIGNATURE_SIZE..];
+	let num_root_fields = VarInt::from_reader(&mut rest).ok().map(u64::from);
+
+	Some(FormatInfo {
+		version,
+		num_root_fields
+	})
+}
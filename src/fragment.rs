@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Serializer};
+
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::types::EpeeEntryType;
+use crate::{section, VarInt};
+
+// Newtype struct name the serializer recognizes to splice raw bytes in
+pub(crate) const RAW_FRAGMENT_TOKEN: &str = "$serde_epee::private::RawFragment";
+
+///////////////////////////////////////////////////////////////////////////////
+// Pre-encoded fragments                                                     //
+///////////////////////////////////////////////////////////////////////////////
+
+// An already encoded entry value: a type code followed by the value's payload
+// exactly as it would appear after the type code inside a section. Serializing
+// a RawFragment as a field writes those bytes verbatim, so a cached sub-object
+// is only encoded once. The enclosing section still counts it as one field.
+// Note that objects and strings inside the fragment are not charged against
+// the serializer's limits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawFragment {
+	encoded: Vec<u8> // type code byte, then payload
+}
+
+impl RawFragment {
+	// Checks that payload is exactly one well-formed entry of type_code, since
+	// it's written out verbatim and a bad one would corrupt the whole document
+	pub fn new(type_code: u8, payload: &[u8]) -> Result<Self> {
+		EpeeEntryType::from_type_code(type_code)?;
+		let mut reader = payload;
+		crate::skip_entry(&mut reader, type_code)?;
+		if !reader.is_empty() {
+			return epee_err!(ExpectedEnd, "{} bytes left over after entry", reader.len());
+		}
+
+		let mut encoded = Vec::with_capacity(payload.len() + 1);
+		encoded.push(type_code);
+		encoded.extend_from_slice(payload);
+		Ok(Self { encoded })
+	}
+
+	// Encodes value once so that it can be embedded many times
+	pub fn from_value<T: ?Sized + Serialize>(value: &T) -> Result<Self> {
+		// Wrap value as the only field of a root section and cut off everything
+		// in front of the type code: signature, field count and key "v"
+		const PREFIX_LEN: usize = constants::PORTABLE_STORAGE_SIGNATURE_SIZE + 3;
+
+		let wrapper = HashMap::from([("v", value)]);
+		let bytes = crate::to_bytes(&wrapper)?;
+		if bytes.len() <= PREFIX_LEN {
+			return epee_err!(SerdeModelUnsupported, "value did not serialize to an entry");
+		}
+
+		Ok(Self { encoded: bytes[PREFIX_LEN..].to_vec() })
+	}
+
+	pub fn type_code(&self) -> u8 {
+		self.encoded[0]
+	}
+
+	pub fn payload(&self) -> &[u8] {
+		&self.encoded[1..]
+	}
+}
+
+struct EncodedBytes<'a>(&'a [u8]);
+
+impl Serialize for EncodedBytes<'_> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serializer.serialize_bytes(self.0)
+	}
+}
+
+// Other serializers just see a newtype around a byte string
+impl Serialize for RawFragment {
+	fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serializer.serialize_newtype_struct(RAW_FRAGMENT_TOKEN, &EncodedBytes(&self.encoded))
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Raw entries of a document                                                 //
+///////////////////////////////////////////////////////////////////////////////
+
+// Iterates over the fields of a document's root section as (key, type code,
+// payload) without decoding them, so that dispatchers can route subtrees by
+// key. Payloads are sliced out of bytes and can be decoded separately with
+// value::from_raw_bytes or re-embedded with RawFragment::new. Iteration stops
+// after the first error. Bytes after the root section fail with ExpectedEnd.
+pub struct RawEntries<'a> {
+	rest: &'a [u8],
+	remaining: u64,
+	failed: bool
+}
+
+impl<'a> RawEntries<'a> {
+	pub fn new(bytes: &'a [u8]) -> Result<Self> {
+		let mut rest = bytes;
+		section::read_signature(&mut rest)?;
+		let remaining = VarInt::from_reader(&mut rest)?.into();
+		Ok(Self { rest, remaining, failed: false })
+	}
+
+	// Number of fields not yielded yet, as declared by the document
+	pub fn remaining(&self) -> u64 {
+		self.remaining
+	}
+
+	fn next_entry(&mut self) -> Result<(&'a str, u8, &'a [u8])> {
+		let keylen = match self.rest.first() {
+			Some(&0) => return epee_err!(EmptySectionKey, "section key length can not be zero!"),
+			Some(&keylen) => keylen as usize,
+			None => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+		};
+		if self.rest.len() < keylen + 2 {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+
+		let key = match std::str::from_utf8(&self.rest[1..1 + keylen]) {
+			Ok(key) => key,
+			Err(_) => return epee_err!(StringBadEncoding, "UTF-8 encoding error while parsing byte buffer for string key")
+		};
+		let type_code = self.rest[1 + keylen];
+
+		let payload_start = &self.rest[keylen + 2..];
+		let mut reader = payload_start;
+		crate::skip_entry(&mut reader, type_code)?;
+		let payload = &payload_start[..payload_start.len() - reader.len()];
+		self.rest = reader;
+		Ok((key, type_code, payload))
+	}
+}
+
+impl<'a> Iterator for RawEntries<'a> {
+	type Item = Result<(&'a str, u8, &'a [u8])>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None;
+		} else if self.remaining == 0 {
+			if self.rest.is_empty() {
+				return None;
+			}
+			self.failed = true;
+			return Some(epee_err!(ExpectedEnd, "{} bytes left over after document", self.rest.len()));
+		}
+
+		self.remaining -= 1;
+		let res = self.next_entry();
+		self.failed = res.is_err();
+		Some(res)
+	}
+}
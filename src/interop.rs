@@ -0,0 +1,183 @@
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::Serializer;
+use serde_bytes::ByteBuf;
+
+///////////////////////////////////////////////////////////////////////////////
+// Adapters for fixed size byte types                                        //
+///////////////////////////////////////////////////////////////////////////////
+
+// Fixed size byte types behind AsRef<[u8]> and TryFrom<&[u8]> (e.g. [u8; 32]
+// or a caller's own hash newtype), which monerod stores as blobs:
+//
+//     #[serde(with = "serde_epee::interop::pod")]
+//     top_block_hash: [u8; 32],
+//     #[serde(with = "serde_epee::interop::pod_list")]
+//     block_ids: Vec<[u8; 32]>,
+//
+// The types of the monero crate don't all implement those traits, see the
+// monero module below.
+
+// Byte vectors and slices as STRING entries, same as serde_bytes
+pub use serde_bytes as blob;
+
+// A single value as a STRING entry (KV_SERIALIZE_VAL_POD_AS_BLOB)
+pub mod pod {
+	use super::*;
+
+	pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		T: AsRef<[u8]>,
+		S: Serializer
+	{
+		serializer.serialize_bytes(value.as_ref())
+	}
+
+	pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+	where
+		T: for<'a> TryFrom<&'a [u8]>,
+		for<'a> <T as TryFrom<&'a [u8]>>::Error: fmt::Display,
+		D: Deserializer<'de>
+	{
+		let bytes = ByteBuf::deserialize(deserializer)?;
+		T::try_from(&bytes).map_err(de::Error::custom)
+	}
+}
+
+// A Vec of values concatenated into one STRING entry
+// (KV_SERIALIZE_CONTAINER_POD_AS_BLOB). The size of a value is taken from
+// T::default(), so T must have a fixed size.
+pub mod pod_list {
+	use super::*;
+
+	pub fn serialize<T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+	where
+		T: AsRef<[u8]>,
+		S: Serializer
+	{
+		let bytes: Vec<u8> = values.iter().flat_map(|value| value.as_ref().iter().copied()).collect();
+		serializer.serialize_bytes(&bytes)
+	}
+
+	pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+	where
+		T: AsRef<[u8]> + Default + for<'a> TryFrom<&'a [u8]>,
+		for<'a> <T as TryFrom<&'a [u8]>>::Error: fmt::Display,
+		D: Deserializer<'de>
+	{
+		let bytes = ByteBuf::deserialize(deserializer)?;
+		let size = T::default().as_ref().len();
+		if size == 0 || bytes.len() % size != 0 {
+			return Err(de::Error::custom(format!("{} byte blob is not a list of {} byte values", bytes.len(), size)));
+		}
+		bytes.chunks(size).map(|chunk| T::try_from(chunk).map_err(de::Error::custom)).collect()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Adapters for the monero crate                                             //
+///////////////////////////////////////////////////////////////////////////////
+
+// Types of the monero crate in EPEE documents, with the "monero" feature:
+//
+//     #[serde(with = "serde_epee::interop::monero::hash")]
+//     top_block_hash: monero::Hash,
+//     #[serde(with = "serde_epee::interop::monero::hash_list")]
+//     block_ids: Vec<monero::Hash>,
+//     #[serde(with = "serde_epee::interop::monero::block_header")]
+//     header: monero::BlockHeader,
+#[cfg(feature = "monero")]
+pub mod monero {
+	use serde::{Deserialize, Serialize};
+
+	use super::*;
+
+	// A monero::Hash as a 32 byte STRING entry
+	pub mod hash {
+		use super::*;
+
+		pub fn serialize<S: Serializer>(hash: &::monero::Hash, serializer: S) -> Result<S::Ok, S::Error> {
+			serializer.serialize_bytes(hash.as_bytes())
+		}
+
+		pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<::monero::Hash, D::Error> {
+			let bytes = ByteBuf::deserialize(deserializer)?;
+			let bytes: [u8; 32] = bytes.as_slice().try_into()
+				.map_err(|_| de::Error::invalid_length(bytes.len(), &"a 32 byte hash"))?;
+			Ok(::monero::Hash::from(bytes))
+		}
+	}
+
+	// A Vec of monero::Hash concatenated into one STRING entry
+	// (KV_SERIALIZE_CONTAINER_POD_AS_BLOB)
+	pub mod hash_list {
+		use super::*;
+
+		pub fn serialize<S: Serializer>(hashes: &[::monero::Hash], serializer: S) -> Result<S::Ok, S::Error> {
+			let bytes: Vec<u8> = hashes.iter().flat_map(|hash| hash.as_bytes().iter().copied()).collect();
+			serializer.serialize_bytes(&bytes)
+		}
+
+		pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<::monero::Hash>, D::Error> {
+			let bytes = ByteBuf::deserialize(deserializer)?;
+			if bytes.len() % 32 != 0 {
+				return Err(de::Error::custom(format!("{} byte blob is not a list of 32 byte hashes", bytes.len())));
+			}
+			Ok(bytes.chunks(32).map(::monero::Hash::from_slice).collect())
+		}
+	}
+
+	// A monero::PublicKey as a 32 byte STRING entry. Bytes which aren't a
+	// point on the curve fail to decode.
+	pub mod public_key {
+		use super::*;
+
+		pub fn serialize<S: Serializer>(key: &::monero::PublicKey, serializer: S) -> Result<S::Ok, S::Error> {
+			serializer.serialize_bytes(key.as_bytes())
+		}
+
+		pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<::monero::PublicKey, D::Error> {
+			let bytes = ByteBuf::deserialize(deserializer)?;
+			::monero::PublicKey::from_slice(&bytes).map_err(de::Error::custom)
+		}
+	}
+
+	// A monero::BlockHeader as a section, with the fields and types monerod's
+	// block_header_response uses for them
+	pub mod block_header {
+		use super::*;
+
+		#[derive(Serialize, Deserialize)]
+		struct Header {
+			major_version: u8,
+			minor_version: u8,
+			timestamp: u64,
+			#[serde(with = "super::hash")]
+			prev_hash: ::monero::Hash,
+			nonce: u32
+		}
+
+		pub fn serialize<S: Serializer>(header: &::monero::BlockHeader, serializer: S) -> Result<S::Ok, S::Error> {
+			let version = |version: &::monero::VarInt| u8::try_from(version.0).map_err(|_| serde::ser::Error::custom(format!("block version {} does not fit in u8", version.0)));
+			Header {
+				major_version: version(&header.major_version)?,
+				minor_version: version(&header.minor_version)?,
+				timestamp: header.timestamp.0,
+				prev_hash: header.prev_id,
+				nonce: header.nonce
+			}.serialize(serializer)
+		}
+
+		pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<::monero::BlockHeader, D::Error> {
+			let header = Header::deserialize(deserializer)?;
+			Ok(::monero::BlockHeader {
+				major_version: ::monero::VarInt(header.major_version as u64),
+				minor_version: ::monero::VarInt(header.minor_version as u64),
+				timestamp: ::monero::VarInt(header.timestamp),
+				prev_id: header.prev_hash,
+				nonce: header.nonce
+			})
+		}
+	}
+}
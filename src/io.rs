@@ -0,0 +1,121 @@
+use std::io::{self, Read};
+
+///////////////////////////////////////////////////////////////////////////////
+// Retrying reads                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RetryAction {
+	Retry,
+	Fail
+}
+
+// True for errors which a non-blocking or timed out socket returns when no data
+// is available yet (WouldBlock, TimedOut) or when the read was interrupted
+pub fn is_transient(err: &io::Error) -> bool {
+	matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted)
+}
+
+// Reader adapter which asks hook what to do when the inner reader fails with a
+// transient error. The hook gets the error and the number of consecutive failed
+// attempts, so it can sleep, poll, or give up. Retrying at this level means a
+// partially read value is not lost, unlike retrying a whole read_exact().
+pub struct RetryReader<R, F> {
+	inner: R,
+	hook: F,
+	attempts: u32
+}
+
+impl<R, F> RetryReader<R, F>
+where
+	R: Read,
+	F: FnMut(&io::Error, u32) -> RetryAction
+{
+	pub fn new(inner: R, hook: F) -> Self {
+		Self {
+			inner,
+			hook,
+			attempts: 0
+		}
+	}
+
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+impl<R, F> Read for RetryReader<R, F>
+where
+	R: Read,
+	F: FnMut(&io::Error, u32) -> RetryAction
+{
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		loop {
+			match self.inner.read(buf) {
+				Ok(nread) => {
+					self.attempts = 0;
+					return Ok(nread);
+				},
+				Err(err) if is_transient(&err) => {
+					self.attempts += 1;
+					if (self.hook)(&err, self.attempts) == RetryAction::Fail {
+						return Err(err);
+					}
+				},
+				Err(err) => return Err(err)
+			}
+		}
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Chunked reads                                                             //
+///////////////////////////////////////////////////////////////////////////////
+
+// Reader adapter which never asks the inner reader for more than chunk_size
+// bytes at once, and calls hook with the total number of bytes read after each
+// read. TLS and proxy layers can hand over megabytes in one go, which would
+// otherwise be copied into a large string or blob without a break; services
+// sharing threads can yield or check a deadline in hook to keep latency even.
+pub struct ChunkedReader<R, F = fn(u64)> {
+	inner: R,
+	chunk_size: usize,
+	hook: F,
+	total: u64
+}
+
+impl<R: Read> ChunkedReader<R> {
+	pub fn new(inner: R, chunk_size: usize) -> Self {
+		Self {
+			inner,
+			chunk_size: std::cmp::max(chunk_size, 1),
+			hook: |_| {},
+			total: 0
+		}
+	}
+}
+
+impl<R: Read, F: FnMut(u64)> ChunkedReader<R, F> {
+	pub fn with_hook<G: FnMut(u64)>(self, hook: G) -> ChunkedReader<R, G> {
+		ChunkedReader {
+			inner: self.inner,
+			chunk_size: self.chunk_size,
+			hook,
+			total: self.total
+		}
+	}
+
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+impl<R: Read, F: FnMut(u64)> Read for ChunkedReader<R, F> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let max_read = std::cmp::min(buf.len(), self.chunk_size);
+		let nread = self.inner.read(&mut buf[..max_read])?;
+		self.total += nread as u64;
+		(self.hook)(self.total);
+		Ok(nread)
+	}
+}
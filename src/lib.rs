@@ -1,15 +1,71 @@
+
+pub mod audit;
+pub mod blob;
+pub mod codegen;
+pub mod custom;
 pub mod de;
+pub mod diff;
+pub mod document;
+pub mod dump;
+pub mod envelope;
+pub mod fixed;
+pub mod scan;
+pub mod schema;
 pub mod ser;
 pub mod section;
+pub mod testing;
+pub mod trace;
 pub mod constants;
 pub mod error;
+pub mod format;
+pub mod interop;
+pub mod fragment;
+pub mod io;
+pub mod limits;
+pub mod migrate;
+pub mod mixed;
+pub mod negotiate;
+pub mod packed;
+pub mod peerlist;
+pub mod replay;
+pub mod rpc;
+pub mod types;
+pub mod value;
 pub mod varint;
+#[cfg(feature = "bytes")]
+pub mod buf;
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compress;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "serde_with")]
+pub mod with;
 
 // Conventional serde package structure
-pub use de::{from_bytes, from_reader};
+pub use de::{Deserializer, Documents, from_bytes, from_cow, from_reader, from_reader_lenient, from_reader_limited, from_slice, read_entry, skip_entry};
+pub use document::{Document, from_reader_buffered};
 pub use error::{Error, Result, ErrorKind};
-pub use ser::{to_bytes, to_writer};
+pub use limits::{LimitBreach, Limits};
+pub use ser::{Serializer, serialized_size, to_bytes, to_bytes_with_capacity, to_writer, to_writer_counted, write_entry};
 
 // EPEE-specific data types
-pub use section::Section;
-pub use varint::VarInt;
\ No newline at end of file
+pub use constants::TypeCode;
+pub use fixed::Fixed;
+pub use format::{FormatInfo, FormatProfile, sniff};
+pub use fragment::{RawEntries, RawFragment};
+pub use migrate::migrate;
+pub use mixed::MixedArray;
+pub use schema::{ValidationReport, validate_as};
+pub use section::{Section, from_bytes_lossy};
+pub use types::{EpeeEntryType, EpeeObject, EpeeScalarType};
+pub use value::from_section;
+pub use varint::VarInt;
+
+// Field adapters, for #[serde(with = "...")]
+pub use packed::{packed_le_f64, packed_le_i16, packed_le_i32, packed_le_i64, packed_le_u16, packed_le_u32, packed_le_u64};
+
+// Macro helpers
+#[cfg(feature = "derive")]
+pub use serde_epee_derive::epee_object;
@@ -0,0 +1,200 @@
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result};
+use crate::types::EpeeScalarType;
+
+///////////////////////////////////////////////////////////////////////////////
+// Limits profiles                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+// Resource limits applied while reading documents, and while writing them if
+// asked to with Serializer::with_limits. Like in monerod, the object, field and
+// string limits are cumulative over a whole document.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Limits {
+	pub max_depth: usize, // max nesting of sections, the root section counts as 1
+	pub max_objects: usize, // max number of non-root sections
+	pub max_fields: usize, // max number of section fields
+	pub max_strings: usize, // max number of string values
+	pub max_string_len: usize, // max length of a single string value
+	// Max bytes of string values and array payloads a document may declare in
+	// total, checked before anything is allocated for them. See
+	// Deserializer::with_frame_len.
+	pub max_alloc_bytes: usize
+}
+
+impl Limits {
+	// Defaults of monerod's portable storage reader
+	pub const fn monerod() -> Self {
+		Self {
+			max_depth: constants::MONEROD_RECURSION_LIMIT,
+			max_objects: constants::MONEROD_OBJECT_LIMIT,
+			max_fields: constants::MONEROD_OBJECT_FIELD_LIMIT,
+			max_strings: constants::MONEROD_STRING_LIMIT,
+			max_string_len: constants::MAX_STRING_LEN_POSSIBLE,
+			max_alloc_bytes: usize::MAX
+		}
+	}
+
+	// Tighter limits monerod applies to payloads received over levin (P2P)
+	pub const fn monerod_levin() -> Self {
+		Self {
+			max_depth: constants::MONEROD_RECURSION_LIMIT,
+			max_objects: constants::MONEROD_LEVIN_OBJECT_LIMIT,
+			max_fields: constants::MONEROD_LEVIN_OBJECT_FIELD_LIMIT,
+			max_strings: constants::MONEROD_LEVIN_STRING_LIMIT,
+			max_string_len: constants::MAX_STRING_LEN_POSSIBLE,
+			max_alloc_bytes: usize::MAX
+		}
+	}
+
+	pub const fn unlimited() -> Self {
+		Self {
+			max_depth: usize::MAX,
+			max_objects: usize::MAX,
+			max_fields: usize::MAX,
+			max_strings: usize::MAX,
+			max_string_len: usize::MAX,
+			max_alloc_bytes: usize::MAX
+		}
+	}
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self::monerod()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Limit breaches                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Which limit a document ran into, see Error::limit_breach. limit is the name
+// of the Limits field to raise if the document should be accepted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LimitBreach {
+	pub limit: &'static str,
+	pub observed: u64,
+	pub max: u64
+}
+
+impl LimitBreach {
+	pub(crate) fn from_kind(kind: &ErrorKind) -> Option<Self> {
+		let (limit, observed, max) = match *kind {
+			ErrorKind::SectionTooDeep { max } => ("max_depth", max.saturating_add(1), max),
+			ErrorKind::TooManySectionFields { count, max } => ("max_fields", count, max),
+			ErrorKind::TooManyObjects { count, max } => ("max_objects", count, max),
+			ErrorKind::TooManyStrings { count, max } => ("max_strings", count, max),
+			ErrorKind::StringTooLong { len, max } => ("max_string_len", len, max),
+			ErrorKind::AllocTooLarge { len, max } => ("max_alloc_bytes", len, max),
+			_ => return None
+		};
+		Some(Self { limit, observed, max })
+	}
+}
+
+fn breach(kind: ErrorKind, what: &str) -> Result<()> {
+	let breach = LimitBreach::from_kind(&kind).unwrap();
+	let msg = format!("{} is {}, max is {} (raise Limits::{} to accept it)", what, breach.observed, breach.max, breach.limit);
+	Err(Error::new(kind, msg))
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Limit tracking                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Keeps the running totals for a single document. Counts are u64 so that
+// declared lengths are checked before they are narrowed to usize.
+#[derive(Debug, Clone)]
+pub(crate) struct LimitTracker {
+	limits: Limits,
+	depth: usize,
+	num_objects: u64,
+	num_fields: u64,
+	num_strings: u64,
+	num_alloc_bytes: u64
+}
+
+impl LimitTracker {
+	pub(crate) fn new(limits: Limits) -> Self {
+		Self {
+			limits,
+			depth: 0,
+			num_objects: 0,
+			num_fields: 0,
+			num_strings: 0,
+			num_alloc_bytes: 0
+		}
+	}
+
+	pub(crate) fn limits(&self) -> &Limits {
+		&self.limits
+	}
+
+	pub(crate) fn enter_section(&mut self, num_fields: u64) -> Result<()> {
+		if self.depth >= self.limits.max_depth {
+			return breach(ErrorKind::SectionTooDeep { max: self.limits.max_depth as u64 }, "section depth");
+		}
+
+		self.num_fields = self.num_fields.saturating_add(num_fields);
+		if self.num_fields > self.limits.max_fields as u64 {
+			return breach(ErrorKind::TooManySectionFields { count: self.num_fields, max: self.limits.max_fields as u64 }, "number of fields in document");
+		}
+
+		self.depth += 1;
+		Ok(())
+	}
+
+	pub(crate) fn leave_section(&mut self) {
+		self.depth = self.depth.saturating_sub(1);
+	}
+
+	pub(crate) fn add_objects(&mut self, count: u64) -> Result<()> {
+		self.num_objects = self.num_objects.saturating_add(count);
+		if self.num_objects > self.limits.max_objects as u64 {
+			return breach(ErrorKind::TooManyObjects { count: self.num_objects, max: self.limits.max_objects as u64 }, "number of objects in document");
+		}
+		Ok(())
+	}
+
+	pub(crate) fn add_strings(&mut self, count: u64) -> Result<()> {
+		self.num_strings = self.num_strings.saturating_add(count);
+		if self.num_strings > self.limits.max_strings as u64 {
+			return breach(ErrorKind::TooManyStrings { count: self.num_strings, max: self.limits.max_strings as u64 }, "number of strings in document");
+		}
+		Ok(())
+	}
+
+	// Charges count values of the given type against the object or string limit
+	pub(crate) fn add_values(&mut self, scalar_type: EpeeScalarType, count: u64) -> Result<()> {
+		match scalar_type {
+			EpeeScalarType::Object => self.add_objects(count),
+			EpeeScalarType::Str => self.add_strings(count),
+			_ => Ok(())
+		}
+	}
+
+	// Charges bytes which are about to be allocated or read into values. Only
+	// sizes taken from the document count, so that documents which fit in a
+	// frame of max_alloc_bytes always pass.
+	pub(crate) fn add_alloc_bytes(&mut self, bytes: u64) -> Result<()> {
+		self.num_alloc_bytes = self.num_alloc_bytes.saturating_add(bytes);
+		if self.num_alloc_bytes > self.limits.max_alloc_bytes as u64 {
+			return breach(ErrorKind::AllocTooLarge { len: self.num_alloc_bytes, max: self.limits.max_alloc_bytes as u64 }, "bytes declared by document");
+		}
+		Ok(())
+	}
+
+	pub(crate) fn check_string_len(&self, len: u64) -> Result<()> {
+		if len > self.limits.max_string_len as u64 {
+			return breach(ErrorKind::StringTooLong { len, max: self.limits.max_string_len as u64 }, "string length");
+		}
+		Ok(())
+	}
+}
+
+impl Default for LimitTracker {
+	fn default() -> Self {
+		Self::new(Limits::default())
+	}
+}
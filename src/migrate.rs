@@ -0,0 +1,20 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::Result;
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Upgrades a persisted document from schema TOld to schema TNew. The old
+// document is read leniently (see de::from_reader_lenient), so files which
+// were already partially upgraded by a newer version still load as TOld.
+pub fn migrate<TOld, TNew, F>(bytes: &[u8], upgrade: F) -> Result<Vec<u8>>
+where
+	TOld: DeserializeOwned,
+	TNew: Serialize,
+	F: FnOnce(TOld) -> TNew
+{
+	let old: TOld = crate::de::from_reader_lenient(bytes)?;
+	crate::to_bytes(&upgrade(old))
+}
@@ -0,0 +1,76 @@
+use std::ops::{Deref, DerefMut};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::section::SectionEntry;
+
+///////////////////////////////////////////////////////////////////////////////
+// Heterogeneous arrays                                                      //
+///////////////////////////////////////////////////////////////////////////////
+
+// Key of the single field wrapping each element on the wire
+pub const MIXED_ARRAY_KEY: &str = "v";
+
+// EPEE arrays must have one element type and can't contain arrays. A MixedArray
+// gets around that by encoding each element as a section with a single field:
+//
+//     [5u64, "abc", [1u32, 2u32]]  =>  [{"v": 5u64}, {"v": "abc"}, {"v": [1u32, 2u32]}]
+//
+// T is usually SectionEntry or an #[serde(untagged)] enum, so that elements can
+// be told apart again when decoding. Other implementations will have to read
+// these arrays as arrays of objects with a field named "v".
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MixedArray<T = SectionEntry>(pub Vec<T>);
+
+#[derive(Serialize)]
+struct ElementRef<'a, T> {
+	v: &'a T
+}
+
+#[derive(Deserialize)]
+struct Element<T> {
+	v: T
+}
+
+impl<T> MixedArray<T> {
+	pub fn new() -> Self {
+		Self(Vec::new())
+	}
+
+	pub fn into_inner(self) -> Vec<T> {
+		self.0
+	}
+}
+
+impl<T> Deref for MixedArray<T> {
+	type Target = Vec<T>;
+
+	fn deref(&self) -> &Vec<T> {
+		&self.0
+	}
+}
+
+impl<T> DerefMut for MixedArray<T> {
+	fn deref_mut(&mut self) -> &mut Vec<T> {
+		&mut self.0
+	}
+}
+
+impl<T> From<Vec<T>> for MixedArray<T> {
+	fn from(elements: Vec<T>) -> Self {
+		Self(elements)
+	}
+}
+
+impl<T: Serialize> Serialize for MixedArray<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serializer.collect_seq(self.0.iter().map(|elem| ElementRef { v: elem }))
+	}
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MixedArray<T> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+		let elements = Vec::<Element<T>>::deserialize(deserializer)?;
+		Ok(Self(elements.into_iter().map(|elem| elem.v).collect()))
+	}
+}
@@ -0,0 +1,80 @@
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::de::Deserializer;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::limits::Limits;
+
+// Preambles are tiny, so a peer sending more than this is misbehaving
+pub const MAX_PREAMBLE_SIZE: u64 = 4096;
+
+///////////////////////////////////////////////////////////////////////////////
+// Preamble                                                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+// First document each side of a connection sends. Fields added to it later are
+// skipped by older peers, see Deserializer::with_lenient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preamble {
+	pub version: u32, // newest protocol version spoken
+	#[serde(default)]
+	pub min_version: u32, // oldest protocol version still spoken
+	#[serde(default)]
+	pub caps: u64 // capability bits, meaning is up to the protocol
+}
+
+impl Preamble {
+	pub fn new(version: u32, caps: u64) -> Self {
+		Self { version, min_version: 0, caps }
+	}
+}
+
+// Outcome of negotiate, which both sides compute identically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Agreement {
+	pub version: u32, // newest version both sides speak
+	pub caps: u64, // capabilities both sides have
+	pub peer: Preamble
+}
+
+impl Agreement {
+	pub fn has_caps(&self, caps: u64) -> bool {
+		self.caps & caps == caps
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Sends our preamble, then reads the peer's, so both sides can call this at
+// the same time. Nothing past the peer's preamble is read, the connection is
+// ready for the first message afterwards. Fails with IncompatibleVersion if no
+// version is spoken by both sides.
+pub fn negotiate<R: Read, W: Write>(mut reader: R, mut writer: W, ours: &Preamble) -> Result<Agreement> {
+	crate::to_writer(&mut writer, ours)?;
+	writer.flush()?;
+
+	let limits = Limits { max_depth: 1, max_objects: 0, ..Limits::monerod_levin() };
+	let mut deserializer = Deserializer::from_reader(reader.by_ref().take(MAX_PREAMBLE_SIZE)).with_lenient().with_limits(limits);
+	let peer = Preamble::deserialize(&mut deserializer)?;
+
+	agree(ours, &peer)
+}
+
+// The negotiation without the exchange, for protocols which carry preambles
+// inside of their own handshake messages
+pub fn agree(ours: &Preamble, peer: &Preamble) -> Result<Agreement> {
+	let version = std::cmp::min(ours.version, peer.version);
+	if version < ours.min_version || version < peer.min_version {
+		return epee_err!(IncompatibleVersion { ours: ours.version, theirs: peer.version }, "we speak versions {}-{}, peer speaks {}-{}",
+			ours.min_version, ours.version, peer.min_version, peer.version);
+	}
+
+	Ok(Agreement {
+		version,
+		caps: ours.caps & peer.caps,
+		peer: *peer
+	})
+}
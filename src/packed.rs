@@ -0,0 +1,253 @@
+use serde::de::{self, DeserializeOwned, DeserializeSeed, SeqAccess, Visitor};
+use serde::Serialize;
+
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::ser::PackedSerializer;
+use crate::varint::{VarInt, VARINT_TOKEN};
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Packs a fixed size value (a number or bool, or a tuple, array or sequence of
+// them) into its raw little endian payloads, with no type codes or lengths.
+// This is how epee stores PODs as blobs (KV_SERIALIZE_CONTAINER_POD_AS_BLOB),
+// so the result is usually embedded as a STRING with serde_bytes.
+pub fn to_packed_bytes<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>> {
+	let mut bytes = Vec::new();
+	let mut serializer = PackedSerializer::new(&mut bytes, 1);
+	value.serialize(&mut serializer)?;
+	Ok(bytes)
+}
+
+// Unpacks bytes made by to_packed_bytes. Packed data isn't self describing, so
+// T decides how many bytes every value takes. Sequences take values until the
+// input ends, and all of the input must be used.
+pub fn from_packed_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+	let mut deserializer = PackedDeserializer { input: bytes };
+	let value = T::deserialize(&mut deserializer)?;
+	if !deserializer.input.is_empty() {
+		return epee_err!(ExpectedEnd, "{} bytes left over after packed value", deserializer.input.len());
+	}
+	Ok(value)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Field adapters                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Vec<$t> as one STRING of little endian values back to back, like the
+// output indices and other number lists which monerod packs into blobs:
+//
+//     #[serde(with = "serde_epee::packed_le_u64")]
+//     output_indices: Vec<u64>,
+//
+// Width and byte order come from the adapter's name, so using one on a field
+// of another element type fails to compile instead of misreading the blob
+macro_rules! packed_le_adapter {
+	($module:ident, $t:ty) => {
+		pub mod $module {
+			pub fn serialize<S: serde::Serializer>(values: &[$t], serializer: S) -> Result<S::Ok, S::Error> {
+				let mut bytes = Vec::with_capacity(std::mem::size_of_val(values));
+				for value in values {
+					bytes.extend_from_slice(&value.to_le_bytes());
+				}
+				serializer.serialize_bytes(&bytes)
+			}
+
+			pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<$t>, D::Error> {
+				let bytes = <serde_bytes::ByteBuf as serde::Deserialize>::deserialize(deserializer)?;
+				super::unpack_le(&bytes, <$t>::from_le_bytes).map_err(serde::de::Error::custom)
+			}
+		}
+	}
+}
+
+packed_le_adapter!{packed_le_u16, u16}
+packed_le_adapter!{packed_le_u32, u32}
+packed_le_adapter!{packed_le_u64, u64}
+packed_le_adapter!{packed_le_i16, i16}
+packed_le_adapter!{packed_le_i32, i32}
+packed_le_adapter!{packed_le_i64, i64}
+packed_le_adapter!{packed_le_f64, f64}
+
+// Copies values out one at a time, blobs have no alignment to cast from
+fn unpack_le<T, const N: usize>(bytes: &[u8], from_le_bytes: fn([u8; N]) -> T) -> std::result::Result<Vec<T>, String> {
+	let chunks = bytes.chunks_exact(N);
+	if !chunks.remainder().is_empty() {
+		return Err(format!("{} byte blob is not a list of {} byte values", bytes.len(), N));
+	}
+	Ok(chunks.map(|chunk| from_le_bytes(chunk.try_into().unwrap())).collect())
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Deserializer                                                              //
+///////////////////////////////////////////////////////////////////////////////
+
+struct PackedDeserializer<'a> {
+	input: &'a [u8]
+}
+
+impl PackedDeserializer<'_> {
+	fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+		if self.input.len() < N {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+		let (taken, rest) = self.input.split_at(N);
+		self.input = rest;
+		let mut buf = [0u8; N];
+		buf.copy_from_slice(taken);
+		Ok(buf)
+	}
+}
+
+macro_rules! deserialize_packed_num {
+	($fname:ident, $numtype:ty, $visit:ident) => {
+		fn $fname<V>(self, visitor: V) -> Result<V::Value>
+		where
+			V: Visitor<'de>
+		{
+			visitor.$visit(<$numtype>::from_le_bytes(self.take()?))
+		}
+	}
+}
+
+macro_rules! unsupported_packed {
+	($($fname:ident),*) => {
+		$(
+			fn $fname<V>(self, _visitor: V) -> Result<V::Value>
+			where
+				V: Visitor<'de>
+			{
+				epee_err!(SerdeModelUnsupported, "packed values must have a fixed size")
+			}
+		)*
+	}
+}
+
+impl<'de> de::Deserializer<'de> for &mut PackedDeserializer<'_> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		epee_err!(SerdeModelUnsupported, "packed data is not self describing")
+	}
+
+	deserialize_packed_num!{deserialize_i8, i8, visit_i8}
+	deserialize_packed_num!{deserialize_i16, i16, visit_i16}
+	deserialize_packed_num!{deserialize_i32, i32, visit_i32}
+	deserialize_packed_num!{deserialize_i64, i64, visit_i64}
+	deserialize_packed_num!{deserialize_u8, u8, visit_u8}
+	deserialize_packed_num!{deserialize_u16, u16, visit_u16}
+	deserialize_packed_num!{deserialize_u32, u32, visit_u32}
+	deserialize_packed_num!{deserialize_u64, u64, visit_u64}
+
+	// Floats are packed as DOUBLE like everywhere else
+	#[cfg(not(feature = "no-float"))]
+	deserialize_packed_num!{deserialize_f64, f64, visit_f64}
+	#[cfg(not(feature = "no-float"))]
+	deserialize_packed_num!{deserialize_f32, f64, visit_f64}
+	#[cfg(feature = "no-float")]
+	unsupported_packed!{deserialize_f32, deserialize_f64}
+
+	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let [byte] = self.take()?;
+		visitor.visit_bool(byte != 0)
+	}
+
+	unsupported_packed!{
+		deserialize_char, deserialize_str, deserialize_string, deserialize_bytes, deserialize_byte_buf,
+		deserialize_option, deserialize_unit, deserialize_map, deserialize_identifier, deserialize_ignored_any
+	}
+
+	fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.deserialize_unit(visitor)
+	}
+
+	// Varints are the only packed values which tell their own size
+	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		if name != VARINT_TOKEN {
+			return visitor.visit_newtype_struct(self);
+		}
+
+		let len = match self.input.first() {
+			Some(&first) => VarInt::encoded_len(first),
+			None => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+		};
+		if self.input.len() < len {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+		let (varint, rest) = self.input.split_at(len);
+		self.input = rest;
+		visitor.visit_bytes(varint)
+	}
+
+	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_seq(PackedSeq { deserializer: self, remaining: None })
+	}
+
+	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_seq(PackedSeq { deserializer: self, remaining: Some(len) })
+	}
+
+	fn deserialize_tuple_struct<V>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.deserialize_tuple(len, visitor)
+	}
+
+	fn deserialize_struct<V>(self, _name: &'static str, _fields: &'static [&'static str], _visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		epee_err!(SerdeModelUnsupported, "packed values must have a fixed size")
+	}
+
+	fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], _visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("Can't deserialize enums")))
+	}
+}
+
+// Tuples know their length, sequences run until the end of the input
+struct PackedSeq<'a, 'b> {
+	deserializer: &'a mut PackedDeserializer<'b>,
+	remaining: Option<usize>
+}
+
+impl<'de> SeqAccess<'de> for PackedSeq<'_, '_> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		match self.remaining {
+			Some(0) => return Ok(None),
+			Some(ref mut remaining) => *remaining -= 1,
+			None if self.deserializer.input.is_empty() => return Ok(None),
+			None => {}
+		}
+		seed.deserialize(&mut *self.deserializer).map(Some)
+	}
+}
@@ -0,0 +1,59 @@
+use rayon::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::Result;
+use crate::VarInt;
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Encodes every value on rayon's thread pool and concatenates the documents in
+// their original order, each prefixed with its length as a varint. Fails with
+// the error of the first value (in order) which couldn't be encoded.
+pub fn to_framed_bytes<T, I>(values: I) -> Result<Vec<u8>>
+where
+	T: Serialize + Sync,
+	I: IntoParallelIterator<Item = T>,
+	I::Iter: IndexedParallelIterator
+{
+	// Collected before checking, since rayon would keep whichever error came
+	// first in time
+	let documents: Vec<Result<Vec<u8>>> = values.into_par_iter()
+		.map(|value| crate::to_bytes(&value))
+		.collect();
+	let documents = documents.into_iter().collect::<Result<Vec<_>>>()?;
+
+	let total_len = documents.iter().map(|doc| doc.len() + 8).sum();
+	let mut framed = Vec::with_capacity(total_len);
+	for doc in documents {
+		VarInt::try_from(doc.len())?.to_writer(&mut framed)?;
+		framed.extend_from_slice(&doc);
+	}
+	Ok(framed)
+}
+
+// Splits bytes made by to_framed_bytes into documents and decodes them in
+// parallel, keeping their order. Fails with the error of the first document
+// (in order) which couldn't be decoded.
+pub fn from_framed_bytes<T>(bytes: &[u8]) -> Result<Vec<T>>
+where
+	T: DeserializeOwned + Send
+{
+	let mut documents = Vec::new();
+	let mut reader = bytes;
+	while !reader.is_empty() {
+		let len: usize = VarInt::from_reader(&mut reader)?.try_into()?;
+		if reader.len() < len {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+		let (doc, rest) = reader.split_at(len);
+		documents.push(doc);
+		reader = rest;
+	}
+
+	let values: Vec<Result<T>> = documents.into_par_iter()
+		.map(|doc| crate::from_bytes(&mut &doc[..]))
+		.collect();
+	values.into_iter().collect()
+}
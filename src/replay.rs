@@ -0,0 +1,369 @@
+use std::io::{self, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de;
+
+use crate::de::{capture_document, capture_document_into};
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::format::FormatProfile;
+use crate::limits::LimitTracker;
+
+// Capture layout: magic, then records of direction (u8), timestamp (u64 LE,
+// microseconds since the UNIX epoch), payload length (u64 LE) and payload.
+// There is no index or trailer, so captures cut short by a crash still replay
+// up to the last whole record.
+pub const CAPTURE_MAGIC: [u8; 8] = *b"EPEECAP1";
+pub const RECORD_HEADER_SIZE: usize = 1 + 8 + 8;
+
+// Records longer than this fail with BudgetExceeded unless raised with
+// Replay::with_max_len, since lengths come from the file
+pub const DEFAULT_MAX_RECORD_LEN: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Direction {
+	Inbound = 0, // read from the peer
+	Outbound = 1 // written to the peer
+}
+
+impl Direction {
+	pub fn from_u8(code: u8) -> Result<Self> {
+		match code {
+			0 => Ok(Self::Inbound),
+			1 => Ok(Self::Outbound),
+			_ => epee_err!(UnknownDirection { code: code }, "unknown capture record direction {}", code)
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+	pub direction: Direction,
+	pub timestamp: u64, // microseconds since the UNIX epoch
+	pub payload: Vec<u8>
+}
+
+impl Record {
+	// Record stamped with the current time
+	pub fn now(direction: Direction, payload: Vec<u8>) -> Self {
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_micros() as u64).unwrap_or(0);
+		Self { direction, timestamp, payload }
+	}
+
+	// Decodes the payload, for records which hold one whole document
+	pub fn decode<'a, T: de::Deserialize<'a>>(&'a self) -> Result<T> {
+		crate::from_slice(&self.payload)
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Writing captures                                                          //
+///////////////////////////////////////////////////////////////////////////////
+
+// Writes records to a capture, e.g. to re-emit a filtered Replay:
+//
+//     let mut out = CaptureWriter::new(File::create("inbound.cap")?)?;
+//     for record in Replay::new(BufReader::new(File::open("session.cap")?))? {
+//         let record = record?;
+//         if record.direction == Direction::Inbound {
+//             out.write_record(&record)?;
+//         }
+//     }
+pub struct CaptureWriter<W: Write> {
+	writer: W
+}
+
+impl<W: Write> CaptureWriter<W> {
+	pub fn new(mut writer: W) -> Result<Self> {
+		writer.write_all(&CAPTURE_MAGIC)?;
+		Ok(Self { writer })
+	}
+
+	pub fn write_record(&mut self, record: &Record) -> Result<()> {
+		let mut header = [0u8; RECORD_HEADER_SIZE];
+		header[0] = record.direction as u8;
+		header[1..9].copy_from_slice(&record.timestamp.to_le_bytes());
+		header[9..].copy_from_slice(&(record.payload.len() as u64).to_le_bytes());
+		self.writer.write_all(&header)?;
+		self.writer.write_all(&record.payload)?;
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> Result<()> {
+		Ok(self.writer.flush()?)
+	}
+
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+// Stream wrapper which records the documents read from and written to stream
+// into a capture, one record per document, so that Record::decode works on
+// every record. Meant for blocking streams carrying back to back documents.
+//
+// Reads take a whole document off the stream before handing out the first of
+// its bytes, but nothing past it. Writes go to the stream right away and are
+// recorded once the recorder notices the document is complete: at the latest
+// on the next read, flush or into_parts, so records keep the order of the
+// session. Bytes which don't form a document are recorded as they come, and
+// so is everything after them going the same way, since message boundaries
+// can't be found anymore.
+//
+// Failing to write the capture never fails the stream's reads and writes,
+// which may already have happened. Recording stops instead, see capture_error.
+pub struct Recorder<S, W: Write> {
+	stream: S,
+	capture: CaptureWriter<W>,
+	profile: FormatProfile,
+	inbound: Vec<u8>, // last document read and recorded
+	inbound_pos: usize, // how much of it was handed out
+	inbound_framed: bool,
+	outbound: Vec<u8>, // written to stream but not recorded yet
+	next_check: usize, // outbound length at which to look for whole documents again
+	outbound_framed: bool,
+	capture_error: Option<Error>
+}
+
+// Read size once inbound bytes are recorded as they come
+const RAW_CHUNK_SIZE: usize = 8192;
+
+impl<S, W: Write> Recorder<S, W> {
+	pub fn new(stream: S, capture: W) -> Result<Self> {
+		Ok(Self {
+			stream,
+			capture: CaptureWriter::new(capture)?,
+			profile: FormatProfile::monero(),
+			inbound: Vec::new(),
+			inbound_pos: 0,
+			inbound_framed: true,
+			outbound: Vec::new(),
+			next_check: 0,
+			outbound_framed: true,
+			capture_error: None
+		})
+	}
+
+	// Signature of the documents on the stream, for forks with their own
+	pub fn with_profile(mut self, profile: FormatProfile) -> Self {
+		self.profile = profile;
+		self
+	}
+
+	pub fn get_ref(&self) -> &S {
+		&self.stream
+	}
+
+	// Why recording stopped, if writing the capture failed
+	pub fn capture_error(&self) -> Option<&Error> {
+		self.capture_error.as_ref()
+	}
+
+	// Records what was written but not recorded yet, including the start of a
+	// document which was never finished
+	pub fn into_parts(mut self) -> (S, W) {
+		self.record_outbound();
+		if !self.outbound.is_empty() {
+			let rest = std::mem::take(&mut self.outbound);
+			self.record(Direction::Outbound, rest);
+		}
+		let _ = self.capture.flush();
+		(self.stream, self.capture.into_inner())
+	}
+
+	fn record(&mut self, direction: Direction, payload: Vec<u8>) {
+		if self.capture_error.is_some() || payload.is_empty() {
+			return;
+		}
+		if let Err(err) = self.capture.write_record(&Record::now(direction, payload)) {
+			self.capture_error = Some(err);
+		}
+	}
+
+	// Records the whole documents at the front of outbound
+	fn record_outbound(&mut self) {
+		let mut rest = std::mem::take(&mut self.outbound);
+		while self.outbound_framed && !rest.is_empty() {
+			let mut reader = rest.as_slice();
+			match capture_document(&mut reader, &self.profile, &mut LimitTracker::default()) {
+				Ok(document) => {
+					rest.drain(..document.len());
+					self.record(Direction::Outbound, document);
+				},
+				Err(err) if err.is_eof() => break,
+				Err(_) => self.outbound_framed = false
+			}
+		}
+		if !self.outbound_framed {
+			self.record(Direction::Outbound, std::mem::take(&mut rest));
+		}
+
+		// Looking again only once the buffer doubled keeps this linear in the
+		// size of documents written in many small pieces
+		self.next_check = rest.len().saturating_mul(2);
+		self.outbound = rest;
+	}
+}
+
+impl<S: Read, W: Write> Recorder<S, W> {
+	// Reads and records the next document, or whatever could be read of it
+	fn fill_inbound(&mut self) -> io::Result<()> {
+		self.record_outbound();
+		self.inbound.clear();
+		self.inbound_pos = 0;
+
+		if !self.inbound_framed {
+			self.inbound.resize(RAW_CHUNK_SIZE, 0);
+			let nread = self.stream.read(&mut self.inbound);
+			self.inbound.truncate(*nread.as_ref().unwrap_or(&0));
+			nread?;
+		} else {
+			let mut captured = std::mem::take(&mut self.inbound);
+			let res = capture_document_into(&mut self.stream, &self.profile, &mut LimitTracker::default(), &mut captured);
+			self.inbound = captured;
+			match res {
+				Ok(()) => {},
+				Err(err) if self.inbound.is_empty() && err.is_eof() => {}, // end of the stream
+				Err(err) if self.inbound.is_empty() => return Err(err.into()),
+				Err(_) => self.inbound_framed = false // hand out what was read, the reader will fail on it too
+			}
+		}
+
+		let document = self.inbound.clone();
+		self.record(Direction::Inbound, document);
+		Ok(())
+	}
+}
+
+impl<S: Read, W: Write> Read for Recorder<S, W> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.inbound_pos == self.inbound.len() {
+			self.fill_inbound()?;
+		}
+		let n = std::cmp::min(buf.len(), self.inbound.len() - self.inbound_pos);
+		buf[..n].copy_from_slice(&self.inbound[self.inbound_pos..self.inbound_pos + n]);
+		self.inbound_pos += n;
+		Ok(n)
+	}
+}
+
+impl<S: Write, W: Write> Write for Recorder<S, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let nwritten = self.stream.write(buf)?;
+		if self.capture_error.is_none() {
+			self.outbound.extend_from_slice(&buf[..nwritten]);
+			if self.outbound.len() >= self.next_check {
+				self.record_outbound();
+			}
+		}
+		Ok(nwritten)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.stream.flush()?;
+		self.record_outbound();
+		if let Err(err) = self.capture.flush() {
+			self.capture_error.get_or_insert(err);
+		}
+		Ok(())
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Reading captures                                                          //
+///////////////////////////////////////////////////////////////////////////////
+
+// Iterates over the records of a capture, ending after the last whole record.
+// A record cut short fails with an IOError (UnexpectedEof), after which
+// iteration stops. Filter with the usual Iterator adapters.
+pub struct Replay<R: Read> {
+	reader: R,
+	max_len: u64,
+	done: bool
+}
+
+impl<R: Read> Replay<R> {
+	pub fn new(mut reader: R) -> Result<Self> {
+		let mut magic = [0u8; CAPTURE_MAGIC.len()];
+		reader.read_exact(&mut magic)?;
+		if magic != CAPTURE_MAGIC {
+			return epee_err!(ExpectedFormatSignature, "not a capture file");
+		}
+		Ok(Self { reader, max_len: DEFAULT_MAX_RECORD_LEN, done: false })
+	}
+
+	pub fn with_max_len(mut self, max_len: u64) -> Self {
+		self.max_len = max_len;
+		self
+	}
+
+	// Concatenated payloads of the remaining records going one way, as a
+	// stream to decode messages from
+	pub fn stream(self, direction: Direction) -> Result<Vec<u8>> {
+		let mut stream = Vec::new();
+		for record in self {
+			let record = record?;
+			if record.direction == direction {
+				stream.extend_from_slice(&record.payload);
+			}
+		}
+		Ok(stream)
+	}
+
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+
+	fn read_record(&mut self) -> Result<Option<Record>> {
+		let mut header = [0u8; RECORD_HEADER_SIZE];
+		let nread = read_up_to(&mut self.reader, &mut header)?;
+		if nread == 0 {
+			return Ok(None);
+		} else if nread < RECORD_HEADER_SIZE {
+			return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+		}
+
+		let direction = Direction::from_u8(header[0])?;
+		let timestamp = u64::from_le_bytes(header[1..9].try_into().unwrap());
+		let len = u64::from_le_bytes(header[9..].try_into().unwrap());
+		if len > self.max_len {
+			return epee_err!(BudgetExceeded { max_bytes: self.max_len }, "capture record is {} bytes long", len);
+		}
+
+		let mut payload = Vec::new();
+		let nread = self.reader.by_ref().take(len).read_to_end(&mut payload)?;
+		if nread as u64 != len {
+			return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+		}
+		Ok(Some(Record { direction, timestamp, payload }))
+	}
+}
+
+impl<R: Read> Iterator for Replay<R> {
+	type Item = Result<Record>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		let res = self.read_record();
+		if !matches!(res, Ok(Some(_))) {
+			self.done = true;
+		}
+		res.transpose()
+	}
+}
+
+// Like read_exact, but a clean end of input before anything was read is fine
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+	let mut filled = 0;
+	while filled < buf.len() {
+		match reader.read(&mut buf[filled..]) {
+			Ok(0) => break,
+			Ok(n) => filled += n,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e)
+		}
+	}
+	Ok(filled)
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use serde::{ser, Deserialize, Serialize, Serializer};
+
+use crate::error::{Error, ErrorKind};
+use crate::section::SectionEntry;
+
+// Status of successful responses, anything else is an error
+pub const STATUS_OK: &str = "OK";
+
+// Response shape shared by most of monerod's RPC endpoints: a status string,
+// the access based payment fields and the endpoint's own fields, all in the
+// root section. Only status is required, the other envelope fields are missing
+// from older daemons. Envelope fields are taken out before the payload sees
+// the section, so payloads with #[serde(deny_unknown_fields)] work.
+//
+//     let res: Envelope<GetHeightResponse> = serde_epee::from_slice(&body)?;
+//     let height = res.into_result()?.height;
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Envelope<T> {
+	pub status: String,
+	#[serde(default)]
+	pub untrusted: bool, // answered by a bootstrap daemon, which may lie
+	#[serde(default)]
+	pub credits: u64,
+	#[serde(default)]
+	pub top_hash: String,
+	#[serde(flatten)]
+	pub payload: T
+}
+
+impl<T> Envelope<T> {
+	// Successful response with the other envelope fields left empty
+	pub fn ok(payload: T) -> Self {
+		Self {
+			status: String::from(STATUS_OK),
+			untrusted: false,
+			credits: 0,
+			top_hash: String::new(),
+			payload
+		}
+	}
+
+	pub fn is_ok(&self) -> bool {
+		self.status == STATUS_OK
+	}
+
+	// The payload if status is OK, otherwise a StatusError with the status and
+	// whatever payload the daemon sent along with it
+	pub fn into_result(self) -> std::result::Result<T, StatusError<T>> {
+		if self.is_ok() {
+			Ok(self.payload)
+		} else {
+			Err(StatusError { status: self.status, payload: self.payload })
+		}
+	}
+}
+
+// The payload is encoded into a Section first to count its fields, since the
+// envelope fields go into the same section. Meant for mock daemons and tests,
+// clients only ever decode envelopes.
+impl<T: Serialize> Serialize for Envelope<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		let payload = crate::to_bytes(&self.payload).map_err(ser::Error::custom)?;
+		let mut section = crate::section::from_bytes(&payload).map_err(ser::Error::custom)?;
+		section.insert(String::from("status"), SectionEntry::from(self.status.as_str()));
+		section.insert(String::from("untrusted"), SectionEntry::Bool(self.untrusted));
+		section.insert(String::from("credits"), SectionEntry::UInt64(self.credits));
+		section.insert(String::from("top_hash"), SectionEntry::from(self.top_hash.as_str()));
+		section.serialize(serializer)
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Status errors                                                             //
+///////////////////////////////////////////////////////////////////////////////
+
+// Response whose status isn't OK, e.g. "BUSY" while the daemon is syncing or
+// "Failed" with the reason in the payload. Converts into an Error with kind
+// BadStatus for callers which only care about the status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusError<T> {
+	pub status: String,
+	pub payload: T
+}
+
+impl<T> StatusError<T> {
+	pub fn is_busy(&self) -> bool {
+		self.status == "BUSY"
+	}
+}
+
+impl<T> fmt::Display for StatusError<T> {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		write!(formatter, "daemon responded with status {:?}", self.status)
+	}
+}
+
+impl<T: fmt::Debug> std::error::Error for StatusError<T> {}
+
+impl<T> From<StatusError<T>> for Error {
+	fn from(err: StatusError<T>) -> Self {
+		Error::new(ErrorKind::BadStatus, err.to_string())
+	}
+}
+
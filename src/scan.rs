@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use serde_bytes::ByteBuf;
+
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::section::SectionEntry;
+use crate::types::{EpeeEntryType, EpeeScalarType};
+use crate::VarInt;
+
+// Bytes of each string kept in ScanEntry::value, the rest is skipped
+pub const MAX_SCANNED_BLOB: usize = 256;
+
+///////////////////////////////////////////////////////////////////////////////
+// Entries                                                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+// One entry of a document as scan reads past it. Arrays are reported first,
+// then each of their elements as entries of their own, with paths like
+// "peers[2]". Sections come before the entries inside of them. Dots, brackets
+// and backslashes in keys are escaped with a backslash ("a\\.b" is the key
+// "a.b"), and bytes which aren't UTF-8 are written as \xNN, so every path names
+// one entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanEntry<'a> {
+	pub path: &'a str,
+	pub offset: u64, // of the key, or of the payload for array elements
+	pub entry_type: EpeeEntryType,
+	// Scalars only. Strings longer than MAX_SCANNED_BLOB are cut short, see
+	// truncated.
+	pub value: Option<SectionEntry>,
+	pub truncated: bool
+}
+
+// Total size of the entries with one path, see path_sizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathSize {
+	pub bytes: u64,
+	pub count: u64 // number of entries with the path
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Reads a document from start to end, calling visit for every entry. Like
+// dump::dump, nothing but the current path and one string preview is held in
+// memory, so this works on arbitrarily large files; pass a buffered reader.
+// Only nesting depth is limited, to monerod's recursion limit.
+// Errors from visit stop the scan and are returned as is. Returns the number
+// of bytes read, which is the length of the document.
+pub fn scan<R, F>(reader: R, mut visit: F) -> Result<u64>
+where
+	R: Read,
+	F: FnMut(&ScanEntry) -> Result<()>
+{
+	let mut scanner = Scanner {
+		reader,
+		offset: 0,
+		path: String::new(),
+		depth: 0,
+		visit: &mut visit
+	};
+
+	let signature: [u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE] = scanner.read_array()?;
+	if signature != constants::PORTABLE_STORAGE_SIGNATURE {
+		return epee_err!(ExpectedFormatSignature, "unexpected signature {:02x?}", signature);
+	}
+	scanner.section_body()?;
+	Ok(scanner.offset)
+}
+
+// Bytes taken up by each key path down to max_depth keys deep, read with scan.
+// Array indexes are left out of paths ("peers[].port"), so every element adds
+// to the same path. Like du(1) does for directories, sizes include everything
+// nested inside an entry, as well as its own key, type code and array length.
+// Returns the document length along with the sizes.
+pub fn path_sizes<R: Read>(reader: R, max_depth: usize) -> Result<(u64, BTreeMap<String, PathSize>)> {
+	let mut sizes = BTreeMap::new();
+	let mut open: Vec<OpenEntry> = Vec::new();
+	let len = scan(reader, |entry| {
+		// Nothing marks the end of an entry, it ends where the next one at the
+		// same or an outer level starts
+		let nesting = separators(entry.path).filter(|c| *c == '.' || *c == '[').count();
+		close_entries(&mut open, &mut sizes, nesting, entry.offset);
+
+		let is_element = separators(entry.path).last() == Some(']') && entry.path.ends_with(']');
+		let key_depth = separators(entry.path).filter(|c| *c == '.').count() + 1;
+		let path = if is_element || key_depth > max_depth { None } else { Some(strip_indexes(entry.path)) };
+		open.push(OpenEntry { nesting, path, offset: entry.offset });
+		Ok(())
+	})?;
+	close_entries(&mut open, &mut sizes, 0, len);
+	Ok((len, sizes))
+}
+
+struct OpenEntry {
+	nesting: usize,
+	path: Option<String>, // None for entries which aren't counted
+	offset: u64
+}
+
+fn close_entries(open: &mut Vec<OpenEntry>, sizes: &mut BTreeMap<String, PathSize>, nesting: usize, end: u64) {
+	while open.last().is_some_and(|entry| entry.nesting >= nesting) {
+		let entry = open.pop().unwrap();
+		if let Some(path) = entry.path {
+			let size = sizes.entry(path).or_default();
+			size.bytes += end - entry.offset;
+			size.count += 1;
+		}
+	}
+}
+
+// "blocks[2].txs[0]" -> "blocks[].txs[]"
+fn strip_indexes(path: &str) -> String {
+	let mut stripped = String::with_capacity(path.len());
+	let mut in_index = false;
+	let mut escaped = false;
+	for c in path.chars() {
+		match c {
+			_ if escaped => escaped = false,
+			'\\' => escaped = true,
+			'[' => in_index = true,
+			']' => in_index = false,
+			_ if in_index => continue,
+			_ => ()
+		}
+		stripped.push(c);
+	}
+	stripped
+}
+
+// The dots and brackets of a path which separate keys and indexes, leaving
+// out escaped ones
+fn separators(path: &str) -> impl Iterator<Item = char> + '_ {
+	let mut escaped = false;
+	path.chars().filter(move |c| {
+		let is_separator = !escaped && matches!(c, '.' | '[' | ']');
+		escaped = !escaped && *c == '\\';
+		is_separator
+	})
+}
+
+// Splits a path into its keys, undoing the escapes of push_key and leaving out
+// array indexes, e.g. "blocks[2].a\\.b" into ["blocks", "a.b"]. Bytes escaped
+// as \xNN become U+FFFD, like in keys decoded lossily.
+pub(crate) fn split_path(path: &str) -> Vec<String> {
+	let mut keys = Vec::new();
+	let mut key = String::new();
+	let mut in_index = false;
+	let mut chars = path.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => match chars.next() {
+				Some('x') => {
+					chars.nth(1);
+					key.push(char::REPLACEMENT_CHARACTER);
+				},
+				Some(escaped) => key.push(escaped),
+				None => key.push('\\')
+			},
+			'[' => in_index = true,
+			']' => in_index = false,
+			_ if in_index => continue,
+			'.' => keys.push(std::mem::take(&mut key)),
+			_ => key.push(c)
+		}
+	}
+	keys.push(key);
+	keys
+}
+
+// Appends a key to a path, escaped as described at ScanEntry
+fn push_key(path: &mut String, key: &[u8]) {
+	for chunk in key.utf8_chunks() {
+		for c in chunk.valid().chars() {
+			if matches!(c, '.' | '[' | ']' | '\\') {
+				path.push('\\');
+			}
+			path.push(c);
+		}
+		for byte in chunk.invalid() {
+			path.push_str(&format!("\\x{:02x}", byte));
+		}
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Scanner                                                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+struct Scanner<'a, R: Read, F> {
+	reader: R,
+	offset: u64,
+	path: String,
+	depth: usize,
+	visit: &'a mut F
+}
+
+impl<R, F> Scanner<'_, R, F>
+where
+	R: Read,
+	F: FnMut(&ScanEntry) -> Result<()>
+{
+	fn section_body(&mut self) -> Result<()> {
+		if self.depth >= constants::MONEROD_RECURSION_LIMIT {
+			return epee_err!(SectionTooDeep { max: constants::MONEROD_RECURSION_LIMIT as u64 }, "sections nested too deep at {}", self.path);
+		}
+		self.depth += 1;
+
+		let num_fields: u64 = self.read_varint()?;
+		for _ in 0..num_fields {
+			let offset = self.offset;
+			let keylen = self.read_u8()? as usize;
+			if keylen == 0 {
+				return epee_err!(EmptySectionKey, "section key length can not be zero!");
+			}
+			let mut key = [0u8; constants::MAX_SECTION_KEY_SIZE];
+			self.read_exact(&mut key[..keylen])?;
+
+			let path_len = self.path.len();
+			if path_len != 0 {
+				self.path.push('.');
+			}
+			push_key(&mut self.path, &key[..keylen]);
+			let type_byte = self.read_u8()?;
+			let res = self.entry(type_byte, offset);
+			self.path.truncate(path_len);
+			res?;
+		}
+		self.depth -= 1;
+		Ok(())
+	}
+
+	fn entry(&mut self, type_byte: u8, offset: u64) -> Result<()> {
+		let entry_type = EpeeEntryType::from_type_code(type_byte)?;
+		if !entry_type.is_array {
+			return self.scalar(entry_type.scalar_type, offset);
+		}
+
+		let count: u64 = self.read_varint()?;
+		self.report(offset, entry_type, None, false)?;
+
+		let path_len = self.path.len();
+		for i in 0..count {
+			self.path.push_str(&format!("[{}]", i));
+			let res = self.scalar(entry_type.scalar_type, self.offset);
+			self.path.truncate(path_len);
+			res?;
+		}
+		Ok(())
+	}
+
+	fn scalar(&mut self, scalar_type: EpeeScalarType, offset: u64) -> Result<()> {
+		let value = match scalar_type {
+			EpeeScalarType::Int64 => SectionEntry::Int64(i64::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::Int32 => SectionEntry::Int32(i32::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::Int16 => SectionEntry::Int16(i16::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::Int8 => SectionEntry::Int8(i8::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::UInt64 => SectionEntry::UInt64(u64::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::UInt32 => SectionEntry::UInt32(u32::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::UInt16 => SectionEntry::UInt16(u16::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::UInt8 => SectionEntry::UInt8(self.read_u8()?),
+			EpeeScalarType::Double if cfg!(feature = "no-float") => return epee_err!(FloatDisabled),
+			EpeeScalarType::Double => SectionEntry::Double(f64::from_le_bytes(self.read_array()?)),
+			EpeeScalarType::Bool => SectionEntry::Bool(self.read_u8()? != 0),
+			EpeeScalarType::Str => return self.string(offset),
+			EpeeScalarType::Object => {
+				self.report(offset, EpeeEntryType::new(EpeeScalarType::Object, false), None, false)?;
+				return self.section_body();
+			}
+		};
+		self.report(offset, EpeeEntryType::new(scalar_type, false), Some(value), false)
+	}
+
+	fn string(&mut self, offset: u64) -> Result<()> {
+		let len: u64 = self.read_varint()?;
+		let preview_len = std::cmp::min(len, MAX_SCANNED_BLOB as u64) as usize;
+		let mut preview = vec![0u8; preview_len];
+		self.read_exact(&mut preview)?;
+		self.skip(len - preview_len as u64)?;
+
+		let value = SectionEntry::Blob(ByteBuf::from(preview));
+		self.report(offset, EpeeEntryType::new(EpeeScalarType::Str, false), Some(value), preview_len as u64 != len)
+	}
+
+	fn report(&mut self, offset: u64, entry_type: EpeeEntryType, value: Option<SectionEntry>, truncated: bool) -> Result<()> {
+		let entry = ScanEntry {
+			path: &self.path,
+			offset,
+			entry_type,
+			value,
+			truncated
+		};
+		(self.visit)(&entry)
+	}
+
+	fn skip(&mut self, len: u64) -> Result<()> {
+		let skipped = std::io::copy(&mut self.reader.by_ref().take(len), &mut std::io::sink())?;
+		self.offset += skipped;
+		if skipped != len {
+			return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+		}
+		Ok(())
+	}
+
+	fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+		self.reader.read_exact(buf)?;
+		self.offset += buf.len() as u64;
+		Ok(())
+	}
+
+	fn read_varint(&mut self) -> Result<u64> {
+		let first = self.read_u8()?;
+		let size = 1usize << (first & 0b11);
+		let mut buf = [0u8; 8];
+		buf[0] = first;
+		self.read_exact(&mut buf[1..size])?;
+		Ok(VarInt::from_reader(&mut &buf[..size])?.into())
+	}
+
+	fn read_u8(&mut self) -> Result<u8> {
+		let [byte] = self.read_array()?;
+		Ok(byte)
+	}
+
+	fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+		let mut buf = [0u8; N];
+		self.read_exact(&mut buf)?;
+		Ok(buf)
+	}
+}
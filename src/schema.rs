@@ -0,0 +1,434 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::section;
+use crate::types::{EpeeEntryType, EpeeScalarType};
+use crate::VarInt;
+
+// Nesting past which sequences are probed empty and options as None, so that
+// recursive types like `struct Tree { children: Vec<Tree> }` terminate
+const MAX_PROBE_DEPTH: usize = 32;
+
+///////////////////////////////////////////////////////////////////////////////
+// Shapes                                                                    //
+///////////////////////////////////////////////////////////////////////////////
+
+// What a Deserialize impl asks the deserializer for, as far as the wire format
+// cares. Integers of any width are one shape since any integer entry decodes
+// into any integer field that can hold it, except for u8: sequences of u8 also
+// decode from blobs, see Deserializer::with_blob_seqs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+	Any, // deserialize_any and friends, e.g. Section or untagged enums
+	Bool,
+	Integer,
+	Byte, // u8
+	Double,
+	Blob, // strings and bytes
+	Struct(Vec<Field>),
+	Map, // a section with arbitrary keys, e.g. HashMap
+	Seq(Box<Shape>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+	pub name: &'static str,
+	pub shape: Shape,
+	pub optional: bool // an Option, which may be left out
+}
+
+// Shape of T, found by running T's Deserialize impl against a deserializer
+// which records what is asked of it and hands back placeholder values. Fields
+// with #[serde(default)] look required, since serde doesn't tell deserializers
+// about defaults.
+pub fn shape_of<'de, T: de::Deserialize<'de>>() -> Shape {
+	let mut state = ProbeState::default();
+
+	// Placeholders may be rejected by the impl, e.g. an empty blob for a fixed
+	// size hash. The path where that happened is noted, and the next run feeds
+	// it last, so that every other field is reached before the impl bails out.
+	loop {
+		state.failed_at = None;
+		let res = T::deserialize(Probe { state: &mut state, path: String::new(), depth: 0 });
+		match (res, state.failed_at.take()) {
+			(Err(_), Some(path)) if !state.failing.contains(&path) => state.failing.insert(path),
+			_ => break
+		};
+	}
+
+	state.build("")
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Validation                                                                //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeMismatch {
+	pub path: String,
+	pub expected: Shape,
+	pub found: EpeeEntryType
+}
+
+// Paths are formatted like in errors, e.g. "blocks[2].txs"
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+	pub missing: Vec<String>,
+	pub mismatched: Vec<TypeMismatch>,
+	pub extra: Vec<String> // entries T doesn't declare, which decoding ignores
+}
+
+impl ValidationReport {
+	// Whether decoding into T could only fail on values, not on structure
+	pub fn is_valid(&self) -> bool {
+		self.missing.is_empty() && self.mismatched.is_empty()
+	}
+}
+
+// Checks that bytes has every field T requires, with compatible wire types,
+// without decoding any values. Only keys, type codes and lengths are read.
+// Malformed documents still fail like they would when decoding.
+pub fn validate_as<'de, T: de::Deserialize<'de>>(bytes: &[u8]) -> Result<ValidationReport> {
+	validate(bytes, &shape_of::<T>())
+}
+
+// Like validate_as, for a shape which was computed once up front
+pub fn validate(bytes: &[u8], shape: &Shape) -> Result<ValidationReport> {
+	let mut reader = bytes;
+	section::read_signature(&mut reader)?;
+	validate_body(reader, shape)
+}
+
+// Like validate, for a document whose signature was already checked, so that
+// documents with other format profiles can be validated as well
+pub(crate) fn validate_body(mut reader: &[u8], shape: &Shape) -> Result<ValidationReport> {
+	let fields = match shape {
+		Shape::Struct(fields) => fields.as_slice(),
+		Shape::Map | Shape::Any => &[],
+		_ => return epee_err!(SerdeModelUnsupported, "the root of a document must be a struct or map")
+	};
+
+	let mut report = ValidationReport::default();
+	if let Shape::Struct(_) = shape {
+		check_section(&mut reader, fields, "", &mut report)?;
+	} else {
+		crate::skip_entry(&mut reader, constants::SERIALIZE_TYPE_OBJECT)?;
+	}
+
+	if !reader.is_empty() {
+		return epee_err!(ExpectedEnd, "{} bytes left over after document", reader.len());
+	}
+	Ok(report)
+}
+
+fn check_section(reader: &mut &[u8], fields: &[Field], path: &str, report: &mut ValidationReport) -> Result<()> {
+	let num_fields: u64 = VarInt::from_reader(reader)?.into();
+	let mut seen = HashSet::new();
+
+	for _ in 0..num_fields {
+		let key = section::read_key(reader)?;
+		let entry_path = join_key(path, &key);
+		let mut type_code = [0u8];
+		std::io::Read::read_exact(reader, &mut type_code)?;
+		let entry_type = EpeeEntryType::from_type_code(type_code[0])?;
+
+		match fields.iter().find(|field| field.name == key) {
+			Some(field) => {
+				seen.insert(field.name);
+				check_entry(reader, &field.shape, entry_type, &entry_path, report)?;
+			},
+			None => {
+				report.extra.push(entry_path);
+				crate::skip_entry(reader, type_code[0])?;
+			}
+		}
+	}
+
+	for field in fields {
+		if !field.optional && !seen.contains(field.name) {
+			report.missing.push(join_key(path, field.name));
+		}
+	}
+	Ok(())
+}
+
+fn check_entry(reader: &mut &[u8], shape: &Shape, entry_type: EpeeEntryType, path: &str, report: &mut ValidationReport) -> Result<()> {
+	match (shape, entry_type.is_array) {
+		(Shape::Struct(fields), false) if entry_type.scalar_type == EpeeScalarType::Object => check_section(reader, fields, path, report),
+		(Shape::Seq(element), true) => {
+			let count: u64 = VarInt::from_reader(reader)?.into();
+			let element_type = EpeeEntryType::new(entry_type.scalar_type, false);
+			// Empty arrays are written as UINT8 arrays whatever their element type
+			let empty = count == 0 && entry_type.scalar_type == EpeeScalarType::UInt8;
+			if !empty && !accepts(element, element_type) {
+				report.mismatched.push(TypeMismatch { path: path.to_string(), expected: shape.clone(), found: entry_type });
+			}
+
+			for i in 0..count {
+				match &**element {
+					Shape::Struct(fields) if entry_type.scalar_type == EpeeScalarType::Object => {
+						check_section(reader, fields, &format!("{}[{}]", path, i), report)?;
+					},
+					_ => crate::skip_entry(reader, element_type.to_type_code())?
+				}
+			}
+			Ok(())
+		},
+		_ => {
+			if !accepts(shape, entry_type) {
+				report.mismatched.push(TypeMismatch { path: path.to_string(), expected: shape.clone(), found: entry_type });
+			}
+			crate::skip_entry(reader, entry_type.to_type_code())
+		}
+	}
+}
+
+// Whether an entry of the given type decodes into a value of the given shape
+fn accepts(shape: &Shape, entry_type: EpeeEntryType) -> bool {
+	use EpeeScalarType::*;
+
+	let scalar_type = entry_type.scalar_type;
+	if entry_type.is_array {
+		return matches!(shape, Shape::Any | Shape::Seq(_));
+	}
+
+	match shape {
+		Shape::Any => true,
+		Shape::Bool => scalar_type == Bool,
+		Shape::Integer | Shape::Byte => is_integer(scalar_type),
+		Shape::Double => scalar_type == Double || is_integer(scalar_type), // serde's float visitors take integers
+		Shape::Blob => scalar_type == Str,
+		Shape::Struct(_) | Shape::Map => scalar_type == Object,
+		Shape::Seq(element) => scalar_type == Str && **element == Shape::Byte
+	}
+}
+
+fn is_integer(scalar_type: EpeeScalarType) -> bool {
+	use EpeeScalarType::*;
+	matches!(scalar_type, Int64 | Int32 | Int16 | Int8 | UInt64 | UInt32 | UInt16 | UInt8)
+}
+
+fn join_key(path: &str, key: &str) -> String {
+	if path.is_empty() {
+		key.to_string()
+	} else {
+		format!("{}.{}", path, key)
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Probe deserializer                                                        //
+///////////////////////////////////////////////////////////////////////////////
+
+// What was asked for at a probe path. Probe paths use "[]" for all elements of
+// a sequence, e.g. "blocks[].txs".
+#[derive(Debug, Clone, Copy)]
+enum Node {
+	Any,
+	Bool,
+	Integer,
+	Byte,
+	Double,
+	Blob,
+	Struct(&'static [&'static str]),
+	Map,
+	Seq
+}
+
+#[derive(Default)]
+struct ProbeState {
+	nodes: HashMap<String, Node>,
+	optional: HashSet<String>,
+	failing: HashSet<String>, // paths whose placeholders were rejected in an earlier run
+	failed_at: Option<String> // innermost path rejected in this run
+}
+
+impl ProbeState {
+	fn record(&mut self, path: &str, node: Node) {
+		self.nodes.entry(path.to_string()).or_insert(node);
+	}
+
+	// Whether path is or contains a path which was rejected before
+	fn is_failing(&self, path: &str) -> bool {
+		self.failing.iter().any(|failing| {
+			failing.strip_prefix(path).is_some_and(|rest| rest.is_empty() || rest.starts_with('.') || rest.starts_with('['))
+		})
+	}
+
+	fn build(&self, path: &str) -> Shape {
+		match self.nodes.get(path) {
+			None | Some(Node::Any) => Shape::Any,
+			Some(Node::Bool) => Shape::Bool,
+			Some(Node::Integer) => Shape::Integer,
+			Some(Node::Byte) => Shape::Byte,
+			Some(Node::Double) => Shape::Double,
+			Some(Node::Blob) => Shape::Blob,
+			Some(Node::Map) => Shape::Map,
+			Some(Node::Seq) => Shape::Seq(Box::new(self.build(&format!("{}[]", path)))),
+			Some(Node::Struct(names)) => Shape::Struct(names.iter().map(|&name| {
+				let field_path = join_key(path, name);
+				Field { name, shape: self.build(&field_path), optional: self.optional.contains(&field_path) }
+			}).collect())
+		}
+	}
+}
+
+struct Probe<'s> {
+	state: &'s mut ProbeState,
+	path: String,
+	depth: usize
+}
+
+impl Probe<'_> {
+	fn child(&mut self, path: String) -> Probe<'_> {
+		Probe { state: &mut *self.state, path, depth: self.depth + 1 }
+	}
+
+	// Runs the placeholder for path through seed, noting where it was rejected
+	fn feed<'de, T: DeserializeSeed<'de>>(&mut self, path: String, seed: T) -> Result<T::Value> {
+		let res = seed.deserialize(self.child(path.clone()));
+		if res.is_err() && self.state.failed_at.is_none() {
+			self.state.failed_at = Some(path);
+		}
+		res
+	}
+}
+
+// Defines a method which records node and hands visitor a placeholder
+macro_rules! define_probe {
+	( $fname:ident, $node:expr, $visit:ident ( $($placeholder:expr)? ) ) => {
+		fn $fname<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+			self.state.record(&self.path, $node);
+			visitor.$visit($($placeholder)?)
+		}
+	}
+}
+
+impl<'de> de::Deserializer<'de> for Probe<'_> {
+	type Error = Error;
+
+	define_probe!{deserialize_any, Node::Any, visit_unit()}
+	define_probe!{deserialize_ignored_any, Node::Any, visit_unit()}
+	define_probe!{deserialize_unit, Node::Any, visit_unit()}
+	define_probe!{deserialize_bool, Node::Bool, visit_bool(false)}
+	define_probe!{deserialize_u8, Node::Byte, visit_u8(0)}
+	define_probe!{deserialize_u16, Node::Integer, visit_u16(0)}
+	define_probe!{deserialize_u32, Node::Integer, visit_u32(0)}
+	define_probe!{deserialize_u64, Node::Integer, visit_u64(0)}
+	define_probe!{deserialize_u128, Node::Integer, visit_u128(0)}
+	define_probe!{deserialize_i8, Node::Integer, visit_i8(0)}
+	define_probe!{deserialize_i16, Node::Integer, visit_i16(0)}
+	define_probe!{deserialize_i32, Node::Integer, visit_i32(0)}
+	define_probe!{deserialize_i64, Node::Integer, visit_i64(0)}
+	define_probe!{deserialize_i128, Node::Integer, visit_i128(0)}
+	define_probe!{deserialize_f32, Node::Double, visit_f32(0.0)}
+	define_probe!{deserialize_f64, Node::Double, visit_f64(0.0)}
+	define_probe!{deserialize_char, Node::Blob, visit_char('\0')}
+	define_probe!{deserialize_str, Node::Blob, visit_str("")}
+	define_probe!{deserialize_string, Node::Blob, visit_str("")}
+	define_probe!{deserialize_identifier, Node::Blob, visit_str("")}
+	define_probe!{deserialize_bytes, Node::Blob, visit_bytes(&[])}
+	define_probe!{deserialize_byte_buf, Node::Blob, visit_bytes(&[])}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+		self.state.optional.insert(self.path.clone());
+		if self.depth >= MAX_PROBE_DEPTH {
+			return visitor.visit_none();
+		}
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+		self.deserialize_unit(visitor)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+		let element_path = format!("{}[]", self.path);
+		let len = if self.depth >= MAX_PROBE_DEPTH || self.state.failing.contains(&element_path) { 0 } else { 1 };
+		self.deserialize_tuple(len, visitor)
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(mut self, len: usize, visitor: V) -> Result<V::Value> {
+		self.state.record(&self.path, Node::Seq);
+		visitor.visit_seq(ProbeSeq { probe: &mut self, remaining: len })
+	}
+
+	fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value> {
+		self.deserialize_tuple(len, visitor)
+	}
+
+	fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+		self.state.record(&self.path, Node::Map);
+		visitor.visit_map(de::value::MapDeserializer::new(std::iter::empty::<((), ())>()))
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(mut self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+		self.state.record(&self.path, Node::Struct(fields));
+
+		// Fields which were rejected before go last
+		let (mut keys, failing): (Vec<_>, Vec<_>) = fields.iter().copied()
+			.partition(|name| !self.state.is_failing(&join_key(&self.path, name)));
+		keys.extend(failing);
+		keys.reverse();
+		visitor.visit_map(ProbeStruct { probe: &mut self, keys, current: "" })
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], _visitor: V) -> Result<V::Value> {
+		self.state.record(&self.path, Node::Any);
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("Can't deserialize enums")))
+	}
+}
+
+struct ProbeSeq<'a, 's> {
+	probe: &'a mut Probe<'s>,
+	remaining: usize
+}
+
+impl<'de> SeqAccess<'de> for ProbeSeq<'_, '_> {
+	type Error = Error;
+
+	fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+		if self.remaining == 0 {
+			return Ok(None);
+		}
+		self.remaining -= 1;
+		let element_path = format!("{}[]", self.probe.path);
+		self.probe.feed(element_path, seed).map(Some)
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.remaining)
+	}
+}
+
+struct ProbeStruct<'a, 's> {
+	probe: &'a mut Probe<'s>,
+	keys: Vec<&'static str>, // reversed, so that the next one can be popped
+	current: &'static str
+}
+
+impl<'de> MapAccess<'de> for ProbeStruct<'_, '_> {
+	type Error = Error;
+
+	fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+		match self.keys.pop() {
+			Some(key) => {
+				self.current = key;
+				seed.deserialize(key.into_deserializer()).map(Some)
+			},
+			None => Ok(None)
+		}
+	}
+
+	fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+		let field_path = join_key(&self.probe.path, self.current);
+		self.probe.feed(field_path, seed)
+	}
+}
@@ -1,11 +1,25 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::ops::{Deref, DerefMut, Index};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use serde;
 use serde::{Serialize, Deserialize};
 use serde_bytes;
 
+use crate::constants;
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::limits::LimitTracker;
+use crate::scan;
+use crate::types::{EpeeEntryType, EpeeScalarType};
+use crate::VarInt;
+
 // The reason for a special array variant is that EPEE doesn't allow immediately nested arrays
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum SectionArray {
 	Int64(Vec<i64>),
@@ -22,7 +36,7 @@ pub enum SectionArray {
 	Object(Vec<Section>)
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum SectionEntry {
 	Int64(i64),
@@ -37,7 +51,1263 @@ pub enum SectionEntry {
 	Blob(serde_bytes::ByteBuf),
 	Bool(bool),
 	Object(Section),
-	Array(SectionArray)
+	Array(SectionArray),
+	// EPEE has no null, so this is never on the wire. Fields set to Nil are
+	// absent: they are left out when the section is serialized (and aren't
+	// counted), and when it is decoded into typed values with from_section.
+	// Indexing into missing paths also yields Nil.
+	Nil
+}
+
+// Untyped document. Use section::from_bytes and section::to_bytes to decode
+// and encode these directly; they keep the wire type of every entry. Serialize
+// skips Nil fields, see SectionEntry::Nil.
+//
+// Breaking change: Section used to be a type alias for HashMap<String,
+// SectionEntry>. Map methods still work through Deref, but code naming the
+// HashMap type needs Section::from(map) or section.into_inner(), and indexing
+// with a missing key gives Nil instead of panicking.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Section(HashMap<String, SectionEntry>);
+
+const MAX_PREALLOC: u64 = 4096;
+
+// Elements of arrays and bytes of blobs shown by Display before eliding the rest
+const MAX_SHOWN_ELEMENTS: usize = 8;
+const MAX_SHOWN_BYTES: usize = 32;
+
+static NIL_ENTRY: SectionEntry = SectionEntry::Nil;
+
+fn empty_section() -> &'static Section {
+	static EMPTY_SECTION: OnceLock<Section> = OnceLock::new();
+	EMPTY_SECTION.get_or_init(Section::new)
+}
+
+impl Section {
+	pub fn new() -> Self {
+		Self(HashMap::new())
+	}
+
+	pub fn into_inner(self) -> HashMap<String, SectionEntry> {
+		self.0
+	}
+
+	// Section::from_pairs([("height", 5u64.into()), ("hash", "ab".into())])
+	pub fn from_pairs<K, V, I>(pairs: I) -> Self
+	where
+		K: Into<String>,
+		V: Into<SectionEntry>,
+		I: IntoIterator<Item = (K, V)>
+	{
+		pairs.into_iter().collect()
+	}
+
+	// Reads a whole portable storage file, e.g. a config or cache file, keeping
+	// the wire types of all entries. Trailing bytes after the root are an error.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+		let mut reader = BufReader::new(File::open(path)?);
+		let section = from_reader(&mut reader)?;
+
+		let mut trailing = [0u8];
+		if reader.read(&mut trailing)? != 0 {
+			return epee_err!(ExpectedEnd, "trailing bytes after root section");
+		}
+		Ok(section)
+	}
+
+	// Writes the section to a temporary file next to path, syncs it and renames
+	// it over path, so that readers never see a half written file. Temporary
+	// names are unique within the process and never reuse an existing file, so
+	// concurrent saves to one path don't write into each other's files.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+		static NUM_SAVES: AtomicUsize = AtomicUsize::new(0);
+
+		let path = path.as_ref();
+		let mut tmp_name = match path.file_name() {
+			Some(name) => name.to_os_string(),
+			None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "path has no file name").into())
+		};
+		tmp_name.push(format!(".{}.{}.tmp", std::process::id(), NUM_SAVES.fetch_add(1, Ordering::Relaxed)));
+		let tmp_path = path.with_file_name(tmp_name);
+
+		let file = File::options().write(true).create_new(true).open(&tmp_path)?;
+		let res = self.write_synced(file).and_then(|_| Ok(fs::rename(&tmp_path, path)?));
+		if res.is_err() {
+			let _ = fs::remove_file(&tmp_path);
+		}
+		res?;
+		sync_parent_dir(path)
+	}
+
+	// Removes Nil fields, here and in nested sections
+	pub fn compact(&mut self) {
+		self.0.retain(|_, entry| !entry.is_nil());
+		for entry in self.0.values_mut() {
+			match entry {
+				SectionEntry::Object(section) => section.compact(),
+				SectionEntry::Array(SectionArray::Object(sections)) => sections.iter_mut().for_each(Section::compact),
+				_ => {}
+			}
+		}
+	}
+
+	fn write_synced(&self, file: File) -> Result<()> {
+		let mut writer = BufWriter::new(file);
+		to_writer(&mut writer, self)?;
+		writer.flush()?;
+		writer.get_ref().sync_all()?;
+		Ok(())
+	}
+}
+
+// Makes a rename into the directory holding path durable. Directories can only
+// be opened and synced like this on unix.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<()> {
+	let dir = match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new(".")
+	};
+	File::open(dir)?.sync_all()?;
+	Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> Result<()> {
+	Ok(())
+}
+
+impl Serialize for Section {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		use serde::ser::SerializeMap;
+
+		let present = self.0.values().filter(|entry| !entry.is_nil()).count();
+		let mut map = serializer.serialize_map(Some(present))?;
+		for (key, entry) in self.0.iter().filter(|(_, entry)| !entry.is_nil()) {
+			map.serialize_entry(key, entry)?;
+		}
+		map.end()
+	}
+}
+
+impl Deref for Section {
+	type Target = HashMap<String, SectionEntry>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DerefMut for Section {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+impl From<HashMap<String, SectionEntry>> for Section {
+	fn from(map: HashMap<String, SectionEntry>) -> Self {
+		Self(map)
+	}
+}
+
+impl<K: Into<String>, V: Into<SectionEntry>> FromIterator<(K, V)> for Section {
+	fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+		let mut section = Self::new();
+		section.extend(pairs);
+		section
+	}
+}
+
+// Later pairs replace earlier ones with the same key
+impl<K: Into<String>, V: Into<SectionEntry>> Extend<(K, V)> for Section {
+	fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+		self.0.extend(pairs.into_iter().map(|(k, v)| (k.into(), v.into())));
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Indexing and accessors                                                    //
+///////////////////////////////////////////////////////////////////////////////
+
+// Missing keys yield SectionEntry::Nil instead of panicking
+impl Index<&str> for Section {
+	type Output = SectionEntry;
+
+	fn index(&self, key: &str) -> &SectionEntry {
+		self.0.get(key).unwrap_or(&NIL_ENTRY)
+	}
+}
+
+impl Index<&str> for SectionEntry {
+	type Output = SectionEntry;
+
+	fn index(&self, key: &str) -> &SectionEntry {
+		match self {
+			SectionEntry::Object(section) => &section[key],
+			_ => &NIL_ENTRY
+		}
+	}
+}
+
+// Only arrays of sections can be indexed by position, since the elements of
+// scalar arrays are not stored as entries. Out of range indexes and other
+// types yield an empty section.
+impl Index<usize> for SectionArray {
+	type Output = Section;
+
+	fn index(&self, index: usize) -> &Section {
+		match self {
+			SectionArray::Object(sections) => sections.get(index).unwrap_or(empty_section()),
+			_ => empty_section()
+		}
+	}
+}
+
+impl Index<usize> for SectionEntry {
+	type Output = Section;
+
+	fn index(&self, index: usize) -> &Section {
+		match self {
+			SectionEntry::Array(array) => &array[index],
+			_ => empty_section()
+		}
+	}
+}
+
+impl Section {
+	// Array of sections under key, e.g. the blocks of a response:
+	//
+	//     for block in response.get_objects("blocks").unwrap_or_default() { ... }
+	//
+	// None if key is missing or holds anything else
+	pub fn get_objects(&self, key: &str) -> Option<&[Section]> {
+		self[key].as_object_array()
+	}
+
+	// Like get_objects, but fails with TypeMismatch at key, for ? chains
+	pub fn try_get_objects(&self, key: &str) -> Result<&[Section]> {
+		match &self[key] {
+			SectionEntry::Array(SectionArray::Object(sections)) => Ok(sections),
+			other => {
+				let found = other.entry_type().map_or(String::from("nil"), |entry_type| entry_type.to_string());
+				Err(Error::new(ErrorKind::TypeMismatch, format!("expected array of sections, found {}", found)).with_path(key))
+			}
+		}
+	}
+
+	// Drops every entry which isn't at or on the way to one of paths, e.g. to
+	// strip tx blobs from a response before forwarding it:
+	//
+	//     response.retain_paths(&["status", "blocks[].block", "blocks[].txs[].hash"]);
+	//
+	// Paths are keys joined by dots and escaped like in scan::path_sizes, e.g.
+	// "a\\.b" for the key "a.b". Like there, array indexes are left out, a path
+	// through an array of sections applies to all elements, and the "[]" may be
+	// left out too. Sections and empty arrays which are kept because a path
+	// leads through them may end up empty. Paths which don't exist are ignored.
+	pub fn retain_paths<P: AsRef<str>>(&mut self, paths: &[P]) {
+		let split: Vec<Vec<String>> = paths.iter().map(|path| scan::split_path(path.as_ref())).collect();
+		let keys: Vec<&[String]> = split.iter().map(Vec::as_slice).collect();
+		retain_section(self, &keys);
+	}
+}
+
+// paths are what is left of each path below section
+fn retain_section(section: &mut Section, paths: &[&[String]]) {
+	section.0.retain(|key, entry| {
+		let below: Vec<&[String]> = paths.iter()
+			.filter_map(|path| path.split_first())
+			.filter(|(first, _)| *first == key)
+			.map(|(_, rest)| rest)
+			.collect();
+		if below.is_empty() {
+			return false;
+		} else if below.iter().any(|rest| rest.is_empty()) {
+			return true; // the path ends here, keep all of entry
+		}
+
+		match entry {
+			SectionEntry::Object(inner) => retain_section(inner, &below),
+			SectionEntry::Array(SectionArray::Object(inner)) => inner.iter_mut().for_each(|inner| retain_section(inner, &below)),
+			SectionEntry::Array(array) if array.is_empty() => {}, // empty arrays of sections come back as UINT8 arrays
+			_ => return false // nothing to go down into
+		}
+		true
+	});
+}
+
+impl SectionEntry {
+	pub fn is_nil(&self) -> bool {
+		matches!(self, SectionEntry::Nil)
+	}
+
+	// Wire type of the entry, None for Nil
+	pub fn entry_type(&self) -> Option<EpeeEntryType> {
+		let scalar_type = match self {
+			SectionEntry::Int64(_) => EpeeScalarType::Int64,
+			SectionEntry::Int32(_) => EpeeScalarType::Int32,
+			SectionEntry::Int16(_) => EpeeScalarType::Int16,
+			SectionEntry::Int8(_) => EpeeScalarType::Int8,
+			SectionEntry::UInt64(_) => EpeeScalarType::UInt64,
+			SectionEntry::UInt32(_) => EpeeScalarType::UInt32,
+			SectionEntry::UInt16(_) => EpeeScalarType::UInt16,
+			SectionEntry::UInt8(_) => EpeeScalarType::UInt8,
+			SectionEntry::Double(_) => EpeeScalarType::Double,
+			SectionEntry::Blob(_) => EpeeScalarType::Str,
+			SectionEntry::Bool(_) => EpeeScalarType::Bool,
+			SectionEntry::Object(_) => EpeeScalarType::Object,
+			SectionEntry::Array(array) => return Some(EpeeEntryType::new(array.element_type(), true)),
+			SectionEntry::Nil => return None
+		};
+		Some(EpeeEntryType::new(scalar_type, false))
+	}
+
+	// Any integer entry which fits in a u64
+	pub fn as_u64(&self) -> Option<u64> {
+		match *self {
+			SectionEntry::UInt64(v) => Some(v),
+			SectionEntry::UInt32(v) => Some(v.into()),
+			SectionEntry::UInt16(v) => Some(v.into()),
+			SectionEntry::UInt8(v) => Some(v.into()),
+			SectionEntry::Int64(v) => v.try_into().ok(),
+			SectionEntry::Int32(v) => v.try_into().ok(),
+			SectionEntry::Int16(v) => v.try_into().ok(),
+			SectionEntry::Int8(v) => v.try_into().ok(),
+			_ => None
+		}
+	}
+
+	// Any integer entry which fits in an i64
+	pub fn as_i64(&self) -> Option<i64> {
+		match *self {
+			SectionEntry::Int64(v) => Some(v),
+			SectionEntry::Int32(v) => Some(v.into()),
+			SectionEntry::Int16(v) => Some(v.into()),
+			SectionEntry::Int8(v) => Some(v.into()),
+			SectionEntry::UInt64(v) => v.try_into().ok(),
+			SectionEntry::UInt32(v) => Some(v.into()),
+			SectionEntry::UInt16(v) => Some(v.into()),
+			SectionEntry::UInt8(v) => Some(v.into()),
+			_ => None
+		}
+	}
+
+	pub fn as_f64(&self) -> Option<f64> {
+		match *self {
+			SectionEntry::Double(v) => Some(v),
+			_ => None
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match *self {
+			SectionEntry::Bool(v) => Some(v),
+			_ => None
+		}
+	}
+
+	pub fn as_bytes(&self) -> Option<&[u8]> {
+		match self {
+			SectionEntry::Blob(blob) => Some(blob.as_slice()),
+			_ => None
+		}
+	}
+
+	// Blobs which are valid UTF-8
+	pub fn as_str(&self) -> Option<&str> {
+		self.as_bytes().and_then(|bytes| std::str::from_utf8(bytes).ok())
+	}
+
+	pub fn as_section(&self) -> Option<&Section> {
+		match self {
+			SectionEntry::Object(section) => Some(section),
+			_ => None
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&SectionArray> {
+		match self {
+			SectionEntry::Array(array) => Some(array),
+			_ => None
+		}
+	}
+
+	pub fn as_object_array(&self) -> Option<&[Section]> {
+		match self {
+			SectionEntry::Array(SectionArray::Object(sections)) => Some(sections),
+			_ => None
+		}
+	}
+}
+
+// Short human readable form of the value, e.g. 5, "OK", 0x0a1b, [1, 2, 3] or
+// {2 fields}. Long blobs and arrays are cut short.
+impl fmt::Display for SectionEntry {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SectionEntry::Int64(v) => write!(formatter, "{}", v),
+			SectionEntry::Int32(v) => write!(formatter, "{}", v),
+			SectionEntry::Int16(v) => write!(formatter, "{}", v),
+			SectionEntry::Int8(v) => write!(formatter, "{}", v),
+			SectionEntry::UInt64(v) => write!(formatter, "{}", v),
+			SectionEntry::UInt32(v) => write!(formatter, "{}", v),
+			SectionEntry::UInt16(v) => write!(formatter, "{}", v),
+			SectionEntry::UInt8(v) => write!(formatter, "{}", v),
+			SectionEntry::Double(v) => write!(formatter, "{}", v),
+			SectionEntry::Blob(blob) => write_blob(formatter, blob),
+			SectionEntry::Bool(v) => write!(formatter, "{}", v),
+			SectionEntry::Object(section) => write!(formatter, "{{{} fields}}", section.values().filter(|entry| !entry.is_nil()).count()),
+			SectionEntry::Array(array) => {
+				write!(formatter, "[")?;
+				for (i, element) in array.iter_entries().take(MAX_SHOWN_ELEMENTS).enumerate() {
+					let sep = if i == 0 { "" } else { ", " };
+					write!(formatter, "{}{}", sep, element)?;
+				}
+				if array.len() > MAX_SHOWN_ELEMENTS {
+					write!(formatter, ", ... {} more", array.len() - MAX_SHOWN_ELEMENTS)?;
+				}
+				write!(formatter, "]")
+			},
+			SectionEntry::Nil => write!(formatter, "nil")
+		}
+	}
+}
+
+// Printable strings are quoted, anything else is shown in hex
+fn write_blob(formatter: &mut fmt::Formatter, blob: &[u8]) -> fmt::Result {
+	let shown = &blob[..blob.len().min(MAX_SHOWN_BYTES)];
+	let ellipsis = if shown.len() < blob.len() { "..." } else { "" };
+	match std::str::from_utf8(shown) {
+		Ok(s) if s.chars().all(|c| !c.is_control()) => write!(formatter, "{:?}{}", s, ellipsis),
+		_ => {
+			write!(formatter, "0x")?;
+			for byte in shown {
+				write!(formatter, "{:02x}", byte)?;
+			}
+			write!(formatter, "{}", ellipsis)
+		}
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Array accessors                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+// Evaluates $body with $v bound to the inner vector, whatever the variant
+macro_rules! with_array_vec {
+	($array:expr, $v:ident => $body:expr) => {
+		match $array {
+			SectionArray::Int64($v) => $body,
+			SectionArray::Int32($v) => $body,
+			SectionArray::Int16($v) => $body,
+			SectionArray::Int8($v) => $body,
+			SectionArray::UInt64($v) => $body,
+			SectionArray::UInt32($v) => $body,
+			SectionArray::UInt16($v) => $body,
+			SectionArray::UInt8($v) => $body,
+			SectionArray::Double($v) => $body,
+			SectionArray::Blob($v) => $body,
+			SectionArray::Bool($v) => $body,
+			SectionArray::Object($v) => $body
+		}
+	}
+}
+
+impl SectionArray {
+	pub fn len(&self) -> usize {
+		with_array_vec!(self, v => v.len())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	pub fn element_type(&self) -> EpeeScalarType {
+		match self {
+			SectionArray::Int64(_) => EpeeScalarType::Int64,
+			SectionArray::Int32(_) => EpeeScalarType::Int32,
+			SectionArray::Int16(_) => EpeeScalarType::Int16,
+			SectionArray::Int8(_) => EpeeScalarType::Int8,
+			SectionArray::UInt64(_) => EpeeScalarType::UInt64,
+			SectionArray::UInt32(_) => EpeeScalarType::UInt32,
+			SectionArray::UInt16(_) => EpeeScalarType::UInt16,
+			SectionArray::UInt8(_) => EpeeScalarType::UInt8,
+			SectionArray::Double(_) => EpeeScalarType::Double,
+			SectionArray::Blob(_) => EpeeScalarType::Str,
+			SectionArray::Bool(_) => EpeeScalarType::Bool,
+			SectionArray::Object(_) => EpeeScalarType::Object
+		}
+	}
+
+	// Copy of the element at index as a standalone entry
+	pub fn get(&self, index: usize) -> Option<SectionEntry> {
+		with_array_vec!(self, v => v.get(index).cloned().map(SectionEntry::from))
+	}
+
+	pub fn iter_entries(&self) -> Box<dyn Iterator<Item = SectionEntry> + '_> {
+		with_array_vec!(self, v => Box::new(v.iter().cloned().map(SectionEntry::from)))
+	}
+
+	pub fn into_entries(self) -> Vec<SectionEntry> {
+		with_array_vec!(self, v => v.into_iter().map(SectionEntry::from).collect())
+	}
+
+	// Elements of any unsigned integer array, widened to u64
+	pub fn iter_u64(&self) -> Option<Box<dyn Iterator<Item = u64> + '_>> {
+		match self {
+			SectionArray::UInt64(v) => Some(Box::new(v.iter().copied())),
+			SectionArray::UInt32(v) => Some(Box::new(v.iter().map(|&x| x.into()))),
+			SectionArray::UInt16(v) => Some(Box::new(v.iter().map(|&x| x.into()))),
+			SectionArray::UInt8(v) => Some(Box::new(v.iter().map(|&x| x.into()))),
+			_ => None
+		}
+	}
+
+	// Elements of any signed integer array, widened to i64
+	pub fn iter_i64(&self) -> Option<Box<dyn Iterator<Item = i64> + '_>> {
+		match self {
+			SectionArray::Int64(v) => Some(Box::new(v.iter().copied())),
+			SectionArray::Int32(v) => Some(Box::new(v.iter().map(|&x| x.into()))),
+			SectionArray::Int16(v) => Some(Box::new(v.iter().map(|&x| x.into()))),
+			SectionArray::Int8(v) => Some(Box::new(v.iter().map(|&x| x.into()))),
+			_ => None
+		}
+	}
+
+	pub fn iter_f64(&self) -> Option<impl Iterator<Item = f64> + '_> {
+		match self {
+			SectionArray::Double(v) => Some(v.iter().copied()),
+			_ => None
+		}
+	}
+
+	pub fn iter_bool(&self) -> Option<impl Iterator<Item = bool> + '_> {
+		match self {
+			SectionArray::Bool(v) => Some(v.iter().copied()),
+			_ => None
+		}
+	}
+
+	pub fn iter_blobs(&self) -> Option<impl Iterator<Item = &[u8]> + '_> {
+		match self {
+			SectionArray::Blob(v) => Some(v.iter().map(|blob| blob.as_slice())),
+			_ => None
+		}
+	}
+
+	pub fn iter_sections(&self) -> Option<impl Iterator<Item = &Section> + '_> {
+		match self {
+			SectionArray::Object(v) => Some(v.iter()),
+			_ => None
+		}
+	}
+
+	// Appends entry if it has the same type as the other elements
+	pub fn push_checked(&mut self, entry: SectionEntry) -> Result<()> {
+		match (self, entry) {
+			(SectionArray::Int64(v), SectionEntry::Int64(x)) => v.push(x),
+			(SectionArray::Int32(v), SectionEntry::Int32(x)) => v.push(x),
+			(SectionArray::Int16(v), SectionEntry::Int16(x)) => v.push(x),
+			(SectionArray::Int8(v), SectionEntry::Int8(x)) => v.push(x),
+			(SectionArray::UInt64(v), SectionEntry::UInt64(x)) => v.push(x),
+			(SectionArray::UInt32(v), SectionEntry::UInt32(x)) => v.push(x),
+			(SectionArray::UInt16(v), SectionEntry::UInt16(x)) => v.push(x),
+			(SectionArray::UInt8(v), SectionEntry::UInt8(x)) => v.push(x),
+			(SectionArray::Double(v), SectionEntry::Double(x)) => v.push(x),
+			(SectionArray::Blob(v), SectionEntry::Blob(x)) => v.push(x),
+			(SectionArray::Bool(v), SectionEntry::Bool(x)) => v.push(x),
+			(SectionArray::Object(v), SectionEntry::Object(x)) => v.push(x),
+			(_, SectionEntry::Array(_)) => {
+				return epee_err!(NestedArrays, "can not push an array into an array");
+			},
+			(array, entry) => {
+				return epee_err!(ArrayMixedTypes, "can not push {:?} into array of {}", entry, array.element_type());
+			}
+		}
+
+		Ok(())
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Conversions                                                               //
+///////////////////////////////////////////////////////////////////////////////
+
+// Implements From<$t> and TryFrom<SectionEntry> for $t using the given variant
+macro_rules! impl_entry_conversions {
+	($t:ty, $enum_type:ident, $variant:ident) => {
+		impl From<$t> for $enum_type {
+			fn from(value: $t) -> Self {
+				$enum_type::$variant(value)
+			}
+		}
+
+		impl TryFrom<$enum_type> for $t {
+			type Error = Error;
+
+			fn try_from(entry: $enum_type) -> Result<Self> {
+				match entry {
+					$enum_type::$variant(value) => Ok(value),
+					other => epee_err!(TypeMismatch, "expected {}, found {:?}", stringify!($variant), other)
+				}
+			}
+		}
+	}
+}
+
+impl_entry_conversions!{i64, SectionEntry, Int64}
+impl_entry_conversions!{i32, SectionEntry, Int32}
+impl_entry_conversions!{i16, SectionEntry, Int16}
+impl_entry_conversions!{i8, SectionEntry, Int8}
+impl_entry_conversions!{u64, SectionEntry, UInt64}
+impl_entry_conversions!{u32, SectionEntry, UInt32}
+impl_entry_conversions!{u16, SectionEntry, UInt16}
+impl_entry_conversions!{u8, SectionEntry, UInt8}
+impl_entry_conversions!{f64, SectionEntry, Double}
+impl_entry_conversions!{serde_bytes::ByteBuf, SectionEntry, Blob}
+impl_entry_conversions!{bool, SectionEntry, Bool}
+impl_entry_conversions!{Section, SectionEntry, Object}
+impl_entry_conversions!{SectionArray, SectionEntry, Array}
+
+impl_entry_conversions!{Vec<i64>, SectionArray, Int64}
+impl_entry_conversions!{Vec<i32>, SectionArray, Int32}
+impl_entry_conversions!{Vec<i16>, SectionArray, Int16}
+impl_entry_conversions!{Vec<i8>, SectionArray, Int8}
+impl_entry_conversions!{Vec<u64>, SectionArray, UInt64}
+impl_entry_conversions!{Vec<u32>, SectionArray, UInt32}
+impl_entry_conversions!{Vec<u16>, SectionArray, UInt16}
+impl_entry_conversions!{Vec<u8>, SectionArray, UInt8}
+impl_entry_conversions!{Vec<f64>, SectionArray, Double}
+impl_entry_conversions!{Vec<serde_bytes::ByteBuf>, SectionArray, Blob}
+impl_entry_conversions!{Vec<bool>, SectionArray, Bool}
+impl_entry_conversions!{Vec<Section>, SectionArray, Object}
+
+impl TryFrom<SectionEntry> for Vec<Section> {
+	type Error = Error;
+
+	fn try_from(entry: SectionEntry) -> Result<Self> {
+		match entry {
+			SectionEntry::Array(array) => array.try_into(),
+			other => epee_err!(TypeMismatch, "expected array of sections, found {:?}", other)
+		}
+	}
 }
 
-pub type Section = HashMap<String, SectionEntry>;
+// Nonzero integers are entries of the underlying type, zero entries fail to
+// convert back with IntOutOfRange
+macro_rules! impl_entry_conversions_nonzero {
+	($nonzero:ty, $t:ty) => {
+		impl From<$nonzero> for SectionEntry {
+			fn from(value: $nonzero) -> Self {
+				value.get().into()
+			}
+		}
+
+		impl TryFrom<SectionEntry> for $nonzero {
+			type Error = Error;
+
+			fn try_from(entry: SectionEntry) -> Result<Self> {
+				match <$nonzero>::new(<$t>::try_from(entry)?) {
+					Some(value) => Ok(value),
+					None => epee_err!(IntOutOfRange { value: 0 }, "expected {}, found 0", stringify!($nonzero))
+				}
+			}
+		}
+	}
+}
+
+impl_entry_conversions_nonzero!{std::num::NonZeroU32, u32}
+impl_entry_conversions_nonzero!{std::num::NonZeroU64, u64}
+
+// Typed vectors become arrays, except for Vec<u8> which is a blob like in C++
+macro_rules! impl_entry_from_vec {
+	($($t:ty),*) => {
+		$(
+			impl From<Vec<$t>> for SectionEntry {
+				fn from(values: Vec<$t>) -> Self {
+					SectionEntry::Array(values.into())
+				}
+			}
+		)*
+	}
+}
+
+impl_entry_from_vec!{i64, i32, i16, i8, u64, u32, u16, f64, serde_bytes::ByteBuf, bool, Section}
+
+impl From<Vec<u8>> for SectionEntry {
+	fn from(bytes: Vec<u8>) -> Self {
+		SectionEntry::Blob(serde_bytes::ByteBuf::from(bytes))
+	}
+}
+
+impl From<&[u8]> for SectionEntry {
+	fn from(bytes: &[u8]) -> Self {
+		SectionEntry::Blob(serde_bytes::ByteBuf::from(bytes))
+	}
+}
+
+impl From<&str> for SectionEntry {
+	fn from(s: &str) -> Self {
+		SectionEntry::Blob(serde_bytes::ByteBuf::from(s.as_bytes()))
+	}
+}
+
+impl From<String> for SectionEntry {
+	fn from(s: String) -> Self {
+		SectionEntry::Blob(serde_bytes::ByteBuf::from(s.into_bytes()))
+	}
+}
+
+impl From<Vec<String>> for SectionEntry {
+	fn from(strings: Vec<String>) -> Self {
+		let blobs = strings.into_iter().map(|s| serde_bytes::ByteBuf::from(s.into_bytes())).collect();
+		SectionEntry::Array(SectionArray::Blob(blobs))
+	}
+}
+
+impl TryFrom<SectionEntry> for Vec<u8> {
+	type Error = Error;
+
+	fn try_from(entry: SectionEntry) -> Result<Self> {
+		serde_bytes::ByteBuf::try_from(entry).map(|blob| blob.into_vec())
+	}
+}
+
+impl TryFrom<SectionEntry> for String {
+	type Error = Error;
+
+	fn try_from(entry: SectionEntry) -> Result<Self> {
+		match String::from_utf8(Vec::<u8>::try_from(entry)?) {
+			Ok(s) => Ok(s),
+			Err(_) => epee_err!(StringBadEncoding, "blob is not valid UTF-8")
+		}
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Native encoding                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+// Encodes a section with Serializer::serialize_section. Same output as
+// crate::to_bytes(section), except that empty arrays keep their element type.
+pub fn to_bytes(section: &Section) -> Result<Vec<u8>> {
+	let mut bytes = Vec::new();
+	crate::Serializer::new(&mut bytes).serialize_section(section)?;
+	Ok(bytes)
+}
+
+// Writes are buffered like with crate::to_writer
+pub fn to_writer<W: Write>(writer: W, section: &Section) -> Result<()> {
+	let mut writer = crate::ser::write::IoWrite::new(writer);
+	crate::Serializer::new(&mut writer).serialize_section(section)?;
+	writer.flush()
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Native decoding                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+// Decodes a whole document without going through serde, keeping the wire type
+// of every entry. This is the way to read documents whose layout isn't known;
+// the Deserialize impl of Section has to guess types from serde's data model.
+// Trailing bytes after the root section fail with ExpectedEnd.
+pub fn from_bytes(bytes: &[u8]) -> Result<Section> {
+	let mut reader = bytes;
+	let section = from_reader(&mut reader)?;
+	if !reader.is_empty() {
+		return epee_err!(ExpectedEnd, "{} bytes left over after root section", reader.len());
+	}
+	Ok(section)
+}
+
+// Like from_bytes, but stops reading after the root section
+pub fn from_reader<R: Read>(mut reader: R) -> Result<Section> {
+	let mut section = Section::new();
+	let mut tracker = LimitTracker::default();
+	read_signature(&mut reader)?;
+	read_section_fields(&mut reader, &mut section, &mut tracker)?;
+	Ok(section)
+}
+
+// Decodes as much of a document as possible, for forensics on damaged files.
+// Entries which fail to decode but can be skipped (e.g. strings over the
+// length limit or sections nested too deep) are replaced by placeholders and
+// reading carries on after them: Nil for fields, empty sections for elements
+// of section arrays and sections which can't be entered. Keys which aren't
+// UTF-8 are kept lossily converted. Since EPEE has no resync points, reading
+// stops at the first entry which can't be skipped, e.g. one cut short or with
+// an unknown type code; what was read of it is kept. Every error is returned
+// alongside the section, in document order and with the path of its entry.
+pub fn from_bytes_lossy(bytes: &[u8]) -> (Section, Vec<Error>) {
+	let mut lossy = LossyReader {
+		reader: bytes,
+		tracker: LimitTracker::default(),
+		path: String::new(),
+		errors: Vec::new()
+	};
+	let mut section = Section::new();
+
+	match read_signature(&mut lossy.reader) {
+		Ok(()) => {
+			lossy.section(&mut section);
+		},
+		Err(e) => lossy.errors.push(e)
+	}
+	(section, lossy.errors)
+}
+
+// Document which T::default() encodes to, keeping the wire types, so that tools
+// can show what a request or response looks like. Fields serde skips (e.g. with
+// skip_serializing_if) are missing, and empty arrays come back as UINT8 arrays.
+#[cfg(feature = "template")]
+pub fn template_section<T: Serialize + Default>() -> Result<Section> {
+	let bytes = crate::to_bytes(&T::default())?;
+	let mut reader = bytes.as_slice();
+	let mut section = Section::new();
+
+	read_signature(&mut reader)?;
+	read_section_fields(&mut reader, &mut section, &mut LimitTracker::default())?;
+	Ok(section)
+}
+
+// XXH64 digest of the section's content, for deduplicating documents and using
+// them as cache keys. Fields are visited in key order and Nil fields are left
+// out, so documents which only differ in field order hash the same, as do empty
+// arrays of any element type. Wire types are part of the content: UINT32 5 and
+// UINT64 5 hash differently. Doubles are hashed by value rather than by bits,
+// so 0.0 and -0.0 hash the same, as do NaNs with different payloads. The
+// digest is stable across runs and platforms.
+#[cfg(feature = "structural-hash")]
+pub fn structural_hash(section: &Section) -> u64 {
+	let mut canonical = Vec::new();
+	write_canonical_section(section, &mut canonical);
+	crate::envelope::xxhash64(&canonical)
+}
+
+// structural_hash of an encoded document
+#[cfg(feature = "structural-hash")]
+pub fn hash_bytes(bytes: &[u8]) -> Result<u64> {
+	Ok(structural_hash(&from_bytes(bytes)?))
+}
+
+// Counts and lengths are written as u64s so that nothing needs escaping
+#[cfg(feature = "structural-hash")]
+fn write_canonical_section(section: &Section, out: &mut Vec<u8>) {
+	let mut keys: Vec<&String> = section.iter()
+		.filter(|(_, entry)| !entry.is_nil())
+		.map(|(key, _)| key)
+		.collect();
+	keys.sort_unstable();
+
+	out.extend_from_slice(&(keys.len() as u64).to_le_bytes());
+	for key in keys {
+		out.extend_from_slice(&(key.len() as u64).to_le_bytes());
+		out.extend_from_slice(key.as_bytes());
+		write_canonical_entry(&section[key.as_str()], out);
+	}
+}
+
+#[cfg(feature = "structural-hash")]
+fn write_canonical_entry(entry: &SectionEntry, out: &mut Vec<u8>) {
+	let type_code = match entry {
+		SectionEntry::Array(array) if array.is_empty() => constants::EMPTY_ARRAY_TYPE_CODE | constants::SERIALIZE_FLAG_ARRAY,
+		_ => match entry.entry_type() {
+			Some(entry_type) => entry_type.to_type_code(),
+			None => return
+		}
+	};
+	out.push(type_code);
+
+	match entry {
+		SectionEntry::Object(section) => write_canonical_section(section, out),
+		SectionEntry::Array(SectionArray::Object(sections)) => {
+			out.extend_from_slice(&(sections.len() as u64).to_le_bytes());
+			sections.iter().for_each(|section| write_canonical_section(section, out));
+		},
+		SectionEntry::Array(array) => {
+			out.extend_from_slice(&(array.len() as u64).to_le_bytes());
+			array.iter_entries().for_each(|element| write_canonical_scalar(&element, out));
+		},
+		scalar => write_canonical_scalar(scalar, out)
+	}
+}
+
+#[cfg(feature = "structural-hash")]
+fn write_canonical_scalar(entry: &SectionEntry, out: &mut Vec<u8>) {
+	match entry {
+		SectionEntry::Int64(v) => out.extend_from_slice(&v.to_le_bytes()),
+		SectionEntry::Int32(v) => out.extend_from_slice(&v.to_le_bytes()),
+		SectionEntry::Int16(v) => out.extend_from_slice(&v.to_le_bytes()),
+		SectionEntry::Int8(v) => out.extend_from_slice(&v.to_le_bytes()),
+		SectionEntry::UInt64(v) => out.extend_from_slice(&v.to_le_bytes()),
+		SectionEntry::UInt32(v) => out.extend_from_slice(&v.to_le_bytes()),
+		SectionEntry::UInt16(v) => out.extend_from_slice(&v.to_le_bytes()),
+		SectionEntry::UInt8(v) => out.push(*v),
+		SectionEntry::Double(v) => out.extend_from_slice(&canonical_double(*v).to_le_bytes()),
+		SectionEntry::Blob(v) => {
+			out.extend_from_slice(&(v.len() as u64).to_le_bytes());
+			out.extend_from_slice(v);
+		},
+		SectionEntry::Bool(v) => out.push(*v as u8),
+		SectionEntry::Object(section) => write_canonical_section(section, out),
+		SectionEntry::Array(_) | SectionEntry::Nil => {}
+	}
+}
+
+#[cfg(feature = "structural-hash")]
+fn canonical_double(v: f64) -> f64 {
+	if v.is_nan() {
+		f64::NAN
+	} else if v == 0.0 {
+		0.0 // and not -0.0
+	} else {
+		v
+	}
+}
+
+// Reads back a single entry value from its type code and payload, as made by
+// value::to_raw_bytes. The payload must hold exactly one value.
+pub(crate) fn entry_from_payload(type_code: u8, payload: &[u8]) -> Result<SectionEntry> {
+	let mut reader = payload;
+	let mut tracker = LimitTracker::default();
+	let mut entry = None;
+	read_entry(&mut reader, type_code, &mut entry, &mut tracker)?;
+
+	if !reader.is_empty() {
+		return epee_err!(ExpectedEnd, "{} bytes left over after entry payload", reader.len());
+	}
+	match entry {
+		Some(entry) => Ok(entry),
+		None => epee_err!(NoLength, "entry payload produced no value")
+	}
+}
+
+// Room to reserve for count fields or elements. Counts come off the wire, so
+// only small ones are trusted up front; bigger containers grow as they are read.
+fn prealloc_len(count: u64) -> usize {
+	count.min(MAX_PREALLOC) as usize
+}
+
+pub(crate) fn read_signature<R: Read>(reader: &mut R) -> Result<()> {
+	let mut sigbuf = [0u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE];
+	reader.read_exact(&mut sigbuf)?;
+	if sigbuf != constants::PORTABLE_STORAGE_SIGNATURE {
+		return epee_err!(ExpectedFormatSignature);
+	}
+	Ok(())
+}
+
+fn read_section_fields<R: Read>(reader: &mut R, section: &mut Section, tracker: &mut LimitTracker) -> Result<()> {
+	let num_fields: u64 = VarInt::from_reader(reader)?.into();
+	tracker.enter_section(num_fields)?;
+	section.reserve(prealloc_len(num_fields));
+
+	for _ in 0..num_fields {
+		let key = read_key(reader)?;
+		let mut type_code = [0u8];
+		reader.read_exact(&mut type_code)?;
+		if type_code[0] == constants::EMPTY_UNKNOWN_ARRAY_TYPE_CODE {
+			crate::de::skip_empty_unknown_array(reader)?;
+			continue;
+		}
+
+		let mut entry = None;
+		let res = read_entry(reader, type_code[0], &mut entry, tracker);
+		if let Some(entry) = entry {
+			section.insert(key, entry);
+		}
+		res?;
+	}
+
+	tracker.leave_section();
+	Ok(())
+}
+
+pub(crate) fn read_key<R: Read>(reader: &mut R) -> Result<String> {
+	let mut strlen = [0u8];
+	reader.read_exact(&mut strlen)?;
+	if strlen[0] == 0 {
+		return epee_err!(EmptySectionKey, "section key length can not be zero!");
+	}
+
+	let mut strbuf = vec![0u8; strlen[0] as usize];
+	reader.read_exact(&mut strbuf)?;
+	match String::from_utf8(strbuf) {
+		Ok(s) => Ok(s),
+		Err(_) => epee_err!(StringBadEncoding, "UTF-8 encoding error while parsing byte buffer for string key")
+	}
+}
+
+fn read_blob<R: Read>(reader: &mut R, tracker: &LimitTracker) -> Result<serde_bytes::ByteBuf> {
+	let strsize: u64 = VarInt::from_reader(reader)?.into();
+	tracker.check_string_len(strsize)?;
+
+	let mut strbuf = Vec::new();
+	reader.take(strsize).read_to_end(&mut strbuf)?;
+	if strbuf.len() as u64 != strsize {
+		return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+	}
+	Ok(serde_bytes::ByteBuf::from(strbuf))
+}
+
+// Reads a fixed width little endian number
+macro_rules! read_num {
+	($reader:expr, $numtype:ty) => {{
+		let mut le_bytes = [0u8; std::mem::size_of::<$numtype>()];
+		$reader.read_exact(&mut le_bytes).map(|_| <$numtype>::from_le_bytes(le_bytes))
+	}}
+}
+
+fn read_scalar<R: Read>(reader: &mut R, scalar_type: EpeeScalarType, out: &mut Option<SectionEntry>, tracker: &mut LimitTracker) -> Result<()> {
+	let entry = match scalar_type {
+		EpeeScalarType::Int64  => SectionEntry::Int64(read_num!(reader, i64)?),
+		EpeeScalarType::Int32  => SectionEntry::Int32(read_num!(reader, i32)?),
+		EpeeScalarType::Int16  => SectionEntry::Int16(read_num!(reader, i16)?),
+		EpeeScalarType::Int8   => SectionEntry::Int8(read_num!(reader, i8)?),
+		EpeeScalarType::UInt64 => SectionEntry::UInt64(read_num!(reader, u64)?),
+		EpeeScalarType::UInt32 => SectionEntry::UInt32(read_num!(reader, u32)?),
+		EpeeScalarType::UInt16 => SectionEntry::UInt16(read_num!(reader, u16)?),
+		EpeeScalarType::UInt8  => SectionEntry::UInt8(read_num!(reader, u8)?),
+		EpeeScalarType::Double if cfg!(feature = "no-float") => return epee_err!(FloatDisabled),
+		EpeeScalarType::Double => SectionEntry::Double(read_num!(reader, f64)?),
+		EpeeScalarType::Str    => SectionEntry::Blob(read_blob(reader, tracker)?),
+		EpeeScalarType::Bool   => SectionEntry::Bool(read_num!(reader, u8)? != 0),
+		EpeeScalarType::Object => {
+			// Keep partially read sections around
+			let mut section = Section::new();
+			let res = read_section_fields(reader, &mut section, tracker);
+			*out = Some(SectionEntry::Object(section));
+			return res;
+		}
+	};
+
+	*out = Some(entry);
+	Ok(())
+}
+
+// Reads array elements one by one into vec, keeping the elements read so far on error
+macro_rules! read_array_elements {
+	($reader:expr, $count:expr, $out:expr, $variant:ident, $read_elem:expr) => {{
+		let mut elems = Vec::with_capacity(prealloc_len($count));
+		let mut res = Ok(());
+		for _ in 0..$count {
+			match $read_elem($reader) {
+				Ok(elem) => elems.push(elem),
+				Err(e) => {
+					res = Err(e);
+					break;
+				}
+			}
+		}
+		*$out = Some(SectionEntry::Array(SectionArray::$variant(elems)));
+		res
+	}}
+}
+
+fn read_array<R: Read>(reader: &mut R, scalar_type: EpeeScalarType, out: &mut Option<SectionEntry>, tracker: &mut LimitTracker) -> Result<()> {
+	let count: u64 = VarInt::from_reader(reader)?.into();
+	tracker.add_values(scalar_type, count)?;
+
+	match scalar_type {
+		EpeeScalarType::Int64  => read_array_elements!(reader, count, out, Int64, |r: &mut R| read_num!(r, i64).map_err(Error::from)),
+		EpeeScalarType::Int32  => read_array_elements!(reader, count, out, Int32, |r: &mut R| read_num!(r, i32).map_err(Error::from)),
+		EpeeScalarType::Int16  => read_array_elements!(reader, count, out, Int16, |r: &mut R| read_num!(r, i16).map_err(Error::from)),
+		EpeeScalarType::Int8   => read_array_elements!(reader, count, out, Int8, |r: &mut R| read_num!(r, i8).map_err(Error::from)),
+		EpeeScalarType::UInt64 => read_array_elements!(reader, count, out, UInt64, |r: &mut R| read_num!(r, u64).map_err(Error::from)),
+		EpeeScalarType::UInt32 => read_array_elements!(reader, count, out, UInt32, |r: &mut R| read_num!(r, u32).map_err(Error::from)),
+		EpeeScalarType::UInt16 => read_array_elements!(reader, count, out, UInt16, |r: &mut R| read_num!(r, u16).map_err(Error::from)),
+		EpeeScalarType::UInt8  => read_array_elements!(reader, count, out, UInt8, |r: &mut R| read_num!(r, u8).map_err(Error::from)),
+		EpeeScalarType::Double if cfg!(feature = "no-float") => epee_err!(FloatDisabled),
+		EpeeScalarType::Double => read_array_elements!(reader, count, out, Double, |r: &mut R| read_num!(r, f64).map_err(Error::from)),
+		EpeeScalarType::Str    => read_array_elements!(reader, count, out, Blob, |r: &mut R| read_blob(r, tracker)),
+		EpeeScalarType::Bool   => read_array_elements!(reader, count, out, Bool, |r: &mut R| read_num!(r, u8).map(|b| b != 0).map_err(Error::from)),
+		EpeeScalarType::Object => {
+			let mut sections = Vec::with_capacity(prealloc_len(count));
+			let mut res = Ok(());
+			for _ in 0..count {
+				let mut section = Section::new();
+				res = read_section_fields(reader, &mut section, tracker);
+				sections.push(section);
+				if res.is_err() {
+					break;
+				}
+			}
+			*out = Some(SectionEntry::Array(SectionArray::Object(sections)));
+			res
+		}
+	}
+}
+
+fn read_entry<R: Read>(reader: &mut R, type_code: u8, out: &mut Option<SectionEntry>, tracker: &mut LimitTracker) -> Result<()> {
+	let entry_type = EpeeEntryType::from_type_code(type_code)?;
+
+	if entry_type.is_array {
+		read_array(reader, entry_type.scalar_type, out, tracker)
+	} else {
+		tracker.add_values(entry_type.scalar_type, 1)?;
+		read_scalar(reader, entry_type.scalar_type, out, tracker)
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Lossy decoding                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// State of from_bytes_lossy. Methods return whether reading can go on.
+struct LossyReader<'a> {
+	reader: &'a [u8],
+	tracker: LimitTracker,
+	path: String, // of the entry being read, for errors
+	errors: Vec<Error>
+}
+
+impl<'a> LossyReader<'a> {
+	fn fail(&mut self, err: Error) {
+		self.errors.push(err.with_path(&self.path));
+	}
+
+	fn section(&mut self, section: &mut Section) -> bool {
+		let start = self.reader;
+		let num_fields: u64 = match VarInt::from_reader(&mut self.reader) {
+			Ok(num_fields) => num_fields.into(),
+			Err(e) => {
+				self.fail(e);
+				return false;
+			}
+		};
+		if let Err(e) = self.tracker.enter_section(num_fields) {
+			// Too deep or too many fields, left empty
+			self.fail(e);
+			self.reader = start;
+			return self.skip(constants::SERIALIZE_TYPE_OBJECT);
+		}
+
+		let path_len = self.path.len();
+		let mut complete = true;
+		for _ in 0..num_fields {
+			complete = self.field(section);
+			self.path.truncate(path_len);
+			if !complete {
+				break;
+			}
+		}
+		self.tracker.leave_section();
+		complete
+	}
+
+	fn field(&mut self, section: &mut Section) -> bool {
+		let mut keylen = [0u8];
+		if let Err(e) = self.reader.read_exact(&mut keylen) {
+			self.fail(e.into());
+			return false;
+		}
+		let mut key = vec![0u8; keylen[0] as usize];
+		if let Err(e) = self.reader.read_exact(&mut key) {
+			self.fail(e.into());
+			return false;
+		}
+		let (key, bad_encoding) = match String::from_utf8(key) {
+			Ok(key) => (key, false),
+			Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), true)
+		};
+		if !self.path.is_empty() {
+			self.path.push('.');
+		}
+		self.path.push_str(&key);
+		if keylen[0] == 0 {
+			self.fail(Error::new(ErrorKind::EmptySectionKey, String::from("section key length can not be zero!")));
+		} else if bad_encoding {
+			self.fail(Error::new(ErrorKind::StringBadEncoding, String::from("UTF-8 encoding error while parsing byte buffer for string key")));
+		}
+
+		let mut type_code = [0u8];
+		if let Err(e) = self.reader.read_exact(&mut type_code) {
+			section.insert(key, SectionEntry::Nil);
+			self.fail(e.into());
+			return false;
+		}
+		let type_code = type_code[0];
+		if type_code == constants::EMPTY_UNKNOWN_ARRAY_TYPE_CODE {
+			return match crate::de::skip_empty_unknown_array(&mut self.reader) {
+				Ok(()) => true,
+				Err(e) => {
+					self.fail(e);
+					false
+				}
+			};
+		}
+
+		let (entry, complete) = self.entry(type_code);
+		section.insert(key, entry);
+		complete
+	}
+
+	// Reads one entry, or gives its placeholder
+	fn entry(&mut self, type_code: u8) -> (SectionEntry, bool) {
+		let start = self.reader;
+		if type_code == constants::SERIALIZE_TYPE_OBJECT {
+			if let Err(e) = self.tracker.add_values(EpeeScalarType::Object, 1) {
+				return self.skip_failed(start, type_code, None, e);
+			}
+			let mut inner = Section::new();
+			let complete = self.section(&mut inner);
+			return (SectionEntry::Object(inner), complete);
+		}
+
+		if type_code == constants::SERIALIZE_TYPE_OBJECT | constants::SERIALIZE_FLAG_ARRAY {
+			let count = VarInt::from_reader(&mut self.reader).map(u64::from)
+				.and_then(|count| self.tracker.add_values(EpeeScalarType::Object, count).map(|_| count));
+			let count = match count {
+				Ok(count) => count,
+				Err(e) => return self.skip_failed(start, type_code, None, e)
+			};
+
+			let path_len = self.path.len();
+			let mut sections = Vec::with_capacity(prealloc_len(count));
+			let mut complete = true;
+			for i in 0..count {
+				self.path.push_str(&format!("[{}]", i));
+				let mut inner = Section::new();
+				complete = self.section(&mut inner);
+				self.path.truncate(path_len);
+				sections.push(inner);
+				if !complete {
+					break;
+				}
+			}
+			return (SectionEntry::Array(SectionArray::Object(sections)), complete);
+		}
+
+		let mut entry = None;
+		match read_entry(&mut self.reader, type_code, &mut entry, &mut self.tracker) {
+			Ok(()) => (entry.unwrap_or(SectionEntry::Nil), true),
+			Err(e) => self.skip_failed(start, type_code, entry, e)
+		}
+	}
+
+	// Moves past an entry which failed to decode, giving a Nil placeholder for
+	// it. If that's impossible as well, whatever was read of it is kept.
+	fn skip_failed(&mut self, start: &'a [u8], type_code: u8, partial: Option<SectionEntry>, err: Error) -> (SectionEntry, bool) {
+		let is_eof = err.is_eof();
+		self.fail(err);
+		if is_eof {
+			return (partial.unwrap_or(SectionEntry::Nil), false);
+		}
+		self.reader = start;
+		match crate::de::skip_entry(&mut self.reader, type_code) {
+			Ok(()) => (SectionEntry::Nil, true),
+			Err(_) => (partial.unwrap_or(SectionEntry::Nil), false)
+		}
+	}
+
+	fn skip(&mut self, type_code: u8) -> bool {
+		match crate::de::skip_entry(&mut self.reader, type_code) {
+			Ok(()) => true,
+			Err(e) => {
+				self.fail(e);
+				false
+			}
+		}
+	}
+}
@@ -0,0 +1,1363 @@
+use std::io::Write;
+use std::sync::Arc;
+use serde::{ser, Serialize};
+use serde::ser::Impossible;
+
+use crate::custom::{self, CustomValue, TypeRegistry};
+use crate::error::{Error, ErrorKind, Result};
+use crate::constants;
+use crate::format::FormatProfile;
+use crate::fragment::RAW_FRAGMENT_TOKEN;
+use crate::varint::VARINT_TOKEN;
+use crate::limits::{LimitTracker, Limits};
+use crate::section::{Section, SectionArray, SectionEntry};
+use crate::trace::{self, TraceLabel, TraceRecord};
+use crate::types::EpeeScalarType;
+use crate::varint::VarInt;
+
+pub mod write;
+
+use write::{EpeeWrite, IoWrite};
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Writes are buffered, see write::IoWrite. Whatever was written is flushed to
+// writer before returning, even if serializing fails.
+pub fn to_writer<T, W>(writer: W, value: &T) -> Result<()>
+where
+	T: Serialize,
+	W: Write
+{
+	let mut writer = IoWrite::new(writer);
+	value.serialize(&mut Serializer::new(&mut writer))?;
+	writer.flush()
+}
+
+// The output is sized by a serialized_size pass first, so big documents are
+// encoded twice but never reallocated
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+	to_bytes_with_capacity(value, serialized_size(value)?)
+}
+
+// Like to_bytes, but skips the sizing pass and starts out with room for
+// capacity bytes instead, for callers who already know about how big value is
+pub fn to_bytes_with_capacity<T: Serialize>(value: &T, capacity: usize) -> Result<Vec<u8>> {
+	let mut byte_stream = Vec::<u8>::with_capacity(capacity); // Vec<u8> implements EpeeWrite
+	let mut serializer = Serializer::new(&mut byte_stream);
+	value.serialize(&mut serializer)?;
+	Ok(byte_stream)
+}
+
+// Exact length of value's encoding, found by encoding it into a sink
+pub fn serialized_size<T: Serialize>(value: &T) -> Result<usize> {
+	let mut sink = std::io::sink();
+	let mut serializer = Serializer::new(&mut sink);
+	value.serialize(&mut serializer)?;
+	Ok(serializer.bytes_written())
+}
+
+// Like to_writer, but also returns the number of bytes written
+pub fn to_writer_counted<T, W>(writer: W, value: &T) -> Result<(W, usize)>
+where
+	T: Serialize,
+	W: Write
+{
+	let mut writer = IoWrite::new(writer);
+	let bytes_written = {
+		let mut serializer = Serializer::new(&mut writer);
+		value.serialize(&mut serializer)?;
+		serializer.bytes_written()
+	};
+	Ok((writer.into_inner()?, bytes_written))
+}
+
+// Writes a single key/value entry exactly like it would appear inside of a
+// section, without a signature or section header around it
+pub fn write_entry<T, W>(writer: W, key: &str, value: &T) -> Result<()>
+where
+	T: Serialize,
+	W: Write
+{
+	let mut writer = IoWrite::new(writer);
+	let mut serializer = Serializer::new_with_format(&mut writer, EpeeStorageFormat::Section, 0, StateRef::Owned(LimitTracker::new(Limits::unlimited())));
+	serializer.write_key_string(key.as_bytes())?;
+	value.serialize(&mut serializer)?;
+	writer.flush()
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Serializer                                                                //
+///////////////////////////////////////////////////////////////////////////////
+
+// Where the values written by a Serializer go. Compound values get their own
+// serializer types (SectionSerializer, ArraySerializer), which write their
+// entries through a Serializer of the matching format.
+#[derive(Debug, PartialEq)]
+enum EpeeStorageFormat {
+	Section, // field values, each after its key and a type code
+	Array, // array elements, sharing one type code written before the first
+	Unstarted // a whole document, which must be a section
+}
+
+// Sub-serializers share per-document state (limit tracker, byte count) with
+// the serializer which created them
+#[derive(Debug)]
+enum StateRef<'a, T> {
+	Owned(T),
+	Borrowed(&'a mut T)
+}
+
+impl<T> StateRef<'_, T> {
+	fn get(&mut self) -> &mut T {
+		match self {
+			StateRef::Owned(state) => state,
+			StateRef::Borrowed(state) => state
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Serializer<'a, W: EpeeWrite> {
+	writer: &'a mut W,
+	storage_format: EpeeStorageFormat,
+	len: u64, // only important for arrays, whose header is written lazily
+	element_type: u8, // only important for arrays to enforce type consistency
+	started: bool,
+	buffered: bool, // array elements go to a buffer, the header is written by ArraySerializer
+	signature: [u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE],
+	strict_keys: bool,
+	path: String, // key path of this compound, for error messages
+	field: String, // key of the entry currently being written
+	index: u64, // index of the array element currently being written
+	bytes_written: StateRef<'a, usize>,
+	tracker: StateRef<'a, LimitTracker>,
+	trace: Option<StateRef<'a, Vec<TraceRecord>>>,
+	registry: Option<Arc<TypeRegistry>>
+}
+
+impl<'a, W> Serializer<'a, W>
+where
+	W: EpeeWrite
+{
+	///////////////////////////////////////////////////////////////////////////////
+	// Constructors                                                              //
+	///////////////////////////////////////////////////////////////////////////////
+
+	// Serializer for a whole document, which will start with the format signature.
+	// Nothing is limited unless with_limits is called, e.g. with Limits::monerod()
+	// to refuse documents monerod wouldn't read.
+	pub fn new(writer: &'a mut W) -> Self {
+		Self::new_with_format(writer, EpeeStorageFormat::Unstarted, 0, StateRef::Owned(LimitTracker::new(Limits::unlimited())))
+	}
+
+	// Serializer for the fields of a section entry, which writes its type code
+	// and field count right away. Exactly len fields must follow.
+	#[deprecated(since = "0.3.0", note = "serialize_map and serialize_struct return the same SectionSerializer")]
+	pub fn new_section(writer: &'a mut W, len: u64) -> Result<SectionSerializer<'a, W>> {
+		let mut ser = Self::new_with_format(writer, EpeeStorageFormat::Section, len, StateRef::Owned(LimitTracker::new(Limits::unlimited())));
+		ser.tracker.get().enter_section(len)?;
+		ser.write_type_code(constants::SERIALIZE_TYPE_OBJECT, false)?;
+		ser.write_varint(VarInt::try_from(len)?)?;
+		Ok(SectionSerializer { ser, fields: 0 })
+	}
+
+	// Like new_section, but for a whole document, starting with the signature
+	#[deprecated(since = "0.3.0", note = "serialize_map and serialize_struct on Serializer::new write whole documents")]
+	pub fn new_root_section(writer: &'a mut W, len: u64) -> Result<SectionSerializer<'a, W>> {
+		let mut ser = Self::new_with_format(writer, EpeeStorageFormat::Section, len, StateRef::Owned(LimitTracker::new(Limits::unlimited())));
+		ser.tracker.get().enter_section(len)?;
+		ser.write_signature()?;
+		ser.write_varint(VarInt::try_from(len)?)?;
+		Ok(SectionSerializer { ser, fields: 0 })
+	}
+
+	// Serializer for the len elements of an array entry, whose header is written
+	// with the first element
+	#[deprecated(since = "0.3.0", note = "serialize_seq and serialize_tuple return the same ArraySerializer")]
+	pub fn new_array(writer: &'a mut W, len: u64) -> Result<ArraySerializer<'a, W>> {
+		if !VarInt::fits(len) {
+			return Err(Error::new(ErrorKind::ArrayTooLong { len }, format!("array length {} can not be encoded", len)));
+		}
+		let ser = Self::new_with_format(writer, EpeeStorageFormat::Array, len, StateRef::Owned(LimitTracker::new(Limits::unlimited())));
+		Ok(ArraySerializer { ser, buffer: None })
+	}
+
+	#[deprecated(since = "0.3.0", note = "use PackedSerializer::new")]
+	pub fn new_packed(writer: &'a mut W, len: u64) -> Result<PackedSerializer<'a, W>> {
+		Ok(PackedSerializer::new(writer, len))
+	}
+
+	fn new_with_format(writer: &'a mut W, storage_format: EpeeStorageFormat, len: u64, tracker: StateRef<'a, LimitTracker>) -> Self {
+		Self {
+			writer,
+			storage_format,
+			len,
+			element_type: constants::SERIALIZE_TYPE_UNKNOWN,
+			started: false,
+			buffered: false,
+			signature: constants::PORTABLE_STORAGE_SIGNATURE,
+			strict_keys: false,
+			path: String::new(),
+			field: String::new(),
+			index: 0,
+			bytes_written: StateRef::Owned(0),
+			tracker,
+			trace: None,
+			registry: None
+		}
+	}
+
+	// Creates a serializer for the entries of a compound value nested inside this one
+	fn new_child(&mut self, storage_format: EpeeStorageFormat, len: u64) -> Serializer<'_, W> {
+		let signature = self.signature;
+		let strict_keys = self.strict_keys;
+		let path = self.field_path();
+		let mut child = Serializer::new_with_format(&mut *self.writer, storage_format, len, StateRef::Borrowed(self.tracker.get()));
+		child.bytes_written = StateRef::Borrowed(self.bytes_written.get());
+		child.trace = self.trace.as_mut().map(|trace| StateRef::Borrowed(trace.get()));
+		child.signature = signature;
+		child.strict_keys = strict_keys;
+		child.path = path;
+		child.registry = self.registry.clone();
+		child
+	}
+
+	// Creates a serializer for one element of an array of unknown length, which
+	// is written to buffer instead, see ArraySerializer::buffered_element
+	fn new_buffered_element<'c>(&'c mut self, buffer: &'c mut Vec<u8>, element_type: Option<u8>) -> Serializer<'c, Vec<u8>> {
+		let mut element = Serializer::new_with_format(buffer, EpeeStorageFormat::Array, 0, StateRef::Borrowed(self.tracker.get()));
+		element.buffered = true;
+		element.started = element_type.is_some();
+		element.element_type = element_type.unwrap_or(constants::SERIALIZE_TYPE_UNKNOWN);
+		element.signature = self.signature;
+		element.strict_keys = self.strict_keys;
+		element.path = self.path.clone();
+		element.index = self.index;
+		element.registry = self.registry.clone();
+		element
+	}
+
+	// Must be called before anything is serialized
+	pub fn with_limits(mut self, limits: Limits) -> Result<Self> {
+		self.tracker = StateRef::Owned(LimitTracker::new(limits));
+		Ok(self)
+	}
+
+	pub fn limits(&mut self) -> Limits {
+		*self.tracker.get().limits()
+	}
+
+	// Number of bytes written so far, including those of nested values
+	pub fn bytes_written(&mut self) -> usize {
+		*self.bytes_written.get()
+	}
+
+	// Records every write with a label and key path, see trace::Trace. Must be
+	// called before anything is serialized.
+	pub fn with_trace(mut self) -> Self {
+		self.trace = Some(StateRef::Owned(Vec::new()));
+		self
+	}
+
+	// Records of everything written so far, if tracing is enabled
+	pub fn take_trace(&mut self) -> Option<Vec<TraceRecord>> {
+		self.trace.as_mut().map(|trace| std::mem::take(trace.get()))
+	}
+
+	// Must be called before anything is serialized
+	pub fn with_profile(mut self, profile: FormatProfile) -> Self {
+		self.signature = profile.signature();
+		self
+	}
+
+	// Rejects keys which aren't ASCII identifiers with KeyBadEncoding, see
+	// constants::is_strict_section_key
+	pub fn with_strict_keys(mut self) -> Self {
+		self.strict_keys = true;
+		self
+	}
+
+	// Writes i128 and u128 values as the custom types registered for them, see
+	// custom::TypeRegistry. Must be called before anything is serialized.
+	pub fn with_type_registry(mut self, registry: TypeRegistry) -> Self {
+		self.registry = Some(Arc::new(registry));
+		self
+	}
+
+	///////////////////////////////////////////////////////////////////////////////
+	// Other methods                                                             //
+	///////////////////////////////////////////////////////////////////////////////
+
+	fn write_raw(&mut self, label: TraceLabel, bytes: &[u8]) -> Result<()> {
+		let write_res = self.writer.write_bytes(bytes);
+		match write_res {
+			Ok(_) => {
+				let offset = *self.bytes_written.get();
+				*self.bytes_written.get() = offset.saturating_add(bytes.len());
+				if self.trace.is_some() {
+					let record = TraceRecord {
+						offset,
+						label,
+						path: self.field_path(),
+						bytes: bytes.to_vec(),
+						meaning: trace::describe(label, bytes)
+					};
+					if let Some(trace) = self.trace.as_mut() {
+						trace.get().push(record);
+					}
+				}
+				Ok(())
+			},
+			Err(err) => Err(err)
+		}
+	}
+
+	fn write_varint(&mut self, varint: VarInt) -> Result<()> {
+		let mut buf = [0u8; 8];
+		let mut unused = &mut buf[..];
+		varint.to_writer(&mut unused)?;
+		let len = 8 - unused.len();
+		self.write_raw(TraceLabel::VarInt, &buf[..len])
+	}
+
+	// Path of the value currently being written, e.g. "blocks[2].txs"
+	fn field_path(&self) -> String {
+		if self.storage_format == EpeeStorageFormat::Array {
+			format!("{}[{}]", self.path, self.index)
+		} else if self.path.is_empty() {
+			self.field.clone()
+		} else {
+			format!("{}.{}", self.path, self.field)
+		}
+	}
+
+	fn write_signature(&mut self) -> Result<()> {
+		let signature = self.signature;
+		self.write_raw(TraceLabel::Signature, &signature)
+	}
+
+	fn write_type_code(&mut self, type_code: u8, is_array: bool) -> Result<()> {
+		let array_mask = if is_array { constants::SERIALIZE_FLAG_ARRAY } else { 0 }; 
+		let type_byte = [type_code | array_mask];
+		self.write_raw(TraceLabel::TypeCode, &type_byte)
+	}
+
+	// Format: one unsigned byte for the length, then the rest of the string, max 255 bytes
+	fn write_key_string(&mut self, s: &[u8]) -> Result<()> {
+		self.field.clear();
+		self.field.push_str(&String::from_utf8_lossy(s));
+
+		if s.len() > constants::MAX_SECTION_KEY_SIZE {
+			let msg = format!("key is {} bytes long, max is {}", s.len(), constants::MAX_SECTION_KEY_SIZE);
+			return Err(Error::new(ErrorKind::KeyTooLong { len: s.len() as u64, max: constants::MAX_SECTION_KEY_SIZE as u64 }, msg).with_path(&self.field_path()));
+		} else if s.is_empty() {
+			let msg = String::from("section key length can not be zero!");
+			return Err(Error::new(ErrorKind::EmptySectionKey, msg).with_path(&self.path));
+		} else if self.strict_keys && !std::str::from_utf8(s).is_ok_and(constants::is_strict_section_key) {
+			let msg = format!("key {:?} is not an ASCII identifier", self.field);
+			return Err(Error::new(ErrorKind::KeyBadEncoding, msg).with_path(&self.field_path()));
+		}
+
+		let mut buf = [0u8; 1 + constants::MAX_SECTION_KEY_SIZE];
+		buf[0] = s.len() as u8;
+		buf[1..=s.len()].copy_from_slice(s);
+		self.write_raw(TraceLabel::Key, &buf[..=s.len()])
+	}
+
+	// Writes whatever has to come before a value's payload
+	fn serialize_start_and_type_code(&mut self, type_code: u8) -> Result<()> {
+		match self.storage_format {
+			EpeeStorageFormat::Section => self.write_type_code(type_code, false),
+			EpeeStorageFormat::Array => self.start_array_element(type_code),
+			EpeeStorageFormat::Unstarted => {
+				Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("a document must be a section")))
+			}
+		}
+	}
+
+	// The array header is written with the first element, which decides the type
+	// code of all of them
+	fn start_array_element(&mut self, type_code: u8) -> Result<()> {
+		if !self.started && self.buffered {
+			self.element_type = type_code;
+			self.started = true;
+		} else if !self.started {
+			self.write_type_code(type_code, true)?;
+			let varlen = VarInt::try_from(self.len)?;
+			self.write_varint(varlen)?;
+
+			self.tracker.get().add_values(EpeeScalarType::from_type_code(type_code)?, self.len)?;
+
+			self.element_type = type_code;
+			self.started = true;
+		}
+
+		if type_code != self.element_type {
+			let msg = format!("type_codes: {} -> {}", self.element_type, type_code);
+			return Err(Error::new(ErrorKind::ArrayMixedTypes, msg));
+		}
+		Ok(())
+	}
+
+	// Splices in a RawFragment: the type code goes wherever a type code would
+	// normally go, followed by the untouched payload
+	fn write_fragment(&mut self, encoded: &[u8]) -> Result<()> {
+		let type_code = encoded[0];
+		let payload = &encoded[1..];
+
+		if (type_code & constants::SERIALIZE_FLAG_ARRAY) != 0 && self.storage_format == EpeeStorageFormat::Array {
+			return Err(Error::new_no_msg(ErrorKind::NestedArrays));
+		}
+
+		if self.storage_format == EpeeStorageFormat::Unstarted {
+			// A whole document, only possible for sections
+			if type_code != constants::SERIALIZE_TYPE_OBJECT {
+				return Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("root fragment must be a section")));
+			}
+			self.write_signature()?;
+		} else {
+			self.serialize_start_and_type_code(type_code)?;
+		}
+
+		self.write_raw(TraceLabel::Fragment, payload)
+	}
+
+	// Custom values are single entries, their payload comes from the registry
+	fn write_custom(&mut self, value: CustomValue) -> Result<()> {
+		let (code, payload) = match &self.registry {
+			Some(registry) => registry.encode(&value)?,
+			None => return Err(Error::new(ErrorKind::SerdeModelUnsupported, format!("can't serialize {:?} values without a type registry", value.kind())))
+		};
+
+		if self.storage_format == EpeeStorageFormat::Array {
+			return Err(custom::unsupported_custom_array(code));
+		}
+		self.serialize_start_and_type_code(code)?;
+		self.write_raw(TraceLabel::Payload, &payload)
+	}
+
+	// Section headers are written right away, through this serializer so that
+	// the type code lands wherever it needs to be (e.g. once per array). The
+	// field count is charged against Limits::max_fields, if with_limits set
+	// one, before anything is written.
+	fn start_section(&mut self, len: Option<usize>) -> Result<SectionSerializer<'_, W>> {
+		let len = match len {
+			Some(l) if VarInt::fits(l as u64) => l as u64,
+			Some(l) => return Err(Error::new(ErrorKind::SectionTooLong { len: l as u64 }, format!("section of {} fields can not be encoded", l))),
+			None => return Err(Error::new(ErrorKind::NoLength, String::from("EPEE serializer needs to know map length ahead of time")))
+		};
+
+		// Sections inside arrays were already counted when the array started
+		if self.storage_format != EpeeStorageFormat::Array {
+			self.tracker.get().add_objects(1)?;
+		}
+		self.tracker.get().enter_section(len)?;
+
+		match self.storage_format {
+			EpeeStorageFormat::Unstarted => self.write_signature()?,
+			_ => self.serialize_start_and_type_code(constants::SERIALIZE_TYPE_OBJECT)?
+		}
+		self.write_varint(VarInt::try_from(len)?)?;
+
+		Ok(SectionSerializer { ser: self.new_child(EpeeStorageFormat::Section, len), fields: 0 })
+	}
+
+	// Nothing is written until the first element, or the end of an empty array.
+	// Sequences of unknown length (e.g. filtered iterators given to collect_seq)
+	// are encoded into a buffer and written once they end.
+	fn start_array(&mut self, len: Option<usize>) -> Result<ArraySerializer<'_, W>> {
+		if self.storage_format == EpeeStorageFormat::Array {
+			return Err(Error::new_no_msg(ErrorKind::NestedArrays));
+		}
+
+		match len {
+			Some(l) if VarInt::fits(l as u64) => Ok(ArraySerializer { ser: self.new_child(EpeeStorageFormat::Array, l as u64), buffer: None }),
+			Some(l) => Err(Error::new(ErrorKind::ArrayTooLong { len: l as u64 }, format!("array length {} can not be encoded", l))),
+			None => Ok(ArraySerializer { ser: self.new_child(EpeeStorageFormat::Array, 0), buffer: Some(Vec::new()) })
+		}
+	}
+}
+
+macro_rules! serialize_num {
+	($fname:ident, $numtype:ty, $numcode:expr) => (
+		fn $fname(self, v: $numtype) -> Result<()> {
+			self.serialize_start_and_type_code($numcode)?;
+			self.write_raw(TraceLabel::Payload, &v.to_le_bytes())
+		}
+	)
+}
+
+impl<'b, 'a: 'b, W> ser::Serializer for &'b mut Serializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	type SerializeSeq = ArraySerializer<'b, W>;
+	type SerializeTuple = ArraySerializer<'b, W>;
+	type SerializeTupleStruct = ArraySerializer<'b, W>;
+	type SerializeTupleVariant = Impossible<(), Error>;
+	type SerializeMap = SectionSerializer<'b, W>;
+	type SerializeStruct = SectionSerializer<'b, W>;
+	type SerializeStructVariant = Impossible<(), Error>;
+
+	serialize_num!{serialize_i8, i8, constants::SERIALIZE_TYPE_INT8}
+	serialize_num!{serialize_i16, i16, constants::SERIALIZE_TYPE_INT16}
+	serialize_num!{serialize_i32, i32, constants::SERIALIZE_TYPE_INT32}
+	serialize_num!{serialize_i64, i64, constants::SERIALIZE_TYPE_INT64}
+	serialize_num!{serialize_u8, u8, constants::SERIALIZE_TYPE_UINT8}
+	serialize_num!{serialize_u16, u16, constants::SERIALIZE_TYPE_UINT16}
+	serialize_num!{serialize_u32, u32, constants::SERIALIZE_TYPE_UINT32}
+	serialize_num!{serialize_u64, u64, constants::SERIALIZE_TYPE_UINT64}
+	#[cfg(not(feature = "no-float"))]
+	serialize_num!{serialize_f64, f64, constants::SERIALIZE_TYPE_DOUBLE}
+
+	#[cfg(feature = "no-float")]
+	fn serialize_f64(self, _v: f64) -> Result<()> {
+		Err(Error::new(ErrorKind::FloatDisabled, String::from("DOUBLE entries are disabled by the no-float feature")))
+	}
+
+	fn serialize_i128(self, v: i128) -> Result<()> {
+		self.write_custom(CustomValue::I128(v))
+	}
+
+	fn serialize_u128(self, v: u128) -> Result<()> {
+		self.write_custom(CustomValue::U128(v))
+	}
+
+	fn serialize_bool(self, v: bool) -> Result<()> {
+		self.serialize_start_and_type_code(constants::SERIALIZE_TYPE_BOOL)?;
+		self.write_raw(TraceLabel::Payload, &[v as u8])
+	}
+
+	fn serialize_f32(self, v: f32) -> Result<()> {
+		self.serialize_f64(v as f64)
+	}
+
+	fn serialize_char(self, v: char) -> Result<()> {
+		self.serialize_u32(v as u32)
+	}
+
+	fn serialize_str(self, v: &str) -> Result<()> {
+		self.serialize_bytes(v.as_bytes())
+	}
+
+	// EPEE "Blob"
+	fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+		self.tracker.get().check_string_len(v.len() as u64)?;
+		if self.storage_format != EpeeStorageFormat::Array {
+			self.tracker.get().add_strings(1)?;
+		}
+
+		self.serialize_start_and_type_code(constants::SERIALIZE_TYPE_STRING)?;
+
+		let varlen = VarInt::try_from(v.len() as u64)?;
+		self.write_varint(varlen)?;
+
+		self.write_raw(TraceLabel::Payload, v)
+	}
+
+	fn serialize_none(self) -> Result<()> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize none")))
+	}
+
+	// Drop the optional wrapper: serialize Some(v) as v
+	fn serialize_some<T>(self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<()> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize anonymous unit")))
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize unit structs")))
+	}
+
+	fn serialize_unit_variant(
+			self,
+			_name: &'static str,
+			_variant_index: u32,
+			_variant: &'static str
+	) -> Result<()> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize unit variants")))
+	}
+
+	fn serialize_newtype_struct<T>(
+		self,
+		name: &'static str,
+		value: &T,
+	) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		if name == RAW_FRAGMENT_TOKEN {
+			value.serialize(FragmentSerializer { ser: self })
+		} else {
+			value.serialize(self)
+		}
+	}
+
+	fn serialize_newtype_variant<T>(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_value: &T,
+	) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize unit variants")))
+	}
+
+	///////////////////////////////////////////////////////////////////////////
+	// Delegate Compound Types                                               //
+	///////////////////////////////////////////////////////////////////////////
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+		self.start_array(len)
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+		self.start_array(Some(len))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_name: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeTupleStruct> {
+		self.start_array(Some(len))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeTupleVariant> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize tuple variants")))
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+		self.start_section(len)
+	}
+
+	fn serialize_struct(
+		self,
+		_name: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeStruct> {
+		self.start_section(Some(len))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeStructVariant> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize struct variants")))
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////
+// Dynamic sections                                                      //
+///////////////////////////////////////////////////////////////////////////
+
+// Concatenated little endian payloads of a scalar array
+macro_rules! le_payload {
+	($values:expr) => ($values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>())
+}
+
+impl<W> Serializer<'_, W>
+where
+	W: EpeeWrite
+{
+	// Writes a Section by walking its entries, instead of going through the serde
+	// model like Section's Serialize impl. Scalar arrays are written in one go,
+	// and empty arrays keep their element type. See section::to_bytes.
+	pub fn serialize_section(&mut self, section: &Section) -> Result<()> {
+		let present = section.values().filter(|entry| !entry.is_nil()).count();
+		let mut fields = self.start_section(Some(present))?;
+		for (key, entry) in section.iter().filter(|(_, entry)| !entry.is_nil()) {
+			fields.ser.write_key_string(key.as_bytes())?;
+			fields.ser.serialize_section_entry(entry).map_err(|e| e.with_path(&fields.ser.field_path()))?;
+		}
+		fields.ser.tracker.get().leave_section();
+		Ok(())
+	}
+
+	fn serialize_section_entry(&mut self, entry: &SectionEntry) -> Result<()> {
+		use ser::Serializer as _;
+
+		match entry {
+			SectionEntry::Int64(v) => self.serialize_i64(*v),
+			SectionEntry::Int32(v) => self.serialize_i32(*v),
+			SectionEntry::Int16(v) => self.serialize_i16(*v),
+			SectionEntry::Int8(v) => self.serialize_i8(*v),
+			SectionEntry::UInt64(v) => self.serialize_u64(*v),
+			SectionEntry::UInt32(v) => self.serialize_u32(*v),
+			SectionEntry::UInt16(v) => self.serialize_u16(*v),
+			SectionEntry::UInt8(v) => self.serialize_u8(*v),
+			SectionEntry::Double(v) => self.serialize_f64(*v),
+			SectionEntry::Blob(v) => self.serialize_bytes(v),
+			SectionEntry::Bool(v) => self.serialize_bool(*v),
+			SectionEntry::Object(section) => self.serialize_section(section),
+			SectionEntry::Array(array) => self.serialize_section_array(array),
+			SectionEntry::Nil => Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize Nil entries")))
+		}
+	}
+
+	fn serialize_section_array(&mut self, array: &SectionArray) -> Result<()> {
+		if matches!(array, SectionArray::Double(_)) && cfg!(feature = "no-float") {
+			return Err(Error::new(ErrorKind::FloatDisabled, String::from("DOUBLE entries are disabled by the no-float feature")));
+		}
+
+		// Writing the header charges every element against the limits, before
+		// any of them is encoded, as for arrays serialized element by element
+		let mut elements = self.start_array(Some(array.len()))?;
+		elements.ser.start_array_element(array.element_type().to_type_code())?;
+
+		let payload = match array {
+			SectionArray::Int64(v) => le_payload!(v),
+			SectionArray::Int32(v) => le_payload!(v),
+			SectionArray::Int16(v) => le_payload!(v),
+			SectionArray::Int8(v) => le_payload!(v),
+			SectionArray::UInt64(v) => le_payload!(v),
+			SectionArray::UInt32(v) => le_payload!(v),
+			SectionArray::UInt16(v) => le_payload!(v),
+			SectionArray::UInt8(v) => v.clone(),
+			SectionArray::Double(v) => le_payload!(v),
+			SectionArray::Bool(v) => v.iter().map(|&b| b as u8).collect(),
+			SectionArray::Blob(blobs) => {
+				let mut payload = Vec::new();
+				for blob in blobs {
+					elements.ser.tracker.get().check_string_len(blob.len() as u64)?;
+					VarInt::try_from(blob.len() as u64)?.to_writer(&mut payload)?;
+					payload.extend_from_slice(blob);
+				}
+				payload
+			},
+			SectionArray::Object(sections) => {
+				for section in sections {
+					elements.ser.serialize_section(section).map_err(|e| e.with_path(&elements.ser.field_path()))?;
+					elements.ser.index += 1;
+				}
+				return Ok(());
+			}
+		};
+		elements.ser.write_raw(TraceLabel::Payload, &payload)
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////
+// Sections                                                              //
+///////////////////////////////////////////////////////////////////////////
+
+// Returned by serialize_map and serialize_struct once the section header is
+// written. Keys go through a KeySerializer, so only strings can become keys.
+#[derive(Debug)]
+pub struct SectionSerializer<'a, W: EpeeWrite> {
+	ser: Serializer<'a, W>,
+	fields: u64 // fields started so far, must end up at the count in the header
+}
+
+impl<W: EpeeWrite> SectionSerializer<'_, W> {
+	fn claim_field(&mut self) -> Result<()> {
+		if self.fields >= self.ser.len {
+			let msg = format!("section of {} fields got another one", self.ser.len);
+			return Err(Error::new(ErrorKind::SizeHintMismatch { expected: self.ser.len, found: self.fields + 1 }, msg).with_path(&self.ser.path));
+		}
+		self.fields += 1;
+		Ok(())
+	}
+
+	// The field count was written up front, so fewer fields would leave the
+	// document unreadable
+	fn finish(mut self) -> Result<()> {
+		self.ser.tracker.get().leave_section();
+		if self.fields != self.ser.len {
+			let msg = format!("section of {} fields ended after {}", self.ser.len, self.fields);
+			return Err(Error::new(ErrorKind::SizeHintMismatch { expected: self.ser.len, found: self.fields }, msg).with_path(&self.ser.path));
+		}
+		Ok(())
+	}
+}
+
+impl<'a, W> ser::SerializeMap for SectionSerializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		self.claim_field()?;
+		key.serialize(KeySerializer { ser: &mut self.ser })
+	}
+
+	fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		value.serialize(&mut self.ser).map_err(|e| e.with_path(&self.ser.field_path()))
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+impl<'a, W> ser::SerializeStruct for SectionSerializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		self.claim_field()?;
+		self.ser.write_key_string(key.as_bytes())?;
+		value.serialize(&mut self.ser).map_err(|e| e.with_path(&self.ser.field_path()))
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////
+// Arrays                                                                //
+///////////////////////////////////////////////////////////////////////////
+
+// Returned by serialize_seq and friends. Elements are written by a Serializer
+// in array format, which can't start another array, so NestedArrays is caught
+// before anything is written.
+#[derive(Debug)]
+pub struct ArraySerializer<'a, W: EpeeWrite> {
+	ser: Serializer<'a, W>,
+	buffer: Option<Vec<u8>> // encoded elements, if the length wasn't known up front
+}
+
+impl<W: EpeeWrite> ArraySerializer<'_, W> {
+	fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+		if self.buffer.is_none() && self.ser.index >= self.ser.len {
+			let msg = format!("array of {} elements got another one", self.ser.len);
+			return Err(Error::new(ErrorKind::SizeHintMismatch { expected: self.ser.len, found: self.ser.index + 1 }, msg).with_path(&self.ser.path));
+		}
+
+		let res = match self.buffer.as_mut() {
+			Some(buffer) => {
+				let element_type = if self.ser.started { Some(self.ser.element_type) } else { None };
+				let mut element = self.ser.new_buffered_element(buffer, element_type);
+				let res = value.serialize(&mut element);
+				let element_type = element.element_type;
+				if res.is_ok() {
+					self.ser.element_type = element_type;
+					self.ser.started = true;
+				}
+				res
+			},
+			None => value.serialize(&mut self.ser)
+		};
+		let res = res.map_err(|e| e.with_path(&self.ser.field_path()));
+		self.ser.index += 1;
+		res
+	}
+
+	// Nothing was written yet for an empty array, and there's no element to take
+	// the type from. Any type code will do since no elements follow. Buffered
+	// arrays get their header now that the length is known, the others must have
+	// as many elements as their header says.
+	fn finish(mut self) -> Result<()> {
+		if let Some(buffer) = self.buffer.take() {
+			let element_type = if self.ser.started { self.ser.element_type } else { constants::EMPTY_ARRAY_TYPE_CODE };
+			self.ser.len = self.ser.index;
+			self.ser.started = false;
+			self.ser.start_array_element(element_type)?;
+			return self.ser.write_raw(TraceLabel::Payload, &buffer);
+		}
+
+		if self.ser.index != self.ser.len {
+			let msg = format!("array of {} elements ended after {}", self.ser.len, self.ser.index);
+			return Err(Error::new(ErrorKind::SizeHintMismatch { expected: self.ser.len, found: self.ser.index }, msg).with_path(&self.ser.path));
+		}
+		if !self.ser.started {
+			self.ser.start_array_element(constants::EMPTY_ARRAY_TYPE_CODE)?;
+		}
+		Ok(())
+	}
+}
+
+impl<'a, W> ser::SerializeSeq for ArraySerializer<'a, W>
+where 
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+// Same as SerializeSeq
+impl<'a, W> ser::SerializeTuple for ArraySerializer<'a, W>
+where
+	W: EpeeWrite	
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+// Same as SerializeSeq
+impl<'a, W> ser::SerializeTupleStruct for ArraySerializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		self.element(value)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////
+// String-only serializers                                               //
+///////////////////////////////////////////////////////////////////////////
+
+// Everything but strings, bytes and the wrappers around them fails with $err
+macro_rules! only_strings {
+	($err:expr) => {
+		type Ok = ();
+		type Error = Error;
+
+		type SerializeSeq = Impossible<(), Error>;
+		type SerializeTuple = Impossible<(), Error>;
+		type SerializeTupleStruct = Impossible<(), Error>;
+		type SerializeTupleVariant = Impossible<(), Error>;
+		type SerializeMap = Impossible<(), Error>;
+		type SerializeStruct = Impossible<(), Error>;
+		type SerializeStructVariant = Impossible<(), Error>;
+
+		fn serialize_bool(self, _v: bool) -> Result<()> { $err }
+		fn serialize_i8(self, _v: i8) -> Result<()> { $err }
+		fn serialize_i16(self, _v: i16) -> Result<()> { $err }
+		fn serialize_i32(self, _v: i32) -> Result<()> { $err }
+		fn serialize_i64(self, _v: i64) -> Result<()> { $err }
+		fn serialize_u8(self, _v: u8) -> Result<()> { $err }
+		fn serialize_u16(self, _v: u16) -> Result<()> { $err }
+		fn serialize_u32(self, _v: u32) -> Result<()> { $err }
+		fn serialize_u64(self, _v: u64) -> Result<()> { $err }
+		fn serialize_f32(self, _v: f32) -> Result<()> { $err }
+		fn serialize_f64(self, _v: f64) -> Result<()> { $err }
+		fn serialize_char(self, _v: char) -> Result<()> { $err }
+		fn serialize_none(self) -> Result<()> { $err }
+		fn serialize_unit(self) -> Result<()> { $err }
+		fn serialize_unit_struct(self, _name: &'static str) -> Result<()> { $err }
+
+		fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str) -> Result<()> {
+			$err
+		}
+
+		fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<()>
+		where
+			T: ?Sized + Serialize,
+		{
+			$err
+		}
+
+		fn serialize_seq(self, _len: Option<usize>) -> Result<Impossible<(), Error>> { $err }
+		fn serialize_tuple(self, _len: usize) -> Result<Impossible<(), Error>> { $err }
+		fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Impossible<(), Error>> { $err }
+		fn serialize_map(self, _len: Option<usize>) -> Result<Impossible<(), Error>> { $err }
+		fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Impossible<(), Error>> { $err }
+
+		fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Impossible<(), Error>> {
+			$err
+		}
+
+		fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Impossible<(), Error>> {
+			$err
+		}
+	}
+}
+
+// Writes map keys, which must be strings
+struct KeySerializer<'s, 'a, W: EpeeWrite> {
+	ser: &'s mut Serializer<'a, W>
+}
+
+impl<W: EpeeWrite> ser::Serializer for KeySerializer<'_, '_, W> {
+	only_strings!{Err(Error::new_no_msg(ErrorKind::KeyBadType))}
+
+	fn serialize_str(self, v: &str) -> Result<()> {
+		self.ser.write_key_string(v.as_bytes())
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+		self.ser.write_key_string(v)
+	}
+
+	fn serialize_some<T>(self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+}
+
+// Writes the bytes of a RawFragment, see Serializer::write_fragment
+struct FragmentSerializer<'s, 'a, W: EpeeWrite> {
+	ser: &'s mut Serializer<'a, W>
+}
+
+impl<W: EpeeWrite> ser::Serializer for FragmentSerializer<'_, '_, W> {
+	only_strings!{Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("raw fragments must serialize as bytes")))}
+
+	fn serialize_str(self, v: &str) -> Result<()> {
+		self.ser.write_fragment(v.as_bytes())
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+		self.ser.write_fragment(v)
+	}
+
+	fn serialize_some<T>(self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////
+// Packed values                                                         //
+///////////////////////////////////////////////////////////////////////////
+
+// Serializer for len values of fixed size (numbers and bools, or tuples and
+// sequences of them) which are written back to back as their raw payloads,
+// with no type codes or lengths. This is the layout of epee's POD blobs, see
+// packed::to_packed_bytes. Writing more than len values fails with
+// TupleTooLong, strings and sections with SerdeModelUnsupported.
+#[derive(Debug)]
+pub struct PackedSerializer<'a, W: EpeeWrite> {
+	writer: &'a mut W,
+	len: u64,
+	written: u64
+}
+
+impl<'a, W: EpeeWrite> PackedSerializer<'a, W> {
+	pub fn new(writer: &'a mut W, len: u64) -> Self {
+		Self { writer, len, written: 0 }
+	}
+
+	fn claim_slot(&mut self) -> Result<()> {
+		if self.written >= self.len {
+			return Err(Error::new(ErrorKind::TupleTooLong, format!("packed tuple only holds {} values", self.len)));
+		}
+		self.written += 1;
+		Ok(())
+	}
+
+	fn write_value(&mut self, bytes: &[u8]) -> Result<()> {
+		self.claim_slot()?;
+		self.writer.write_bytes(bytes)
+	}
+
+	fn start_tuple(&mut self, len: Option<usize>) -> Result<PackedSerializer<'_, W>> {
+		match len {
+			Some(l) => {
+				self.claim_slot()?;
+				Ok(PackedSerializer::new(&mut *self.writer, l as u64))
+			},
+			None => Err(Error::new(ErrorKind::NoLength, String::from("EPEE serializer needs to know seq length ahead of time")))
+		}
+	}
+
+	// Packed tuples must be filled completely since nothing records their length
+	fn finish(self) -> Result<()> {
+		if self.written != self.len {
+			let msg = format!("packed tuple of {} values ended after {}", self.len, self.written);
+			return Err(Error::new(ErrorKind::SizeHintMismatch { expected: self.len, found: self.written }, msg));
+		}
+		Ok(())
+	}
+}
+
+macro_rules! serialize_packed_num {
+	($fname:ident, $numtype:ty) => (
+		fn $fname(self, v: $numtype) -> Result<()> {
+			self.write_value(&v.to_le_bytes())
+		}
+	)
+}
+
+fn unsized_packed_value() -> Result<()> {
+	Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("packed values must have a fixed size")))
+}
+
+impl<'b, 'a: 'b, W> ser::Serializer for &'b mut PackedSerializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	type SerializeSeq = PackedSerializer<'b, W>;
+	type SerializeTuple = PackedSerializer<'b, W>;
+	type SerializeTupleStruct = PackedSerializer<'b, W>;
+	type SerializeTupleVariant = Impossible<(), Error>;
+	type SerializeMap = Impossible<(), Error>;
+	type SerializeStruct = Impossible<(), Error>;
+	type SerializeStructVariant = Impossible<(), Error>;
+
+	serialize_packed_num!{serialize_i8, i8}
+	serialize_packed_num!{serialize_i16, i16}
+	serialize_packed_num!{serialize_i32, i32}
+	serialize_packed_num!{serialize_i64, i64}
+	serialize_packed_num!{serialize_u8, u8}
+	serialize_packed_num!{serialize_u16, u16}
+	serialize_packed_num!{serialize_u32, u32}
+	serialize_packed_num!{serialize_u64, u64}
+	#[cfg(not(feature = "no-float"))]
+	serialize_packed_num!{serialize_f64, f64}
+
+	#[cfg(feature = "no-float")]
+	fn serialize_f64(self, _v: f64) -> Result<()> {
+		Err(Error::new(ErrorKind::FloatDisabled, String::from("DOUBLE entries are disabled by the no-float feature")))
+	}
+
+	fn serialize_bool(self, v: bool) -> Result<()> {
+		self.write_value(&[v as u8])
+	}
+
+	fn serialize_f32(self, v: f32) -> Result<()> {
+		self.serialize_f64(v as f64)
+	}
+
+	fn serialize_char(self, v: char) -> Result<()> {
+		self.serialize_u32(v as u32)
+	}
+
+	fn serialize_str(self, _v: &str) -> Result<()> {
+		unsized_packed_value()
+	}
+
+	fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+		unsized_packed_value()
+	}
+
+	fn serialize_none(self) -> Result<()> {
+		unsized_packed_value()
+	}
+
+	fn serialize_some<T>(self, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<()> {
+		unsized_packed_value()
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+		unsized_packed_value()
+	}
+
+	fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str) -> Result<()> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize unit variants")))
+	}
+
+	fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		if name == VARINT_TOKEN {
+			value.serialize(PackedVarIntSerializer { ser: self })
+		} else {
+			value.serialize(self)
+		}
+	}
+
+	fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize newtype variants")))
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+		self.start_tuple(len)
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+		self.start_tuple(Some(len))
+	}
+
+	fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
+		self.start_tuple(Some(len))
+	}
+
+	fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize tuple variants")))
+	}
+
+	fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("packed values must have a fixed size")))
+	}
+
+	fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("packed values must have a fixed size")))
+	}
+
+	fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
+		Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("can't serialize struct variants")))
+	}
+}
+
+impl<'a, W> ser::SerializeSeq for PackedSerializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+impl<'a, W> ser::SerializeTuple for PackedSerializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+impl<'a, W> ser::SerializeTupleStruct for PackedSerializer<'a, W>
+where
+	W: EpeeWrite
+{
+	type Ok = ();
+	type Error = Error;
+
+	fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+	where
+		T: ?Sized + ser::Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn end(self) -> Result<()> {
+		self.finish()
+	}
+}
+
+// Writes the bytes of a VarInt as one packed value, see Serialize for VarInt
+struct PackedVarIntSerializer<'s, 'a, W: EpeeWrite> {
+	ser: &'s mut PackedSerializer<'a, W>
+}
+
+impl<W: EpeeWrite> ser::Serializer for PackedVarIntSerializer<'_, '_, W> {
+	only_strings!{Err(Error::new(ErrorKind::SerdeModelUnsupported, String::from("packed varints must serialize as bytes")))}
+
+	fn serialize_str(self, _v: &str) -> Result<()> {
+		unsized_packed_value()
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+		self.ser.write_value(v)
+	}
+
+	fn serialize_some<T>(self, _value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		unsized_packed_value()
+	}
+
+	fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+	where
+		T: ?Sized + Serialize,
+	{
+		value.serialize(self)
+	}
+}
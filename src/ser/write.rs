@@ -0,0 +1,83 @@
+use std::io::{BufWriter, Write};
+
+use crate::error::Result;
+
+// Output of a Serializer, the write side of de::read::EpeeRead. Every byte the
+// serializer produces goes through write_bytes, so wrappers which count,
+// trace or limit output only need to implement this. Provided outputs:
+//
+//     Vec<u8>        - extends the vector, nothing to fail but allocation
+//     BytesMut       - the same for bytes::BytesMut (feature "bytes")
+//     Sink           - discards everything, for measuring documents
+//     IoWrite        - any io::Write, buffered
+pub trait EpeeWrite {
+	fn write_bytes(&mut self, bytes: &[u8]) -> Result<()>;
+}
+
+impl EpeeWrite for Vec<u8> {
+	#[inline]
+	fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+		self.extend_from_slice(bytes);
+		Ok(())
+	}
+}
+
+#[cfg(feature = "bytes")]
+impl EpeeWrite for bytes::BytesMut {
+	#[inline]
+	fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+		self.extend_from_slice(bytes);
+		Ok(())
+	}
+}
+
+impl EpeeWrite for std::io::Sink {
+	#[inline]
+	fn write_bytes(&mut self, _bytes: &[u8]) -> Result<()> {
+		Ok(())
+	}
+}
+
+// The serializer writes a few bytes at a time, so writes to io::Writes are
+// gathered in a buffer first. Call into_inner or flush when done, whatever is
+// still buffered when an IoWrite is dropped is flushed with errors ignored.
+pub struct IoWrite<W: Write> {
+	inner: BufWriter<W>
+}
+
+impl<W: Write> IoWrite<W> {
+	pub fn new(inner: W) -> Self {
+		Self { inner: BufWriter::new(inner) }
+	}
+
+	pub fn with_capacity(capacity: usize, inner: W) -> Self {
+		Self { inner: BufWriter::with_capacity(capacity, inner) }
+	}
+
+	pub fn flush(&mut self) -> Result<()> {
+		Ok(self.inner.flush()?)
+	}
+
+	pub fn get_ref(&self) -> &W {
+		self.inner.get_ref()
+	}
+
+	// Flushes the buffer before handing the writer back
+	pub fn into_inner(self) -> Result<W> {
+		self.inner.into_inner().map_err(|err| err.into_error().into())
+	}
+}
+
+impl<W: Write> EpeeWrite for IoWrite<W> {
+	#[inline]
+	fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+		Ok(self.inner.write_all(bytes)?)
+	}
+}
+
+impl<W: EpeeWrite + ?Sized> EpeeWrite for &mut W {
+	#[inline]
+	fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+		(**self).write_bytes(bytes)
+	}
+}
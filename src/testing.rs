@@ -0,0 +1,245 @@
+use std::fmt::Debug;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_bytes::ByteBuf;
+
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::section::{Section, SectionArray, SectionEntry};
+
+///////////////////////////////////////////////////////////////////////////////
+// Assertions for wire compatibility tests                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+// Encodes value, decodes it again and checks that nothing changed. Returns the
+// encoding so that callers can make further assertions on it.
+#[track_caller]
+pub fn assert_round_trip<T>(value: &T) -> Vec<u8>
+where
+	T: Serialize + DeserializeOwned + PartialEq + Debug
+{
+	let bytes = match crate::to_bytes(value) {
+		Ok(bytes) => bytes,
+		Err(err) => panic!("failed to serialize {:?}: {}", value, err)
+	};
+	match crate::from_bytes::<T>(&mut bytes.as_slice()) {
+		Ok(decoded) => assert_eq!(*value, decoded, "value changed in round trip through {}", encode_hex(&bytes)),
+		Err(err) => panic!("failed to deserialize {}: {}", encode_hex(&bytes), err)
+	}
+	bytes
+}
+
+// Decodes a hex document, e.g. captured from monerod, and compares the result
+#[track_caller]
+pub fn assert_decodes_to<T>(hex: &str, expected: &T)
+where
+	T: DeserializeOwned + PartialEq + Debug
+{
+	let bytes = match decode_hex(hex) {
+		Ok(bytes) => bytes,
+		Err(err) => panic!("bad hex fixture: {}", err)
+	};
+	match crate::from_bytes::<T>(&mut bytes.as_slice()) {
+		Ok(decoded) => assert_eq!(*expected, decoded),
+		Err(err) => panic!("failed to deserialize {}: {}", hex, err)
+	}
+}
+
+// Like assert_decodes_to, but also checks that expected encodes to the exact
+// same bytes. Only meaningful for types with a deterministic field order.
+#[track_caller]
+pub fn assert_wire_compatible<T>(hex: &str, expected: &T)
+where
+	T: Serialize + DeserializeOwned + PartialEq + Debug
+{
+	assert_decodes_to(hex, expected);
+	match crate::to_bytes(expected) {
+		Ok(bytes) => assert_eq!(hex.to_ascii_lowercase(), encode_hex(&bytes), "encoding of {:?} differs from fixture", expected),
+		Err(err) => panic!("failed to serialize {:?}: {}", expected, err)
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Hex fixtures                                                              //
+///////////////////////////////////////////////////////////////////////////////
+
+// Decodes hex text, ignoring whitespace and everything after a '#' on a line,
+// so fixtures can be wrapped and annotated
+pub fn decode_hex(text: &str) -> Result<Vec<u8>> {
+	let digits: Vec<u8> = text.lines()
+		.map(|line| line.split('#').next().unwrap_or(""))
+		.flat_map(|line| line.bytes())
+		.filter(|b| !b.is_ascii_whitespace())
+		.collect();
+
+	if !digits.len().is_multiple_of(2) {
+		return epee_err!(Custom, "odd number of hex digits");
+	}
+
+	let mut bytes = Vec::with_capacity(digits.len() / 2);
+	for pair in digits.chunks(2) {
+		match (hex_value(pair[0]), hex_value(pair[1])) {
+			(Some(high), Some(low)) => bytes.push(high << 4 | low),
+			_ => return epee_err!(Custom, "invalid hex digit in {:?}", String::from_utf8_lossy(pair))
+		}
+	}
+	Ok(bytes)
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+	const DIGITS: &[u8; 16] = b"0123456789abcdef";
+	let mut hex = String::with_capacity(bytes.len() * 2);
+	for b in bytes {
+		hex.push(DIGITS[(b >> 4) as usize] as char);
+		hex.push(DIGITS[(b & 0xf) as usize] as char);
+	}
+	hex
+}
+
+// Reads and decodes a hex fixture file, see decode_hex for the format
+pub fn load_hex_fixture<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+	let text = std::fs::read_to_string(path)?;
+	decode_hex(&text)
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+	match digit {
+		b'0'..=b'9' => Some(digit - b'0'),
+		b'a'..=b'f' => Some(digit - b'a' + 10),
+		b'A'..=b'F' => Some(digit - b'A' + 10),
+		_ => None
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Differential testing                                                      //
+///////////////////////////////////////////////////////////////////////////////
+
+// Deterministic generator of random documents, so that a failing seed can be
+// replayed. Covers the corners unit tests tend to miss: every integer width at
+// its extremes, empty strings and arrays, arrays of sections and nesting.
+pub struct DocumentGen {
+	state: u64
+}
+
+impl DocumentGen {
+	pub fn new(seed: u64) -> Self {
+		Self { state: seed.wrapping_mul(0x9e3779b97f4a7c15) | 1 }
+	}
+
+	// xorshift64*
+	fn next_u64(&mut self) -> u64 {
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+		self.state.wrapping_mul(0x2545f4914f6cdd1d)
+	}
+
+	fn below(&mut self, n: u64) -> u64 {
+		self.next_u64() % n
+	}
+
+	// Mostly extremes and small values, which is where encodings go wrong
+	fn number(&mut self) -> u64 {
+		match self.below(4) {
+			0 => 0,
+			1 => u64::MAX,
+			2 => self.below(300),
+			_ => self.next_u64()
+		}
+	}
+
+	fn blob(&mut self) -> ByteBuf {
+		let len = match self.below(4) {
+			0 => 0,
+			1 => 300, // past the one byte varint
+			_ => self.below(20)
+		};
+		ByteBuf::from((0..len).map(|_| self.next_u64() as u8).collect::<Vec<u8>>())
+	}
+
+	pub fn section(&mut self, max_depth: usize) -> Section {
+		let num_fields = self.below(8);
+		(0..num_fields).map(|i| (format!("k{}", i), self.entry(max_depth))).collect()
+	}
+
+	fn entry(&mut self, max_depth: usize) -> SectionEntry {
+		let kinds = if max_depth > 1 { 13 } else { 11 };
+		match self.below(kinds) {
+			0 => SectionEntry::Int64(self.number() as i64),
+			1 => SectionEntry::Int32(self.number() as i32),
+			2 => SectionEntry::Int16(self.number() as i16),
+			3 => SectionEntry::Int8(self.number() as i8),
+			4 => SectionEntry::UInt64(self.number()),
+			5 => SectionEntry::UInt32(self.number() as u32),
+			6 => SectionEntry::UInt16(self.number() as u16),
+			7 => SectionEntry::UInt8(self.number() as u8),
+			8 => SectionEntry::Blob(self.blob()),
+			9 => SectionEntry::Bool(self.below(2) == 1),
+			10 => SectionEntry::Array(self.array()),
+			11 => SectionEntry::Object(self.section(max_depth - 1)),
+			_ => {
+				let len = 1 + self.below(3);
+				SectionEntry::Array(SectionArray::Object((0..len).map(|_| self.section(max_depth - 1)).collect()))
+			}
+		}
+	}
+
+	// Empty arrays are UINT8 arrays, since that's what they decode as
+	fn array(&mut self) -> SectionArray {
+		let len = self.below(5);
+		match if len == 0 { 7 } else { self.below(10) } {
+			0 => SectionArray::Int64((0..len).map(|_| self.number() as i64).collect()),
+			1 => SectionArray::Int32((0..len).map(|_| self.number() as i32).collect()),
+			2 => SectionArray::Int16((0..len).map(|_| self.number() as i16).collect()),
+			3 => SectionArray::Int8((0..len).map(|_| self.number() as i8).collect()),
+			4 => SectionArray::UInt64((0..len).map(|_| self.number()).collect()),
+			5 => SectionArray::UInt32((0..len).map(|_| self.number() as u32).collect()),
+			6 => SectionArray::UInt16((0..len).map(|_| self.number() as u16).collect()),
+			7 => SectionArray::UInt8((0..len).map(|_| self.number() as u8).collect()),
+			8 => SectionArray::Blob((0..len).map(|_| self.blob()).collect()),
+			_ => SectionArray::Bool((0..len).map(|_| self.below(2) == 1).collect())
+		}
+	}
+}
+
+// Runs an external reference implementation with document on stdin and returns
+// its stdout. The reference is expected to load the document and store it again,
+// e.g. a small program around epee's portable_storage load_from_binary and
+// store_to_binary.
+pub fn run_reference<P: AsRef<Path>>(reference: P, document: &[u8]) -> Result<Vec<u8>> {
+	let mut child = Command::new(reference.as_ref())
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()?;
+
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(document)?;
+	}
+	let output = child.wait_with_output()?;
+	if !output.status.success() {
+		return epee_err!(Custom, "reference exited with {}", output.status);
+	}
+	Ok(output.stdout)
+}
+
+// Checks that the reference reads our encoding of section as the same document,
+// and that we read the reference's encoding back as the same document. Field
+// order isn't compared, since neither side keeps it.
+#[track_caller]
+pub fn assert_reference_agrees<P: AsRef<Path>>(reference: P, section: &Section) {
+	let ours = match crate::to_bytes(section) {
+		Ok(bytes) => bytes,
+		Err(err) => panic!("failed to serialize {:?}: {}", section, err)
+	};
+	let theirs = match run_reference(reference, &ours) {
+		Ok(bytes) => bytes,
+		Err(err) => panic!("reference failed on {}: {}", encode_hex(&ours), err)
+	};
+
+	let (decoded, errors) = crate::from_bytes_lossy(&theirs);
+	assert!(errors.is_empty(), "failed to read reference encoding {}: {:?}", encode_hex(&theirs), errors);
+	assert_eq!(*section, decoded, "reference changed {} into {}", encode_hex(&ours), encode_hex(&theirs));
+}
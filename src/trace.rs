@@ -0,0 +1,139 @@
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::constants;
+use crate::de::Deserializer;
+use crate::error::Result;
+use crate::ser::Serializer;
+use crate::types::EpeeEntryType;
+use crate::varint::VarInt;
+
+///////////////////////////////////////////////////////////////////////////////
+// Trace records                                                             //
+///////////////////////////////////////////////////////////////////////////////
+
+// What a run of bytes in the encoding is
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceLabel {
+	Signature,
+	TypeCode,
+	Key, // length byte and key
+	VarInt, // section field count, array length or string length
+	Payload,
+	Fragment, // pre-encoded RawFragment payload
+	Skipped // whole value which was skipped while reading, e.g. an unknown field
+}
+
+impl TraceLabel {
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Signature => "signature",
+			Self::TypeCode => "type code",
+			Self::Key => "key",
+			Self::VarInt => "varint",
+			Self::Payload => "payload",
+			Self::Fragment => "fragment",
+			Self::Skipped => "skipped"
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+	pub offset: usize,
+	pub label: TraceLabel,
+	pub path: String, // key path of the value the bytes belong to
+	pub bytes: Vec<u8>,
+	pub meaning: String // decoded value, e.g. the type name of a type code
+}
+
+// Annotated hex dump, one record per line, e.g.
+//
+//     0000000c  06        type code  my_port = UINT32
+//     0000000d  a0460000  payload    my_port = 18080
+//
+// Long records are wrapped every BYTES_PER_LINE bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace(pub Vec<TraceRecord>);
+
+const BYTES_PER_LINE: usize = 16;
+
+impl fmt::Display for Trace {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		for record in &self.0 {
+			let mut chunks = record.bytes.chunks(BYTES_PER_LINE);
+			let first = chunks.next().unwrap_or(&[]);
+			write!(formatter, "{:08x}  {:<32}  {:<9}  {}", record.offset, hex_string(first), record.label.name(), record.path)?;
+			if !record.meaning.is_empty() {
+				write!(formatter, " = {}", record.meaning)?;
+			}
+			writeln!(formatter)?;
+			for (i, chunk) in chunks.enumerate() {
+				writeln!(formatter, "{:08x}  {}", record.offset + (i + 1) * BYTES_PER_LINE, hex_string(chunk))?;
+			}
+		}
+		Ok(())
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// to_bytes, but also returns a trace of how every byte came to be, for lining
+// up an encoding against the C++ encoder's field by field
+pub fn to_bytes_traced<T: Serialize>(value: &T) -> Result<(Vec<u8>, Trace)> {
+	let mut bytes = Vec::new();
+	let mut serializer = Serializer::new(&mut bytes).with_trace();
+	value.serialize(&mut serializer)?;
+	let records = serializer.take_trace().unwrap_or_default();
+	Ok((bytes, Trace(records)))
+}
+
+// Reads value from bytes, returning the trace along with the result. The trace
+// covers everything up to the point of failure if decoding fails.
+pub fn from_bytes_traced<T: DeserializeOwned>(bytes: &[u8]) -> (Result<T>, Trace) {
+	let mut deserializer = Deserializer::from_slice(bytes).with_trace();
+	let res = T::deserialize(&mut deserializer);
+	let records = deserializer.take_trace().unwrap_or_default();
+	(res, Trace(records))
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Meanings                                                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+// Meaning of structural bytes, which can be decoded without context. Payloads
+// need to know their type, so they get an empty meaning here.
+pub fn describe(label: TraceLabel, bytes: &[u8]) -> String {
+	match label {
+		TraceLabel::Signature => match <&[u8; constants::PORTABLE_STORAGE_SIGNATURE_SIZE]>::try_from(bytes) {
+			Ok(signature) => format!("version {}", constants::split_signature(signature).2),
+			Err(_) => String::from("truncated signature")
+		},
+		TraceLabel::TypeCode => match bytes.first().map(|b| EpeeEntryType::from_type_code(*b)) {
+			Some(Ok(entry_type)) => entry_type.to_string(),
+			_ => String::from("invalid type code")
+		},
+		TraceLabel::Key => describe_string(bytes.get(1..).unwrap_or(&[])),
+		TraceLabel::VarInt => match VarInt::from_reader(&mut &bytes[..]) {
+			Ok(varint) => u64::from(varint).to_string(),
+			Err(_) => String::from("truncated varint")
+		},
+		TraceLabel::Payload | TraceLabel::Fragment | TraceLabel::Skipped => String::new()
+	}
+}
+
+// Strings are shown quoted if they are printable, otherwise by length
+pub fn describe_string(bytes: &[u8]) -> String {
+	const MAX_SHOWN: usize = 32;
+	match std::str::from_utf8(bytes) {
+		Ok(s) if s.len() <= MAX_SHOWN && s.chars().all(|c| !c.is_control()) => format!("{:?}", s),
+		_ => format!("{} bytes", bytes.len())
+	}
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+
+use crate::constants::{self, TypeCode};
+use crate::error::{Error, ErrorKind, Result, epee_err};
+
+///////////////////////////////////////////////////////////////////////////////
+// EPEE Type definitions                                                     //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EpeeScalarType {
+	Int64,
+	Int32,
+	Int16,
+	Int8,
+	UInt64,
+	UInt32,
+	UInt16,
+	UInt8,
+	Double,
+	Str,
+	Bool,
+	Object
+}
+
+impl EpeeScalarType {
+	// Parses the scalar part of a raw type code byte, ignoring the array flag
+	pub fn from_type_code(type_code: u8) -> Result<Self> {
+		match TypeCode::from_u8(type_code)? {
+			TypeCode::Int64  => Ok(Self::Int64),
+			TypeCode::Int32  => Ok(Self::Int32),
+			TypeCode::Int16  => Ok(Self::Int16),
+			TypeCode::Int8   => Ok(Self::Int8),
+			TypeCode::UInt64 => Ok(Self::UInt64),
+			TypeCode::UInt32 => Ok(Self::UInt32),
+			TypeCode::UInt16 => Ok(Self::UInt16),
+			TypeCode::UInt8  => Ok(Self::UInt8),
+			#[cfg(not(feature = "no-float"))]
+			TypeCode::Double => Ok(Self::Double),
+			#[cfg(feature = "no-float")]
+			TypeCode::Double => epee_err!(FloatDisabled, "DOUBLE entries are disabled by the no-float feature"),
+			TypeCode::String => Ok(Self::Str),
+			TypeCode::Bool   => Ok(Self::Bool),
+			TypeCode::Object => Ok(Self::Object),
+			TypeCode::Unknown => unknown_type_code()
+		}
+	}
+
+	pub fn type_code(self) -> TypeCode {
+		match self {
+			Self::Int64  => TypeCode::Int64,
+			Self::Int32  => TypeCode::Int32,
+			Self::Int16  => TypeCode::Int16,
+			Self::Int8   => TypeCode::Int8,
+			Self::UInt64 => TypeCode::UInt64,
+			Self::UInt32 => TypeCode::UInt32,
+			Self::UInt16 => TypeCode::UInt16,
+			Self::UInt8  => TypeCode::UInt8,
+			Self::Double => TypeCode::Double,
+			Self::Str    => TypeCode::String,
+			Self::Bool   => TypeCode::Bool,
+			Self::Object => TypeCode::Object
+		}
+	}
+
+	pub fn to_type_code(self) -> u8 {
+		self.type_code().as_u8()
+	}
+
+	// Size of a single value on the wire, None if variable-width
+	pub fn fixed_size(self) -> Option<usize> {
+		self.type_code().wire_size_hint()
+	}
+}
+
+impl fmt::Display for EpeeScalarType {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		self.type_code().fmt(formatter)
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EpeeEntryType {
+	pub scalar_type: EpeeScalarType,
+	pub is_array: bool
+}
+
+impl EpeeEntryType {
+	pub fn new(scalar_type: EpeeScalarType, is_array: bool) -> Self {
+		Self {
+			scalar_type,
+			is_array
+		}
+	}
+
+	pub fn from_type_code(type_code: u8) -> Result<Self> {
+		let scalar = EpeeScalarType::from_type_code(type_code)?;
+		let is_array = TypeCode::is_array_flagged(type_code);
+
+		Ok(Self::new(scalar, is_array))
+	}
+
+	pub fn to_type_code(self) -> u8 {
+		let array_mask = if self.is_array { constants::SERIALIZE_FLAG_ARRAY } else { 0 };
+		self.scalar_type.to_type_code() | array_mask
+	}
+}
+
+impl fmt::Display for EpeeEntryType {
+	fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		if self.is_array {
+			formatter.write_fmt(format_args!("ARRAY<{}>", self.scalar_type))
+		} else {
+			self.scalar_type.fmt(formatter)
+		}
+	}
+}
+
+#[cold]
+pub(crate) fn unknown_type_code<T>() -> Result<T> {
+	epee_err!(UnknownTypeCode, "type code 0 (SERIALIZE_TYPE_UNKNOWN) is never written by epee, the document is likely corrupt here")
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Object descriptions                                                       //
+///////////////////////////////////////////////////////////////////////////////
+
+// Structs encoded as sections, with the keys of their entries in field order.
+// Implemented by #[epee_object] with the derive feature.
+pub trait EpeeObject {
+	const KEYS: &'static [&'static str];
+}
@@ -0,0 +1,275 @@
+use std::collections::hash_map;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::error::{Error, ErrorKind, Result, epee_err};
+use crate::fragment::RawFragment;
+use crate::section::{self, Section, SectionEntry};
+
+///////////////////////////////////////////////////////////////////////////////
+// User functions                                                            //
+///////////////////////////////////////////////////////////////////////////////
+
+// Decodes a typed value from an in-memory Section, e.g. one built by hand in a
+// test or one which was decoded dynamically first. Errors carry the key path.
+pub fn from_section<T: de::DeserializeOwned>(section: Section) -> Result<T> {
+	T::deserialize(SectionDeserializer::new(section))
+}
+
+// Wire form of a single entry, as it appears after its key inside a section:
+// the type code and the payload following it. Lets storage layers keep values
+// column-wise and splice them back into documents with RawFragment::new.
+// Empty arrays come back as UINT8 arrays, like they are encoded in documents.
+pub fn to_raw_bytes(entry: &SectionEntry) -> Result<(u8, Vec<u8>)> {
+	let fragment = RawFragment::from_value(entry)?;
+	Ok((fragment.type_code(), fragment.payload().to_vec()))
+}
+
+// Inverse of to_raw_bytes
+pub fn from_raw_bytes(type_code: u8, payload: &[u8]) -> Result<SectionEntry> {
+	section::entry_from_payload(type_code, payload)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Deserializers over the dynamic model                                      //
+///////////////////////////////////////////////////////////////////////////////
+
+// Deserializes typed values out of an in-memory Section, without going
+// through the wire format. Created with section.into_deserializer().
+pub struct SectionDeserializer {
+	section: Section,
+	path: String
+}
+
+pub struct EntryDeserializer {
+	entry: SectionEntry,
+	path: String
+}
+
+impl SectionDeserializer {
+	pub fn new(section: Section) -> Self {
+		Self { section, path: String::new() }
+	}
+}
+
+impl EntryDeserializer {
+	pub fn new(entry: SectionEntry) -> Self {
+		Self { entry, path: String::new() }
+	}
+
+	fn with_path(entry: SectionEntry, path: String) -> Self {
+		Self { entry, path }
+	}
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Section {
+	type Deserializer = SectionDeserializer;
+
+	fn into_deserializer(self) -> SectionDeserializer {
+		SectionDeserializer::new(self)
+	}
+}
+
+impl<'de> IntoDeserializer<'de, Error> for SectionEntry {
+	type Deserializer = EntryDeserializer;
+
+	fn into_deserializer(self) -> EntryDeserializer {
+		EntryDeserializer::new(self)
+	}
+}
+
+impl<'de> de::Deserializer<'de> for SectionDeserializer {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let path = self.path.clone();
+		visitor.visit_map(SectionAccess::new(self.section, self.path)).map_err(|e| e.with_path(&path))
+	}
+
+	fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_newtype_struct(self)
+	}
+
+	serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+impl<'de> de::Deserializer<'de> for EntryDeserializer {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		let res = match self.entry {
+			SectionEntry::Int64(v) => visitor.visit_i64(v),
+			SectionEntry::Int32(v) => visitor.visit_i32(v),
+			SectionEntry::Int16(v) => visitor.visit_i16(v),
+			SectionEntry::Int8(v) => visitor.visit_i8(v),
+			SectionEntry::UInt64(v) => visitor.visit_u64(v),
+			SectionEntry::UInt32(v) => visitor.visit_u32(v),
+			SectionEntry::UInt16(v) => visitor.visit_u16(v),
+			SectionEntry::UInt8(v) => visitor.visit_u8(v),
+			SectionEntry::Double(_) if cfg!(feature = "no-float") => {
+				epee_err!(FloatDisabled, "DOUBLE entries are disabled by the no-float feature")
+			},
+			SectionEntry::Double(v) => visitor.visit_f64(v),
+			SectionEntry::Blob(v) => visitor.visit_byte_buf(v.into_vec()),
+			SectionEntry::Bool(v) => visitor.visit_bool(v),
+			SectionEntry::Object(section) => {
+				return SectionDeserializer { section, path: self.path }.deserialize_any(visitor);
+			},
+			SectionEntry::Array(array) => {
+				let path = self.path.clone();
+				visitor.visit_seq(ArrayAccess::new(array.into_entries(), path))
+			},
+			SectionEntry::Nil => visitor.visit_unit()
+		};
+
+		res.map_err(|e| e.with_path(&self.path))
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		match self.entry {
+			SectionEntry::Nil => visitor.visit_none(),
+			_ => visitor.visit_some(self)
+		}
+	}
+
+	fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		visitor.visit_newtype_struct(self)
+	}
+
+	// Blobs are sent for byte arrays too, e.g. Vec<u8> or [u8; 32] fields
+	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		match self.entry {
+			SectionEntry::Blob(v) => {
+				let seq = de::value::SeqDeserializer::<_, Error>::new(v.into_vec().into_iter());
+				seq.deserialize_any(visitor).map_err(|e| e.with_path(&self.path))
+			},
+			_ => self.deserialize_any(visitor)
+		}
+	}
+
+	fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+	where
+		V: Visitor<'de>
+	{
+		self.deserialize_seq(visitor)
+	}
+
+	serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf unit unit_struct
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Compound access                                                           //
+///////////////////////////////////////////////////////////////////////////////
+
+struct SectionAccess {
+	entries: hash_map::IntoIter<String, SectionEntry>,
+	value: Option<(String, SectionEntry)>, // entry whose key was just visited
+	path: String
+}
+
+impl SectionAccess {
+	fn new(section: Section, path: String) -> Self {
+		Self {
+			entries: section.into_inner().into_iter(),
+			value: None,
+			path
+		}
+	}
+}
+
+impl<'de> MapAccess<'de> for SectionAccess {
+	type Error = Error;
+
+	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+	where
+		K: DeserializeSeed<'de>
+	{
+		// Nil fields are absent, see SectionEntry::Nil
+		match self.entries.find(|(_, entry)| !entry.is_nil()) {
+			Some((key, entry)) => {
+				let res = seed.deserialize(key.as_str().into_deserializer()).map(Some);
+				self.value = Some((key, entry));
+				res
+			},
+			None => Ok(None)
+		}
+	}
+
+	fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+	where
+		V: DeserializeSeed<'de>
+	{
+		match self.value.take() {
+			Some((key, entry)) => {
+				let path = if self.path.is_empty() { key } else { format!("{}.{}", self.path, key) };
+				seed.deserialize(EntryDeserializer::with_path(entry, path))
+			},
+			None => Err(de::Error::custom("next_value_seed() called before next_key_seed()"))
+		}
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.entries.len())
+	}
+}
+
+struct ArrayAccess {
+	elements: std::iter::Enumerate<std::vec::IntoIter<SectionEntry>>,
+	path: String
+}
+
+impl ArrayAccess {
+	fn new(elements: Vec<SectionEntry>, path: String) -> Self {
+		Self {
+			elements: elements.into_iter().enumerate(),
+			path
+		}
+	}
+}
+
+impl<'de> SeqAccess<'de> for ArrayAccess {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: DeserializeSeed<'de>
+	{
+		match self.elements.next() {
+			Some((index, entry)) => {
+				let path = format!("{}[{}]", self.path, index);
+				seed.deserialize(EntryDeserializer::with_path(entry, path)).map(Some)
+			},
+			None => Ok(None)
+		}
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.elements.len())
+	}
+}
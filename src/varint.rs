@@ -1,24 +1,50 @@
-use std::convert::{From, Into, TryFrom, TryInto};
+use std::convert::{From, TryFrom, TryInto};
+use std::fmt;
 
-use crate::error::{Error, ErrorKind, Result};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 
-const MAX_BYTE_VAL:   u64 = (1 <<  6) - 1;
-const MAX_WORD_VAL:   u64 = (1 << 14) - 1;
-const MAX_DWORD_VAL:  u64 = (1 << 30) - 1;
-const MAX_QWORD_VAL:  u64 = (1 << 62) - 1;
-const MAX_VARINT_VAL: u64 = MAX_QWORD_VAL;
+use crate::error::{Error, ErrorKind, Result, epee_err};
 
-#[derive(Debug)]
+// Largest values encodable in 1, 2, 4 and 8 bytes, two bits go to the width
+pub const MAX_BYTE_VAL:   u64 = (1 <<  6) - 1;
+pub const MAX_WORD_VAL:   u64 = (1 << 14) - 1;
+pub const MAX_DWORD_VAL:  u64 = (1 << 30) - 1;
+pub const MAX_QWORD_VAL:  u64 = (1 << 62) - 1;
+pub const MAX_VARINT_VAL: u64 = MAX_QWORD_VAL;
+
+// Newtype name which tells the packed serializer and deserializers to write and
+// read a varint as its raw bytes, see Serialize for VarInt
+pub(crate) const VARINT_TOKEN: &str = "$serde_epee::private::VarInt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct VarInt {
 	value: u64,
 }
 
 impl VarInt {
+	pub const MAX: VarInt = VarInt { value: MAX_VARINT_VAL };
+
+	// Whether value can be written as a length or count, i.e. whether
+	// VarInt::try_from(value) succeeds
+	pub const fn fits(value: u64) -> bool {
+		value <= MAX_VARINT_VAL
+	}
+
 	///////////////////////////////////////////////////////////////////////////////
 	// Raw Read/Write methods                                                    //
 	///////////////////////////////////////////////////////////////////////////////
 
 	pub fn to_writer<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+		let (encoded, byte_size) = self.encode();
+		let write_res = writer.write_all(&encoded[..byte_size]);
+		match write_res {
+			Ok(_) => Ok(()),
+			Err(ioe) => Err(ioe.into())
+		}
+	}
+
+	// Little endian bytes and how many of them are used
+	fn encode(&self) -> ([u8; 8], usize) {
 		let (var_mask, byte_size) = if self.value <= MAX_BYTE_VAL {
 			(0b00, 1)
 		} else if self.value <= MAX_WORD_VAL {
@@ -28,30 +54,35 @@ impl VarInt {
 		} else {
 			(0b11, 8)
 		};
+		(((self.value << 2) | var_mask).to_le_bytes(), byte_size)
+	}
 
-		let encoded = ((self.value << 2) | var_mask).to_le_bytes();
-
-		let write_res = writer.write_all(&encoded[..byte_size]);
-		match write_res {
-			Ok(_) => Ok(()),
-			Err(ioe) => Err(ioe.into())
-		}
+	// Bytes taken by the varint starting with first_byte
+	pub(crate) fn encoded_len(first_byte: u8) -> usize {
+		1 << (first_byte & 0b11)
 	}
 
-	pub fn from_reader<R: std::io::Read>(reader: &mut R) -> Result<Self> {
+	#[inline]
+	pub fn from_reader<R: std::io::Read + ?Sized>(reader: &mut R) -> Result<Self> {
 		let mut buf = [0u8; 8];
 		if let Err(ioe) = reader.read_exact(&mut buf[..1]) {
 			return Err(ioe.into());
 		}
 
 		let var_mask = buf[0] & 0b11;
-		let byte_size = 1 << var_mask;
+		let byte_size = match 1usize.checked_shl(var_mask as u32) {
+			Some(byte_size) if byte_size <= buf.len() => byte_size,
+			_ => return epee_err!(LengthOverflow { len: var_mask as u64 }, "varint size mark {} is out of range", var_mask)
+		};
 
 		if let Err(ioe) = reader.read_exact(&mut buf[1..byte_size]) {
 			return Err(ioe.into());
 		}
 
-		Ok(Self { value: u64::from_le_bytes(buf) >> 2 })
+		match u64::from_le_bytes(buf).checked_shr(2) {
+			Some(value) => Ok(Self { value }),
+			None => epee_err!(LengthOverflow { len: u64::from_le_bytes(buf) }, "varint does not fit in u64")
+		}
 	}
 }
 
@@ -95,9 +126,9 @@ impl TryInto<u32> for VarInt {
 	}
 }
 
-impl Into<u64> for VarInt {
-	fn into(self) -> u64 {
-		self.value
+impl From<VarInt> for u64 {
+	fn from(varint: VarInt) -> u64 {
+		varint.value
 	}
 }
 
@@ -105,10 +136,9 @@ impl TryInto<usize> for VarInt {
 	type Error = Error;
 
 	fn try_into(self) -> Result<usize> {
-		if self.value <= usize::MAX as u64  {
-			Ok(self.value as usize)
-		} else {
-			Err(Error::new_no_msg(ErrorKind::VarIntTooBig))
+		match usize::try_from(self.value) {
+			Ok(value) => Ok(value),
+			Err(_) => Err(Error::new(ErrorKind::LengthOverflow { len: self.value }, format!("length {} does not fit in usize", self.value)))
 		}
 	}
 }
@@ -136,9 +166,9 @@ impl TryFrom<u64> for VarInt {
 
 	fn try_from(value: u64) -> Result<Self> {
 		if value <= MAX_VARINT_VAL {
-			Ok(Self { value: value })
+			Ok(Self { value })
 		} else {
-			Err(Error::new(ErrorKind::VarIntTooSmall, String::from("u64 value exceeds maximum varint value")))
+			Err(Error::new(ErrorKind::VarIntOverflow, String::from("u64 value exceeds maximum varint value")))
 		}
 	}
 }
@@ -147,10 +177,65 @@ impl TryFrom<usize> for VarInt {
 	type Error = Error;
 
 	fn try_from(value: usize) -> Result<Self> {
-		if (value as u64) <= MAX_VARINT_VAL {
-			Ok(Self { value: value as u64 })
-		} else {
-			Err(Error::new(ErrorKind::VarIntTooSmall, String::from("usize value exceeds maximum varint value")))
+		match u64::try_from(value) {
+			Ok(value) if value <= MAX_VARINT_VAL => Ok(Self { value }),
+			_ => Err(Error::new(ErrorKind::VarIntOverflow, String::from("usize value exceeds maximum varint value")))
 		}
 	}
-}
\ No newline at end of file
+}
+///////////////////////////////////////////////////////////////////////////////
+// Serde                                                                     //
+///////////////////////////////////////////////////////////////////////////////
+
+// VarInt fields are STRING entries holding the varint's bytes, or just the
+// bytes when packed with packed::to_packed_bytes. Varints tell their own
+// length, so packed values after one are still found, but the blob no longer
+// has a size which follows from its type.
+impl Serialize for VarInt {
+	fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		let (encoded, byte_size) = self.encode();
+		serializer.serialize_newtype_struct(VARINT_TOKEN, serde_bytes::Bytes::new(&encoded[..byte_size]))
+	}
+}
+
+impl<'de> Deserialize<'de> for VarInt {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+		deserializer.deserialize_newtype_struct(VARINT_TOKEN, VarIntVisitor)
+	}
+}
+
+struct VarIntVisitor;
+
+impl<'de> de::Visitor<'de> for VarIntVisitor {
+	type Value = VarInt;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("an epee varint in a byte string")
+	}
+
+	fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<VarInt, D::Error> {
+		deserializer.deserialize_bytes(self)
+	}
+
+	fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<VarInt, E> {
+		if v.is_empty() || v.len() != VarInt::encoded_len(v[0]) {
+			return Err(E::invalid_length(v.len(), &self));
+		}
+		VarInt::from_reader(&mut &v[..]).map_err(E::custom)
+	}
+}
+
+// Adapter for u64 fields which are written as varints, e.g. amounts in packed
+// blobs:
+//
+//     #[serde(with = "serde_epee::varint")]
+//     amount: u64,
+//
+// Values above MAX_VARINT_VAL fail to serialize.
+pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+	VarInt::try_from(*value).map_err(ser::Error::custom)?.serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+	VarInt::deserialize(deserializer).map(u64::from)
+}
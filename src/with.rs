@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{DeserializeAs, SerializeAs};
+
+///////////////////////////////////////////////////////////////////////////////
+// serde_with adapters                                                       //
+///////////////////////////////////////////////////////////////////////////////
+
+// What works with #[serde_as] as is:
+//
+//     DisplayFromStr, hex::Hex, Bytes     STRING
+//     TimestampSeconds<i64> and friends   INT64 / DOUBLE
+//     Map<_, _> (pairs as a section)      OBJECT, keys must serialize as strings
+//     Vec<(_, _)> (a map as pairs)        fails, tuples are arrays and arrays
+//                                         can't nest, use PairsAsObjects
+//
+// Adapters which need enums (e.g. EnumMap) fail since EPEE has no enums.
+
+// Stores a map, or a Vec of pairs, as an array of {"key": K, "value": V}
+// sections, so that non-string keys and duplicate keys survive
+pub struct PairsAsObjects;
+
+#[derive(Serialize)]
+struct PairRef<'a, K, V> {
+	key: &'a K,
+	value: &'a V
+}
+
+#[derive(Deserialize)]
+struct Pair<K, V> {
+	key: K,
+	value: V
+}
+
+fn serialize_pairs<'a, K, V, I, S>(pairs: I, serializer: S) -> Result<S::Ok, S::Error>
+where
+	K: Serialize + 'a,
+	V: Serialize + 'a,
+	I: ExactSizeIterator<Item = (&'a K, &'a V)>,
+	S: Serializer
+{
+	serializer.collect_seq(pairs.map(|(key, value)| PairRef { key, value }))
+}
+
+fn deserialize_pairs<'de, K, V, D>(deserializer: D) -> Result<impl Iterator<Item = (K, V)>, D::Error>
+where
+	K: Deserialize<'de>,
+	V: Deserialize<'de>,
+	D: Deserializer<'de>
+{
+	let pairs = Vec::<Pair<K, V>>::deserialize(deserializer)?;
+	Ok(pairs.into_iter().map(|pair| (pair.key, pair.value)))
+}
+
+impl<K: Serialize, V: Serialize> SerializeAs<Vec<(K, V)>> for PairsAsObjects {
+	fn serialize_as<S: Serializer>(source: &Vec<(K, V)>, serializer: S) -> Result<S::Ok, S::Error> {
+		serialize_pairs(source.iter().map(|(key, value)| (key, value)), serializer)
+	}
+}
+
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> DeserializeAs<'de, Vec<(K, V)>> for PairsAsObjects {
+	fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(K, V)>, D::Error> {
+		Ok(deserialize_pairs(deserializer)?.collect())
+	}
+}
+
+impl<K: Serialize, V: Serialize> SerializeAs<BTreeMap<K, V>> for PairsAsObjects {
+	fn serialize_as<S: Serializer>(source: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error> {
+		serialize_pairs(source.iter(), serializer)
+	}
+}
+
+impl<'de, K: Deserialize<'de> + Ord, V: Deserialize<'de>> DeserializeAs<'de, BTreeMap<K, V>> for PairsAsObjects {
+	fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error> {
+		Ok(deserialize_pairs(deserializer)?.collect())
+	}
+}
+
+impl<K: Serialize, V: Serialize> SerializeAs<HashMap<K, V>> for PairsAsObjects {
+	fn serialize_as<S: Serializer>(source: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error> {
+		serialize_pairs(source.iter(), serializer)
+	}
+}
+
+impl<'de, K: Deserialize<'de> + Eq + Hash, V: Deserialize<'de>> DeserializeAs<'de, HashMap<K, V>> for PairsAsObjects {
+	fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<K, V>, D::Error> {
+		Ok(deserialize_pairs(deserializer)?.collect())
+	}
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use serde_epee::{constants, Deserializer};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+    struct Item {
+        id: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+    struct AllArrays {
+        i64s: Vec<i64>,
+        i32s: Vec<i32>,
+        i16s: Vec<i16>,
+        i8s: Vec<i8>,
+        u64s: Vec<u64>,
+        u32s: Vec<u32>,
+        u16s: Vec<u16>,
+        u8s: Vec<u8>,
+        #[cfg(not(feature = "no-float"))]
+        f64s: Vec<f64>,
+        strings: Vec<String>,
+        bools: Vec<bool>,
+        items: Vec<Item>,
+    }
+
+    #[test]
+    fn every_array_kind_round_trips() {
+        let arrays = AllArrays {
+            i64s: vec![i64::MIN, -1, i64::MAX],
+            i32s: vec![i32::MIN, 0],
+            i16s: vec![-2, i16::MAX],
+            i8s: vec![i8::MIN, i8::MAX],
+            u64s: vec![0, u64::MAX],
+            u32s: vec![u32::MAX],
+            u16s: vec![1, 2, 3],
+            u8s: vec![0, 255],
+            #[cfg(not(feature = "no-float"))]
+            f64s: vec![-0.5, 1e300],
+            strings: vec!["a".to_string(), String::new()],
+            bools: vec![true, false, true],
+            items: vec![Item { id: 1 }, Item { id: 2 }],
+        };
+
+        let bytes = serde_epee::to_bytes(&arrays).unwrap();
+        assert_eq!(arrays, serde_epee::from_bytes(&mut bytes.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn empty_arrays_of_every_kind() {
+        let keys = [
+            ("i64s", constants::SERIALIZE_TYPE_INT64),
+            ("i32s", constants::SERIALIZE_TYPE_INT32),
+            ("i16s", constants::SERIALIZE_TYPE_INT16),
+            ("i8s", constants::SERIALIZE_TYPE_INT8),
+            ("u64s", constants::SERIALIZE_TYPE_UINT64),
+            ("u32s", constants::SERIALIZE_TYPE_UINT32),
+            ("u16s", constants::SERIALIZE_TYPE_UINT16),
+            ("u8s", constants::SERIALIZE_TYPE_UINT8),
+            #[cfg(not(feature = "no-float"))]
+            ("f64s", constants::SERIALIZE_TYPE_DOUBLE),
+            ("strings", constants::SERIALIZE_TYPE_STRING),
+            ("bools", constants::SERIALIZE_TYPE_BOOL),
+            ("items", constants::SERIALIZE_TYPE_OBJECT),
+        ];
+
+        let mut bytes = constants::PORTABLE_STORAGE_SIGNATURE.to_vec();
+        bytes.push((keys.len() as u8) << 2);
+        for (key, type_code) in keys {
+            bytes.push(key.len() as u8);
+            bytes.extend_from_slice(key.as_bytes());
+            bytes.push(type_code | constants::SERIALIZE_FLAG_ARRAY);
+            bytes.push(0);
+        }
+
+        let decoded: AllArrays = serde_epee::from_bytes(&mut bytes.as_slice()).unwrap();
+        assert_eq!(AllArrays::default(), decoded);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Sparse {
+        empty: Vec<u64>,
+        nested: Vec<AllArrays>,
+        last: u8,
+    }
+
+    #[test]
+    fn empty_sequences_round_trip() {
+        let sparse = Sparse { empty: vec![], nested: vec![AllArrays::default()], last: 9 };
+        let bytes = serde_epee::to_bytes(&sparse).unwrap();
+        assert_eq!(sparse, serde_epee::from_bytes(&mut bytes.as_slice()).unwrap());
+
+        let (section, errors) = serde_epee::from_bytes_lossy(&bytes);
+        assert!(errors.is_empty());
+        assert_eq!(Some(9), section["last"].as_u64());
+    }
+
+    #[test]
+    fn byte_vectors_from_blobs_or_arrays() {
+        #[derive(Serialize)]
+        struct AsBlob {
+            #[serde(with = "serde_bytes")]
+            data: Vec<u8>,
+            hash: serde_bytes::ByteBuf
+        }
+
+        #[derive(Serialize)]
+        struct AsArray {
+            data: Vec<u8>,
+            hash: Vec<u8>
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Target {
+            data: Vec<u8>,
+            hash: [u8; 4]
+        }
+
+        let expected = Target { data: vec![1, 2, 3], hash: [9; 4] };
+        let as_blob = serde_epee::to_bytes(&AsBlob { data: vec![1, 2, 3], hash: serde_bytes::ByteBuf::from(vec![9; 4]) }).unwrap();
+        let as_array = serde_epee::to_bytes(&AsArray { data: vec![1, 2, 3], hash: vec![9; 4] }).unwrap();
+        assert_eq!(expected, serde_epee::from_bytes::<Target>(&mut as_blob.as_slice()).unwrap());
+        assert_eq!(expected, serde_epee::from_bytes::<Target>(&mut as_array.as_slice()).unwrap());
+
+        // Fixed size arrays need a blob of exactly their length
+        let short = serde_epee::to_bytes(&AsBlob { data: vec![], hash: serde_bytes::ByteBuf::from(vec![9; 3]) }).unwrap();
+        assert!(serde_epee::from_bytes::<Target>(&mut short.as_slice()).is_err());
+
+        let mut strict = Deserializer::from_reader(as_blob.as_slice()).with_blob_seqs(false);
+        assert!(Target::deserialize(&mut strict).is_err());
+
+        // Only bytes come out of blobs, not one wider integer per byte
+        #[derive(Deserialize, Debug)]
+        struct Words {
+            #[allow(dead_code)]
+            data: Vec<u64>
+        }
+
+        let pod = serde_epee::to_bytes(&AsBlob { data: vec![7; 16], hash: serde_bytes::ByteBuf::new() }).unwrap();
+        let err = serde_epee::from_bytes::<Words>(&mut pod.as_slice()).unwrap_err();
+        assert_eq!(serde_epee::ErrorKind::TypeMismatch, err.kind());
+    }
+}
@@ -0,0 +1,52 @@
+#![cfg(not(feature = "no-float"))]
+
+use serde::Serialize;
+use serde_epee::audit::*;
+use serde_epee::to_bytes;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Plain {
+        a: u32,
+        b: f64,
+    }
+
+    #[derive(Serialize)]
+    struct Swapped {
+        b: f64,
+        a: u32,
+    }
+
+    #[test]
+    fn detect_nondeterminism() {
+        let report = audit_determinism(&Plain { a: 1, b: 0.5 }).unwrap();
+        assert!(report.is_deterministic());
+        assert_eq!(report.first_digest, report.second_digest);
+
+        let first = to_bytes(&Plain { a: 1, b: 0.5 }).unwrap();
+        let second = to_bytes(&Swapped { b: 0.5, a: 1 }).unwrap();
+        let report = compare_encodings(&first, &second);
+        assert_eq!(report.sources, vec![NondeterminismSource::FieldOrder { path: String::new() }]);
+
+        let second = to_bytes(&Plain { a: 1, b: -0.5 }).unwrap();
+        let report = compare_encodings(&first, &second);
+        assert!(matches!(report.sources[0], NondeterminismSource::FloatValue { .. }));
+        assert_eq!(serde_epee::envelope::xxhash64(&first), report.first_digest);
+    }
+
+    #[derive(Serialize)]
+    struct Heights {
+        heights: Vec<u64>
+    }
+
+    #[test]
+    fn compare_large_encodings() {
+        let first = to_bytes(&Heights { heights: (0..200_000).collect() }).unwrap();
+        let second = to_bytes(&Heights { heights: (0..200_000).map(|h| if h == 7 { 0 } else { h }).collect() }).unwrap();
+        let report = compare_encodings(&first, &second);
+        assert_eq!(report.sources, vec![NondeterminismSource::ValueChanged { path: "heights[7]".to_string() }]);
+    }
+}
@@ -0,0 +1,44 @@
+use serde::{Serialize, Deserialize};
+use serde_epee::blob::{self, BlobCipher, EncryptedBlob};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Toy cipher: xor with a constant key
+    struct XorCipher;
+
+    impl BlobCipher for XorCipher {
+        fn encrypt(plaintext: &[u8]) -> serde_epee::Result<Vec<u8>> {
+            Ok(plaintext.iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        fn decrypt(ciphertext: &[u8]) -> serde_epee::Result<Vec<u8>> {
+            Self::encrypt(ciphertext)
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct WalletCache {
+        cache_data: EncryptedBlob<XorCipher>,
+        #[serde(serialize_with = "blob::serialize_encrypted::<XorCipher, _>")]
+        #[serde(deserialize_with = "blob::deserialize_encrypted::<XorCipher, _>")]
+        keys_data: Vec<u8>
+    }
+
+    #[test]
+    fn encrypted_blob_round_trip() {
+        let cache = WalletCache {
+            cache_data: EncryptedBlob::new(b"secret".to_vec()),
+            keys_data: b"hidden".to_vec()
+        };
+
+        let bytes = serde_epee::to_bytes(&cache).unwrap();
+        assert!(!bytes.windows(6).any(|w| w == b"secret"));
+        assert!(!bytes.windows(6).any(|w| w == b"hidden"));
+
+        let decoded: WalletCache = serde_epee::from_bytes(&mut bytes.as_slice()).unwrap();
+        assert_eq!(cache, decoded);
+        assert_eq!(b"secret", &decoded.cache_data[..]);
+    }
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "bytes")]
+
+use bytes::{Buf, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use serde_epee::buf::{from_buf, from_shared_bytes, to_buf_mut, SharedBlob};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Block {
+        height: u64,
+        blob: SharedBlob
+    }
+
+    #[test]
+    fn shared_blobs_point_into_input() {
+        let block = Block { height: 3, blob: SharedBlob(Bytes::from_static(&[0xab; 100])) };
+        let mut buf = BytesMut::new();
+        to_buf_mut(&block, &mut buf).unwrap();
+        assert_eq!(serde_epee::to_bytes(&block).unwrap(), buf[..]);
+
+        let input = buf.freeze();
+        let decoded: Block = from_shared_bytes(&input).unwrap();
+        assert_eq!(block, decoded);
+        let input_range = input.as_ptr_range();
+        assert!(input_range.contains(&decoded.blob.0.as_ptr()));
+
+        // Copies everywhere else
+        let (head, tail) = input.split_at(10);
+        let chained: Block = from_buf(Bytes::copy_from_slice(head).chain(Bytes::copy_from_slice(tail))).unwrap();
+        assert_eq!(block, chained);
+        let copied: Block = serde_epee::from_bytes(&mut &input[..]).unwrap();
+        assert!(!input_range.contains(&copied.blob.0.as_ptr()));
+    }
+
+    // Holds a whole document in a blob and decodes it on the spot, with a
+    // deserializer of its own
+    #[derive(Debug, PartialEq)]
+    struct Envelope {
+        inner: Block
+    }
+
+    impl<'de> Deserialize<'de> for Envelope {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            #[derive(Deserialize)]
+            struct Raw {
+                #[serde(with = "serde_bytes")]
+                document: Vec<u8>
+            }
+            let raw = Raw::deserialize(deserializer)?;
+            let inner = serde_epee::from_bytes(&mut raw.document.as_slice()).map_err(serde::de::Error::custom)?;
+            Ok(Envelope { inner })
+        }
+    }
+
+    #[test]
+    fn nested_deserializers_copy() {
+        let inner = Block { height: 7, blob: SharedBlob(Bytes::from_static(b"inner blob")) };
+        #[derive(Serialize)]
+        struct Raw {
+            #[serde(with = "serde_bytes")]
+            document: Vec<u8>
+        }
+        let outer = serde_epee::to_bytes(&Raw { document: serde_epee::to_bytes(&inner).unwrap() }).unwrap();
+
+        let decoded: Envelope = from_shared_bytes(&Bytes::from(outer)).unwrap();
+        assert_eq!(inner, decoded.inner);
+    }
+
+    #[test]
+    fn serializes_into_bytes_mut() {
+        let block = Block { height: 5, blob: SharedBlob(Bytes::from_static(b"blob")) };
+        let mut buf = BytesMut::with_capacity(64);
+        block.serialize(&mut serde_epee::Serializer::new(&mut buf)).unwrap();
+        assert_eq!(serde_epee::to_bytes(&block).unwrap(), buf[..]);
+    }
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "codec")]
+
+use bytes::BytesMut;
+use serde::{Serialize, Deserialize};
+use serde_epee::codec::{EpeeCodec, LevinHeader, LevinMessage, LEVIN_HEADER_SIZE};
+use serde_epee::ErrorKind;
+use tokio_util::codec::{Decoder, Encoder};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Ping {
+        status: String,
+        peer_id: u64
+    }
+
+    #[test]
+    fn levin_frames() {
+        let message = LevinMessage {
+            header: LevinHeader { expect_response: true, ..LevinHeader::new(1003) },
+            body: Ping { status: "OK".to_string(), peer_id: 42 }
+        };
+
+        let mut codec = EpeeCodec::<Ping>::new();
+        let mut encoded = BytesMut::new();
+        codec.encode(message.clone(), &mut encoded).unwrap();
+        codec.encode(message.clone(), &mut encoded).unwrap();
+        let frame_len = encoded.len() / 2;
+
+        // Frames are only decoded once they are complete
+        let mut buf = BytesMut::new();
+        for (i, b) in encoded.iter().enumerate() {
+            buf.extend_from_slice(&[*b]);
+            let decoded = codec.decode(&mut buf).unwrap();
+            assert_eq!((i + 1) % frame_len == 0, decoded.is_some());
+            if let Some(decoded) = decoded {
+                assert_eq!(message, decoded);
+            }
+        }
+        assert!(buf.is_empty());
+
+        // Oversized frames are rejected from the header alone
+        let mut codec = EpeeCodec::<Ping>::new().with_max_payload(10);
+        let mut header_only = BytesMut::from(&encoded[..LEVIN_HEADER_SIZE]);
+        let err = codec.decode(&mut header_only).unwrap_err();
+        assert_eq!(ErrorKind::BudgetExceeded { max_bytes: 10 }, err.kind());
+
+        let mut garbage = BytesMut::from(&[0u8; LEVIN_HEADER_SIZE][..]);
+        assert_eq!(ErrorKind::ExpectedFormatSignature, codec.decode(&mut garbage).unwrap_err().kind());
+    }
+
+    #[test]
+    fn payload_with_extra_bytes() {
+        let message = LevinMessage { header: LevinHeader::new(1003), body: Ping { status: "OK".to_string(), peer_id: 42 } };
+        let mut codec = EpeeCodec::<Ping>::new();
+        let mut encoded = BytesMut::new();
+        codec.encode(message, &mut encoded).unwrap();
+
+        // Payload length in the header covers a byte past the document
+        let payload_len = (encoded.len() - LEVIN_HEADER_SIZE + 1) as u64;
+        encoded[8..16].copy_from_slice(&payload_len.to_le_bytes());
+        encoded.extend_from_slice(&[0]);
+        let err = codec.decode(&mut encoded).unwrap_err();
+        assert_eq!(ErrorKind::ExpectedEnd, err.kind());
+    }
+
+    #[test]
+    fn huge_payload_length() {
+        // Frame length of the header plus u64::MAX payload bytes overflows usize
+        let mut codec = EpeeCodec::<Ping>::new().with_max_payload(u64::MAX);
+        let mut header = BytesMut::from(&0x0101010101012101u64.to_le_bytes()[..]);
+        header.extend_from_slice(&u64::MAX.to_le_bytes());
+        header.extend_from_slice(&[0u8; LEVIN_HEADER_SIZE - 16]);
+        let err = codec.decode(&mut header).unwrap_err();
+        assert_eq!(ErrorKind::LengthOverflow { len: u64::MAX }, err.kind());
+    }
+}
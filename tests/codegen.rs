@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use serde_epee::section::SectionEntry;
+use serde_epee::Section;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(fields: Vec<(&str, SectionEntry)>) -> Section {
+        Section::from(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect::<HashMap<_, _>>())
+    }
+
+    #[test]
+    fn infer_nested_types() {
+        let peer = |with_port: bool| {
+            let mut fields = vec![("adr", SectionEntry::from("1.2.3.4"))];
+            if with_port {
+                fields.push(("port", SectionEntry::UInt16(18080)));
+            }
+            section(fields)
+        };
+        let doc = section(vec![
+            ("blockHash", SectionEntry::from(vec![0xffu8, 0x00])),
+            ("type", SectionEntry::UInt8(1)),
+            ("node_data", SectionEntry::Object(section(vec![("my_port", SectionEntry::UInt32(1))]))),
+            ("peers", SectionEntry::from(vec![peer(true), peer(false)]))
+        ]);
+
+        let expected = "\
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+\t#[serde(rename = \"blockHash\")]
+\t#[serde(with = \"serde_bytes\")]
+\tpub block_hash: Vec<u8>,
+\tpub node_data: NodeData,
+\tpub peers: Vec<Peer>,
+\t#[serde(rename = \"type\")]
+\tpub type_: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeData {
+\tpub my_port: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Peer {
+\tpub adr: String,
+\t#[serde(default)]
+\tpub port: Option<u16>,
+}
+";
+        assert_eq!(expected, serde_epee::codegen::infer_types(&doc));
+    }
+}
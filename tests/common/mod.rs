@@ -0,0 +1,34 @@
+use std::path::Path;
+
+// Compiles every tests/<dir>/*.rs, all of which must fail, and checks that the
+// compiler output mentions the text given for each file. Whole outputs aren't
+// compared since their layout changes between Rust releases, so the cases are
+// copied somewhere trybuild can write the outputs to instead.
+pub fn assert_compile_errors(dir: &str, expected: &[(&str, &str)]) {
+    let cases_dir = Path::new("tests").join(dir);
+    let mut files: Vec<String> = std::fs::read_dir(&cases_dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".rs"))
+        .collect();
+    files.sort();
+    let mut listed: Vec<String> = expected.iter().map(|(file, _)| file.to_string()).collect();
+    listed.sort();
+    assert_eq!(files, listed, "every case in {} needs an expected error", cases_dir.display());
+
+    let work_dir = std::env::temp_dir().join(format!("serde_epee_{}_{}", dir, std::process::id()));
+    std::fs::create_dir_all(&work_dir).unwrap();
+    for file in &files {
+        std::fs::copy(cases_dir.join(file), work_dir.join(file)).unwrap();
+    }
+
+    std::env::set_var("TRYBUILD", "overwrite");
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail(work_dir.join("*.rs"));
+    drop(cases); // compiles the cases
+
+    for (file, text) in expected {
+        let stderr = std::fs::read_to_string(work_dir.join(file).with_extension("stderr")).unwrap();
+        assert!(stderr.contains(text), "{} should fail with {:?}, got:\n{}", file, text, stderr);
+    }
+    std::fs::remove_dir_all(&work_dir).unwrap();
+}
@@ -0,0 +1,69 @@
+#![cfg(any(feature = "gzip", feature = "zstd"))]
+
+use serde::{Serialize, Deserialize};
+use serde_epee::compress::{self, Compression};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Capture {
+        blob: String
+    }
+
+    fn compressions() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "gzip")]
+            Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd
+        ]
+    }
+
+    #[test]
+    fn compressed_round_trip_and_bomb_protection() {
+        let capture = Capture { blob: "a".repeat(100000) };
+
+        for compression in compressions() {
+            let mut compressed = Vec::new();
+            compress::to_writer_compressed(&mut compressed, &capture, compression).unwrap();
+            assert!(compressed.len() < 1000);
+
+            let decoded: Capture = compress::from_reader_compressed(compressed.as_slice(), compression, 200000).unwrap();
+            assert_eq!(capture, decoded);
+
+            let err = compress::from_reader_compressed::<Capture, _>(compressed.as_slice(), compression, 50000).unwrap_err();
+            assert_eq!(ErrorKind::BudgetExceeded { max_bytes: 50000 }, err.kind());
+        }
+    }
+
+    fn compress_raw(bytes: &[u8], compression: Compression) -> Vec<u8> {
+        match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Write;
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            },
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::stream::encode_all(bytes, zstd::DEFAULT_COMPRESSION_LEVEL).unwrap()
+        }
+    }
+
+    #[test]
+    fn compressed_huge_declared_string() {
+        // A string declaring about 1 GiB, with nothing behind it
+        let mut bytes = serde_epee::to_bytes(&Capture { blob: String::new() }).unwrap();
+        bytes.pop();
+        bytes.extend_from_slice(&(((1u32 << 30) - 1) << 2 | 2).to_le_bytes());
+
+        for compression in compressions() {
+            let compressed = compress_raw(&bytes, compression);
+            let err = compress::from_reader_compressed::<Capture, _>(compressed.as_slice(), compression, 200000).unwrap_err();
+            assert_eq!(ErrorKind::BudgetExceeded { max_bytes: 200000 }, err.kind());
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use serde_epee::constants::{self, TypeCode};
+use serde_epee::types::{EpeeEntryType, EpeeScalarType};
+use serde_epee::{sniff, FormatInfo};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_code_conversions() {
+        for code in 0u8..=12 {
+            let type_code = TypeCode::from_u8(code).unwrap();
+            assert_eq!(code, u8::from(type_code));
+            assert_eq!(type_code, TypeCode::from_u8(code | constants::SERIALIZE_FLAG_ARRAY).unwrap());
+        }
+
+        assert!(TypeCode::from_u8(13).is_err());
+        assert!(TypeCode::is_array_flagged(0x85));
+        assert!(!TypeCode::is_array_flagged(0x05));
+        assert_eq!(0x8c, TypeCode::Object.to_wire(true));
+    }
+
+    #[test]
+    fn type_code_sizes_and_names() {
+        assert_eq!(Some(8), TypeCode::UInt64.wire_size_hint());
+        assert_eq!(Some(1), TypeCode::Bool.wire_size_hint());
+        assert_eq!(None, TypeCode::String.wire_size_hint());
+        assert_eq!("INT32", TypeCode::Int32.to_string());
+    }
+
+    #[test]
+    fn entry_type_parsing() {
+        let entry_type = EpeeEntryType::from_type_code(0x8a).unwrap();
+        assert_eq!(EpeeEntryType::new(EpeeScalarType::Str, true), entry_type);
+        assert_eq!(0x8a, entry_type.to_type_code());
+        assert_eq!("ARRAY<STRING>", entry_type.to_string());
+
+        assert!(EpeeScalarType::from_type_code(constants::SERIALIZE_TYPE_UNKNOWN).is_err());
+    }
+
+    #[test]
+    fn sniff_signature() {
+        let mut doc = constants::PORTABLE_STORAGE_SIGNATURE.to_vec();
+        assert_eq!(Some(FormatInfo { version: 1, num_root_fields: None }), sniff(&doc));
+
+        doc.push(0x0c);
+        let info = sniff(&doc).unwrap();
+        assert_eq!(Some(3), info.num_root_fields);
+        assert!(info.is_supported_version());
+
+        doc[8] = 2;
+        assert!(!sniff(&doc).unwrap().is_supported_version());
+
+        assert_eq!(None, sniff(&doc[..8]));
+        assert_eq!(None, sniff(b"GET / HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn signature_forms_agree() {
+        const FORK_SIGNATURE: [u8; 9] = constants::make_signature(0xdeadbeef, 0x01020101, 2);
+
+        assert_eq!(hex::decode("011101010101020101").unwrap(), constants::PORTABLE_STORAGE_SIGNATURE);
+        assert_eq!((0xdeadbeef, 0x01020101, 2), constants::split_signature(&FORK_SIGNATURE));
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_epee::custom::{CustomKind, CustomType, CustomValue, TypeRegistry};
+use serde_epee::{Deserializer, ErrorKind, Serializer};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_i128(value: &CustomValue, writer: &mut dyn Write) -> serde_epee::Result<()> {
+        if let CustomValue::I128(v) = value {
+            writer.write_all(&v.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn decode_i128(reader: &mut dyn Read) -> serde_epee::Result<CustomValue> {
+        let mut buf = [0u8; 16];
+        reader.read_exact(&mut buf)?;
+        Ok(CustomValue::I128(i128::from_le_bytes(buf)))
+    }
+
+    const INT128: CustomType = CustomType { name: "INT128", kind: CustomKind::I128, encode: encode_i128, decode: decode_i128 };
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Balance {
+        amount: i128,
+        height: u64,
+        label: String
+    }
+
+    #[test]
+    fn custom_int128_round_trip() {
+        let registry = TypeRegistry::new().register(0x40, INT128).unwrap();
+        let balance = Balance { amount: -(1i128 << 100), height: 7, label: "savings".to_string() };
+
+        let mut bytes = Vec::new();
+        let mut serializer = Serializer::new(&mut bytes).with_type_registry(registry.clone());
+        balance.serialize(&mut serializer).unwrap();
+        assert!(bytes.windows(17).any(|w| w[0] == 0x40 && w[1..] == balance.amount.to_le_bytes()));
+
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_type_registry(registry);
+        assert_eq!(balance, Balance::deserialize(&mut deserializer).unwrap());
+
+        // Unregistered readers don't know the code, unregistered writers can't write i128
+        let res: serde_epee::Result<Balance> = serde_epee::from_bytes(&mut bytes.as_slice());
+        assert_eq!(ErrorKind::BadTypeCode { code: 0x40 }, res.unwrap_err().kind());
+        assert_eq!(ErrorKind::SerdeModelUnsupported, serde_epee::to_bytes(&balance).unwrap_err().kind());
+    }
+
+    #[test]
+    fn custom_codes_must_be_in_range() {
+        assert_eq!(ErrorKind::BadTypeCode { code: 0x05 }, TypeRegistry::new().register(0x05, INT128).unwrap_err().kind());
+        let registry = TypeRegistry::new().register(0x41, INT128).unwrap();
+        assert_eq!(ErrorKind::BadTypeCode { code: 0x41 }, registry.register(0x41, INT128).unwrap_err().kind());
+    }
+}
@@ -0,0 +1,826 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::num::{NonZeroU32, NonZeroU64, Wrapping};
+
+use serde::{Serialize, Deserialize};
+use serde_epee::{constants, skip_entry, Deserializer, EpeeScalarType, ErrorKind, LimitBreach, Limits, Section, VarInt};
+use serde_epee::section::SectionEntry;
+use serde_epee::de::IntConversion;
+use serde_epee::de::read::{BufIoRead, EpeeRead, IoRead, SliceRead};
+use serde_epee::trace::TraceLabel;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Debug)]
+    struct Wide {
+        height: u64,
+        blobs: Vec<String>,
+        nested: HashMap<String, u32>,
+        ok: bool
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct WithNote {
+        ok: bool,
+        note: String
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Narrow {
+        ok: bool
+    }
+
+    #[test]
+    fn ignore_unknown_fields() {
+        let wide = Wide {
+            height: 1234567,
+            blobs: vec!["ab".to_string(), "".to_string()],
+            nested: HashMap::from([("x".to_string(), 7)]),
+            ok: true
+        };
+        let bytes = serde_epee::to_bytes(&wide).unwrap();
+
+        let narrow: Narrow = serde_epee::from_bytes(&mut bytes.as_slice()).unwrap();
+        assert_eq!(Narrow { ok: true }, narrow);
+    }
+
+    #[test]
+    fn skip_single_entries() {
+        // Array of 2 strings followed by a trailing marker byte
+        let bytes = [0x08u8, 0x08, b'a', b'b', 0x00, 0xff];
+        let mut reader = &bytes[..];
+        skip_entry(&mut reader, constants::SERIALIZE_TYPE_STRING | constants::SERIALIZE_FLAG_ARRAY).unwrap();
+        assert_eq!([0xff], reader);
+
+        let mut reader = &[1u8, 2, 3][..];
+        assert!(skip_entry(&mut reader, constants::SERIALIZE_TYPE_UINT64).is_err());
+    }
+
+    #[test]
+    fn limited_reader_budget() {
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "hello".to_string() }).unwrap();
+
+        let decoded: Narrow = serde_epee::from_reader_limited(bytes.as_slice(), bytes.len() as u64).unwrap();
+        assert_eq!(Narrow { ok: true }, decoded);
+
+        let err = serde_epee::from_reader_limited::<Narrow, _>(bytes.as_slice(), bytes.len() as u64 - 1).unwrap_err();
+        assert_eq!(ErrorKind::BudgetExceeded { max_bytes: bytes.len() as u64 - 1 }, err.kind());
+
+        // Running out of input before the budget is not a budget error
+        let err = serde_epee::from_reader_limited::<Narrow, _>(&bytes[..10], 1000).unwrap_err();
+        assert_eq!(ErrorKind::TruncatedCompound { expected: 2, got: 0 }, err.kind());
+
+        // A string declaring about 1 GiB fails before anything is allocated for it
+        let mut bytes = serde_epee::to_bytes(&WithNote { ok: true, note: String::new() }).unwrap();
+        let len_at = bytes.len() - 1;
+        bytes.truncate(len_at);
+        bytes.extend_from_slice(&(((1u32 << 30) - 1) << 2 | 2).to_le_bytes());
+        let err = serde_epee::from_reader_limited::<WithNote, _>(bytes.as_slice(), 1000).unwrap_err();
+        assert_eq!(ErrorKind::BudgetExceeded { max_bytes: 1000 }, err.kind());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Outer {
+        inner: Inner
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Inner {
+        names: Vec<String>
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let outer = Outer { inner: Inner { names: vec!["a".to_string(), "b".to_string()] } };
+        let bytes = serde_epee::to_bytes(&outer).unwrap();
+
+        let decode = |limits: Limits| {
+            let mut reader = bytes.as_slice();
+            let mut deserializer = Deserializer::from_reader(&mut reader).with_limits(limits);
+            Outer::deserialize(&mut deserializer)
+        };
+
+        assert_eq!(outer, decode(Limits::monerod_levin()).unwrap());
+        assert_eq!(ErrorKind::SectionTooDeep { max: 1 }, decode(Limits { max_depth: 1, ..Limits::monerod() }).unwrap_err().kind());
+        assert_eq!(ErrorKind::TooManyStrings { count: 2, max: 1 }, decode(Limits { max_strings: 1, ..Limits::monerod() }).unwrap_err().kind());
+        assert_eq!(ErrorKind::TooManyObjects { count: 1, max: 0 }, decode(Limits { max_objects: 0, ..Limits::monerod() }).unwrap_err().kind());
+        assert_eq!(ErrorKind::TooManySectionFields { count: 2, max: 1 }, decode(Limits { max_fields: 1, ..Limits::monerod() }).unwrap_err().kind());
+        assert_eq!(ErrorKind::StringTooLong { len: 1, max: 0 }, decode(Limits { max_string_len: 0, ..Limits::monerod() }).unwrap_err().kind());
+    }
+
+    #[test]
+    fn limit_breaches_name_the_knob() {
+        let outer = Outer { inner: Inner { names: vec!["a".to_string(), "bcd".to_string()] } };
+        let bytes = serde_epee::to_bytes(&outer).unwrap();
+
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_limits(Limits { max_string_len: 2, ..Limits::monerod() });
+        let err = Outer::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(Some(LimitBreach { limit: "max_string_len", observed: 3, max: 2 }), err.limit_breach());
+        assert_eq!(Some("inner.names[1]"), err.path());
+        assert!(err.to_string().contains("Limits::max_string_len"));
+
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_limits(Limits { max_depth: 1, ..Limits::monerod() });
+        let err = Outer::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(Some(LimitBreach { limit: "max_depth", observed: 2, max: 1 }), err.limit_breach());
+        assert_eq!(Some("inner"), err.path());
+        assert_eq!(None, serde_epee::Error::new(ErrorKind::TypeMismatch, String::new()).limit_breach());
+    }
+
+    #[test]
+    fn frame_len_caps_declared_sizes() {
+        let outer = Outer { inner: Inner { names: vec!["a".to_string(), "bcd".to_string()] } };
+        let bytes = serde_epee::to_bytes(&outer).unwrap();
+        let mut deserializer = Deserializer::from_slice(&bytes).with_frame_len(bytes.len() as u64);
+        assert_eq!(outer, Outer::deserialize(&mut deserializer).unwrap());
+
+        // {"blob": <a million bytes>} cut short after the length
+        let mut huge = constants::PORTABLE_STORAGE_SIGNATURE.to_vec();
+        huge.extend_from_slice(&[1 << 2, 4, b'b', b'l', b'o', b'b', constants::SERIALIZE_TYPE_STRING]);
+        VarInt::try_from(1000000u64).unwrap().to_writer(&mut huge).unwrap();
+        let mut deserializer = Deserializer::from_reader(huge.as_slice()).with_frame_len(huge.len() as u64);
+        let err = HashMap::<String, Vec<u8>>::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(ErrorKind::AllocTooLarge { len: 1000000, max: huge.len() as u64 }, err.kind());
+        assert_eq!(Some("max_alloc_bytes"), err.limit_breach().map(|breach| breach.limit));
+        assert_eq!(Some("blob"), err.path());
+
+        // Arrays are charged for their elements before any are read
+        let mut huge = constants::PORTABLE_STORAGE_SIGNATURE.to_vec();
+        huge.extend_from_slice(&[1 << 2, 1, b'x', constants::SERIALIZE_TYPE_UINT64 | constants::SERIALIZE_FLAG_ARRAY]);
+        VarInt::try_from(1000u64).unwrap().to_writer(&mut huge).unwrap();
+        let mut deserializer = Deserializer::from_slice(&huge).with_limits(Limits::monerod_levin()).with_frame_len(huge.len() as u64);
+        let err = HashMap::<String, Vec<u64>>::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(ErrorKind::AllocTooLarge { len: 8000, max: huge.len() as u64 }, err.kind());
+        assert_eq!(Limits::monerod_levin().max_strings, deserializer.limits().max_strings);
+    }
+
+    #[test]
+    fn owned_reader_into_inner() {
+        let mut bytes = serde_epee::to_bytes(&WithNote { ok: false, note: "n".to_string() }).unwrap();
+        let doc_len = bytes.len() as u64;
+        bytes.extend_from_slice(b"trailing");
+
+        let mut deserializer = Deserializer::from_reader(std::io::Cursor::new(bytes));
+        let decoded = Narrow::deserialize(&mut deserializer).unwrap();
+        assert_eq!(Narrow { ok: false }, decoded);
+        assert_eq!(doc_len, deserializer.into_inner().position());
+    }
+
+    #[test]
+    fn readers_peek_and_count_alike() {
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "note".to_string() }).unwrap();
+        let mut with_trailing = bytes.clone();
+        with_trailing.extend_from_slice(b"tail");
+
+        fn check<'a, R: EpeeRead<'a>>(mut read: R, bytes: &[u8]) -> R {
+            assert_eq!(&bytes[..4], read.peek(4).unwrap());
+            assert_eq!(0, read.offset());
+            let mut first = [0u8; 2];
+            read.read_exact(&mut first).unwrap();
+            assert_eq!((&bytes[..2], 2), (&first[..], read.offset()));
+            assert_eq!(&bytes[2..], read.peek_up_to(1000).unwrap());
+            assert!(read.peek(1000).is_err());
+            read
+        }
+        assert_eq!(&bytes[2..], check(SliceRead::new(&bytes), &bytes).into_inner());
+        check(IoRead::new(bytes.as_slice()), &bytes);
+        check(BufIoRead::new(std::io::BufReader::with_capacity(3, bytes.as_slice())), &bytes);
+
+        let mut deserializer = Deserializer::from_slice(&with_trailing);
+        assert_eq!(Narrow { ok: true }, Narrow::deserialize(&mut deserializer).unwrap());
+        assert_eq!(b"tail", deserializer.into_inner());
+
+        let mut deserializer = Deserializer::from_buf_read(std::io::BufReader::with_capacity(5, with_trailing.as_slice()));
+        assert_eq!(Narrow { ok: true }, Narrow::deserialize(&mut deserializer).unwrap());
+        assert_eq!(bytes.len() as u64, deserializer.offset());
+
+        let mut rest = with_trailing.as_slice();
+        assert_eq!(Narrow { ok: true }, serde_epee::from_bytes(&mut rest).unwrap());
+        assert_eq!(b"tail", rest);
+    }
+
+    #[test]
+    fn array_of_sections_round_trip() {
+        let outers = HashMap::from([("list".to_string(), vec![
+            Outer { inner: Inner { names: vec!["x".to_string()] } },
+            Outer { inner: Inner { names: vec!["y".to_string(), "z".to_string()] } }
+        ])]);
+        let bytes = serde_epee::to_bytes(&outers).unwrap();
+        let decoded: HashMap<String, Vec<Outer>> = serde_epee::from_bytes(&mut bytes.as_slice()).unwrap();
+        assert_eq!(outers, decoded);
+    }
+
+    #[test]
+    fn declared_lengths_past_u32() {
+        // {"s": <string of 2^32 bytes>} and {"s": [<2^32 sections>]}, both truncated
+        let long_string = hex::decode("011101010101020101040173".to_owned() + "0a0300000004000000").unwrap();
+        let many_sections = hex::decode("011101010101020101040173".to_owned() + "8c0300000004000000").unwrap();
+
+        let err = serde_epee::from_bytes::<HashMap<String, serde_bytes::ByteBuf>>(&mut long_string.as_slice()).unwrap_err();
+        assert_eq!(ErrorKind::StringTooLong { len: 1 << 32, max: 2000000000 }, err.kind());
+        let (_, errors) = serde_epee::from_bytes_lossy(&long_string);
+        assert_eq!(ErrorKind::StringTooLong { len: 1 << 32, max: 2000000000 }, errors[0].kind());
+
+        let err = serde_epee::from_bytes::<HashMap<String, Vec<HashMap<String, u8>>>>(&mut many_sections.as_slice()).unwrap_err();
+        assert_eq!(ErrorKind::TooManyObjects { count: 1 << 32, max: 65536 }, err.kind());
+        let (_, errors) = serde_epee::from_bytes_lossy(&many_sections);
+        assert_eq!(ErrorKind::TooManyObjects { count: 1 << 32, max: 65536 }, errors[0].kind());
+    }
+
+    #[test]
+    fn peek_without_consuming() {
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "n".to_string() }).unwrap();
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice());
+
+        let peeked = deserializer.peek_type().unwrap().unwrap();
+        assert_eq!(EpeeScalarType::Object, peeked.scalar_type);
+        assert!(!peeked.is_array);
+        assert_eq!(None, deserializer.peek_key().unwrap());
+
+        assert_eq!(Narrow { ok: true }, Narrow::deserialize(&mut deserializer).unwrap());
+        assert_eq!(None, deserializer.peek_type().unwrap());
+    }
+
+    #[test]
+    fn optional_root_signature() {
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "n".to_string() }).unwrap();
+        let body = &bytes[constants::PORTABLE_STORAGE_SIGNATURE_SIZE..];
+
+        for input in [&bytes[..], body] {
+            let mut deserializer = Deserializer::from_reader(input).with_optional_signature();
+            assert_eq!(Narrow { ok: true }, Narrow::deserialize(&mut deserializer).unwrap());
+        }
+
+        // Shorter than a signature
+        let mut deserializer = Deserializer::from_reader(&[0u8][..]).with_optional_signature();
+        assert!(HashMap::<String, u8>::deserialize(&mut deserializer).unwrap().is_empty());
+
+        let err = Narrow::deserialize(&mut Deserializer::from_reader(body)).unwrap_err();
+        assert_eq!(ErrorKind::ExpectedFormatSignature, err.kind());
+    }
+
+    #[test]
+    fn slices_and_cows() {
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "n".to_string() }).unwrap();
+        assert_eq!(Narrow { ok: true }, serde_epee::from_slice(&bytes).unwrap());
+        assert_eq!(Narrow { ok: true }, serde_epee::from_cow::<Narrow>(Cow::Borrowed(&bytes)).unwrap());
+        assert_eq!(Narrow { ok: true }, serde_epee::from_cow::<Narrow>(Cow::Owned(bytes.clone())).unwrap());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(ErrorKind::ExpectedEnd, serde_epee::from_slice::<Narrow>(&trailing).unwrap_err().kind());
+    }
+
+    #[test]
+    fn standalone_entries() {
+        let mut stream = Vec::new();
+        serde_epee::write_entry(&mut stream, "ok", &true).unwrap();
+        serde_epee::write_entry(&mut stream, "inner", &Inner { names: vec!["q".to_string()] }).unwrap();
+        assert!(serde_epee::write_entry(&mut stream, "", &1u8).is_err());
+
+        // Entries are the same bytes as the body of a section
+        let mut doc = constants::PORTABLE_STORAGE_SIGNATURE.to_vec();
+        doc.push(2 << 2);
+        doc.extend_from_slice(&stream);
+        let decoded: Outer = serde_epee::from_bytes(&mut doc.as_slice()).unwrap();
+        assert_eq!(vec!["q".to_string()], decoded.inner.names);
+
+        let mut reader = stream.as_slice();
+        assert_eq!(("ok".to_string(), true), serde_epee::read_entry(&mut reader).unwrap());
+        let (key, inner): (String, Inner) = serde_epee::read_entry(&mut reader).unwrap();
+        assert_eq!(("inner", vec!["q".to_string()]), (key.as_str(), inner.names));
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn caller_supplied_scratch() {
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "hello".to_string() }).unwrap();
+
+        let mut scratch = [0u8; 8];
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_scratch(&mut scratch[..]);
+        assert_eq!(Narrow { ok: true }, Narrow::deserialize(&mut deserializer).unwrap());
+
+        // Skipped fields still need their keys read, and "note" is 4 bytes long
+        let mut small = [0u8; 3];
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_scratch(&mut small[..]);
+        assert_eq!(ErrorKind::ScratchOverflow { len: 4, capacity: 3 }, Narrow::deserialize(&mut deserializer).unwrap_err().kind());
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct WrongInner {
+        #[allow(dead_code)]
+        names: Vec<u64>
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct WrongOuter {
+        #[allow(dead_code)]
+        inner: WrongInner
+    }
+
+    #[test]
+    fn mismatch_error_paths() {
+        let outer = Outer { inner: Inner { names: vec!["a".to_string(), "b".to_string()] } };
+        let bytes = serde_epee::to_bytes(&outer).unwrap();
+
+        let err = serde_epee::from_bytes::<WrongOuter>(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(ErrorKind::TypeMismatch, err.kind());
+        assert_eq!(Some("inner.names[0]"), err.path());
+        assert!(err.to_string().contains("expected u64"));
+        assert!(err.to_string().contains("wire type STRING"));
+
+        // The path stack is unwound on the way out
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice());
+        assert!(WrongOuter::deserialize(&mut deserializer).is_err());
+        assert_eq!("", deserializer.current_path());
+    }
+
+    #[test]
+    fn trace_reads_of_truncated_document() {
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "hello".to_string() }).unwrap();
+
+        let (res, trace) = serde_epee::trace::from_bytes_traced::<Narrow>(&bytes);
+        assert_eq!(Narrow { ok: true }, res.unwrap());
+        let skipped = trace.0.iter().find(|record| record.label == TraceLabel::Skipped).unwrap();
+        assert_eq!(("note", 6), (skipped.path.as_str(), skipped.bytes.len()));
+
+        // Everything read before the input ran out is still there
+        let (res, trace) = serde_epee::trace::from_bytes_traced::<Narrow>(&bytes[..bytes.len() - 2]);
+        assert_eq!(ErrorKind::TruncatedCompound { expected: 2, got: 1 }, res.unwrap_err().kind());
+        let last = trace.0.last().unwrap();
+        assert_eq!((TraceLabel::Skipped, "note", 4), (last.label, last.path.as_str(), last.bytes.len()));
+        assert!(trace.to_string().contains("payload    ok = true"));
+    }
+
+    #[test]
+    fn errors_are_cloneable() {
+        fn assert_shareable<T: Clone + Send + Sync + std::error::Error>() {}
+        assert_shareable::<serde_epee::Error>();
+
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: "n".to_string() }).unwrap();
+        let err = serde_epee::from_bytes::<Narrow>(&mut &bytes[..5]).unwrap_err();
+        let cached = err.clone();
+        assert_eq!(err.to_string(), cached.to_string());
+        assert!(std::error::Error::source(&cached).is_some());
+    }
+
+    #[test]
+    fn migrate_from_newer_document() {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct ConfigV1 {
+            ok: bool
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct ConfigV2 {
+            ok: bool,
+            note: String
+        }
+
+        // Written by a newer version which already added "note"
+        let bytes = serde_epee::to_bytes(&WithNote { ok: true, note: String::from("hi") }).unwrap();
+        assert!(serde_epee::from_bytes::<ConfigV1>(&mut bytes.as_slice()).is_err());
+
+        let upgraded = serde_epee::migrate(&bytes, |old: ConfigV1| ConfigV2 { ok: old.ok, note: String::from("v2") }).unwrap();
+        let decoded: ConfigV2 = serde_epee::from_bytes(&mut upgraded.as_slice()).unwrap();
+        assert_eq!(ConfigV2 { ok: true, note: String::from("v2") }, decoded);
+    }
+
+    #[test]
+    fn varint_boundaries() {
+        let max = (1u64 << 62) - 1;
+        assert_eq!(max, u64::from(VarInt::from_reader(&mut &[0xffu8; 8][..]).unwrap()));
+        assert_eq!(VarInt::MAX, VarInt::try_from(max).unwrap());
+        assert!(VarInt::fits(max) && !VarInt::fits(max + 1));
+        assert_eq!(ErrorKind::VarIntOverflow, VarInt::try_from(max + 1).unwrap_err().kind());
+
+        // {"a": "..."} and {"a": [...]} with the largest possible declared lengths
+        let mut doc = hex::decode("0111010101010201010401610a").unwrap();
+        doc.extend_from_slice(&[0xff; 8]);
+        let err = serde_epee::from_bytes::<HashMap<String, String>>(&mut doc.as_slice()).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::StringTooLong { len, .. } if len == max));
+
+        doc[12] = 0x88;
+        let err = serde_epee::from_bytes::<HashMap<String, Vec<u8>>>(&mut doc.as_slice()).unwrap_err();
+        assert_eq!(ErrorKind::TruncatedCompound { expected: max, got: 0 }, err.kind());
+        assert_eq!(Some("a"), err.path());
+
+        // A root section declaring the most fields, cut short after one
+        let mut doc = hex::decode("011101010101020101").unwrap();
+        doc.extend_from_slice(&[0xff; 8]);
+        doc.extend_from_slice(&hex::decode("0161080701").unwrap());
+        let mut deserializer = Deserializer::from_slice(&doc).with_limits(Limits::unlimited());
+        let err = HashMap::<String, u8>::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(ErrorKind::TruncatedCompound { expected: max, got: 1 }, err.kind());
+    }
+
+    fn read_int<T: serde::de::DeserializeOwned, V: Serialize>(value: V, conversion: IntConversion) -> Result<T, ErrorKind> {
+        let bytes = serde_epee::to_bytes(&HashMap::from([("v", value)])).unwrap();
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_int_conversion(conversion);
+        HashMap::<String, T>::deserialize(&mut deserializer).map(|mut map| map.remove("v").unwrap()).map_err(|e| e.kind())
+    }
+
+    #[test]
+    fn integer_boundaries_and_sign_mismatches() {
+        use IntConversion::*;
+
+        assert_eq!(Ok(i64::MIN), read_int::<i64, _>(i64::MIN, Checked));
+        assert_eq!(Ok(u64::MAX), read_int::<u64, _>(u64::MAX, Checked));
+        assert_eq!(Ok(i8::MIN as i64), read_int::<i64, _>(i8::MIN, Checked));
+        assert_eq!(Ok(u64::MAX as i128), read_int::<i128, _>(u64::MAX, Checked));
+
+        assert_eq!(Err(ErrorKind::IntOutOfRange { value: i64::MIN as i128 }), read_int::<u64, _>(i64::MIN, Checked));
+        assert_eq!(Ok(1u64 << 63), read_int::<u64, _>(i64::MIN, Wrapping));
+        assert_eq!(Ok(0u64), read_int::<u64, _>(i64::MIN, Saturating));
+
+        assert_eq!(Err(ErrorKind::IntOutOfRange { value: u64::MAX as i128 }), read_int::<i64, _>(u64::MAX, Checked));
+        assert_eq!(Ok(-1i64), read_int::<i64, _>(u64::MAX, Wrapping));
+        assert_eq!(Ok(i64::MAX), read_int::<i64, _>(u64::MAX, Saturating));
+
+        assert_eq!(Ok(44u8), read_int::<u8, _>(300u16, Wrapping));
+        assert_eq!(Ok(255u8), read_int::<u8, _>(300u16, Saturating));
+        assert_eq!(Ok(i8::MIN), read_int::<i8, _>(-200i16, Saturating));
+        assert_eq!(Ok(5u128), read_int::<u128, _>(5u8, Saturating));
+        assert_eq!(Ok(u64::MAX as u128), read_int::<u128, _>(u64::MAX, Saturating));
+        assert_eq!(Ok(0u128), read_int::<u128, _>(-5i64, Saturating));
+        assert_eq!(Ok(i64::MIN as i128), read_int::<i128, _>(i64::MIN, Saturating));
+        assert_eq!(Err(ErrorKind::TypeMismatch), read_int::<u8, _>("1", Saturating));
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Blocks {
+        blocks: Vec<Inner>,
+        height: u64
+    }
+
+    #[test]
+    fn progress_reports() {
+        let blocks = Blocks { blocks: (0..100).map(|i| Inner { names: vec![format!("block {}", i)] }).collect(), height: 100 };
+        let bytes = serde_epee::to_bytes(&blocks).unwrap();
+
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice())
+            .with_progress(500, move |progress| sink.lock().unwrap().push(progress));
+        assert_eq!(blocks, Blocks::deserialize(&mut deserializer).unwrap());
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(bytes.len() as u64 / 500 + 1, reports.len() as u64);
+        assert!(reports.windows(2).all(|w| w[0].bytes_consumed < w[1].bytes_consumed && w[0].entries_parsed < w[1].entries_parsed));
+        let last = reports.last().unwrap();
+        assert!(last.done && !reports[0].done);
+        assert_eq!(bytes.len() as u64, last.bytes_consumed);
+        assert_eq!(302, last.entries_parsed); // 2 root fields, 100 elements, 100 names fields and 100 names elements
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Pair {
+        #[allow(dead_code)]
+        ports: [u16; 2]
+    }
+
+    #[test]
+    fn compound_counts_against_stream() {
+        let outer = Outer { inner: Inner { names: vec!["a".to_string(), "b".to_string(), "c".to_string()] } };
+        let bytes = serde_epee::to_bytes(&outer).unwrap();
+
+        // Cut off inside of the last name
+        let err = serde_epee::from_slice::<Outer>(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(ErrorKind::TruncatedCompound { expected: 3, got: 2 }, err.kind());
+        assert_eq!(Some("inner.names"), err.path());
+        assert_eq!(std::io::ErrorKind::UnexpectedEof, err.into_io().kind());
+
+        // Cut off between the names key and its value
+        let cut = bytes.iter().position(|&b| b == 0x8a).unwrap();
+        let err = serde_epee::from_slice::<Outer>(&bytes[..cut]).unwrap_err();
+        assert_eq!(ErrorKind::TruncatedCompound { expected: 1, got: 0 }, err.kind());
+        assert_eq!(Some("inner"), err.path());
+
+        // Arrays the visitor leaves entries of
+        let bytes = serde_epee::to_bytes(&HashMap::from([("ports", vec![1u16, 2, 3])])).unwrap();
+        let err = serde_epee::from_slice::<Pair>(&bytes).unwrap_err();
+        assert_eq!(ErrorKind::SurplusEntries { expected: 3, got: 2 }, err.kind());
+        assert_eq!(Some("ports"), err.path());
+    }
+
+    #[test]
+    fn root_lifecycle() {
+        let first = serde_epee::to_bytes(&HashMap::from([("ok", true)])).unwrap();
+        let empty = serde_epee::to_bytes(&HashMap::<String, bool>::new()).unwrap();
+
+        // Nothing can be read after the root section, not even units
+        for bytes in [&first, &empty] {
+            let mut deserializer = Deserializer::from_reader(bytes.as_slice());
+            HashMap::<String, bool>::deserialize(&mut deserializer).unwrap();
+            assert!(deserializer.is_done());
+            assert_eq!(ErrorKind::ExpectedEnd, Narrow::deserialize(&mut deserializer).unwrap_err().kind());
+            assert_eq!(ErrorKind::ExpectedEnd, <()>::deserialize(&mut deserializer).unwrap_err().kind());
+            assert_eq!(ErrorKind::ExpectedEnd, Option::<u8>::deserialize(&mut deserializer).unwrap_err().kind());
+            assert_eq!(ErrorKind::ExpectedEnd, serde::de::IgnoredAny::deserialize(&mut deserializer).unwrap_err().kind());
+        }
+
+        // Unless more documents may follow
+        let mut concatenated = [first.clone(), empty.clone(), first.clone()].concat();
+        let mut deserializer = Deserializer::from_reader(concatenated.as_slice()).with_multi_doc();
+        assert_eq!(Narrow { ok: true }, Narrow::deserialize(&mut deserializer).unwrap());
+        assert!(HashMap::<String, bool>::deserialize(&mut deserializer).unwrap().is_empty());
+        assert_eq!(Narrow { ok: true }, Narrow::deserialize(&mut deserializer).unwrap());
+
+        let docs: Vec<HashMap<String, bool>> = Deserializer::from_reader(concatenated.as_slice())
+            .documents()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec![1, 0, 1], docs.iter().map(HashMap::len).collect::<Vec<_>>());
+
+        // Input ending inside of a document is still an error, after which the iterator stops
+        concatenated.pop();
+        let mut docs = Deserializer::from_reader(concatenated.as_slice()).documents::<HashMap<String, bool>>();
+        assert!(docs.next().unwrap().is_ok());
+        assert!(docs.next().unwrap().is_ok());
+        assert!(docs.next().unwrap().is_err());
+        assert!(docs.next().is_none());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+    struct Nothing {}
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+    struct Empties {
+        text: String,
+        #[serde(with = "serde_bytes")]
+        blob: Vec<u8>,
+        texts: Vec<String>,
+        blobs: Vec<serde_bytes::ByteBuf>,
+        nothing: Nothing,
+        nothings: Vec<Nothing>,
+        map: HashMap<String, String>,
+        maps: Vec<HashMap<String, Nothing>>
+    }
+
+    #[test]
+    fn zero_length_strings_blobs_and_sections() {
+        let empties = Empties {
+            texts: vec![String::new(), "x".to_string(), String::new()],
+            blobs: vec![serde_bytes::ByteBuf::new(); 2],
+            nothings: vec![Nothing {}, Nothing {}],
+            map: HashMap::from([("k".to_string(), String::new())]),
+            maps: vec![HashMap::new(), HashMap::from([("n".to_string(), Nothing {})])],
+            ..Default::default()
+        };
+        let bytes = serde_epee::to_bytes(&empties).unwrap();
+
+        // A zero length string is its type code and a zero length varint
+        let text_entry = [&[4u8][..], b"text", &[constants::SERIALIZE_TYPE_STRING, 0]].concat();
+        assert!(bytes.windows(text_entry.len()).any(|window| window == text_entry));
+
+        assert_eq!(empties, serde_epee::from_slice(&bytes).unwrap());
+        assert_eq!(empties, serde_epee::from_reader(bytes.as_slice()).unwrap());
+        let mut scratch = [0u8; 8];
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_scratch(&mut scratch[..]);
+        assert_eq!(empties, Empties::deserialize(&mut deserializer).unwrap());
+
+        // Same through the dynamic model, and for empty root sections
+        let section = serde_epee::section::from_bytes(&bytes).unwrap();
+        assert_eq!(Some(&b""[..]), section["text"].as_bytes());
+        assert_eq!(Some(0), section["nothing"].as_section().map(|nothing| nothing.len()));
+        assert_eq!(section, serde_epee::section::from_bytes(&serde_epee::section::to_bytes(&section).unwrap()).unwrap());
+        let root = serde_epee::to_bytes(&Nothing {}).unwrap();
+        assert_eq!(Nothing {}, serde_epee::from_slice(&root).unwrap());
+        assert!(serde_epee::section::from_bytes(&root).unwrap().is_empty());
+
+        // Keys are the one place where empty strings are still rejected
+        let empty_key = HashMap::from([(String::new(), 1u8)]);
+        assert_eq!(ErrorKind::EmptySectionKey, serde_epee::to_bytes(&empty_key).unwrap_err().kind());
+        let mut empty_key = root.clone();
+        empty_key.truncate(empty_key.len() - 1);
+        empty_key.extend_from_slice(&[1 << 2, 0, constants::SERIALIZE_TYPE_UINT8, 1]);
+        assert_eq!(ErrorKind::EmptySectionKey, serde_epee::from_slice::<HashMap<String, u8>>(&empty_key).unwrap_err().kind());
+        assert_eq!(ErrorKind::EmptySectionKey, serde_epee::section::from_bytes(&empty_key).unwrap_err().kind());
+    }
+
+    #[test]
+    fn unknown_type_codes() {
+        // {"x": <type code>, then whatever follows it, "ok": true}
+        let doc = |entry: &[u8]| {
+            let mut doc = constants::PORTABLE_STORAGE_SIGNATURE.to_vec();
+            doc.extend_from_slice(&[2 << 2, 1, b'x']);
+            doc.extend_from_slice(entry);
+            doc.extend_from_slice(&[2, b'o', b'k', constants::SERIALIZE_TYPE_BOOL, 1]);
+            doc
+        };
+
+        // A lone zero can't be framed, wherever it is
+        let zero = doc(&[constants::SERIALIZE_TYPE_UNKNOWN]);
+        assert_eq!(ErrorKind::UnknownTypeCode, serde_epee::from_slice::<Narrow>(&zero).unwrap_err().kind());
+        assert_eq!(ErrorKind::UnknownTypeCode, serde_epee::from_reader_lenient::<Narrow, _>(zero.as_slice()).unwrap_err().kind());
+        assert_eq!(ErrorKind::UnknownTypeCode, serde_epee::section::from_bytes(&zero).unwrap_err().kind());
+
+        // Empty arrays of unknown type have nothing to skip
+        let empty = doc(&[constants::EMPTY_UNKNOWN_ARRAY_TYPE_CODE, 0]);
+        assert_eq!(Narrow { ok: true }, serde_epee::from_slice(&empty).unwrap());
+        assert_eq!(Narrow { ok: true }, serde_epee::from_reader_lenient(empty.as_slice()).unwrap());
+        let section = serde_epee::section::from_bytes(&empty).unwrap();
+        assert_eq!((1, Some(true)), (section.len(), section["ok"].as_bool()));
+        assert!(skip_entry(&mut &[0u8][..], constants::EMPTY_UNKNOWN_ARRAY_TYPE_CODE).is_ok());
+
+        // Unless they aren't empty
+        let full = doc(&[constants::EMPTY_UNKNOWN_ARRAY_TYPE_CODE, 1 << 2, 0]);
+        assert_eq!(ErrorKind::UnknownTypeCode, serde_epee::from_reader_lenient::<Narrow, _>(full.as_slice()).unwrap_err().kind());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Borrowing<'a> {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+        #[serde(borrow)]
+        blob: Cow<'a, [u8]>
+    }
+
+    #[test]
+    fn cow_fields_borrow_from_slices() {
+        let bytes = serde_epee::to_bytes(&HashMap::from([("name", "peer"), ("blob", "\u{1}\u{2}")])).unwrap();
+
+        let from_slice: Borrowing = serde_epee::from_slice(&bytes).unwrap();
+        assert!(matches!((&from_slice.name, &from_slice.blob), (Cow::Borrowed("peer"), Cow::Borrowed([1, 2]))));
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice());
+        let from_stream = Borrowing::deserialize(&mut deserializer).unwrap();
+        assert!(matches!((&from_stream.name, &from_stream.blob), (Cow::Owned(_), Cow::Owned(_))));
+        assert_eq!(from_slice, from_stream);
+
+        // Plain references work too, as long as the input is a slice
+        let names: HashMap<String, &str> = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!("peer", names["name"]);
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice());
+        assert!(HashMap::<String, &str>::deserialize(&mut deserializer).is_err());
+
+        // Invalid UTF-8 is a blob, not a str, either way
+        let bytes = serde_epee::to_bytes(&HashMap::from([("name", serde_bytes::Bytes::new(b"\xff")), ("blob", serde_bytes::Bytes::new(b"\xff"))])).unwrap();
+        assert_eq!(Some("name"), serde_epee::from_slice::<Borrowing>(&bytes).unwrap_err().path());
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice());
+        assert_eq!(Some("name"), Borrowing::deserialize(&mut deserializer).unwrap_err().path());
+        let blobs: HashMap<String, Cow<[u8]>> = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!(&[0xff][..], &*blobs["name"]);
+    }
+
+    #[derive(Serialize)]
+    struct EmptySections {
+        height: HashMap<String, u32>,
+        ok: HashMap<String, u32>,
+        name: HashMap<String, u32>,
+        txs: HashMap<String, u32>,
+        fee: HashMap<String, u32>,
+        extra: HashMap<String, u32>
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Defaulted<'a> {
+        height: u64,
+        ok: bool,
+        name: String,
+        txs: Vec<String>,
+        fee: Option<u64>,
+        #[serde(borrow)]
+        extra: &'a [u8]
+    }
+
+    #[test]
+    fn empty_sections_as_defaults() {
+        let empty = EmptySections {
+            height: HashMap::new(),
+            ok: HashMap::new(),
+            name: HashMap::new(),
+            txs: HashMap::new(),
+            fee: HashMap::new(),
+            extra: HashMap::new()
+        };
+        let bytes = serde_epee::to_bytes(&empty).unwrap();
+        let expected = Defaulted { height: 0, ok: false, name: String::new(), txs: Vec::new(), fee: None, extra: &[] };
+
+        let mut deserializer = Deserializer::from_slice(&bytes).with_empty_section_defaults();
+        assert_eq!(expected, Defaulted::deserialize(&mut deserializer).unwrap());
+        assert!(deserializer.is_done());
+
+        // Off by default
+        let err = serde_epee::from_slice::<Defaulted>(&bytes).unwrap_err();
+        assert_eq!(ErrorKind::TypeMismatch, err.kind());
+
+        // Sections with fields in them aren't defaults
+        let mut full = empty;
+        full.height.insert(String::from("x"), 1);
+        let bytes = serde_epee::to_bytes(&full).unwrap();
+        let mut deserializer = Deserializer::from_slice(&bytes).with_empty_section_defaults();
+        let err = Defaulted::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(Some("height"), err.path());
+
+        // Also inside arrays of sections
+        let bytes = serde_epee::to_bytes(&HashMap::from([("heights", vec![HashMap::<String, u32>::new(); 2])])).unwrap();
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_empty_section_defaults();
+        let heights = HashMap::<String, Vec<u32>>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(vec![0, 0], heights["heights"]);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Counters {
+        port: NonZeroU32,
+        height: Option<NonZeroU64>,
+        seq: Wrapping<u32>,
+        delta: Wrapping<i64>
+    }
+
+    #[test]
+    fn nonzero_and_wrapping_fields() {
+        let counters = Counters {
+            port: NonZeroU32::new(18080).unwrap(),
+            height: NonZeroU64::new(3000000),
+            seq: Wrapping(u32::MAX) + Wrapping(2),
+            delta: Wrapping(-5)
+        };
+        let bytes = serde_epee::to_bytes(&counters).unwrap();
+        let section = serde_epee::section::from_bytes(&bytes).unwrap();
+        assert_eq!((Some(18080), Some(1), Some(-5)), (section["port"].as_u64(), section["seq"].as_u64(), section["delta"].as_i64()));
+        assert_eq!(counters, serde_epee::from_slice(&bytes).unwrap());
+        assert_eq!(counters, serde_epee::from_section::<Counters>(section).unwrap());
+
+        // Zero is out of range for nonzero fields, whatever the wire type
+        let zero = serde_epee::to_bytes(&HashMap::from([("port", 0u8), ("seq", 1), ("delta", 1)])).unwrap();
+        let err = serde_epee::from_slice::<Counters>(&zero).unwrap_err();
+        assert_eq!((ErrorKind::IntOutOfRange { value: 0 }, Some("port")), (err.kind(), err.path()));
+        let err = serde_epee::from_section::<Counters>(serde_epee::section::from_bytes(&zero).unwrap()).unwrap_err();
+        assert_eq!(ErrorKind::IntOutOfRange { value: 0 }, err.kind());
+
+        // Other integers rejected by a visitor aren't, e.g. variant indexes
+        let unexp = serde::de::Unexpected::Unsigned(5);
+        let err = <serde_epee::Error as serde::de::Error>::invalid_value(unexp, &"variant index 0 <= i < 2");
+        assert_eq!(ErrorKind::Custom, err.kind());
+
+        // Packed too
+        let packed = serde_epee::packed::to_packed_bytes(&(counters.port, counters.seq)).unwrap();
+        assert_eq!((counters.port, counters.seq), serde_epee::packed::from_packed_bytes(&packed).unwrap());
+        assert!(serde_epee::packed::from_packed_bytes::<(NonZeroU32, Wrapping<u32>)>(&[0; 8]).is_err());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Untyped {
+        Unsigned(u64),
+        Signed(i64),
+        Text(String),
+        List(Vec<Untyped>)
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Amounts {
+        amount: u64,
+        change: i64,
+        outputs: Vec<u64>
+    }
+
+    #[test]
+    fn js_safe_ints() {
+        let big = serde_epee::de::JS_MAX_SAFE_INTEGER + 1;
+        let amounts = Amounts { amount: big, change: -(big as i64), outputs: vec![1, u64::MAX] };
+        let bytes = serde_epee::to_bytes(&amounts).unwrap();
+
+        // Untyped visitors, like serde_json::Value, get strings for integers
+        // JavaScript can't hold
+        let mut deserializer = Deserializer::from_slice(&bytes).with_js_safe_ints();
+        let untyped = HashMap::<String, Untyped>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(Untyped::Text(big.to_string()), untyped["amount"]);
+        assert_eq!(Untyped::Text(format!("-{}", big)), untyped["change"]);
+        assert_eq!(Untyped::List(vec![Untyped::Unsigned(1), Untyped::Text(u64::MAX.to_string())]), untyped["outputs"]);
+        let plain: HashMap<String, Untyped> = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!(Untyped::Unsigned(big), plain["amount"]);
+
+        // Typed fields are unaffected, and take the strings back
+        let mut deserializer = Deserializer::from_slice(&bytes).with_js_safe_ints();
+        assert_eq!(amounts, Amounts::deserialize(&mut deserializer).unwrap());
+        let mut doc = Section::from_pairs([
+            ("amount", SectionEntry::from(big.to_string())),
+            ("change", SectionEntry::from(format!("-{}", big))),
+            ("outputs", SectionEntry::from(vec![String::from("1"), u64::MAX.to_string()]))
+        ]);
+        let bytes = serde_epee::to_bytes(&doc).unwrap();
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_js_safe_ints();
+        assert_eq!(amounts, Amounts::deserialize(&mut deserializer).unwrap());
+        assert!(serde_epee::from_slice::<Amounts>(&bytes).is_err());
+
+        // and count them against the string limit like any other strings
+        let limits = Limits { max_strings: 3, ..Limits::monerod() };
+        let mut deserializer = Deserializer::from_slice(&bytes).with_js_safe_ints().with_limits(limits);
+        let err = Amounts::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(ErrorKind::TooManyStrings { count: 4, max: 3 }, err.kind());
+
+        // The strings still have to be integers which fit
+        doc.insert(String::from("amount"), "12 XMR".into());
+        let bytes = serde_epee::to_bytes(&doc).unwrap();
+        let mut deserializer = Deserializer::from_slice(&bytes).with_js_safe_ints();
+        let err = Amounts::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!((ErrorKind::TypeMismatch, Some("amount")), (err.kind(), err.path()));
+        doc.insert(String::from("amount"), "-1".into());
+        let bytes = serde_epee::to_bytes(&doc).unwrap();
+        let mut deserializer = Deserializer::from_slice(&bytes).with_js_safe_ints();
+        assert_eq!(ErrorKind::IntOutOfRange { value: -1 }, Amounts::deserialize(&mut deserializer).unwrap_err().kind());
+    }
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "derive")]
+
+use serde::{Serialize, Deserialize};
+use serde_epee::{EpeeObject, epee_object};
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Hash = [u8; 32];
+
+    #[epee_object]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct ChainResponse {
+        #[epee(pod)]
+        top_block_hash: Hash,
+        #[epee(pod_list)]
+        m_block_ids: Vec<Hash>,
+        #[epee(blob)]
+        first_block: Vec<u8>,
+        #[serde(default, rename = "current_height")]
+        height: u64,
+        #[serde(skip)]
+        cached: bool,
+        r#type: u8
+    }
+
+    // Same wire layout, spelled out by hand
+    #[derive(Serialize)]
+    struct HandWritten<'a> {
+        #[serde(with = "serde_epee::interop::pod")]
+        top_block_hash: Hash,
+        #[serde(with = "serde_epee::interop::pod_list")]
+        m_block_ids: &'a [Hash],
+        #[serde(with = "serde_bytes")]
+        first_block: &'a [u8],
+        current_height: u64,
+        r#type: u8
+    }
+
+    #[test]
+    fn attributes_expand_to_adapters() {
+        let response = ChainResponse {
+            top_block_hash: [1; 32],
+            m_block_ids: vec![[2; 32], [3; 32]],
+            first_block: vec![4, 5, 6],
+            height: 7,
+            cached: false,
+            r#type: 8
+        };
+        let bytes = serde_epee::to_bytes(&response).unwrap();
+        let hand_written = HandWritten {
+            top_block_hash: response.top_block_hash,
+            m_block_ids: &response.m_block_ids,
+            first_block: &response.first_block,
+            current_height: 7,
+            r#type: 8
+        };
+        assert_eq!(serde_epee::to_bytes(&hand_written).unwrap(), bytes);
+        assert_eq!(response, serde_epee::from_slice(&bytes).unwrap());
+    }
+
+    #[test]
+    fn keys_follow_renames_and_skips() {
+        assert_eq!(["top_block_hash", "m_block_ids", "first_block", "current_height", "type"], ChainResponse::KEYS);
+    }
+
+    #[epee_object]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct Renamed {
+        block_hash: u8,
+        #[serde(rename(deserialize = "tx_count"))]
+        tx_count: u8,
+        #[serde(rename = "HEIGHT")]
+        height: u8
+    }
+
+    #[epee_object]
+    #[derive(Serialize)]
+    #[serde(rename_all(serialize = "SCREAMING-KEBAB-CASE"))]
+    struct Shouting {
+        top_block_hash: u8
+    }
+
+    #[test]
+    fn keys_follow_rename_all() {
+        assert_eq!(["blockHash", "txCount", "HEIGHT"], Renamed::KEYS);
+        assert_eq!(["TOP-BLOCK-HASH"], Shouting::KEYS);
+
+        // Same keys as serde writes
+        let renamed = Renamed { block_hash: 1, tx_count: 2, height: 3 };
+        let section = serde_epee::section::from_bytes(&serde_epee::to_bytes(&renamed).unwrap()).unwrap();
+        let mut keys: Vec<&str> = section.keys().map(String::as_str).collect();
+        keys.sort();
+        let mut expected = Renamed::KEYS.to_vec();
+        expected.sort();
+        assert_eq!(expected, keys);
+        let section = serde_epee::section::from_bytes(&serde_epee::to_bytes(&Shouting { top_block_hash: 1 }).unwrap()).unwrap();
+        assert!(section.contains_key("TOP-BLOCK-HASH"));
+    }
+
+    #[test]
+    fn invalid_objects_fail_to_compile() {
+        common::assert_compile_errors("ui_derive", &[
+            ("long_deserialize_key.rs", "EPEE section keys must be 1 to 255 bytes long"),
+            ("long_field_key.rs", "EPEE section keys must be 1 to 255 bytes long"),
+            ("tuple_struct.rs", "#[epee_object] structs need named fields"),
+            ("unknown_adapter.rs", "unknown EPEE adapter, expected one of: blob, pod, pod_list"),
+            ("unknown_rename_rule.rs", "unknown rename_all rule, expected one of: lowercase")
+        ]);
+    }
+}
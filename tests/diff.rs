@@ -0,0 +1,65 @@
+use std::process::Command;
+
+use serde_epee::diff::{diff, Change};
+use serde_epee::section::{SectionArray, SectionEntry};
+use serde_epee::Section;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(port: u16) -> Section {
+        Section::from_pairs([("port", SectionEntry::UInt16(port))])
+    }
+
+    fn response(height: SectionEntry, ports: &[u16]) -> Section {
+        let peers = ports.iter().map(|&port| peer(port)).collect();
+        Section::from_pairs([
+            ("status", SectionEntry::from("OK")),
+            ("height", height),
+            ("peers", SectionEntry::Array(SectionArray::Object(peers)))
+        ])
+    }
+
+    #[test]
+    fn structural_changes() {
+        let first = response(SectionEntry::UInt32(5), &[18080, 18081]);
+        let mut second = response(SectionEntry::UInt64(5), &[18080, 28080, 38080]);
+        second.insert("top_hash".to_string(), SectionEntry::from(&[0xab, 0xcd][..]));
+        second.insert("status".to_string(), SectionEntry::Nil);
+
+        assert!(diff(&first, &first).is_empty());
+        let changes = diff(&first, &second);
+        let lines: Vec<String> = changes.iter().map(Change::to_string).collect();
+        assert_eq!(vec![
+            "~ height: UINT32 5 -> UINT64 5",
+            "~ peers[1].port: 18081 -> 28080",
+            "+ peers[2]: OBJECT {1 fields}",
+            "- status: STRING \"OK\"",
+            "+ top_hash: STRING 0xabcd"
+        ], lines);
+        assert!(matches!(&changes[0], Change::TypeChanged { .. }));
+        assert_eq!("peers[1].port", changes[1].path());
+    }
+
+    #[test]
+    fn tool_diff_command() {
+        let dir = std::env::temp_dir().join(format!("serde_epee_diff_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (first_path, second_path) = (dir.join("first.dat"), dir.join("second.dat"));
+        response(SectionEntry::UInt64(5), &[18080]).save(&first_path).unwrap();
+        response(SectionEntry::UInt64(6), &[18080]).save(&second_path).unwrap();
+
+        let tool = env!("CARGO_BIN_EXE_epee-tool");
+        let output = Command::new(tool).arg("diff").arg(&first_path).arg(&second_path).output().unwrap();
+        assert_eq!(Some(1), output.status.code());
+        assert_eq!("~ height: 5 -> 6\n", String::from_utf8_lossy(&output.stdout));
+
+        let output = Command::new(tool).arg("diff").arg(&first_path).arg(&first_path).output().unwrap();
+        assert_eq!(Some(0), output.status.code());
+        let output = Command::new(tool).arg("diff").arg(&first_path).arg(dir.join("missing.dat")).output().unwrap();
+        assert_eq!(Some(2), output.status.code());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,41 @@
+use serde_epee::testing::{self, DocumentGen};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The generated documents must survive our own codec before they mean
+    // anything to the reference
+    #[test]
+    fn generated_documents_round_trip() {
+        for seed in 0..200 {
+            let section = DocumentGen::new(seed).section(4);
+            let bytes = serde_epee::to_bytes(&section).unwrap();
+            let (decoded, errors) = serde_epee::from_bytes_lossy(&bytes);
+            assert!(errors.is_empty(), "seed {}: {:?}", seed, errors);
+            assert_eq!(section, decoded, "seed {}", seed);
+        }
+    }
+
+    // Set EPEE_REFERENCE to a program which loads a document from stdin with
+    // epee's portable_storage and stores it to stdout, then run with --ignored
+    #[test]
+    #[ignore]
+    fn reference_agrees() {
+        let reference = std::env::var("EPEE_REFERENCE").expect("EPEE_REFERENCE is not set");
+        let seeds: u64 = std::env::var("EPEE_REFERENCE_SEEDS").ok().and_then(|n| n.parse().ok()).unwrap_or(1000);
+        for seed in 0..seeds {
+            testing::assert_reference_agrees(&reference, &DocumentGen::new(seed).section(4));
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reference_mismatch_is_reported() {
+        let section = DocumentGen::new(7).section(2);
+        testing::assert_reference_agrees("cat", &section);
+        assert!(testing::run_reference("false", b"").is_err());
+        let res = std::panic::catch_unwind(|| testing::assert_reference_agrees("true", &section));
+        assert!(res.is_err());
+    }
+}
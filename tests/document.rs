@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_epee::{from_reader_buffered, Document, ErrorKind};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Status {
+        height: u64,
+        status: String
+    }
+
+    #[test]
+    fn buffered_documents_from_a_stream() {
+        let first = Status { height: 1, status: "OK".to_string() };
+        let second = Status { height: 2, status: "BUSY".to_string() };
+        let mut stream = serde_epee::to_bytes(&first).unwrap();
+        stream.extend(serde_epee::to_bytes(&second).unwrap());
+        let mut reader = stream.as_slice();
+
+        let doc: Document<Status> = from_reader_buffered(&mut reader).unwrap();
+        assert_eq!(first, doc.value().unwrap());
+        assert_eq!(2, doc.decode::<HashMap<String, serde_epee::section::SectionEntry>>().unwrap().len());
+        assert_eq!(serde_epee::to_bytes(&first).unwrap(), doc.as_bytes());
+
+        let doc = from_reader_buffered::<Status, _>(&mut reader).unwrap();
+        assert_eq!(second, doc.value().unwrap());
+        assert!(reader.is_empty());
+
+        let truncated = &doc.as_bytes()[..doc.as_bytes().len() - 1];
+        assert_eq!(ErrorKind::IOError, from_reader_buffered::<Status, _>(truncated).unwrap_err().kind());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct BorrowedStatus<'a> {
+        height: u64,
+        status: &'a str
+    }
+
+    #[derive(Serialize)]
+    struct Height {
+        height: u64
+    }
+
+    #[derive(Deserialize)]
+    struct TextHeight<'a> {
+        #[allow(dead_code)]
+        height: &'a str
+    }
+
+    #[test]
+    fn documents_lend_values() {
+        let bytes = serde_epee::to_bytes(&Status { height: 3, status: "OK".to_string() }).unwrap();
+        let doc: Document<BorrowedStatus> = from_reader_buffered(bytes.as_slice()).unwrap();
+        let status = doc.value().unwrap();
+        assert_eq!(BorrowedStatus { height: 3, status: "OK" }, status);
+        assert!(doc.as_bytes().as_ptr_range().contains(&status.status.as_ptr()));
+
+        // Wire types are checked against the type up front
+        let bytes = serde_epee::to_bytes(&Height { height: 3 }).unwrap();
+        let err = from_reader_buffered::<TextHeight, _>(bytes.as_slice()).unwrap_err();
+        assert_eq!(ErrorKind::TypeMismatch, err.kind());
+        assert_eq!(Some("height"), err.path());
+
+        // Missing fields are left to the type
+        let doc = from_reader_buffered::<BorrowedStatus, _>(bytes.as_slice()).unwrap();
+        assert!(doc.value().is_err());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Defaulted {
+        height: u64,
+        #[serde(default)]
+        status: String
+    }
+
+    #[test]
+    fn documents_with_default_fields() {
+        let bytes = serde_epee::to_bytes(&Height { height: 3 }).unwrap();
+        let doc: Document<Defaulted> = from_reader_buffered(bytes.as_slice()).unwrap();
+        assert_eq!(Defaulted { height: 3, status: String::new() }, doc.value().unwrap());
+    }
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+use serde_epee::dump::{dump, DumpOptions};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Inner {
+        id: u16,
+        deeper: Deeper
+    }
+
+    #[derive(Serialize)]
+    struct Deeper {
+        x: i8
+    }
+
+    #[derive(Serialize)]
+    struct Doc {
+        name: String,
+        #[serde(with = "serde_bytes")]
+        blob: Vec<u8>,
+        items: Vec<Inner>,
+        ok: bool
+    }
+
+    #[test]
+    fn streaming_dump() {
+        let doc = Doc {
+            name: "hello".to_string(),
+            blob: vec![0, 1, 2, 3, 4, 5],
+            items: vec![Inner { id: 7, deeper: Deeper { x: -1 } }],
+            ok: true
+        };
+        let bytes = serde_epee::to_bytes(&doc).unwrap();
+
+        let mut out = Vec::new();
+        let options = DumpOptions { max_blob_preview: 4, max_depth: 2, ..DumpOptions::default() };
+        dump(bytes.as_slice(), &mut out, &options).unwrap();
+
+        let expected = "\
+name: STRING(5) \"hell\"...
+blob: STRING(6) 0x00010203...
+items: ARRAY<OBJECT> (1 elements)
+  [0] OBJECT
+    id: UINT16 7
+    deeper: OBJECT {...}
+ok: BOOL true
+";
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+
+        let mut out = Vec::new();
+        assert!(dump(&bytes[..bytes.len() - 1], &mut out, &DumpOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Serialize, Deserialize};
+use serde_epee::envelope::{self, Checksum};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Cache {
+        height: u64,
+        blob: String
+    }
+
+    #[test]
+    fn checksum_vectors() {
+        assert_eq!(0xcbf43926, envelope::crc32(b"123456789"));
+        assert_eq!(0xef46db3751d8e999, envelope::xxhash64(b""));
+        assert_eq!(0x44bc2cf5ad770999, envelope::xxhash64(b"abc"));
+        assert_eq!(0xfbcea83c8a378bf1, envelope::xxhash64(b"Nobody inspects the spammish repetition"));
+    }
+
+    #[test]
+    fn envelope_detects_corruption_and_truncation() {
+        let cache = Cache { height: 3000000, blob: "x".repeat(100) };
+
+        for checksum in [Checksum::Crc32, Checksum::XxHash64] {
+            let enveloped = envelope::to_bytes_enveloped(&cache, checksum).unwrap();
+            assert_eq!(cache, envelope::from_slice_enveloped::<Cache>(&enveloped).unwrap());
+            assert_eq!(cache, envelope::from_reader_enveloped::<Cache, _>(enveloped.as_slice(), 1000).unwrap());
+
+            let mut rotten = enveloped.clone();
+            *rotten.last_mut().unwrap() ^= 0x10;
+            let err = envelope::from_slice_enveloped::<Cache>(&rotten).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::ChecksumMismatch { .. }));
+
+            let len = enveloped.len() as u64 - envelope::ENVELOPE_HEADER_SIZE as u64;
+            let truncated = &enveloped[..enveloped.len() - 5];
+            let err = envelope::from_reader_enveloped::<Cache, _>(truncated, 1000).unwrap_err();
+            assert_eq!(ErrorKind::EnvelopeTruncated { len, found: len - 5 }, err.kind());
+
+            let err = envelope::from_reader_enveloped::<Cache, _>(enveloped.as_slice(), 10).unwrap_err();
+            assert_eq!(ErrorKind::BudgetExceeded { max_bytes: 10 }, err.kind());
+        }
+
+        let plain = serde_epee::to_bytes(&cache).unwrap();
+        assert_eq!(ErrorKind::ExpectedFormatSignature, envelope::from_slice_enveloped::<Cache>(&plain).unwrap_err().kind());
+    }
+}
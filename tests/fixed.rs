@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use serde_epee::Fixed;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fixed is sent as DOUBLE, which no-float rejects
+    #[cfg(not(feature = "no-float"))]
+    #[test]
+    fn fixed_point_doubles() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Fee {
+            fee: Fixed<12>
+        }
+
+        // Fees of forks which send them as XMR instead of atomic units
+        let bytes = serde_epee::to_bytes(&HashMap::from([("fee", 0.000123456789f64)])).unwrap();
+        let fee: Fee = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!(123456789, fee.fee.mantissa);
+        assert_eq!("0.000123456789", fee.fee.to_string());
+        assert_eq!(bytes, serde_epee::to_bytes(&fee).unwrap());
+
+        let bytes = serde_epee::to_bytes(&HashMap::from([("fee", 18446744.5f64)])).unwrap();
+        let err = serde_epee::from_slice::<Fee>(&bytes).unwrap_err();
+        assert_eq!(serde_epee::ErrorKind::Custom, err.kind());
+
+        // Amounts with more decimals than the scale don't round
+        let bytes = serde_epee::to_bytes(&HashMap::from([("fee", 0.15f64)])).unwrap();
+        assert_eq!(Fixed::<2>::new(15), serde_epee::from_slice::<HashMap<String, Fixed<2>>>(&bytes).unwrap()["fee"]);
+        assert!(serde_epee::from_slice::<HashMap<String, Fixed<1>>>(&bytes).is_err());
+
+        // Integers scale up
+        let bytes = serde_epee::to_bytes(&HashMap::from([("fee", 3u32)])).unwrap();
+        assert_eq!(3_000_000_000_000, serde_epee::from_slice::<Fee>(&bytes).unwrap().fee.mantissa);
+        let bytes = serde_epee::to_bytes(&HashMap::from([("fee", 10_000u32)])).unwrap();
+        assert!(serde_epee::from_slice::<Fee>(&bytes).is_err());
+        let bytes = serde_epee::to_bytes(&HashMap::from([("fee", i64::MIN)])).unwrap();
+        assert!(serde_epee::from_slice::<HashMap<String, Fixed<0>>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn fixed_point_conversions() {
+        assert_eq!(Some(Fixed::<3>::new(-1500)), Fixed::from_f64(-1.5));
+        assert_eq!(None, Fixed::<3>::from_f64(0.0005));
+        assert_eq!(None, Fixed::<3>::from_f64(f64::NAN));
+        assert_eq!(None, Fixed::<0>::from_f64(1e300));
+        assert_eq!("-1.500", Fixed::<3>::new(-1500).to_string());
+        assert_eq!("7", Fixed::<0>::new(7).to_string());
+        assert_eq!("0.05", Fixed::<2>::new(5).to_string());
+        assert!(serde_epee::to_bytes(&HashMap::from([("fee", Fixed::<0>::new(i64::MAX))])).is_err());
+        assert_eq!(None, Fixed::<0>::from_integer(i64::MIN));
+        assert!(serde_epee::to_bytes(&HashMap::from([("fee", Fixed::<0>::new(i64::MIN))])).is_err());
+    }
+}
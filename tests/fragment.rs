@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_epee::section::SectionEntry;
+use serde_epee::{constants, ErrorKind, RawEntries, RawFragment};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Notification {
+        kind: String,
+        height: u64,
+        block: HashMap<String, Vec<u32>>
+    }
+
+    #[test]
+    fn raw_top_level_entries() {
+        let notification = Notification {
+            kind: "new_block".to_string(),
+            height: 3000000,
+            block: HashMap::from([("txs".to_string(), vec![1, 2, 3])])
+        };
+        let bytes = serde_epee::to_bytes(&notification).unwrap();
+
+        let entries = RawEntries::new(&bytes).unwrap();
+        assert_eq!(3, entries.remaining());
+        let entries: Vec<_> = entries.collect::<Result<_, _>>().unwrap();
+        assert_eq!(vec!["kind", "height", "block"], entries.iter().map(|(key, _, _)| *key).collect::<Vec<_>>());
+
+        let (_, type_code, payload) = entries[1];
+        assert_eq!(constants::SERIALIZE_TYPE_UINT64, type_code);
+        assert_eq!(SectionEntry::UInt64(3000000), serde_epee::value::from_raw_bytes(type_code, payload).unwrap());
+
+        // Subtrees come back exactly as they were encoded
+        let (_, type_code, payload) = entries[2];
+        let block = RawFragment::from_value(&notification.block).unwrap();
+        assert_eq!((block.type_code(), block.payload()), (type_code, payload));
+    }
+
+    #[test]
+    fn raw_entries_of_bad_documents() {
+        let bytes = serde_epee::to_bytes(&HashMap::from([("a", 1u8), ("b", 2u8)])).unwrap();
+
+        // Truncated documents fail at the entry which was cut off, then stop
+        let mut entries = RawEntries::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(entries.next().unwrap().is_ok());
+        assert_eq!(ErrorKind::IOError, entries.next().unwrap().unwrap_err().kind());
+        assert!(entries.next().is_none());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let res: Result<Vec<_>, _> = RawEntries::new(&trailing).unwrap().collect();
+        assert_eq!(ErrorKind::ExpectedEnd, res.unwrap_err().kind());
+
+        assert_eq!(ErrorKind::ExpectedFormatSignature, RawEntries::new(&bytes[1..]).err().unwrap().kind());
+    }
+}
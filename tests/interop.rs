@@ -0,0 +1,109 @@
+use serde::{Serialize, Deserialize};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Shaped like a fixed-hash type, e.g. monero::Hash
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    struct Hash([u8; 32]);
+
+    impl AsRef<[u8]> for Hash {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl TryFrom<&[u8]> for Hash {
+        type Error = std::array::TryFromSliceError;
+
+        fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+            Ok(Self(bytes.try_into()?))
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct ChainResponse {
+        #[serde(with = "serde_epee::interop::pod")]
+        top_block_hash: Hash,
+        #[serde(with = "serde_epee::interop::pod_list")]
+        block_ids: Vec<Hash>,
+        #[serde(with = "serde_epee::interop::pod")]
+        key: [u8; 32]
+    }
+
+    #[test]
+    fn pod_adapters_round_trip() {
+        let response = ChainResponse {
+            top_block_hash: Hash([1; 32]),
+            block_ids: vec![Hash([2; 32]), Hash([3; 32])],
+            key: [4; 32]
+        };
+
+        let bytes = serde_epee::to_bytes(&response).unwrap();
+        assert!(bytes.windows(3).any(|w| w == [0x0a, 0x01, 0x01])); // block_ids is one 64 byte STRING
+        assert_eq!(response, serde_epee::from_slice::<ChainResponse>(&bytes).unwrap());
+
+        let short = serde_epee::to_bytes(&std::collections::HashMap::from([
+            ("top_block_hash", serde_bytes::ByteBuf::from(vec![1; 31])),
+            ("block_ids", serde_bytes::ByteBuf::from(vec![])),
+            ("key", serde_bytes::ByteBuf::from(vec![4; 32]))
+        ])).unwrap();
+        let err = serde_epee::from_slice::<ChainResponse>(&short).unwrap_err();
+        assert_eq!(ErrorKind::Custom, err.kind());
+        assert_eq!(Some("top_block_hash"), err.path());
+    }
+
+    #[cfg(feature = "monero")]
+    #[derive(Serialize, Deserialize, Debug)]
+    struct BlockResponse {
+        #[serde(with = "serde_epee::interop::monero::hash")]
+        top_block_hash: monero::Hash,
+        #[serde(with = "serde_epee::interop::monero::hash_list")]
+        block_ids: Vec<monero::Hash>,
+        #[serde(with = "serde_epee::interop::monero::public_key")]
+        miner_key: monero::PublicKey,
+        #[serde(with = "serde_epee::interop::monero::block_header")]
+        header: monero::BlockHeader
+    }
+
+    #[cfg(feature = "monero")]
+    #[test]
+    fn monero_adapters_round_trip() {
+        let private_key = monero::PrivateKey::from_slice(&[1; 32]).unwrap();
+        let response = BlockResponse {
+            top_block_hash: monero::Hash::new(b"top"),
+            block_ids: vec![monero::Hash::new(b"a"), monero::Hash::new(b"b")],
+            miner_key: monero::PublicKey::from_private_key(&private_key),
+            header: monero::BlockHeader {
+                major_version: monero::VarInt(16),
+                minor_version: monero::VarInt(16),
+                timestamp: monero::VarInt(1700000000),
+                prev_id: monero::Hash::new(b"prev"),
+                nonce: 7
+            }
+        };
+
+        let bytes = serde_epee::to_bytes(&response).unwrap();
+        let decoded: BlockResponse = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!(response.top_block_hash, decoded.top_block_hash);
+        assert_eq!(response.block_ids, decoded.block_ids);
+        assert_eq!(response.miner_key, decoded.miner_key);
+        assert_eq!(response.header.to_string(), decoded.header.to_string()); // no PartialEq
+
+        // Field names and types of monerod's block_header_response
+        let section = serde_epee::section::from_bytes(&bytes).unwrap();
+        assert_eq!(Some(16), section["header"]["major_version"].as_u64());
+        assert!(matches!(section["header"]["major_version"], serde_epee::section::SectionEntry::UInt8(16)));
+        assert_eq!(Some(response.header.prev_id.as_bytes()), section["header"]["prev_hash"].as_bytes());
+
+        // Keys which aren't points on the curve are rejected
+        let mut off_curve = vec![0; 32];
+        off_curve[0] = 2;
+        let mut broken = section.clone();
+        broken.insert("miner_key".to_string(), serde_epee::section::SectionEntry::Blob(serde_bytes::ByteBuf::from(off_curve)));
+        let err = serde_epee::from_slice::<BlockResponse>(&serde_epee::section::to_bytes(&broken).unwrap()).unwrap_err();
+        assert_eq!(Some("miner_key"), err.path());
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::{self, Read};
+
+use serde::{Serialize, Deserialize};
+use serde_epee::io::{ChunkedReader, RetryAction, RetryReader};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Ping {
+        status: String,
+        peer_id: u64
+    }
+
+    // Returns a single byte per read, with a WouldBlock in between
+    struct FlakyReader {
+        data: Vec<u8>,
+        pos: usize,
+        block_next: bool
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.block_next = !self.block_next;
+            if !self.block_next {
+                return Err(io::Error::from(io::ErrorKind::WouldBlock));
+            } else if self.pos == self.data.len() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.data[self.pos];
+            self.pos += 1;
+            Ok(1)
+        }
+    }
+
+    fn flaky_ping() -> (Ping, FlakyReader) {
+        let ping = Ping { status: "OK".to_string(), peer_id: 0x1122334455 };
+        let data = serde_epee::to_bytes(&ping).unwrap();
+        (ping, FlakyReader { data, pos: 0, block_next: false })
+    }
+
+    #[test]
+    fn retry_transient_errors() {
+        let (ping, reader) = flaky_ping();
+        let reader = RetryReader::new(reader, |_, attempt| {
+            assert_eq!(1, attempt);
+            RetryAction::Retry
+        });
+        assert_eq!(ping, serde_epee::from_reader(reader).unwrap());
+    }
+
+    #[test]
+    fn surface_timeouts() {
+        let (_, reader) = flaky_ping();
+        let err = serde_epee::from_reader::<Ping, _>(reader).unwrap_err();
+        assert_eq!(ErrorKind::IOTimeout, err.kind());
+
+        let (_, reader) = flaky_ping();
+        let reader = RetryReader::new(reader, |_, _| RetryAction::Fail);
+        let err = serde_epee::from_reader::<Ping, _>(reader).unwrap_err();
+        assert_eq!(ErrorKind::IOTimeout, err.kind());
+    }
+
+    #[test]
+    fn convert_into_io_errors() {
+        let bytes = serde_epee::to_bytes(&Ping { status: "OK".to_string(), peer_id: 1 }).unwrap();
+
+        let err = serde_epee::from_bytes::<Ping>(&mut &bytes[..4]).unwrap_err().into_io();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+
+        let err: io::Error = serde_epee::from_reader_limited::<Ping, _>(bytes.as_slice(), 4).unwrap_err().into();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        let inner = err.get_ref().and_then(|inner| inner.downcast_ref::<serde_epee::Error>()).unwrap();
+        assert_eq!(ErrorKind::BudgetExceeded { max_bytes: 4 }, inner.kind());
+    }
+
+    // Hands over as much as it is asked for, like a TLS layer with a full buffer
+    struct GreedyReader<'a> {
+        data: &'a [u8],
+        largest_request: usize
+    }
+
+    impl Read for GreedyReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.largest_request = std::cmp::max(self.largest_request, buf.len());
+            self.data.read(buf)
+        }
+    }
+
+    #[test]
+    fn cap_read_sizes() {
+        let ping = Ping { status: "x".repeat(100_000), peer_id: 7 };
+        let data = serde_epee::to_bytes(&ping).unwrap();
+
+        let mut hook_calls = 0;
+        let mut last_total = 0;
+        let mut reader = ChunkedReader::new(GreedyReader { data: &data, largest_request: 0 }, 4096)
+            .with_hook(|total| { hook_calls += 1; last_total = total; });
+        let decoded: Ping = serde_epee::from_reader(&mut reader).unwrap();
+        assert_eq!(ping, decoded);
+        assert_eq!(4096, reader.into_inner().largest_request);
+        assert!(hook_calls > 100_000 / 4096);
+        assert_eq!(data.len() as u64, last_total);
+    }
+}
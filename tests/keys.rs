@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use serde::{Deserialize, Serialize};
+use serde_epee::{constants, ErrorKind, Serializer};
+
+mod common;
+
+serde_epee::assert_section_keys!("list", "inner", "a", "b");
+
+// Counts allocations of the current thread, since tests run in parallel
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Outer {
+        list: Vec<HashMap<String, u8>>,
+        inner: HashMap<String, u8>,
+    }
+
+    #[derive(Serialize)]
+    struct Optional {
+        a: Vec<Inner>,
+    }
+
+    #[derive(Serialize)]
+    struct Inner {
+        b: Option<u8>,
+    }
+
+    #[test]
+    fn key_errors_carry_paths() {
+        let long_key = "k".repeat(256);
+        let outer = Outer { list: vec![], inner: HashMap::from([(long_key.clone(), 1)]) };
+        let err = serde_epee::to_bytes(&outer).unwrap_err();
+        assert_eq!(ErrorKind::KeyTooLong { len: 256, max: 255 }, err.kind());
+        assert_eq!(Some(format!("inner.{}", long_key).as_str()), err.path());
+
+        let outer = Outer { list: vec![HashMap::from([(String::new(), 1)])], inner: HashMap::new() };
+        let err = serde_epee::to_bytes(&outer).unwrap_err();
+        assert_eq!(ErrorKind::EmptySectionKey, err.kind());
+        assert_eq!(Some("list[0]"), err.path());
+
+        let optional = Optional { a: vec![Inner { b: Some(1) }, Inner { b: None }] };
+        let err = serde_epee::to_bytes(&optional).unwrap_err();
+        assert_eq!(Some("a[1].b"), err.path());
+    }
+
+    #[test]
+    fn strict_key_charset() {
+        assert!(constants::is_strict_section_key("current_height"));
+        assert!(constants::is_strict_section_key("_x9"));
+        assert!(!constants::is_strict_section_key("9x"));
+        assert!(!constants::is_strict_section_key("a-b"));
+        assert!(!constants::is_strict_section_key("clé"));
+
+        let outer = Outer { list: vec![HashMap::from([("ok_key".to_string(), 1)])], inner: HashMap::from([("bad key".to_string(), 1)]) };
+        assert!(serde_epee::to_bytes(&outer).is_ok());
+
+        let mut bytes = Vec::new();
+        let err = outer.serialize(&mut Serializer::new(&mut bytes).with_strict_keys()).unwrap_err();
+        assert_eq!(ErrorKind::KeyBadEncoding, err.kind());
+        assert_eq!(Some("inner.bad key"), err.path());
+    }
+
+    #[test]
+    fn keys_and_documents_must_be_strings_and_sections() {
+        let numbered = HashMap::from([(1u32, 2u8)]);
+        assert_eq!(ErrorKind::KeyBadType, serde_epee::to_bytes(&numbered).unwrap_err().kind());
+        assert_eq!(ErrorKind::SerdeModelUnsupported, serde_epee::to_bytes(&5u64).unwrap_err().kind());
+    }
+
+    #[test]
+    fn invalid_keys_fail_to_compile() {
+        common::assert_compile_errors("ui", &[
+            ("empty_section_key.rs", "EPEE section keys must be 1 to 255 bytes long"),
+            ("long_section_key.rs", "EPEE section keys must be 1 to 255 bytes long")
+        ]);
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Peer {
+        last_seen: u64,
+        pruning_seed: u32,
+        rpc_port: u16
+    }
+
+    #[derive(Serialize)]
+    struct Peers {
+        local_peerlist_new: Vec<Peer>
+    }
+
+    // Decodes every peer without keeping them, so that only the deserializer allocates
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct CountedPeers {
+        local_peerlist_new: PeerCount
+    }
+
+    struct PeerCount;
+
+    impl<'de> Deserialize<'de> for PeerCount {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct PeerVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for PeerVisitor {
+                type Value = PeerCount;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("peers")
+                }
+
+                fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<PeerCount, A::Error> {
+                    while seq.next_element::<Peer>()?.is_some() {}
+                    Ok(PeerCount)
+                }
+            }
+
+            deserializer.deserialize_seq(PeerVisitor)
+        }
+    }
+
+    fn allocations_to_decode(num_peers: u64) -> usize {
+        let peers = (0..num_peers).map(|i| Peer { last_seen: i, pruning_seed: 0, rpc_port: 18089 }).collect();
+        let bytes = serde_epee::to_bytes(&Peers { local_peerlist_new: peers }).unwrap();
+
+        let before = ALLOCATIONS.with(Cell::get);
+        serde_epee::from_slice::<CountedPeers>(&bytes).unwrap();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    #[test]
+    fn keys_are_read_without_allocating() {
+        assert_eq!(allocations_to_decode(10), allocations_to_decode(1000));
+    }
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use serde_epee::MixedArray;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Item {
+        Number(u64),
+        Text(String),
+        Numbers(Vec<u32>),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Irregular {
+        items: MixedArray<Item>,
+    }
+
+    #[derive(Deserialize)]
+    struct Dynamic {
+        items: MixedArray,
+    }
+
+    #[test]
+    fn mixed_array_round_trip() {
+        let doc = Irregular {
+            items: vec![Item::Number(5), Item::Text("abc".to_string()), Item::Numbers(vec![1, 2])].into(),
+        };
+        let bytes = serde_epee::to_bytes(&doc).unwrap();
+        assert_eq!(doc, serde_epee::from_bytes(&mut bytes.as_slice()).unwrap());
+
+        // Plain EPEE readers see an array of single field sections
+        let (section, errors) = serde_epee::from_bytes_lossy(&bytes);
+        assert!(errors.is_empty());
+        assert_eq!(Some(5), section["items"][0]["v"].as_u64());
+        assert_eq!(Some("abc"), section["items"][1]["v"].as_str());
+
+        let dynamic: Dynamic = serde_epee::from_bytes(&mut bytes.as_slice()).unwrap();
+        assert_eq!(3, dynamic.items.len());
+        assert_eq!(Some(5), dynamic.items[0].as_u64());
+    }
+}
@@ -0,0 +1,36 @@
+use std::io::Read;
+
+use serde_epee::negotiate::{agree, negotiate, Preamble};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric_negotiation() {
+        let ours = Preamble { version: 3, min_version: 2, caps: 0b1011 };
+        let theirs = Preamble { version: 5, min_version: 1, caps: 0b0110 };
+
+        let mut incoming = serde_epee::to_bytes(&theirs).unwrap();
+        incoming.extend_from_slice(b"first message");
+        let mut reader = incoming.as_slice();
+        let mut sent = Vec::new();
+
+        let agreement = negotiate(&mut reader, &mut sent, &ours).unwrap();
+        assert_eq!(3, agreement.version);
+        assert_eq!(0b0010, agreement.caps);
+        assert!(agreement.has_caps(0b0010) && !agreement.has_caps(0b0011));
+        assert_eq!(theirs, agreement.peer);
+
+        // The peer comes to the same conclusion, and nothing past the preamble was read
+        assert_eq!(ours, serde_epee::from_bytes(&mut sent.as_slice()).unwrap());
+        assert_eq!(agreement.version, agree(&theirs, &ours).unwrap().version);
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!("first message", rest);
+
+        let old = Preamble::new(1, 0);
+        assert_eq!(ErrorKind::IncompatibleVersion { ours: 3, theirs: 1 }, agree(&ours, &old).unwrap_err().kind());
+    }
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "no-float")]
+
+use serde::{Deserialize, Serialize};
+use serde_epee::section::{SectionArray, SectionEntry};
+use serde_epee::{ErrorKind, Section, from_bytes, from_bytes_lossy, to_bytes};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Price {
+        amount: f64,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Anything {}
+
+    #[test]
+    fn reject_doubles() {
+        let err = to_bytes(&Price { amount: 1.5 }).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FloatDisabled);
+
+        // {"amount": 1.5f64}
+        let bytes = hex::decode("0111010101010201010406616d6f756e7409000000000000f83f").unwrap();
+        let err = from_bytes::<Anything>(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FloatDisabled);
+
+        let (section, errors) = from_bytes_lossy(&bytes);
+        assert!(section["amount"].is_nil());
+        assert_eq!(errors[0].kind(), ErrorKind::FloatDisabled);
+    }
+
+    // The Double variants stay, so that enabling the feature breaks no matches
+    #[test]
+    fn reject_double_entries() {
+        let section = Section::from_pairs([("amount", SectionEntry::Double(1.5))]);
+        assert_eq!(ErrorKind::FloatDisabled, serde_epee::section::to_bytes(&section).unwrap_err().kind());
+        assert_eq!(ErrorKind::FloatDisabled, to_bytes(&section).unwrap_err().kind());
+
+        let section = Section::from_pairs([("amounts", SectionEntry::Array(SectionArray::Double(vec![1.5])))]);
+        assert_eq!(ErrorKind::FloatDisabled, serde_epee::section::to_bytes(&section).unwrap_err().kind());
+
+        let section = Section::from_pairs([("amount", SectionEntry::Double(1.5))]);
+        let err = serde_epee::from_section::<std::collections::HashMap<String, f64>>(section).unwrap_err();
+        assert_eq!(ErrorKind::FloatDisabled, err.kind());
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use serde_epee::packed::{from_packed_bytes, to_packed_bytes};
+use serde_epee::ErrorKind;
+use serde_epee::ser::PackedSerializer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_round_trips() {
+        let bytes = to_packed_bytes(&(1u32, true, [2u16, 3u16])).unwrap();
+        assert_eq!("010000000102000300", hex::encode(&bytes));
+        assert_eq!((1u32, true, [2u16, 3u16]), from_packed_bytes(&bytes).unwrap());
+
+        let hashes = vec![[7u8; 4], [9u8; 4]];
+        let bytes = to_packed_bytes(&hashes).unwrap();
+        assert_eq!(8, bytes.len());
+        assert_eq!(hashes, from_packed_bytes::<Vec<[u8; 4]>>(&bytes).unwrap());
+
+        let err = from_packed_bytes::<u32>(&[1, 0, 0, 0, 0]).unwrap_err();
+        assert_eq!(ErrorKind::ExpectedEnd, err.kind());
+        assert_eq!(ErrorKind::IOError, from_packed_bytes::<(u32, u32)>(&[1, 0, 0, 0]).unwrap_err().kind());
+    }
+
+    #[test]
+    fn reject_unframeable_packed_values() {
+        let mut bytes = Vec::new();
+        let mut serializer = PackedSerializer::new(&mut bytes, 2);
+        serde::Serialize::serialize(&1u8, &mut serializer).unwrap();
+        serde::Serialize::serialize(&2u8, &mut serializer).unwrap();
+        let err = serde::Serialize::serialize(&3u8, &mut serializer).unwrap_err();
+        assert_eq!(ErrorKind::TupleTooLong, err.kind());
+        assert_eq!(vec![1, 2], bytes);
+
+        let err = to_packed_bytes(&(1u8, "strings have no fixed size")).unwrap_err();
+        assert_eq!(ErrorKind::SerdeModelUnsupported, err.kind());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct TxOutputIndices {
+        #[serde(with = "serde_epee::packed_le_u64")]
+        output_indices: Vec<u64>,
+        #[serde(with = "serde_epee::packed_le_i32")]
+        deltas: Vec<i32>
+    }
+
+    #[test]
+    fn packed_le_adapters() {
+        let indices = TxOutputIndices { output_indices: vec![1, u64::MAX, 0x0102030405060708], deltas: vec![-1, 2] };
+        let bytes = serde_epee::to_bytes(&indices).unwrap();
+        let packed = serde_epee::section::from_bytes(&bytes).unwrap();
+        assert_eq!(Some(to_packed_bytes(&indices.output_indices).unwrap().as_slice()), packed["output_indices"].as_bytes());
+        assert_eq!(Some(&[0xff, 0xff, 0xff, 0xff, 2, 0, 0, 0][..]), packed["deltas"].as_bytes());
+
+        // Values in the blob aren't aligned, here or in the input
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&bytes);
+        assert_eq!(indices, serde_epee::from_slice(&shifted[1..]).unwrap());
+
+        let short = serde_epee::Section::from_pairs([("output_indices", vec![0u8; 12]), ("deltas", vec![])]);
+        let err = serde_epee::from_slice::<TxOutputIndices>(&serde_epee::to_bytes(&short).unwrap()).unwrap_err();
+        assert_eq!(Some("output_indices"), err.path());
+    }
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "rayon")]
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_epee::parallel::{from_framed_bytes, to_framed_bytes};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Response {
+        height: u64,
+        status: String
+    }
+
+    #[test]
+    fn parallel_framed_round_trip() {
+        let responses: Vec<Response> = (0..1000).map(|i| Response { height: i, status: "OK".to_string() }).collect();
+        let framed = to_framed_bytes(&responses).unwrap();
+
+        // Same bytes as encoding one by one
+        let first = serde_epee::to_bytes(&responses[0]).unwrap();
+        assert_eq!(first.len() as u8 * 4, framed[0]);
+        assert_eq!(first, framed[1..=first.len()]);
+
+        assert_eq!(responses, from_framed_bytes::<Response>(&framed).unwrap());
+        let err = from_framed_bytes::<Response>(&framed[..framed.len() - 1]).unwrap_err();
+        assert_eq!(ErrorKind::IOError, err.kind());
+    }
+
+    // Fails to serialize for odd numbers
+    struct Even(u64);
+
+    impl Serialize for Even {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if self.0 % 2 == 1 {
+                return Err(serde::ser::Error::custom(format!("{} is odd", self.0)));
+            }
+            Response { height: self.0, status: "OK".to_string() }.serialize(serializer)
+        }
+    }
+
+    #[test]
+    fn parallel_errors_in_order() {
+        for _ in 0..20 {
+            let values: Vec<Even> = [0, 2, 4, 7, 8, 9].into_iter().map(Even).collect();
+            let err = to_framed_bytes(&values).unwrap_err();
+            assert!(err.to_string().contains("7 is odd"), "{}", err);
+        }
+
+        // A truncated document followed by one without any fields
+        let truncated = serde_epee::to_bytes(&Response { height: 1, status: "OK".to_string() }).unwrap();
+        let truncated = &truncated[..truncated.len() - 1];
+        let mut framed = vec![truncated.len() as u8 * 4];
+        framed.extend_from_slice(truncated);
+        framed.extend(to_framed_bytes(&[serde_epee::Section::new()]).unwrap());
+        for _ in 0..20 {
+            let err = from_framed_bytes::<Response>(&framed).unwrap_err();
+            assert_eq!(ErrorKind::TruncatedCompound { expected: 2, got: 1 }, err.kind());
+        }
+    }
+}
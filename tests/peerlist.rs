@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use serde_epee::peerlist::{read_peerlist, AddressFields, NetworkAddress, PeerlistEntry};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct HandshakeResponse {
+        node_data: NodeData,
+        local_peerlist_new: Vec<PeerlistEntry>
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct NodeData {
+        my_port: u32,
+        peer_id: u64
+    }
+
+    fn sample_peers() -> Vec<PeerlistEntry> {
+        let ipv4 = PeerlistEntry {
+            adr: NetworkAddress { addr_type: 1, addr: AddressFields { m_ip: Some(0x0100007f), m_port: Some(18080), ..Default::default() } },
+            id: 0x1122334455667788,
+            last_seen: 1700000000,
+            pruning_seed: 384,
+            rpc_port: 18089,
+            rpc_credits_per_hash: 0
+        };
+        let tor = PeerlistEntry {
+            adr: NetworkAddress { addr_type: 4, addr: AddressFields { host: Some("abc.onion".to_string()), port: Some(18083), ..Default::default() } },
+            id: 7,
+            ..Default::default()
+        };
+        vec![ipv4, tor]
+    }
+
+    #[test]
+    fn native_peerlist_matches_derive() {
+        let response = HandshakeResponse { node_data: NodeData { my_port: 18080, peer_id: 1 }, local_peerlist_new: sample_peers() };
+        let bytes = serde_epee::to_bytes(&response).unwrap();
+
+        let derived: HandshakeResponse = serde_epee::from_bytes(&mut bytes.as_slice()).unwrap();
+        assert_eq!(response, derived);
+        assert_eq!(sample_peers(), read_peerlist(&bytes, "local_peerlist_new").unwrap());
+        assert!(read_peerlist(&bytes, "missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn native_peerlist_type_mismatch() {
+        #[derive(Serialize)]
+        struct BadEntry {
+            id: u32
+        }
+        #[derive(Serialize)]
+        struct BadResponse {
+            local_peerlist_new: Vec<BadEntry>
+        }
+
+        let bytes = serde_epee::to_bytes(&BadResponse { local_peerlist_new: vec![BadEntry { id: 1 }] }).unwrap();
+        let err = read_peerlist(&bytes, "local_peerlist_new").unwrap_err();
+        assert_eq!(ErrorKind::TypeMismatch, err.kind());
+        assert_eq!(Some("local_peerlist_new[0]"), err.path());
+    }
+
+    #[test]
+    fn native_peerlist_empty_arrays() {
+        let response = HandshakeResponse { node_data: NodeData { my_port: 18080, peer_id: 1 }, local_peerlist_new: vec![] };
+        let bytes = serde_epee::to_bytes(&response).unwrap();
+        assert!(read_peerlist(&bytes, "local_peerlist_new").unwrap().is_empty());
+
+        // Only empty arrays may have another element type
+        #[derive(Serialize)]
+        struct NotPeers {
+            local_peerlist_new: Vec<u8>
+        }
+        let bytes = serde_epee::to_bytes(&NotPeers { local_peerlist_new: vec![1] }).unwrap();
+        assert_eq!(ErrorKind::TypeMismatch, read_peerlist(&bytes, "local_peerlist_new").unwrap_err().kind());
+    }
+}
@@ -0,0 +1,172 @@
+use std::io::{Cursor, Read, Write};
+
+use serde::{Serialize, Deserialize};
+use serde_epee::replay::{CaptureWriter, Direction, Record, Recorder, Replay, CAPTURE_MAGIC};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Ping {
+        height: u64
+    }
+
+    // Both ends of a connection, as seen from our side
+    struct Duplex {
+        incoming: Cursor<Vec<u8>>,
+        outgoing: Vec<u8>
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.incoming.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.outgoing.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_and_replay_session() {
+        let request = serde_epee::to_bytes(&Ping { height: 1 }).unwrap();
+        let response = serde_epee::to_bytes(&Ping { height: 2 }).unwrap();
+
+        let peer = Duplex { incoming: Cursor::new(response.clone()), outgoing: Vec::new() };
+        let mut recorder = Recorder::new(peer, Vec::new()).unwrap();
+        recorder.write_all(&request).unwrap();
+        let received: Ping = serde_epee::from_reader(&mut recorder).unwrap();
+        assert_eq!(Ping { height: 2 }, received);
+        let (peer, capture) = recorder.into_parts();
+        assert_eq!(request, peer.outgoing);
+
+        // One record per message, however it was read or written
+        let records: Vec<Record> = Replay::new(capture.as_slice()).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(2, records.len());
+        assert_eq!(Direction::Outbound, records[0].direction);
+        assert_eq!(Ping { height: 1 }, records[0].decode().unwrap());
+        assert_eq!(Direction::Inbound, records[1].direction);
+        assert_eq!(Ping { height: 2 }, records[1].decode().unwrap());
+        assert!(records[0].timestamp <= records[1].timestamp);
+    }
+
+    #[test]
+    fn record_messages_written_in_pieces() {
+        let first = serde_epee::to_bytes(&Ping { height: 1 }).unwrap();
+        let second = serde_epee::to_bytes(&Ping { height: 2 }).unwrap();
+        let mut written = first.clone();
+        written.extend_from_slice(&second);
+
+        let peer = Duplex { incoming: Cursor::new(Vec::new()), outgoing: Vec::new() };
+        let mut recorder = Recorder::new(peer, Vec::new()).unwrap();
+        for byte in &written {
+            recorder.write_all(&[*byte]).unwrap();
+        }
+        recorder.write_all(&first[..5]).unwrap(); // never finished
+        let (peer, capture) = recorder.into_parts();
+        assert_eq!(written.len() + 5, peer.outgoing.len());
+
+        let records: Vec<Record> = Replay::new(capture.as_slice()).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(3, records.len());
+        assert_eq!(Ping { height: 1 }, records[0].decode().unwrap());
+        assert_eq!(Ping { height: 2 }, records[1].decode().unwrap());
+        assert_eq!(first[..5], records[2].payload[..]);
+    }
+
+    #[test]
+    fn record_garbage_raw() {
+        let peer = Duplex { incoming: Cursor::new(b"not epee".to_vec()), outgoing: Vec::new() };
+        let mut recorder = Recorder::new(peer, Vec::new()).unwrap();
+        let mut received = Vec::new();
+        recorder.read_to_end(&mut received).unwrap();
+        assert_eq!(b"not epee"[..], received[..]);
+
+        let (_, capture) = recorder.into_parts();
+        let inbound = Replay::new(capture.as_slice()).unwrap().stream(Direction::Inbound).unwrap();
+        assert_eq!(b"not epee"[..], inbound[..]);
+    }
+
+    // Capture which fails after the magic
+    struct BrokenCapture {
+        written: usize
+    }
+
+    impl Write for BrokenCapture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.written >= CAPTURE_MAGIC.len() {
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.written += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn capture_errors_spare_the_stream() {
+        let request = serde_epee::to_bytes(&Ping { height: 1 }).unwrap();
+        let response = serde_epee::to_bytes(&Ping { height: 2 }).unwrap();
+
+        let peer = Duplex { incoming: Cursor::new(response), outgoing: Vec::new() };
+        let mut recorder = Recorder::new(peer, BrokenCapture { written: 0 }).unwrap();
+        recorder.write_all(&request).unwrap();
+        recorder.flush().unwrap();
+        assert_eq!(ErrorKind::IOError, recorder.capture_error().unwrap().kind());
+
+        let received: Ping = serde_epee::from_reader(&mut recorder).unwrap();
+        assert_eq!(Ping { height: 2 }, received);
+        assert_eq!(request, recorder.get_ref().outgoing);
+    }
+
+    #[test]
+    fn filter_and_re_emit() {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        for (i, direction) in [Direction::Inbound, Direction::Outbound, Direction::Inbound].into_iter().enumerate() {
+            writer.write_record(&Record { direction, timestamp: i as u64, payload: vec![i as u8; i] }).unwrap();
+        }
+        let capture = writer.into_inner();
+
+        let mut filtered = CaptureWriter::new(Vec::new()).unwrap();
+        for record in Replay::new(capture.as_slice()).unwrap() {
+            let record = record.unwrap();
+            if record.direction == Direction::Inbound {
+                filtered.write_record(&record).unwrap();
+            }
+        }
+        let timestamps: Vec<u64> = Replay::new(filtered.into_inner().as_slice()).unwrap().map(|record| record.unwrap().timestamp).collect();
+        assert_eq!(vec![0, 2], timestamps);
+    }
+
+    #[test]
+    fn damaged_captures() {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer.write_record(&Record { direction: Direction::Inbound, timestamp: 5, payload: vec![1, 2, 3] }).unwrap();
+        let capture = writer.into_inner();
+
+        // Cut short: the error is reported once, then iteration stops
+        let mut replay = Replay::new(&capture[..capture.len() - 1]).unwrap();
+        assert_eq!(ErrorKind::IOError, replay.next().unwrap().unwrap_err().kind());
+        assert!(replay.next().is_none());
+
+        let mut bad_direction = capture.clone();
+        bad_direction[CAPTURE_MAGIC.len()] = 7;
+        let err = Replay::new(bad_direction.as_slice()).unwrap().next().unwrap().unwrap_err();
+        assert_eq!(ErrorKind::UnknownDirection { code: 7 }, err.kind());
+
+        let err = Replay::new(capture.as_slice()).unwrap().with_max_len(2).next().unwrap().unwrap_err();
+        assert_eq!(ErrorKind::BudgetExceeded { max_bytes: 2 }, err.kind());
+
+        assert!(Replay::new(&b"EPEECAP0"[..]).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+use serde_epee::rpc::{Envelope, StatusError};
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct GetHeight {
+        height: u64,
+        hash: String
+    }
+
+    #[test]
+    fn envelope_round_trip() {
+        let mut res = Envelope::ok(GetHeight { height: 3000000, hash: String::from("ab12") });
+        res.untrusted = true;
+        res.credits = 500;
+        res.top_hash = String::from("cd34");
+
+        let bytes = serde_epee::to_bytes(&res).unwrap();
+        let section = serde_epee::section::from_bytes(&bytes).unwrap();
+        assert_eq!(6, section.len());
+        assert_eq!(Some("OK"), section["status"].as_str());
+        assert_eq!(Some(3000000), section["height"].as_u64());
+
+        let decoded: Envelope<GetHeight> = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!(res, decoded);
+        assert_eq!(res.payload, decoded.into_result().unwrap());
+    }
+
+    #[test]
+    fn envelope_fields_are_optional_but_status() {
+        let bytes = serde_epee::to_bytes(&HashMap::from([("status", "OK"), ("hash", "ab12")])).unwrap();
+        assert!(serde_epee::from_slice::<Envelope<GetHeight>>(&bytes).is_err());
+
+        let mut doc = serde_epee::section::from_bytes(&bytes).unwrap();
+        doc.insert(String::from("height"), 5u64.into());
+        let bytes = serde_epee::to_bytes(&doc).unwrap();
+        let res: Envelope<GetHeight> = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!((false, 0, ""), (res.untrusted, res.credits, res.top_hash.as_str()));
+        assert_eq!(5, res.into_result().unwrap().height);
+
+        doc.remove("status");
+        let bytes = serde_epee::to_bytes(&doc).unwrap();
+        assert!(serde_epee::from_slice::<Envelope<GetHeight>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn bad_status_carries_payload() {
+        let mut res = Envelope::ok(GetHeight { height: 0, hash: String::new() });
+        res.status = String::from("BUSY");
+        let bytes = serde_epee::to_bytes(&res).unwrap();
+
+        let decoded: Envelope<GetHeight> = serde_epee::from_slice(&bytes).unwrap();
+        assert!(!decoded.is_ok());
+        let err = decoded.into_result().unwrap_err();
+        assert!(err.is_busy());
+        assert_eq!(StatusError { status: String::from("BUSY"), payload: res.payload }, err);
+
+        let err = serde_epee::Error::from(err);
+        assert_eq!(ErrorKind::BadStatus, err.kind());
+    }
+}
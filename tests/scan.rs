@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+use serde::Serialize;
+use serde_epee::scan::{path_sizes, scan, PathSize, MAX_SCANNED_BLOB};
+use serde_epee::section::SectionEntry;
+use serde_epee::ErrorKind;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Peer {
+        port: u16
+    }
+
+    #[derive(Serialize)]
+    struct Response {
+        status: String,
+        heights: Vec<u8>,
+        peers: Vec<Peer>,
+        #[serde(with = "serde_bytes")]
+        blob: Vec<u8>
+    }
+
+    fn response_bytes() -> Vec<u8> {
+        let response = Response {
+            status: "OK".to_string(),
+            heights: vec![7, 8],
+            peers: vec![Peer { port: 18080 }],
+            blob: vec![0xab; MAX_SCANNED_BLOB + 1]
+        };
+        serde_epee::to_bytes(&response).unwrap()
+    }
+
+    #[test]
+    fn entries_in_document_order() {
+        let bytes = response_bytes();
+        let mut seen = Vec::new();
+        scan(bytes.as_slice(), |entry| {
+            let value = entry.value.as_ref().map(SectionEntry::to_string);
+            seen.push((entry.path.to_string(), entry.offset, entry.entry_type.to_string(), value, entry.truncated));
+            Ok(())
+        }).unwrap();
+
+        let paths: Vec<&str> = seen.iter().map(|(path, ..)| path.as_str()).collect();
+        assert_eq!(vec!["status", "heights", "heights[0]", "heights[1]", "peers", "peers[0]", "peers[0].port", "blob"], paths);
+
+        // Offsets point at keys, or at the payloads of array elements
+        assert_eq!((10, 10 + 1 + 6 + 1 + 1 + 2), (seen[0].1, seen[1].1));
+        assert_eq!(7, bytes[seen[2].1 as usize]);
+        assert_eq!(Some("\"OK\""), seen[0].3.as_deref());
+        assert_eq!("ARRAY<UINT8>", seen[1].2);
+        assert_eq!(None, seen[1].3);
+
+        let (_, _, blob_type, _, truncated) = &seen[7];
+        assert_eq!(("STRING", true), (blob_type.as_str(), *truncated));
+    }
+
+    #[test]
+    fn visitor_errors_stop_the_scan() {
+        let bytes = response_bytes();
+        let mut count = 0;
+        let err = scan(bytes.as_slice(), |_| {
+            count += 1;
+            Err(serde::de::Error::custom("enough"))
+        });
+        assert!(err.is_err());
+        assert_eq!(1, count);
+
+        let err = scan(&bytes[..bytes.len() - 1], |_| Ok(())).unwrap_err();
+        assert_eq!(ErrorKind::IOError, err.kind());
+    }
+
+    #[test]
+    fn sizes_per_key_path() {
+        let bytes = response_bytes();
+        let (len, sizes) = path_sizes(bytes.as_slice(), 1).unwrap();
+        assert_eq!(bytes.len() as u64, len);
+        let top: Vec<&str> = sizes.keys().map(String::as_str).collect();
+        assert_eq!(vec!["blob", "heights", "peers", "status"], top);
+
+        // Key, type code, length and payload of each entry, which with the
+        // signature and the field count add up to the whole document
+        assert_eq!(PathSize { bytes: 1 + 6 + 1 + 1 + 2, count: 1 }, sizes["status"]);
+        assert_eq!(PathSize { bytes: 1 + 7 + 1 + 1 + 2, count: 1 }, sizes["heights"]);
+        assert_eq!(len, 9 + 1 + sizes.values().map(|size| size.bytes).sum::<u64>());
+
+        let (_, sizes) = path_sizes(bytes.as_slice(), 2).unwrap();
+        assert_eq!(PathSize { bytes: 1 + 4 + 1 + 2, count: 1 }, sizes["peers[].port"]);
+    }
+
+    #[test]
+    fn keys_are_escaped_in_paths() {
+        let mut inner = HashMap::new();
+        inner.insert("x]".to_string(), 1u8);
+        let mut outer = HashMap::new();
+        outer.insert("a.b[0]\\".to_string(), inner);
+        let bytes = serde_epee::to_bytes(&outer).unwrap();
+
+        let mut paths = Vec::new();
+        scan(bytes.as_slice(), |entry| {
+            paths.push(entry.path.to_string());
+            Ok(())
+        }).unwrap();
+        assert_eq!(vec!["a\\.b\\[0\\]\\\\", "a\\.b\\[0\\]\\\\.x\\]"], paths);
+
+        // Escaped separators don't nest, so each key is its own path
+        let (_, sizes) = path_sizes(bytes.as_slice(), 2).unwrap();
+        assert_eq!(PathSize { bytes: 1 + 2 + 1 + 1, count: 1 }, sizes["a\\.b\\[0\\]\\\\.x\\]"]);
+        let (_, sizes) = path_sizes(bytes.as_slice(), 1).unwrap();
+        assert_eq!(1, sizes.len());
+    }
+
+    #[test]
+    fn tool_grep_and_stat_commands() {
+        let dir = std::env::temp_dir().join(format!("serde_epee_grep_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("response.dat");
+        std::fs::write(&path, response_bytes()).unwrap();
+
+        let tool = env!("CARGO_BIN_EXE_epee-tool");
+        let output = Command::new(tool).args(["grep", "peers*port"]).arg(&path).output().unwrap();
+        assert_eq!(Some(0), output.status.code());
+        assert_eq!("0000002a: peers[0].port = UINT16 18080\n", String::from_utf8_lossy(&output.stdout));
+
+        let output = Command::new(tool).args(["grep", "--values", "O?"]).arg(&path).output().unwrap();
+        assert_eq!("0000000a: status = STRING \"OK\"\n", String::from_utf8_lossy(&output.stdout));
+
+        let output = Command::new(tool).args(["grep", "nothing"]).arg(&path).arg(&path).output().unwrap();
+        assert_eq!(Some(1), output.status.code());
+
+        // Patterns with many stars don't backtrack into every one of them
+        let long_path = dir.join("long.dat");
+        let mut long = HashMap::new();
+        long.insert("status", "a".repeat(200));
+        std::fs::write(&long_path, serde_epee::to_bytes(&long).unwrap()).unwrap();
+        let output = Command::new(tool).args(["grep", "--values", "*a*a*a*a*a*a*a*a*a*a*a*a*b"]).arg(&long_path).output().unwrap();
+        assert_eq!(Some(1), output.status.code());
+        let output = Command::new(tool).args(["grep", "*s?a*s"]).arg(&path).output().unwrap();
+        assert_eq!("0000000a: status = STRING \"OK\"\n", String::from_utf8_lossy(&output.stdout));
+
+        let output = Command::new(tool).args(["stat"]).arg(&path).arg(&path).output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let rows: Vec<Vec<&str>> = stdout.lines().map(|line| line.split_whitespace().collect()).collect();
+        assert_eq!(vec!["bytes", "share", "count", "path"], rows[0]);
+        assert_eq!(vec!["blob"], rows[1][3..]);
+        assert_eq!("2", rows[1][2]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
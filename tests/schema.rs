@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+use serde_epee::schema::{self, Field, Shape};
+use serde_epee::{EpeeEntryType, EpeeScalarType, ErrorKind};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Tx {
+        #[serde(with = "serde_epee::interop::pod")]
+        hash: [u8; 32],
+        fee: u64
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Block {
+        height: u64,
+        txs: Vec<Tx>,
+        miner: Option<String>,
+        extra: HashMap<String, u8>,
+        difficulty: f64,
+        orphan: bool
+    }
+
+    #[derive(Serialize)]
+    struct WireTx {
+        hash: serde_bytes::ByteBuf,
+        fee: u32
+    }
+
+    #[derive(Serialize)]
+    struct WireBlock {
+        height: String,
+        txs: Vec<WireTx>,
+        extra: HashMap<String, u8>,
+        difficulty: u64,
+        version: u8
+    }
+
+    #[test]
+    fn shapes_of_types() {
+        let shape = schema::shape_of::<Block>();
+        let tx = Shape::Struct(vec![
+            Field { name: "hash", shape: Shape::Blob, optional: false },
+            Field { name: "fee", shape: Shape::Integer, optional: false }
+        ]);
+        let expected = Shape::Struct(vec![
+            Field { name: "height", shape: Shape::Integer, optional: false },
+            Field { name: "txs", shape: Shape::Seq(Box::new(tx)), optional: false },
+            Field { name: "miner", shape: Shape::Blob, optional: true },
+            Field { name: "extra", shape: Shape::Map, optional: false },
+            Field { name: "difficulty", shape: Shape::Double, optional: false },
+            Field { name: "orphan", shape: Shape::Bool, optional: false }
+        ]);
+        assert_eq!(expected, shape);
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Tree {
+        children: Vec<Tree>
+    }
+
+    #[test]
+    fn recursive_shapes_terminate() {
+        let mut shape = schema::shape_of::<Tree>();
+        let mut depth = 0;
+        while let Shape::Struct(mut fields) = shape {
+            shape = match fields.pop().unwrap().shape {
+                Shape::Seq(element) => *element,
+                other => other
+            };
+            depth += 1;
+        }
+        assert!(depth > 1);
+        assert_eq!(Shape::Any, shape);
+    }
+
+    #[test]
+    fn validate_documents() {
+        let block = WireBlock {
+            height: "12".to_string(),
+            txs: vec![WireTx { hash: serde_bytes::ByteBuf::from(vec![1; 32]), fee: 5 }],
+            extra: HashMap::new(),
+            difficulty: 7,
+            version: 1
+        };
+        let bytes = serde_epee::to_bytes(&block).unwrap();
+
+        let report = schema::validate_as::<Block>(&bytes).unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(vec!["orphan".to_string()], report.missing);
+        assert_eq!(vec!["version".to_string()], report.extra);
+        assert_eq!(1, report.mismatched.len());
+        assert_eq!("height", report.mismatched[0].path);
+        assert_eq!(Shape::Integer, report.mismatched[0].expected);
+        assert_eq!(EpeeEntryType::new(EpeeScalarType::Str, false), report.mismatched[0].found);
+
+        // Nested sections inside of arrays are checked too
+        let bytes = serde_epee::to_bytes(&HashMap::from([("txs", vec![HashMap::from([("fee", 1u8)])])])).unwrap();
+        let report = schema::validate_as::<Block>(&bytes).unwrap();
+        assert!(report.missing.contains(&"txs[0].hash".to_string()));
+        assert!(report.missing.contains(&"height".to_string()));
+        assert!(!report.missing.contains(&"miner".to_string()));
+
+        // Documents which decode are valid
+        let bytes = serde_epee::to_bytes(&HashMap::from([("children", Vec::<HashMap<String, u8>>::new())])).unwrap();
+        assert_eq!(schema::ValidationReport::default(), schema::validate_as::<Tree>(&bytes).unwrap());
+
+        // Only sequences of u8 decode from blobs
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Blobs {
+            bytes: Vec<u8>,
+            words: Vec<u64>
+        }
+        assert_eq!(Shape::Seq(Box::new(Shape::Byte)), match schema::shape_of::<Blobs>() {
+            Shape::Struct(fields) => fields[0].shape.clone(),
+            other => panic!("unexpected shape {:?}", other)
+        });
+        let blob = serde_bytes::ByteBuf::from(vec![0; 16]);
+        let bytes = serde_epee::to_bytes(&HashMap::from([("bytes", &blob), ("words", &blob)])).unwrap();
+        let report = schema::validate_as::<Blobs>(&bytes).unwrap();
+        assert_eq!(1, report.mismatched.len());
+        assert_eq!("words", report.mismatched[0].path);
+
+        // Malformed documents aren't
+        let err = schema::validate_as::<Tree>(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(ErrorKind::IOError, err.kind());
+    }
+}
@@ -0,0 +1,554 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::num::{NonZeroU32, NonZeroU64};
+
+use serde::de::IntoDeserializer;
+use serde_epee::section::{SectionArray, SectionEntry};
+use serde_epee::{EpeeScalarType, ErrorKind, Section};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // {"a": 5u32, "b": {"c": "xyz", "d": [1u16, 2u16]}}
+    const DOC_HEX: &str = "011101010101020101080161060500000001620c0801630a\
+                           0c78797a0164870801000200";
+
+    #[test]
+    fn lossy_decode_complete_document() {
+        let bytes = hex::decode(DOC_HEX).unwrap();
+        let (section, errors) = serde_epee::from_bytes_lossy(&bytes);
+        assert!(errors.is_empty());
+        assert!(matches!(section["a"], SectionEntry::UInt32(5)));
+    }
+
+    #[test]
+    fn lossy_decode_truncated_document() {
+        let bytes = hex::decode(DOC_HEX).unwrap();
+        // Cut off in the middle of the last array element
+        let (section, errors) = serde_epee::from_bytes_lossy(&bytes[..bytes.len() - 1]);
+        assert_eq!(1, errors.len());
+        assert!(matches!(section["a"], SectionEntry::UInt32(5)));
+
+        let inner = match &section["b"] {
+            SectionEntry::Object(inner) => inner,
+            other => panic!("unexpected entry {:?}", other)
+        };
+        assert!(matches!(&inner["c"], SectionEntry::Blob(blob) if blob.as_slice() == b"xyz"));
+        assert!(matches!(&inner["d"], SectionEntry::Array(SectionArray::UInt16(v)) if v == &[1]));
+    }
+
+    #[test]
+    fn lossy_decode_collects_every_error() {
+        let mut bytes = hex::decode("011101010101020101").unwrap();
+        bytes.push(5 << 2);
+        bytes.extend_from_slice(&[0, 0x08, 2]); // empty key
+        bytes.extend_from_slice(&[2, b'k', 0xff, 0x08, 7]); // key isn't UTF-8
+        bytes.extend_from_slice(b"\x04deep\x0c");
+        for _ in 0..100 {
+            bytes.extend_from_slice(b"\x04\x01d\x0c");
+        }
+        bytes.push(0);
+        bytes.extend_from_slice(b"\x01x\x7f"); // can't be skipped
+        bytes.extend_from_slice(b"\x05after\x08\x01");
+
+        let (section, errors) = serde_epee::from_bytes_lossy(&bytes);
+        let kinds: Vec<ErrorKind> = errors.iter().map(|e| e.kind()).collect();
+        assert_eq!(vec![
+            ErrorKind::EmptySectionKey,
+            ErrorKind::StringBadEncoding,
+            ErrorKind::SectionTooDeep { max: 100 },
+            ErrorKind::BadTypeCode { code: 0x7f }
+        ], kinds);
+        assert_eq!(Some("x"), errors[3].path());
+
+        // Entries around the bad ones are kept, with placeholders for those
+        // which couldn't be decoded
+        assert_eq!(Some(2), section[""].as_u64());
+        assert_eq!(Some(7), section["k\u{fffd}"].as_u64());
+        let mut deep = &section["deep"];
+        for _ in 0..99 {
+            deep = &deep["d"];
+        }
+        assert!(matches!(deep, SectionEntry::Object(inner) if inner.is_empty()));
+        assert!(section["x"].is_nil());
+        assert!(section.get("x").is_some());
+        assert!(section.get("after").is_none());
+    }
+
+    #[test]
+    fn lossy_decode_bad_signature() {
+        let (section, errors) = serde_epee::from_bytes_lossy(&[0u8; 12]);
+        assert!(section.is_empty());
+        assert_eq!(1, errors.len());
+    }
+
+    #[test]
+    fn entry_conversions() {
+        let mut section = Section::new();
+        section.insert("status".into(), "OK".into());
+        section.insert("height".into(), 3000000u64.into());
+        section.insert("heights".into(), vec![1u64, 2, 3].into());
+        section.insert("inner".into(), Section::new().into());
+
+        assert_eq!("OK", String::try_from(section["status"].clone()).unwrap());
+        assert_eq!(3000000, u64::try_from(section["height"].clone()).unwrap());
+        assert!(matches!(&section["heights"], SectionEntry::Array(SectionArray::UInt64(v)) if v == &[1, 2, 3]));
+        assert!(matches!(&section["inner"], SectionEntry::Object(_)));
+
+        let err = u32::try_from(section["height"].clone()).unwrap_err();
+        assert_eq!(ErrorKind::TypeMismatch, err.kind());
+    }
+
+    #[test]
+    fn chained_indexing() {
+        let bytes = hex::decode(DOC_HEX).unwrap();
+        let (doc, _) = serde_epee::from_bytes_lossy(&bytes);
+
+        assert_eq!(Some(5), doc["a"].as_u64());
+        assert_eq!(Some("xyz"), doc["b"]["c"].as_str());
+        assert!(doc["b"]["missing"]["deeper"].is_nil());
+        assert!(doc["a"]["not_a_section"].is_nil());
+
+        let mut block = Section::new();
+        block.insert("height".into(), 42u64.into());
+        let mut root = Section::new();
+        root.insert("blocks".into(), vec![block].into());
+
+        assert_eq!(Some(42), root["blocks"][0]["height"].as_u64());
+        assert!(root["blocks"][1]["height"].is_nil());
+    }
+
+    #[test]
+    fn array_accessors() {
+        let mut array = SectionArray::from(vec![1u32, 2]);
+        array.push_checked(3u32.into()).unwrap();
+        assert_eq!(ErrorKind::ArrayMixedTypes, array.push_checked(4u64.into()).unwrap_err().kind());
+        assert_eq!(ErrorKind::NestedArrays, array.push_checked(vec![5u32].into()).unwrap_err().kind());
+
+        assert_eq!(3, array.len());
+        assert_eq!(EpeeScalarType::UInt32, array.element_type());
+        assert_eq!(Some(SectionEntry::UInt32(2)), array.get(1));
+        assert_eq!(vec![1u64, 2, 3], array.iter_u64().unwrap().collect::<Vec<_>>());
+        assert!(array.iter_i64().is_none());
+        assert!(array.iter_sections().is_none());
+        assert_eq!(vec![1u32, 2, 3], Vec::<u32>::try_from(array).unwrap());
+    }
+
+    #[test]
+    fn object_array_getters() {
+        let blocks = vec![Section::from_pairs([("height", 1u64)]), Section::from_pairs([("height", 2u64)])];
+        let response = Section::from_pairs([
+            ("blocks", SectionEntry::from(blocks.clone())),
+            ("heights", vec![1u64, 2].into())
+        ]);
+
+        assert_eq!(Some(blocks.as_slice()), response["blocks"].as_object_array());
+        assert_eq!(Some(blocks.as_slice()), response.get_objects("blocks"));
+        assert_eq!(vec![1, 2], response.try_get_objects("blocks").unwrap().iter().filter_map(|block| block["height"].as_u64()).collect::<Vec<_>>());
+        assert_eq!(None, response.get_objects("heights"));
+        assert_eq!(None, response.get_objects("missing"));
+
+        let err = response.try_get_objects("heights").unwrap_err();
+        assert_eq!((ErrorKind::TypeMismatch, Some("heights")), (err.kind(), err.path()));
+        assert_eq!(Some("missing"), response.try_get_objects("missing").unwrap_err().path());
+
+        assert_eq!(blocks, Vec::<Section>::try_from(response["blocks"].clone()).unwrap());
+        assert!(Vec::<Section>::try_from(response["heights"].clone()).is_err());
+    }
+
+    #[test]
+    fn retain_paths_projects_documents() {
+        let tx = |hash: &str| Section::from_pairs([("hash", SectionEntry::from(hash)), ("blob", SectionEntry::from(vec![0u8; 1000]))]);
+        let block = |height: u64, hashes: &[&str]| Section::from_pairs([
+            ("height", SectionEntry::from(height)),
+            ("block", SectionEntry::from("header")),
+            ("txs", SectionEntry::from(hashes.iter().map(|hash| tx(hash)).collect::<Vec<_>>()))
+        ]);
+        let mut response = Section::from_pairs([
+            ("status", SectionEntry::from("OK")),
+            ("credits", SectionEntry::from(0u64)),
+            ("blocks", SectionEntry::from(vec![block(1, &["a", "b"]), block(2, &[])])),
+            ("meta", SectionEntry::from(Section::from_pairs([("top", 2u64), ("count", 2u64)])))
+        ]);
+
+        response.retain_paths(&["status", "blocks[].height", "blocks.txs[].hash", "meta.top", "credits.nothing", "missing"]);
+        let stripped_tx = |hash: &str| Section::from_pairs([("hash", hash)]);
+        let expected = Section::from_pairs([
+            ("status", SectionEntry::from("OK")),
+            ("blocks", SectionEntry::from(vec![
+                Section::from_pairs([("height", SectionEntry::from(1u64)), ("txs", SectionEntry::from(vec![stripped_tx("a"), stripped_tx("b")]))]),
+                Section::from_pairs([("height", SectionEntry::from(2u64)), ("txs", SectionEntry::from(Vec::<Section>::new()))])
+            ])),
+            ("meta", SectionEntry::from(Section::from_pairs([("top", 2u64)])))
+        ]);
+        assert_eq!(expected, response);
+
+        // Empty sequences go on the wire as UINT8 arrays, and are kept all the same
+        #[derive(serde::Serialize)]
+        struct Block {
+            height: u64,
+            txs: Vec<HashMap<&'static str, &'static str>>
+        }
+        let blocks = vec![
+            Block { height: 1, txs: vec![HashMap::from([("hash", "a"), ("blob", "00")])] },
+            Block { height: 2, txs: vec![] }
+        ];
+        let wire = serde_epee::to_bytes(&HashMap::from([("blocks", blocks)])).unwrap();
+        let mut decoded = serde_epee::section::from_bytes(&wire).unwrap();
+        assert_eq!(SectionEntry::Array(SectionArray::UInt8(vec![])), decoded["blocks"][1]["txs"]);
+        decoded.retain_paths(&["blocks[].txs[].hash"]);
+        assert_eq!(1, decoded["blocks"][0]["txs"][0].len());
+        assert_eq!(Some("a"), decoded["blocks"][0]["txs"][0]["hash"].as_str());
+        assert_eq!(SectionEntry::Array(SectionArray::UInt8(vec![])), decoded["blocks"][1]["txs"]);
+
+        // Keeping a path keeps everything below it
+        let mut copy = expected.clone();
+        copy.retain_paths(&["blocks"]);
+        assert_eq!(expected["blocks"], copy["blocks"]);
+        assert_eq!(1, copy.len());
+        copy.retain_paths::<&str>(&[]);
+        assert!(copy.is_empty());
+    }
+
+    #[test]
+    fn retain_paths_with_escaped_keys() {
+        let mut section = Section::from_pairs([
+            ("a.b", SectionEntry::from(Section::from_pairs([("c", 1u64), ("d", 2u64)]))),
+            ("a", SectionEntry::from(Section::from_pairs([("b", Section::from_pairs([("c", 3u64)]))]))),
+            ("x[y]", SectionEntry::from(4u64)),
+        ]);
+
+        // Paths as scan::path_sizes writes them
+        let (_, sizes) = serde_epee::scan::path_sizes(serde_epee::section::to_bytes(&section).unwrap().as_slice(), 10).unwrap();
+        assert!(sizes.contains_key(r"a\.b.c"));
+        assert!(sizes.contains_key(r"x\[y\]"));
+
+        section.retain_paths(&[r"a\.b.c", r"x\[y\]"]);
+        let expected = Section::from_pairs([
+            ("a.b", SectionEntry::from(Section::from_pairs([("c", 1u64)]))),
+            ("x[y]", SectionEntry::from(4u64)),
+        ]);
+        assert_eq!(expected, section);
+    }
+
+    #[test]
+    fn nonzero_conversions() {
+        let port = NonZeroU32::new(18080).unwrap();
+        assert_eq!(SectionEntry::UInt32(18080), SectionEntry::from(port));
+        assert_eq!(port, NonZeroU32::try_from(SectionEntry::UInt32(18080)).unwrap());
+        assert_eq!(SectionEntry::UInt64(7), SectionEntry::from(NonZeroU64::new(7).unwrap()));
+
+        assert_eq!(ErrorKind::IntOutOfRange { value: 0 }, NonZeroU64::try_from(SectionEntry::UInt64(0)).unwrap_err().kind());
+        assert_eq!(ErrorKind::TypeMismatch, NonZeroU64::try_from(SectionEntry::UInt32(1)).unwrap_err().kind());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Doc {
+        a: u32,
+        b: Nested,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Nested {
+        c: String,
+        d: Vec<u16>,
+        e: Option<u8>,
+    }
+
+    #[test]
+    fn typed_decode_from_section() {
+        let bytes = hex::decode(DOC_HEX).unwrap();
+        let (section, _) = serde_epee::from_bytes_lossy(&bytes);
+
+        let doc = Doc::deserialize(section.into_deserializer()).unwrap();
+        let expected = Doc { a: 5, b: Nested { c: "xyz".to_string(), d: vec![1, 2], e: None } };
+        assert_eq!(expected, doc);
+
+        let nested = Nested::deserialize(SectionEntry::from(Section::new()).into_deserializer());
+        assert!(nested.is_err());
+    }
+
+    #[test]
+    fn from_section_error_paths() {
+        let nested = |d: SectionEntry| -> Section {
+            HashMap::from([
+                ("c".to_string(), SectionEntry::from("xyz")),
+                ("d".to_string(), d),
+            ]).into()
+        };
+        let doc = |d: SectionEntry| -> Section {
+            HashMap::from([
+                ("a".to_string(), SectionEntry::from(5u32)),
+                ("b".to_string(), SectionEntry::from(nested(d))),
+            ]).into()
+        };
+
+        let decoded: Doc = serde_epee::from_section(doc(vec![3u16, 4u16].into())).unwrap();
+        assert_eq!(vec![3, 4], decoded.b.d);
+
+        let err = serde_epee::from_section::<Doc>(doc(vec!["x".to_string()].into())).unwrap_err();
+        assert_eq!(Some("b.d[0]"), err.path());
+
+        let mut missing = doc(vec![3u16].into());
+        if let SectionEntry::Object(b) = missing.get_mut("b").unwrap() {
+            b.remove("c");
+        }
+        let err = serde_epee::from_section::<Doc>(missing).unwrap_err();
+        assert_eq!(Some("b"), err.path());
+        assert!(err.to_string().contains("at b:"));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Bytes {
+        data: Vec<u8>,
+        hash: [u8; 4]
+    }
+
+    #[test]
+    fn byte_arrays_from_section_blobs() {
+        let section: Section = HashMap::from([
+            ("data".to_string(), SectionEntry::Blob(vec![1, 2, 3].into())),
+            ("hash".to_string(), SectionEntry::Blob(vec![9; 4].into())),
+        ]).into();
+        let section = serde_epee::section::from_bytes(&serde_epee::section::to_bytes(&section).unwrap()).unwrap();
+        let decoded: Bytes = serde_epee::from_section(section).unwrap();
+        assert_eq!(Bytes { data: vec![1, 2, 3], hash: [9; 4] }, decoded);
+
+        let short: Section = HashMap::from([
+            ("data".to_string(), SectionEntry::Blob(vec![].into())),
+            ("hash".to_string(), SectionEntry::Blob(vec![9; 3].into())),
+        ]).into();
+        let err = serde_epee::from_section::<Bytes>(short).unwrap_err();
+        assert_eq!(Some("hash"), err.path());
+    }
+
+    #[test]
+    fn build_from_pairs() {
+        let mut section = Section::from_pairs([
+            ("height", SectionEntry::from(5u64)),
+            ("hash", SectionEntry::from("ab")),
+            ("inner", SectionEntry::from(Section::from_pairs([("x", 1u8)])))
+        ]);
+        assert_eq!(Some(5), section["height"].as_u64());
+        assert_eq!(Some(1), section["inner"]["x"].as_u64());
+
+        section.extend([("height".to_string(), SectionEntry::from(6u64))]);
+        assert_eq!(Some(6), section["height"].as_u64());
+
+        let collected: Section = vec![("a", true), ("b", false)].into_iter().collect();
+        assert_eq!(Some(false), collected["b"].as_bool());
+        assert_eq!(2, collected.len());
+    }
+
+    #[test]
+    fn single_entry_raw_bytes() {
+        let entries: Vec<SectionEntry> = vec![
+            SectionEntry::UInt16(0x1234),
+            "xyz".into(),
+            vec![1u16, 2u16].into(),
+            Section::from_pairs(vec![("c", true)]).into(),
+        ];
+        for entry in entries {
+            let (type_code, payload) = serde_epee::value::to_raw_bytes(&entry).unwrap();
+            assert_eq!(entry, serde_epee::value::from_raw_bytes(type_code, &payload).unwrap());
+        }
+
+        let (type_code, payload) = serde_epee::value::to_raw_bytes(&SectionEntry::UInt16(0x1234)).unwrap();
+        assert_eq!((7, vec![0x34, 0x12]), (type_code, payload));
+
+        let err = serde_epee::value::from_raw_bytes(7, &[0x34, 0x12, 0x00]).unwrap_err();
+        assert_eq!(ErrorKind::ExpectedEnd, err.kind());
+    }
+
+    #[test]
+    fn save_and_load_file() {
+        let dir = std::env::temp_dir().join(format!("serde_epee_save_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("epee_example.dat");
+
+        let section = Section::from_pairs(vec![
+            ("port", SectionEntry::UInt16(18080)),
+            ("peers", vec![1u32, 2u32].into()),
+        ]);
+        section.save(&path).unwrap();
+        assert_eq!(section, Section::load(&path).unwrap());
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count()); // no temporary files left
+
+        // Concurrent saves to one path each write their own temporary file
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| section.save(&path).unwrap());
+            }
+        });
+        assert_eq!(section, Section::load(&path).unwrap());
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.push(0);
+        std::fs::write(&path, bytes).unwrap();
+        assert_eq!(ErrorKind::ExpectedEnd, Section::load(&path).unwrap_err().kind());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Patched {
+        a: u32,
+        #[serde(default)]
+        b: Option<Section>
+    }
+
+    #[test]
+    fn nil_fields_are_absent() {
+        let (mut section, _) = serde_epee::from_bytes_lossy(&hex::decode(DOC_HEX).unwrap());
+        section.insert("b".to_string(), SectionEntry::Nil);
+        section.insert("c".to_string(), SectionEntry::Nil);
+
+        // Left out of the wire format, field count included
+        let bytes = serde_epee::to_bytes(&section).unwrap();
+        let expected = serde_epee::to_bytes(&Section::from_pairs([("a", 5u32)])).unwrap();
+        assert_eq!(expected, bytes);
+
+        // And out of typed values
+        let patched: Patched = serde_epee::from_section(section.clone()).unwrap();
+        assert_eq!(Patched { a: 5, b: None }, patched);
+
+        // Nested ones too, and compact drops them for good
+        let mut outer = Section::from_pairs([("inner", SectionEntry::Object(section.clone()))]);
+        let bytes = serde_epee::to_bytes(&outer).unwrap();
+        outer.compact();
+        assert_eq!(1, outer["inner"].as_section().unwrap().len());
+        assert_eq!(bytes, serde_epee::to_bytes(&outer).unwrap());
+
+        // There is nothing to write for a Nil on its own
+        assert!(serde_epee::value::to_raw_bytes(&SectionEntry::Nil).is_err());
+    }
+
+    #[test]
+    fn native_encoding() {
+        let (mut section, _) = serde_epee::from_bytes_lossy(&hex::decode(DOC_HEX).unwrap());
+        section.insert("e".to_string(), vec![serde_bytes::ByteBuf::from(b"x".to_vec()), serde_bytes::ByteBuf::from(b"yz".to_vec())].into());
+        let inner = section.clone();
+        section.insert("f".to_string(), SectionEntry::Array(SectionArray::Object(vec![inner, Section::new()])));
+        section.insert("g".to_string(), vec![true, false].into());
+        section.insert("h".to_string(), SectionEntry::Nil);
+
+        // Same bytes as going through serde
+        let bytes = serde_epee::section::to_bytes(&section).unwrap();
+        assert_eq!(serde_epee::to_bytes(&section).unwrap(), bytes);
+        section.remove("h");
+        assert_eq!(section, serde_epee::from_bytes_lossy(&bytes).0);
+
+        // Except that empty arrays keep their element type
+        let empty = Section::from_pairs([("heights", SectionEntry::Array(SectionArray::UInt64(Vec::new())))]);
+        let bytes = serde_epee::section::to_bytes(&empty).unwrap();
+        assert_eq!(empty, serde_epee::from_bytes_lossy(&bytes).0);
+        assert_ne!(serde_epee::to_bytes(&empty).unwrap(), bytes);
+
+        // Limits still apply
+        let limits = serde_epee::Limits { max_objects: 2, ..Default::default() };
+        let mut out = Vec::new();
+        let err = serde_epee::Serializer::new(&mut out).with_limits(limits).unwrap().serialize_section(&section).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::TooManyObjects { .. }));
+
+        // Every element of scalar arrays counts, as if serialized one by one
+        let blobs = Section::from_pairs([("blobs", SectionEntry::Array(SectionArray::Blob(["a", "b", "c"].map(|s| serde_bytes::ByteBuf::from(s.as_bytes())).to_vec())))]);
+        let limits = serde_epee::Limits { max_strings: 2, ..Default::default() };
+        let mut out = Vec::new();
+        let err = serde_epee::Serializer::new(&mut out).with_limits(limits).unwrap().serialize_section(&blobs).unwrap_err();
+        assert_eq!(ErrorKind::TooManyStrings { count: 3, max: 2 }, err.kind());
+        let limits = serde_epee::Limits { max_string_len: 0, ..Default::default() };
+        let err = serde_epee::Serializer::new(&mut out).with_limits(limits).unwrap().serialize_section(&blobs).unwrap_err();
+        assert_eq!(ErrorKind::StringTooLong { len: 1, max: 0 }, err.kind());
+    }
+
+    #[test]
+    fn native_decoding() {
+        let bytes = hex::decode(DOC_HEX).unwrap();
+        let section = serde_epee::section::from_bytes(&bytes).unwrap();
+        assert!(matches!(&section["b"]["d"], SectionEntry::Array(SectionArray::UInt16(v)) if v == &[1, 2]));
+        assert_eq!(section, serde_epee::section::from_bytes(&serde_epee::section::to_bytes(&section).unwrap()).unwrap());
+
+        // Unlike from_bytes_lossy, anything wrong fails the whole decode
+        assert!(serde_epee::section::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(ErrorKind::ExpectedEnd, serde_epee::section::from_bytes(&trailing).unwrap_err().kind());
+
+        // Huge counts are not taken at their word
+        let lying = hex::decode("01110101010102010104016185feffffff").unwrap();
+        assert!(serde_epee::section::from_bytes(&lying).is_err());
+    }
+
+    #[cfg(feature = "template")]
+    #[derive(serde::Serialize, Default)]
+    struct GetBlocksRequest {
+        block_ids: Vec<u8>,
+        start_height: u64,
+        prune: bool,
+        client: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pool_info_since: Option<u64>,
+        limits: HashMap<String, u32>
+    }
+
+    #[cfg(feature = "template")]
+    #[test]
+    fn template_sections() {
+        let template = serde_epee::section::template_section::<GetBlocksRequest>().unwrap();
+        assert_eq!(5, template.len());
+        assert_eq!(SectionEntry::Array(SectionArray::UInt8(vec![])), template["block_ids"]);
+        assert_eq!(SectionEntry::UInt64(0), template["start_height"]);
+        assert_eq!(SectionEntry::Bool(false), template["prune"]);
+        assert_eq!(Some(""), template["client"].as_str());
+        assert_eq!(Some(&Section::new()), template["limits"].as_section());
+        assert!(!template.contains_key("pool_info_since"));
+    }
+
+    #[cfg(feature = "structural-hash")]
+    #[test]
+    fn structural_hashes_ignore_field_order() {
+        #[derive(serde::Serialize)]
+        struct Forward { height: u64, hash: &'static str, txs: Vec<u32> }
+        #[derive(serde::Serialize)]
+        struct Backward { txs: Vec<u32>, hash: &'static str, height: u64 }
+
+        let forward = serde_epee::to_bytes(&Forward { height: 7, hash: "ab", txs: vec![1, 2] }).unwrap();
+        let backward = serde_epee::to_bytes(&Backward { txs: vec![1, 2], hash: "ab", height: 7 }).unwrap();
+        assert_ne!(forward, backward);
+        let hash = serde_epee::section::hash_bytes(&forward).unwrap();
+        assert_eq!(hash, serde_epee::section::hash_bytes(&backward).unwrap());
+
+        let mut section = serde_epee::section::from_bytes(&forward).unwrap();
+        assert_eq!(hash, serde_epee::section::structural_hash(&section));
+        section.insert(String::from("extra"), SectionEntry::Nil);
+        assert_eq!(hash, serde_epee::section::structural_hash(&section));
+
+        section.insert(String::from("height"), SectionEntry::UInt64(8));
+        assert_ne!(hash, serde_epee::section::structural_hash(&section));
+        section.insert(String::from("height"), SectionEntry::UInt32(7));
+        assert_ne!(hash, serde_epee::section::structural_hash(&section));
+
+        // Nested sections are canonicalized too
+        let outer = |inner: &[u8]| Section::from_pairs([("res", SectionEntry::Object(serde_epee::section::from_bytes(inner).unwrap()))]);
+        assert_eq!(
+            serde_epee::section::structural_hash(&outer(&forward)),
+            serde_epee::section::structural_hash(&outer(&backward))
+        );
+
+        let empty_u8 = Section::from_pairs([("txs", SectionEntry::Array(SectionArray::UInt8(vec![])))]);
+        let empty_u64 = Section::from_pairs([("txs", SectionEntry::Array(SectionArray::UInt64(vec![])))]);
+        assert_eq!(serde_epee::section::structural_hash(&empty_u8), serde_epee::section::structural_hash(&empty_u64));
+
+        // Doubles by value, not by bits
+        let double = |v: f64| serde_epee::section::structural_hash(&Section::from_pairs([("fee", SectionEntry::Double(v))]));
+        assert_eq!(double(0.0), double(-0.0));
+        assert_eq!(double(f64::NAN), double(f64::from_bits(f64::NAN.to_bits() ^ 1)));
+        assert_eq!(double(f64::NAN), double(-f64::NAN));
+        assert_ne!(double(0.0), double(f64::NAN));
+        assert_ne!(double(1.0), double(-1.0));
+
+        assert!(serde_epee::section::hash_bytes(&forward[1..]).is_err());
+    }
+}
@@ -1,8 +1,13 @@
-use serde_epee;
-use serde_epee::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
 
-use hex;
 use serde::{Serialize, Deserialize};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde_epee::{constants, Deserializer, ErrorKind, FormatProfile, Limits, RawFragment, Serializer};
+use serde_epee::ser::write::{EpeeWrite, IoWrite};
+use serde_epee::trace::TraceLabel;
 
 #[cfg(test)]
 mod tests {
@@ -24,4 +29,414 @@ mod tests {
             Err(err) => panic!("Error: {}", err)
         }
     }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Nested {
+        inner: Request
+    }
+
+    #[test]
+    fn serialize_with_limits() {
+        let nested = Nested { inner: Request { txid: [0; 32] } };
+
+        let mut bytes = Vec::new();
+        let mut serializer = Serializer::new(&mut bytes).with_limits(Limits { max_depth: 1, ..Limits::monerod() }).unwrap();
+        let err = nested.serialize(&mut serializer).unwrap_err();
+        assert_eq!(ErrorKind::SectionTooDeep { max: 1 }, err.kind());
+
+        // Arrays are not limited by the section field limit anymore
+        let long_array = vec![0u64; 20000];
+        assert!(serde_epee::to_bytes(&std::collections::HashMap::from([("a", long_array)])).is_ok());
+    }
+
+    #[derive(Serialize, Clone)]
+    struct NodeData {
+        network_id: [u8; 4],
+        my_port: u32
+    }
+
+    #[derive(Serialize)]
+    struct Handshake<N: Serialize> {
+        node_data: N,
+        nodes: Vec<N>,
+        payload_size: u64
+    }
+
+    #[test]
+    fn splice_raw_fragments() {
+        let node_data = NodeData { network_id: [1, 2, 3, 4], my_port: 18080 };
+        let direct = Handshake { node_data: node_data.clone(), nodes: vec![node_data.clone(); 2], payload_size: 7 };
+
+        let fragment = RawFragment::from_value(&node_data).unwrap();
+        let spliced = Handshake { node_data: fragment.clone(), nodes: vec![fragment.clone(); 2], payload_size: 7 };
+
+        assert_eq!(serde_epee::to_bytes(&direct).unwrap(), serde_epee::to_bytes(&spliced).unwrap());
+
+        let manual = RawFragment::new(fragment.type_code(), fragment.payload()).unwrap();
+        assert_eq!(fragment, manual);
+        assert!(RawFragment::new(0, &[]).is_err());
+
+        // Payloads have to be exactly one entry of the given type
+        let payload = fragment.payload();
+        let err = RawFragment::new(fragment.type_code(), &payload[..payload.len() - 1]).unwrap_err();
+        assert_eq!(ErrorKind::IOError, err.kind());
+        let padded = [payload, &[0]].concat();
+        assert_eq!(ErrorKind::ExpectedEnd, RawFragment::new(fragment.type_code(), &padded).unwrap_err().kind());
+        let err = RawFragment::new(constants::SERIALIZE_TYPE_UINT32, payload).unwrap_err();
+        assert_eq!(ErrorKind::ExpectedEnd, err.kind());
+    }
+
+    // Declares a sequence length without actually holding that many elements
+    struct DeclaredLen(usize);
+
+    impl Serialize for DeclaredLen {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(Some(self.0))?;
+            seq.serialize_element(&7u8)?;
+            seq.end()
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn array_lengths_past_u32() {
+        // The header is out before the missing elements are noticed
+        let encode_tail = |len: usize| {
+            let doc = HashMap::from([("v", DeclaredLen(len))]);
+            let mut bytes = Vec::new();
+            let err = serde_epee::to_writer(&mut bytes, &doc).unwrap_err();
+            assert_eq!(ErrorKind::SizeHintMismatch { expected: len as u64, found: 1 }, err.kind());
+            hex::encode(&bytes[12..])
+        };
+
+        assert_eq!("88ffffffff0300000007", encode_tail(u32::MAX as usize));
+        assert_eq!("88030000000400000007", encode_tail(1 << 32));
+
+        let doc = HashMap::from([("v", DeclaredLen(1 << 62))]);
+        assert_eq!(ErrorKind::ArrayTooLong { len: 1 << 62 }, serde_epee::to_bytes(&doc).unwrap_err().kind());
+    }
+
+    struct DeclaredFields(usize);
+
+    impl Serialize for DeclaredFields {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_map(Some(self.0))?.end()
+        }
+    }
+
+    #[test]
+    fn section_field_counts() {
+        // Serializing is only limited on request
+        let big: HashMap<String, u8> = (0..70000).map(|i| (format!("k{}", i), 1)).collect();
+        let mut bytes = Vec::new();
+        let mut serializer = Serializer::new(&mut bytes).with_limits(Limits::monerod()).unwrap();
+        let err = big.serialize(&mut serializer).unwrap_err();
+        assert_eq!(ErrorKind::TooManySectionFields { count: 70000, max: 65536 }, err.kind());
+
+        let bytes = serde_epee::to_bytes(&big).unwrap();
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_limits(Limits { max_fields: 100000, ..Limits::monerod() });
+        assert_eq!(big, HashMap::deserialize(&mut deserializer).unwrap());
+
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(ErrorKind::SectionTooLong { len: 1 << 62 }, serde_epee::to_bytes(&DeclaredFields(1 << 62)).unwrap_err().kind());
+    }
+
+    // Counts in headers are written up front, so they must match what follows
+    #[test]
+    fn declared_lengths_are_enforced() {
+        let err = serde_epee::to_bytes(&DeclaredFields(2)).unwrap_err();
+        assert_eq!(ErrorKind::SizeHintMismatch { expected: 2, found: 0 }, err.kind());
+
+        struct ExtraField;
+        impl Serialize for ExtraField {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("a", &1u8)?;
+                map.serialize_entry("b", &2u8)?;
+                map.end()
+            }
+        }
+        let err = serde_epee::to_bytes(&HashMap::from([("inner", ExtraField)])).unwrap_err();
+        assert_eq!(ErrorKind::SizeHintMismatch { expected: 1, found: 2 }, err.kind());
+        assert_eq!(Some("inner"), err.path());
+
+        let err = serde_epee::to_bytes(&HashMap::from([("v", DeclaredLen(0))])).unwrap_err();
+        assert_eq!(ErrorKind::SizeHintMismatch { expected: 0, found: 1 }, err.kind());
+        assert_eq!(Some("v"), err.path());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_compound_constructors() {
+        let mut bytes = Vec::new();
+        let mut root = Serializer::new_root_section(&mut bytes, 1).unwrap();
+        root.serialize_entry("height", &5u64).unwrap();
+        SerializeMap::end(root).unwrap();
+        assert_eq!(serde_epee::to_bytes(&HashMap::from([("height", 5u64)])).unwrap(), bytes);
+
+        let mut bytes = Vec::new();
+        let mut array = Serializer::new_array(&mut bytes, 2).unwrap();
+        array.serialize_element(&1u16).unwrap();
+        array.serialize_element(&2u16).unwrap();
+        SerializeSeq::end(array).unwrap();
+        assert_eq!(vec![0x87, 8, 1, 0, 2, 0], bytes);
+
+        let mut bytes = Vec::new();
+        let section = Serializer::new_section(&mut bytes, 1).unwrap();
+        assert!(SerializeMap::end(section).is_err());
+    }
+
+    #[test]
+    fn fork_format_profile() {
+        let fork = FormatProfile { signature_a: 0x01011102, ..FormatProfile::monero() };
+        let nested = Nested { inner: Request { txid: [5; 32] } };
+
+        let mut bytes = Vec::new();
+        nested.serialize(&mut Serializer::new(&mut bytes).with_profile(fork)).unwrap();
+        assert_eq!(fork.signature(), bytes[..9]);
+
+        let mut deserializer = Deserializer::from_reader(bytes.as_slice()).with_profile(fork);
+        assert_eq!([5; 32], Nested::deserialize(&mut deserializer).unwrap().inner.txid);
+
+        let err = serde_epee::from_bytes::<Nested>(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(ErrorKind::ExpectedFormatSignature, err.kind());
+    }
+
+    // io::Write which counts the calls made to it
+    struct CallCounter {
+        bytes: Vec<u8>,
+        calls: usize
+    }
+
+    impl std::io::Write for CallCounter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.calls += 1;
+            self.bytes.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // EpeeWrite which fails past a fixed length
+    struct Capped {
+        bytes: Vec<u8>,
+        max: usize
+    }
+
+    impl EpeeWrite for Capped {
+        fn write_bytes(&mut self, bytes: &[u8]) -> serde_epee::Result<()> {
+            if self.bytes.len() + bytes.len() > self.max {
+                return Err(serde_epee::Error::new(ErrorKind::BudgetExceeded { max_bytes: self.max as u64 }, String::from("capped")));
+            }
+            self.bytes.write_bytes(bytes)
+        }
+    }
+
+    #[test]
+    fn epee_write_outputs() {
+        let nested = Nested { inner: Request { txid: [1; 32] } };
+        let expected = serde_epee::to_bytes(&nested).unwrap();
+
+        let (counter, _) = serde_epee::to_writer_counted(CallCounter { bytes: Vec::new(), calls: 0 }, &nested).unwrap();
+        assert_eq!((&expected, 1), (&counter.bytes, counter.calls));
+
+        let mut io = IoWrite::with_capacity(8, CallCounter { bytes: Vec::new(), calls: 0 });
+        nested.serialize(&mut Serializer::new(&mut io)).unwrap();
+        let counter = io.into_inner().unwrap();
+        assert_eq!(expected, counter.bytes);
+        assert!(counter.calls > 1);
+
+        let mut capped = Capped { bytes: Vec::new(), max: expected.len() };
+        nested.serialize(&mut Serializer::new(&mut capped)).unwrap();
+        assert_eq!(expected, capped.bytes);
+        let mut capped = Capped { bytes: Vec::new(), max: expected.len() - 1 };
+        let err = nested.serialize(&mut Serializer::new(&mut capped)).unwrap_err();
+        assert_eq!(ErrorKind::BudgetExceeded { max_bytes: expected.len() as u64 - 1 }, err.kind());
+    }
+
+    #[test]
+    fn count_bytes_written() {
+        let nested = Nested { inner: Request { txid: [1; 32] } };
+        let expected = serde_epee::to_bytes(&nested).unwrap();
+
+        let mut bytes = Vec::new();
+        let mut serializer = Serializer::new(&mut bytes);
+        nested.serialize(&mut serializer).unwrap();
+        assert_eq!(expected.len(), serializer.bytes_written());
+
+        let (writer, count) = serde_epee::to_writer_counted(Vec::new(), &nested).unwrap();
+        assert_eq!(expected.len(), count);
+        assert_eq!(expected, writer);
+
+        let size = serde_epee::serialized_size(&nested).unwrap();
+        assert_eq!(expected.len(), size);
+        let preallocated = serde_epee::to_bytes_with_capacity(&nested, size).unwrap();
+        assert_eq!(expected, preallocated);
+        assert!(preallocated.capacity() >= size);
+        assert!(expected.capacity() >= size);
+    }
+
+    #[derive(Serialize)]
+    struct Ports {
+        ports: Vec<u16>
+    }
+
+    #[test]
+    fn trace_every_write() {
+        let ports = Ports { ports: vec![1, 2] };
+        let (bytes, trace) = serde_epee::trace::to_bytes_traced(&ports).unwrap();
+        assert_eq!(serde_epee::to_bytes(&ports).unwrap(), bytes);
+
+        let labels: Vec<TraceLabel> = trace.0.iter().map(|record| record.label).collect();
+        assert_eq!(vec![
+            TraceLabel::Signature, TraceLabel::VarInt, TraceLabel::Key, TraceLabel::TypeCode, TraceLabel::VarInt,
+            TraceLabel::Payload, TraceLabel::Payload
+        ], labels);
+
+        // Records tile the whole encoding
+        let joined: Vec<u8> = trace.0.iter().flat_map(|record| record.bytes.clone()).collect();
+        assert_eq!(bytes, joined);
+
+        let dump = trace.to_string();
+        assert!(dump.contains("0000000a  05"));
+        assert!(dump.lines().last().unwrap().ends_with("payload    ports[1]"));
+    }
+
+    #[derive(Serialize)]
+    struct Peers<'a> {
+        ports: Vec<&'a u16>,
+        names: &'a [String],
+        #[serde(serialize_with = "even_ports")]
+        even: &'a [u16],
+        #[serde(serialize_with = "even_ports")]
+        none: &'a [u16]
+    }
+
+    // Filtered iterators don't know their length up front
+    fn even_ports<S: serde::Serializer>(ports: &&[u16], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ports.iter().filter(|port| *port % 2 == 0).map(|port| *port as u32))
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct OwnedPeers {
+        ports: Vec<u16>,
+        names: Vec<String>,
+        even: Vec<u32>,
+        none: Vec<u32>
+    }
+
+    #[test]
+    fn serialize_borrowed_and_unsized_seqs() {
+        let ports = [18080u16, 18081, 18082];
+        let names = vec!["a".to_string(), "b".to_string()];
+        let peers = Peers { ports: ports.iter().collect(), names: &names, even: &ports, none: &[] };
+
+        let bytes = serde_epee::to_bytes(&peers).unwrap();
+        let decoded: OwnedPeers = serde_epee::from_slice(&bytes).unwrap();
+        assert_eq!(OwnedPeers { ports: ports.to_vec(), names: names.clone(), even: vec![18080, 18082], none: vec![] }, decoded);
+        assert_eq!(bytes.len(), serde_epee::serialized_size(&peers).unwrap());
+
+        // Buffered elements are still limited and type checked
+        let mut bytes = Vec::new();
+        let mut serializer = Serializer::new(&mut bytes).with_limits(Limits { max_strings: 1, ..Limits::monerod() }).unwrap();
+        let err = HashMap::from([("v", Unsized(&names))]).serialize(&mut serializer).unwrap_err();
+        assert_eq!(ErrorKind::TooManyStrings { count: 2, max: 1 }, err.kind());
+        let err = serde_epee::to_bytes(&HashMap::from([("v", MixedUnsized)])).unwrap_err();
+        assert_eq!(ErrorKind::ArrayMixedTypes, err.kind());
+        assert_eq!(Some("v[1]"), err.path());
+    }
+
+    struct Unsized<'a>(&'a [String]);
+
+    impl Serialize for Unsized<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.0.iter().filter(|_| true))
+        }
+    }
+
+    struct MixedUnsized;
+
+    impl Serialize for MixedUnsized {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(None)?;
+            seq.serialize_element(&1u8)?;
+            seq.serialize_element(&2u16)?;
+            seq.end()
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct Peer {
+        ip: u32,
+        port: u16
+    }
+
+    #[derive(Serialize)]
+    struct BorrowedPeers<'a> {
+        first: &'a Peer,
+        all: &'a [Peer],
+        note: &'a str
+    }
+
+    #[derive(Serialize)]
+    struct OwnedPeerList {
+        first: Peer,
+        all: Vec<Peer>,
+        note: String
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct PointerPeers<'a> {
+        first: Box<Peer>,
+        all: Rc<[Peer]>,
+        shared: Arc<Vec<Peer>>,
+        note: Cow<'a, str>,
+        cached: Cow<'a, [Peer]>,
+        nested: Arc<Peer>
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct OwnedPointerPeers {
+        first: Peer,
+        all: Vec<Peer>,
+        shared: Vec<Peer>,
+        note: String,
+        cached: Vec<Peer>,
+        nested: Peer
+    }
+
+    #[test]
+    fn references_and_smart_pointers() {
+        let peers = vec![Peer { ip: 0x7f000001, port: 18080 }, Peer { ip: 0x0a000001, port: 18081 }];
+
+        // References serialize like what they point to
+        let borrowed = BorrowedPeers { first: &peers[0], all: &peers, note: "seed" };
+        let bytes = serde_epee::to_bytes(&borrowed).unwrap();
+        let expected = OwnedPeerList { first: peers[0].clone(), all: peers.clone(), note: "seed".to_string() };
+        assert_eq!(serde_epee::to_bytes(&expected).unwrap(), bytes);
+
+        // So do smart pointers, which also deserialize
+        let pointers = PointerPeers {
+            first: Box::new(peers[0].clone()),
+            all: Rc::from(peers.clone()),
+            shared: Arc::new(peers.clone()),
+            note: Cow::Borrowed("seed"),
+            cached: Cow::Borrowed(&peers),
+            nested: Arc::new(peers[1].clone())
+        };
+        let owned = OwnedPointerPeers {
+            first: peers[0].clone(),
+            all: peers.clone(),
+            shared: peers.clone(),
+            note: "seed".to_string(),
+            cached: peers.clone(),
+            nested: peers[1].clone()
+        };
+        let bytes = serde_epee::to_bytes(&pointers).unwrap();
+        assert_eq!(serde_epee::to_bytes(&owned).unwrap(), bytes);
+        assert_eq!(pointers, serde_epee::from_slice::<PointerPeers>(&bytes).unwrap());
+        assert_eq!(pointers, serde_epee::from_reader::<PointerPeers, _>(bytes.as_slice()).unwrap());
+    }
 }
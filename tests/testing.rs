@@ -0,0 +1,32 @@
+use serde::{Serialize, Deserialize};
+use serde_epee::testing;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct NodeData {
+        my_port: u32
+    }
+
+    #[test]
+    fn fixture_helpers() {
+        let node_data = NodeData { my_port: 18080 };
+        let bytes = testing::assert_round_trip(&node_data);
+
+        let fixture = testing::load_hex_fixture(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/node_data.hex")).unwrap();
+        assert_eq!(bytes, fixture);
+        testing::assert_wire_compatible(&testing::encode_hex(&fixture), &node_data);
+        testing::assert_decodes_to("011101010101020101 04 076d795f706f7274 06 a0460000", &node_data);
+
+        assert!(testing::decode_hex("0g").is_err());
+        assert!(testing::decode_hex("abc").is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn decodes_to_mismatch_panics() {
+        testing::assert_decodes_to("01110101010102010104076d795f706f727406a0460000", &NodeData { my_port: 1 });
+    }
+}
@@ -0,0 +1,3 @@
+serde_epee::assert_section_keys!("height", "");
+
+fn main() {}
@@ -0,0 +1,7 @@
+serde_epee::assert_section_keys!(
+    "this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_\
+     this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_\
+     this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long"
+);
+
+fn main() {}
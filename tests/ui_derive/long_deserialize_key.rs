@@ -0,0 +1,10 @@
+#[serde_epee::epee_object]
+#[derive(serde::Deserialize)]
+struct Request {
+    #[serde(rename(serialize = "height", deserialize = "this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_\
+                                                     this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long_\
+                                                     this_key_is_way_too_long_this_key_is_way_too_long_this_key_is_way_too_long"))]
+    height: u64
+}
+
+fn main() {}
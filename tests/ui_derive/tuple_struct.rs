@@ -0,0 +1,5 @@
+#[serde_epee::epee_object]
+#[derive(serde::Serialize)]
+struct Height(u64);
+
+fn main() {}
@@ -0,0 +1,8 @@
+#[serde_epee::epee_object]
+#[derive(serde::Serialize)]
+struct Request {
+    #[epee(hex)]
+    txid: Vec<u8>
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+#[serde_epee::epee_object]
+#[derive(serde::Serialize)]
+#[serde(rename_all = "Title Case")]
+struct Request {
+    height: u64
+}
+
+fn main() {}
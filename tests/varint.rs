@@ -0,0 +1,52 @@
+use serde::{Serialize, Deserialize};
+use serde_epee::packed::{from_packed_bytes, to_packed_bytes};
+use serde_epee::{Section, VarInt};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Output {
+        index: VarInt,
+        #[serde(with = "serde_epee::varint")]
+        amount: u64
+    }
+
+    #[test]
+    fn varint_fields_are_blobs() {
+        let output = Output { index: VarInt::from(300u16), amount: 1 << 40 };
+        let bytes = serde_epee::to_bytes(&output).unwrap();
+
+        let section = serde_epee::section::from_bytes(&bytes).unwrap();
+        assert_eq!(Some(&[0xb1, 0x04][..]), section["index"].as_bytes());
+        assert_eq!(8, section["amount"].as_bytes().unwrap().len());
+
+        assert_eq!(output, serde_epee::from_slice(&bytes).unwrap());
+        assert_eq!(output, serde_epee::from_section::<Output>(section).unwrap());
+
+        // The whole blob must be one varint
+        let bad = Section::from_pairs([("index", &[0xb1, 0x04, 0x00][..]), ("amount", &[0x04][..])]);
+        assert!(serde_epee::from_section::<Output>(bad).is_err());
+        let bad = Section::from_pairs([("index", &[0xb1][..]), ("amount", &[0x04][..])]);
+        assert!(serde_epee::from_section::<Output>(bad).is_err());
+
+        let too_big = serde_epee::to_bytes(&std::collections::HashMap::from([("amount", VarInt::MAX)])).unwrap();
+        assert!(serde_epee::from_slice::<Output>(&too_big).is_err());
+    }
+
+    #[test]
+    fn packed_varints() {
+        let values = vec![VarInt::from(1u8), VarInt::from(300u16), VarInt::MAX];
+        let packed = to_packed_bytes(&values).unwrap();
+        assert_eq!(1 + 2 + 8, packed.len());
+        assert_eq!(values, from_packed_bytes::<Vec<VarInt>>(&packed).unwrap());
+
+        let tuple = (VarInt::from(70000u32), 7u16);
+        let packed = to_packed_bytes(&tuple).unwrap();
+        assert_eq!(vec![0xc2, 0x45, 0x04, 0x00, 7, 0], packed);
+        assert_eq!(tuple, from_packed_bytes(&packed).unwrap());
+
+        assert!(from_packed_bytes::<Vec<VarInt>>(&packed[..3]).is_err());
+    }
+}
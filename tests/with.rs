@@ -0,0 +1,62 @@
+#![cfg(feature = "serde_with")]
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, Map, TimestampSeconds};
+use serde_with::hex::Hex;
+use serde_epee::with::PairsAsObjects;
+use serde_epee::testing::assert_round_trip;
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Adapted {
+        #[serde_as(as = "DisplayFromStr")]
+        amount: u64,
+        #[serde_as(as = "Hex")]
+        tx_hash: [u8; 4],
+        #[serde_as(as = "TimestampSeconds<i64>")]
+        last_seen: SystemTime,
+        #[serde_as(as = "Map<_, _>")]
+        ports: Vec<(String, u16)>,
+        #[serde_as(as = "PairsAsObjects")]
+        heights: BTreeMap<u64, String>
+    }
+
+    #[test]
+    fn serde_with_matrix() {
+        let value = Adapted {
+            amount: 18446744073709551615,
+            tx_hash: [0xde, 0xad, 0xbe, 0xef],
+            last_seen: SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000),
+            ports: vec![("p2p".to_string(), 18080), ("rpc".to_string(), 18081)],
+            heights: BTreeMap::from([(1, "genesis".to_string()), (3000000, "recent".to_string())])
+        };
+        let bytes = assert_round_trip(&value);
+
+        let section = serde_epee::from_bytes_lossy(&bytes).0;
+        assert_eq!(Some("deadbeef"), section["tx_hash"].as_str());
+        assert_eq!(Some("18446744073709551615"), section["amount"].as_str());
+        assert_eq!(Some(1700000000), section["last_seen"].as_i64());
+        assert_eq!(Some(18081), section["ports"]["rpc"].as_u64());
+        assert_eq!(Some("recent"), section["heights"][1]["value"].as_str());
+    }
+
+    #[test]
+    fn pairs_as_tuples_are_unsupported() {
+        #[serde_as]
+        #[derive(Serialize)]
+        struct Tuples {
+            #[serde_as(as = "Vec<(_, _)>")]
+            heights: BTreeMap<u64, String>
+        }
+
+        let value = Tuples { heights: BTreeMap::from([(1, "genesis".to_string())]) };
+        assert_eq!(serde_epee::ErrorKind::NestedArrays, serde_epee::to_bytes(&value).unwrap_err().kind());
+    }
+}